@@ -1,3 +1,5 @@
+use burn_lm_inference::{BackendKind, FloatDtype};
+
 #[derive(Debug, Clone, PartialEq, Eq, clap::ValueEnum, strum::Display, strum::EnumIter)]
 pub(crate) enum BackendValues {
     // cuda ------------------------------------------------------------------
@@ -46,3 +48,17 @@ pub(crate) enum BackendValues {
     #[strum(to_string = "wgpu-cpu")]
     WgpuCpu,
 }
+
+/// Return true if the backend value supports the data type.
+pub(crate) fn supports_dtype(backend: &str, dtype: &str) -> bool {
+    // The BLAS variants of ndarray are the same runtime backend
+    let backend = if backend.starts_with("ndarray") {
+        "ndarray"
+    } else {
+        backend
+    };
+    match (backend.parse::<BackendKind>(), dtype.parse::<FloatDtype>()) {
+        (Ok(backend), Ok(dtype)) => backend.supports(dtype),
+        _ => false,
+    }
+}
@@ -24,11 +24,15 @@ pub fn run(backend: &str, dtype: &str) -> anyhow::Result<()> {
         .subcommand(commands::chat::create())
         .subcommand(commands::delete::create())
         .subcommand(commands::download::create())
+        .subcommand(commands::eval::create())
         .subcommand(commands::models::create())
         .subcommand(commands::new::create())
+        .subcommand(commands::quantize::create())
         .subcommand(commands::run::create())
         .subcommand(commands::server::create())
         .subcommand(commands::shell::create())
+        .subcommand(commands::tokenize::create())
+        .subcommand(commands::train::create())
         .subcommand(commands::web::create());
 
     // Execute commands
@@ -50,16 +54,24 @@ pub fn run(backend: &str, dtype: &str) -> anyhow::Result<()> {
         commands::delete::handle(args).map(|_| ())
     } else if let Some(args) = matches.subcommand_matches("download") {
         commands::download::handle(args).map(|_| ())
+    } else if let Some(args) = matches.subcommand_matches("eval") {
+        commands::eval::handle(args).map(|_| ())
     } else if matches.subcommand_matches("models").is_some() {
         commands::models::handle(false).map(|_| ())
     } else if let Some(args) = matches.subcommand_matches("new") {
         commands::new::handle(args).map(|_| ())
+    } else if let Some(args) = matches.subcommand_matches("quantize") {
+        commands::quantize::handle(args).map(|_| ())
     } else if let Some(args) = matches.subcommand_matches("run") {
         commands::run::handle(args).map(|_| ())
     } else if let Some(args) = matches.subcommand_matches("server") {
         commands::server::handle(args, backend, dtype).map(|_| ())
     } else if matches.subcommand_matches("shell").is_some() {
         commands::shell::handle(backend, dtype).map(|_| ())
+    } else if let Some(args) = matches.subcommand_matches("tokenize") {
+        commands::tokenize::handle(args).map(|_| ())
+    } else if let Some(args) = matches.subcommand_matches("train") {
+        commands::train::handle(args).map(|_| ())
     } else if let Some(args) = matches.subcommand_matches("web") {
         commands::web::handle(args, backend, dtype).map(|_| ())
     } else {
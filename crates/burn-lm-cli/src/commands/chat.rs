@@ -85,7 +85,8 @@ pub(crate) fn create() -> clap::Command {
     for (_name, plugin) in installed {
         let subcommand = clap::Command::new(plugin.model_cli_param_name())
             .about(format!("Chat with {} model", plugin.model_name()))
-            .args((plugin.create_cli_flags_fn())().get_arguments());
+            .args((plugin.create_cli_flags_fn())().get_arguments())
+            .args(super::device_args());
         root = root.subcommand(subcommand);
     }
     root
@@ -125,6 +126,7 @@ pub(crate) fn handle(
         None => panic!("Model {plugin_name} not available, did you forget to download it first?"),
     };
     plugin.parse_cli_config(plugin_args);
+    super::set_device(plugin.as_ref(), plugin_args)?;
 
     // load the model
     let mut spin_msg = super::SpinningMessage::new(
@@ -1,8 +1,27 @@
-use burn_lm_inference::{InferenceError, InferenceResult, Stats};
-use burn_lm_registry::Registry;
+use std::sync::{Arc, Mutex};
+
+use burn_lm_inference::{
+    download::{set_download_listener, DownloadEvent},
+    InferenceError, InferenceResult, Stats,
+};
+use burn_lm_registry::{download_hub_model, Registry, HUB_PREFIX};
+
+use super::SpinningMessage;
 
 pub(crate) fn create() -> clap::Command {
-    let mut root = clap::Command::new("download").about("Download models");
+    let mut root = clap::Command::new("download")
+        .about("Download models")
+        .long_about(
+            "Download models. The MessagePack weights of the pre-trained Llama models are \
+             converted to safetensors, which replace them in the cache and are memory-mapped \
+             when loading the models.",
+        )
+        .after_help(format!(
+            "Any Llama model of the Hugging Face Hub can be downloaded with \
+             '{HUB_PREFIX}<org>/<repo>[@revision]'."
+        ))
+        // The hub models are given as external subcommands
+        .allow_external_subcommands(true);
     let registry = Registry::new();
     // Download all
     let subcommand = clap::Command::new("all").about("Download all downloadable models");
@@ -26,29 +45,50 @@ pub(crate) fn create() -> clap::Command {
     root
 }
 
+type Downloader = Box<dyn Fn() -> InferenceResult<Option<Stats>>>;
+
 pub(crate) fn handle(args: &clap::ArgMatches) -> super::HandleCommandResult {
     let registry = Registry::new();
-    let downloaders = match args.subcommand_name() {
+    let downloaders: Vec<(String, Downloader)> = match args.subcommand_name() {
         Some("all") => {
-            #[allow(clippy::type_complexity)]
-            let mut candidates: Vec<(String, fn() -> InferenceResult<Option<Stats>>)> = registry
+            let mut candidates: Vec<(String, Downloader)> = registry
                 .get()
                 .iter()
-                .filter(|(_, plugin)| plugin.downloader().is_some())
-                .map(|(name, plugin)| (name.to_string(), plugin.downloader().unwrap()))
+                .filter_map(|(name, plugin)| {
+                    let dl = plugin.downloader()?;
+                    let settings = plugin.device_settings();
+                    Some((
+                        name.to_string(),
+                        Box::new(move || dl(settings)) as Downloader,
+                    ))
+                })
                 .collect();
             candidates.sort_by(|(name1, ..), (name2, ..)| name1.cmp(name2));
             candidates
         }
+        Some(spec) if spec.starts_with(HUB_PREFIX) => {
+            let hub_spec = spec.to_string();
+            vec![(
+                spec.to_string(),
+                Box::new(move || download_hub_model(&hub_spec)),
+            )]
+        }
         Some(model) => {
             let (name, plugin) = registry
                 .get()
                 .iter()
                 .find(|(_, p)| p.model_cli_param_name() == model)
-                .expect("Plugin should be registered");
+                // Unknown names are accepted as external subcommands
+                .ok_or_else(|| anyhow::anyhow!("Unknown model '{model}'"))?;
             let downloader = plugin.downloader();
             match downloader {
-                Some(dl) => vec![(name.to_string(), dl)],
+                Some(dl) => {
+                    let settings = plugin.device_settings();
+                    vec![(
+                        name.to_string(),
+                        Box::new(move || dl(settings)) as Downloader,
+                    )]
+                }
                 None => anyhow::bail!(InferenceError::PluginDownloadUnsupportedError(
                     model.to_string()
                 )),
@@ -64,16 +104,81 @@ pub(crate) fn handle(args: &clap::ArgMatches) -> super::HandleCommandResult {
     // be awful if a large number of models are registered. Moerover currently the llama
     // models use the Burn downloader. The Burn downloader uses a progress bar that does
     // not support MultiProgress of indicatif crate.
+    let spinner = Arc::new(Mutex::new(None));
+    let listener_spinner = spinner.clone();
+    set_download_listener(Some(Box::new(move |event| {
+        display_progress(&mut listener_spinner.lock().unwrap(), event)
+    })));
     for (i, (name, dl)) in downloaders.iter().enumerate() {
         println!(
             "[{}/{}] Downloading model: {name}\nPlease wait...",
             i + 1,
             downloaders.len()
         );
-        if let Err(err) = dl() {
+        let result = dl();
+        // Clear the spinner of an interrupted download
+        if let Some(mut spinner) = spinner.lock().unwrap().take() {
+            spinner.end(true);
+        }
+        if let Err(err) = result {
             eprintln!("Download error: {err}");
         }
         println!("✅ Download complete!");
     }
+    set_download_listener(None);
     Ok(Some(super::ShellMetaAction::RefreshParser))
 }
+
+/// Display the progress of the file being downloaded with a spinner.
+fn display_progress(spinner: &mut Option<SpinningMessage>, event: &DownloadEvent) {
+    match event {
+        DownloadEvent::Started {
+            file,
+            resumed_from,
+            total,
+        } => {
+            if let Some(mut previous) = spinner.take() {
+                previous.end(true);
+            }
+            let msg = progress_message(file, *resumed_from, *total);
+            *spinner = Some(SpinningMessage::new(&msg, &format!("Downloaded {file}")));
+        }
+        DownloadEvent::Progress {
+            file,
+            downloaded,
+            total,
+        } => {
+            if let Some(spinner) = spinner.as_mut() {
+                spinner.update(&progress_message(file, *downloaded, *total));
+            }
+        }
+        DownloadEvent::Finished { .. } => {
+            if let Some(mut spinner) = spinner.take() {
+                spinner.end(false);
+            }
+        }
+    }
+}
+
+fn progress_message(file: &str, downloaded: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) if total > 0 => format!(
+            "Downloading {file}: {} / {} ({:.0}%)",
+            format_bytes(downloaded),
+            format_bytes(total),
+            downloaded as f64 * 100.0 / total as f64
+        ),
+        _ => format!("Downloading {file}: {}", format_bytes(downloaded)),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
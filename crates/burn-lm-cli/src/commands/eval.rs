@@ -0,0 +1,255 @@
+use std::path::PathBuf;
+
+use burn_lm_inference::{
+    InferenceError, InferencePlugin, MultipleChoiceArgs, MultipleChoiceFields, MultipleChoiceTask,
+    Normalization, PerplexityArgs, NORMALIZATIONS,
+};
+use burn_lm_registry::Registry;
+
+pub(crate) fn create() -> clap::Command {
+    clap::Command::new("eval")
+        .about("Evaluate a downloaded model")
+        .subcommand(create_perplexity())
+        .subcommand(create_multiple_choice())
+}
+
+fn create_perplexity() -> clap::Command {
+    let root = clap::Command::new("perplexity")
+        .about("Compute the perplexity and bits-per-byte of a model on a text file");
+    add_model_subcommands(root, |subcommand| {
+        subcommand
+            .arg(
+                clap::Arg::new("file")
+                    .long("file")
+                    .help("The text file to evaluate")
+                    .required(true)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                clap::Arg::new("stride")
+                    .long("stride")
+                    .help("Number of tokens between the starts of two evaluation windows, smaller strides give more context to the predictions")
+                    .default_value("512")
+                    .value_parser(clap::value_parser!(usize)),
+            )
+    })
+}
+
+fn create_multiple_choice() -> clap::Command {
+    let root = clap::Command::new("multiple-choice")
+        .about("Compute the accuracy of a model on multiple-choice tasks scored by log-likelihood");
+    let fields = MultipleChoiceFields::default();
+    add_model_subcommands(root, |subcommand| {
+        subcommand
+            .arg(
+                clap::Arg::new("file")
+                    .long("file")
+                    .help("The JSON lines (.jsonl) file with one task per line")
+                    .required(true)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                clap::Arg::new("num-fewshot")
+                    .long("num-fewshot")
+                    .help("Number of solved tasks from the start of the file prepended to each query as examples")
+                    .default_value("0")
+                    .value_parser(clap::value_parser!(usize)),
+            )
+            .arg(
+                clap::Arg::new("normalization")
+                    .long("normalization")
+                    .help(format!(
+                        "The length normalization of the choices log-likelihood, one of: {}",
+                        NORMALIZATIONS.join(", ")
+                    ))
+                    .default_value("bytes")
+                    .value_parser(|s: &str| s.parse::<Normalization>()),
+            )
+            .arg(
+                clap::Arg::new("limit")
+                    .long("limit")
+                    .help("Maximum number of evaluated tasks")
+                    .value_parser(clap::value_parser!(usize)),
+            )
+            .arg(
+                clap::Arg::new("query-field")
+                    .long("query-field")
+                    .help("Field holding the question or the context to complete")
+                    .default_value(fields.query.clone()),
+            )
+            .arg(
+                clap::Arg::new("choices-field")
+                    .long("choices-field")
+                    .help("Field holding the list of candidate answers")
+                    .default_value(fields.choices.clone()),
+            )
+            .arg(
+                clap::Arg::new("answer-field")
+                    .long("answer-field")
+                    .help("Field holding the index or the letter of the correct answer")
+                    .default_value(fields.answer.clone()),
+            )
+    })
+}
+
+/// Add a subcommand for each downloaded model with its associated flags.
+fn add_model_subcommands(
+    mut root: clap::Command,
+    args: impl Fn(clap::Command) -> clap::Command,
+) -> clap::Command {
+    let registry = Registry::new();
+    let mut installed: Vec<_> = registry
+        .get()
+        .iter()
+        .filter(|(_name, plugin)| plugin.is_downloaded())
+        .collect();
+    installed.sort_by_key(|(key, ..)| *key);
+    for (_name, plugin) in installed {
+        let subcommand = clap::Command::new(plugin.model_cli_param_name())
+            .about(format!("Evaluate model '{}'", plugin.model_name()))
+            .args((plugin.create_cli_flags_fn())().get_arguments());
+        root = root.subcommand(args(subcommand));
+    }
+    root
+}
+
+pub(crate) fn handle(args: &clap::ArgMatches) -> super::HandleCommandResult {
+    match args.subcommand() {
+        Some(("perplexity", args)) => handle_perplexity(args),
+        Some(("multiple-choice", args)) => handle_multiple_choice(args),
+        _ => {
+            create().print_help().unwrap();
+            Ok(None)
+        }
+    }
+}
+
+fn handle_perplexity(args: &clap::ArgMatches) -> super::HandleCommandResult {
+    let (model, eval_args) = match args.subcommand() {
+        Some(subcommand) => subcommand,
+        None => {
+            create_perplexity().print_help().unwrap();
+            return Ok(None);
+        }
+    };
+    let file = eval_args
+        .get_one::<PathBuf>("file")
+        .expect("The file argument should be set.");
+    let stride = *eval_args
+        .get_one::<usize>("stride")
+        .expect("The stride argument should be set.");
+    let text = std::fs::read_to_string(file)
+        .map_err(|err| anyhow::anyhow!("Cannot read file '{}': {err}", file.display()))?;
+
+    let registry = Registry::new();
+    let plugin = find_plugin(&registry, model, eval_args);
+
+    let mut spin_msg = super::SpinningMessage::new(
+        &format!(
+            "evaluating perplexity of model '{}'...",
+            plugin.model_name()
+        ),
+        "model evaluated!",
+    );
+    let result = plugin.perplexity(PerplexityArgs { text, stride });
+    let _ = plugin.unload();
+    match result {
+        Ok(stats) => {
+            spin_msg.end(false);
+            crate::utils::display_stats(&stats);
+            Ok(None)
+        }
+        Err(err) => {
+            spin_msg.end(true);
+            evaluation_error(model, err)
+        }
+    }
+}
+
+fn handle_multiple_choice(args: &clap::ArgMatches) -> super::HandleCommandResult {
+    let (model, eval_args) = match args.subcommand() {
+        Some(subcommand) => subcommand,
+        None => {
+            create_multiple_choice().print_help().unwrap();
+            return Ok(None);
+        }
+    };
+    let file = eval_args
+        .get_one::<PathBuf>("file")
+        .expect("The file argument should be set.");
+    let field = |name: &str| {
+        eval_args
+            .get_one::<String>(name)
+            .expect("The field argument should be set.")
+            .clone()
+    };
+    let fields = MultipleChoiceFields {
+        query: field("query-field"),
+        choices: field("choices-field"),
+        answer: field("answer-field"),
+    };
+    let mc_args = MultipleChoiceArgs {
+        normalization: *eval_args
+            .get_one::<Normalization>("normalization")
+            .expect("The normalization argument should be set."),
+        num_fewshot: *eval_args
+            .get_one::<usize>("num-fewshot")
+            .expect("The num-fewshot argument should be set."),
+        ..Default::default()
+    };
+    let content = std::fs::read_to_string(file)
+        .map_err(|err| anyhow::anyhow!("Cannot read file '{}': {err}", file.display()))?;
+    let mut tasks = MultipleChoiceTask::parse_json_lines(&content, &fields)
+        .map_err(|err| anyhow::anyhow!("Invalid task file '{}': {err}", file.display()))?;
+    if let Some(limit) = eval_args.get_one::<usize>("limit") {
+        tasks.truncate(mc_args.num_fewshot + limit);
+    }
+
+    let registry = Registry::new();
+    let plugin = find_plugin(&registry, model, eval_args);
+
+    let mut spin_msg = super::SpinningMessage::new(
+        &format!(
+            "evaluating model '{}' on {} tasks...",
+            plugin.model_name(),
+            tasks.len().saturating_sub(mc_args.num_fewshot)
+        ),
+        "model evaluated!",
+    );
+    let result = mc_args.evaluate(&tasks, |requests| plugin.loglikelihood(requests));
+    let _ = plugin.unload();
+    match result {
+        Ok(output) => {
+            spin_msg.end(false);
+            crate::utils::display_stats(&output.stats(&mc_args));
+            Ok(None)
+        }
+        Err(err) => {
+            spin_msg.end(true);
+            evaluation_error(model, err)
+        }
+    }
+}
+
+fn find_plugin<'a>(
+    registry: &'a Registry,
+    model: &str,
+    args: &clap::ArgMatches,
+) -> &'a dyn InferencePlugin {
+    let (_, plugin) = registry
+        .get()
+        .iter()
+        .find(|(_, p)| p.model_cli_param_name() == model)
+        .expect("Plugin should be registered");
+    plugin.parse_cli_config(args);
+    plugin.as_ref()
+}
+
+fn evaluation_error(model: &str, err: InferenceError) -> super::HandleCommandResult {
+    match err {
+        InferenceError::PluginEvaluationUnsupportedError => {
+            anyhow::bail!("The model '{model}' does not support evaluation.")
+        }
+        err => anyhow::bail!("Evaluation error: {err}"),
+    }
+}
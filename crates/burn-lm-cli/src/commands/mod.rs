@@ -4,11 +4,15 @@ pub(crate) mod chat;
 pub(crate) mod delete;
 pub(crate) mod download;
 pub(crate) mod dtype;
+pub(crate) mod eval;
 pub(crate) mod models;
 pub(crate) mod new;
+pub(crate) mod quantize;
 pub(crate) mod run;
 pub(crate) mod server;
 pub(crate) mod shell;
+pub(crate) mod tokenize;
+pub(crate) mod train;
 pub(crate) mod web;
 
 const ANSI_CODE_DELETE_LINE: &str = "\r\x1b[K";
@@ -27,7 +31,57 @@ pub(crate) type HandleCommandResult = anyhow::Result<Option<ShellMetaAction>>;
 
 use std::io::{stdout, Write};
 
+use burn_lm_inference::{BackendKind, FloatDtype, InferencePlugin};
 use yansi::Paint;
+
+/// Flags selecting the backend, device and float type of a model, see [set_device].
+pub(crate) fn device_args() -> [clap::Arg; 3] {
+    let backends: Vec<_> = BackendKind::available()
+        .iter()
+        .map(BackendKind::name)
+        .collect();
+    [
+        clap::Arg::new("backend")
+            .help("The backend of the model, among the backends of the build")
+            .long("backend")
+            .value_parser(clap::builder::PossibleValuesParser::new(backends))
+            .required(false),
+        clap::Arg::new("device-index")
+            .help("The device index of the model, the default device of the backend if omitted")
+            .long("device-index")
+            .value_parser(clap::value_parser!(usize))
+            .required(false),
+        clap::Arg::new("dtype")
+            .help("The float type of the model")
+            .long("dtype")
+            .value_parser(["f32", "f16", "bf16"])
+            .required(false),
+    ]
+}
+
+/// Select the device of the plugin with the flags of [device_args], the flags which are not
+/// given keep the current settings of the plugin, except for a float type the selected backend
+/// doesn't support.
+pub(crate) fn set_device(
+    plugin: &dyn InferencePlugin,
+    args: &clap::ArgMatches,
+) -> anyhow::Result<()> {
+    let current = plugin.device_settings();
+    let mut settings = match args.get_one::<String>("backend") {
+        Some(backend) => current.with_backend(backend.parse().map_err(anyhow::Error::msg)?),
+        None => current,
+    };
+    if let Some(index) = args.get_one::<usize>("device-index") {
+        settings.index = Some(*index);
+    }
+    if let Some(dtype) = args.get_one::<String>("dtype") {
+        settings.dtype = dtype.parse::<FloatDtype>().map_err(anyhow::Error::msg)?;
+    }
+    if settings != current {
+        plugin.set_device(settings)?;
+    }
+    Ok(())
+}
 /// Rustyline custom line editor helper
 /// Principal aim for this is to provide a way to stylize the prompt.
 #[derive(
@@ -78,6 +132,15 @@ impl SpinningMessage {
         }
     }
 
+    /// Replace the message displayed next to the spinner.
+    pub fn update(&mut self, msg: &str) {
+        self.spinner.stop();
+        print!("{ANSI_CODE_DELETE_LINE}");
+        stdout().flush().unwrap();
+        self.spinner =
+            spinners::Spinner::new(spinners::Spinners::Bounce, msg.bright_black().to_string());
+    }
+
     /// Stop the spinner and replace the line with the end message.
     /// If delete is true then delete the spinner line altogether.
     pub fn end(&mut self, delete: bool) {
@@ -0,0 +1,78 @@
+use burn_lm_inference::{InferenceError, QuantizationScheme, QUANTIZATION_SCHEMES};
+use burn_lm_registry::Registry;
+
+pub(crate) fn create() -> clap::Command {
+    let mut root = clap::Command::new("quantize")
+        .about("Quantize a downloaded model and save it as a new model variant");
+    let registry = Registry::new();
+    // Create a a subcommand for each downloaded model that can be quantized
+    let mut reg_entries: Vec<_> = registry
+        .get()
+        .iter()
+        .filter(|(_, plugin)| plugin.quantizer().is_some() && plugin.is_downloaded())
+        .collect();
+    reg_entries.sort_by_key(|(key, ..)| *key);
+    for (_name, plugin) in reg_entries {
+        let subcommand = clap::Command::new(plugin.model_cli_param_name())
+            .about(format!("Quantize model '{}'", plugin.model_name()))
+            .arg(
+                clap::Arg::new("scheme")
+                    .long("scheme")
+                    .help(format!(
+                        "The quantization scheme, one of: {}",
+                        QUANTIZATION_SCHEMES.join(", ")
+                    ))
+                    .default_value("q4-block32")
+                    .value_parser(|s: &str| s.parse::<QuantizationScheme>()),
+            )
+            .args(super::device_args());
+        root = root.subcommand(subcommand);
+    }
+    root
+}
+
+pub(crate) fn handle(args: &clap::ArgMatches) -> super::HandleCommandResult {
+    let model = match args.subcommand_name() {
+        Some(model) => model,
+        None => {
+            create().print_help().unwrap();
+            return Ok(None);
+        }
+    };
+    let quantize_args = args.subcommand_matches(model).unwrap();
+    let scheme = *quantize_args
+        .get_one::<QuantizationScheme>("scheme")
+        .expect("The scheme argument should be set.");
+    let registry = Registry::new();
+    let (name, plugin) = registry
+        .get()
+        .iter()
+        .find(|(_, p)| p.model_cli_param_name() == model)
+        .expect("Plugin should be registered");
+    super::set_device(plugin.as_ref(), quantize_args)?;
+    let quantizer = match plugin.quantizer() {
+        Some(quantizer) => quantizer,
+        None => anyhow::bail!(InferenceError::PluginQuantizationUnsupportedError(
+            model.to_string()
+        )),
+    };
+
+    let mut spin_msg = super::SpinningMessage::new(
+        &format!("quantizing model '{name}' with scheme '{scheme}'..."),
+        "model quantized!",
+    );
+    match quantizer(plugin.device_settings(), scheme) {
+        Ok(stats) => {
+            spin_msg.end(false);
+            if let Some(stats) = stats {
+                crate::utils::display_stats(&stats);
+            }
+            println!("✅ Quantized model variant is available with 'run {model}-{scheme}'");
+            Ok(Some(super::ShellMetaAction::RefreshParser))
+        }
+        Err(err) => {
+            spin_msg.end(true);
+            anyhow::bail!("Quantization error: {err}")
+        }
+    }
+}
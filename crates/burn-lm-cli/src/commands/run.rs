@@ -18,6 +18,7 @@ pub(crate) fn create() -> clap::Command {
         let subcommand = clap::Command::new(plugin.model_cli_param_name())
             .about(format!("Use {} model", plugin.model_name()))
             .args((plugin.create_cli_flags_fn())().get_arguments())
+            .args(super::device_args())
             .arg(
                 clap::Arg::new("no-stats")
                     .help("Disable display of statistics at the end of the inference")
@@ -57,6 +58,7 @@ fn run(plugin_name: &str, run_args: &clap::ArgMatches) -> super::HandleCommandRe
         .map(|(_, plugin)| plugin);
     let plugin = plugin.unwrap_or_else(|| panic!("Plugin should be registered: {plugin_name}"));
     plugin.parse_cli_config(run_args);
+    super::set_device(plugin.as_ref(), run_args)?;
 
     // load the model
     let mut spin_msg = super::SpinningMessage::new(
@@ -11,6 +11,12 @@ pub(crate) fn create() -> clap::Command {
                 .default_value("3001")
                 .required(false)
                 .help("The listening port for the server"),
+        )
+        .arg(
+            clap::Arg::new("workers")
+                .long("workers")
+                .action(clap::ArgAction::SetTrue)
+                .help("Run each model in a worker process which is restarted if it crashes"),
         );
     root = root.subcommand(run);
     root
@@ -39,6 +45,7 @@ fn run(args: &clap::ArgMatches, backend: &str, dtype: &str) -> super::HandleComm
     let run_args = args.subcommand_matches("run").unwrap();
     let port = run_args.get_one::<u16>("port").unwrap();
     let port_string = port.to_string();
+    let workers = run_args.get_flag("workers");
     let inference_feature = format!("burn-lm-inference/{backend},burn-lm-inference/{dtype}");
     let common_args = vec![
         "--release",
@@ -56,6 +63,9 @@ fn run(args: &clap::ArgMatches, backend: &str, dtype: &str) -> super::HandleComm
     let mut run_args = vec!["run"];
     run_args.extend(common_args.clone());
     run_args.extend(vec!["--", "run", "--port", &port_string]);
+    if workers {
+        run_args.push("--workers");
+    }
     let run_args = run_args.join(" ").to_string();
     watch_args.push(&run_args);
     let mut spin_msg =
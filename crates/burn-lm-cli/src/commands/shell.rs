@@ -38,10 +38,14 @@ fn create_parser() -> clap::Command {
         .subcommand(super::delete::create())
         .subcommand(super::dtype::create())
         .subcommand(super::download::create())
+        .subcommand(super::eval::create())
         .subcommand(super::models::create())
         .subcommand(super::new::create())
+        .subcommand(super::quantize::create())
         .subcommand(super::run::create())
         .subcommand(super::server::create())
+        .subcommand(super::tokenize::create())
+        .subcommand(super::train::create())
         .subcommand(super::web::create())
         .multicall(true)
 }
@@ -113,6 +117,8 @@ pub(crate) fn handle(backend: &str, dtype: &str) -> anyhow::Result<()> {
                 let new_dtype = new_dtype.clone().unwrap_or(dtype.to_string());
                 if backend == new_backend && new_dtype == dtype {
                     println!("Backend {new_backend} already selected...");
+                } else if !crate::backends::supports_dtype(new_backend, &new_dtype) {
+                    println!("Backend {new_backend} does not support the {new_dtype} data type...");
                 } else {
                     println!("Reinitializing backend...");
                     let config = BurnLmConfig::new(new_backend.clone(), new_dtype);
@@ -123,6 +129,8 @@ pub(crate) fn handle(backend: &str, dtype: &str) -> anyhow::Result<()> {
             ShellMetaAction::ChangeDtype(new_dtype) => {
                 if dtype == new_dtype {
                     println!("Data type {new_dtype} already selected...");
+                } else if !crate::backends::supports_dtype(backend, new_dtype) {
+                    println!("Backend {backend} does not support the {new_dtype} data type...");
                 } else {
                     println!("Reinitializing backend dtype...");
                     let config = BurnLmConfig::new(backend.to_string(), new_dtype.clone());
@@ -146,14 +154,22 @@ pub(crate) fn handle(backend: &str, dtype: &str) -> anyhow::Result<()> {
                 super::delete::handle(args)?
             } else if let Some(args) = args.subcommand_matches("download") {
                 super::download::handle(args)?
+            } else if let Some(args) = args.subcommand_matches("eval") {
+                super::eval::handle(args)?
             } else if args.subcommand_matches("models").is_some() {
                 super::models::handle(true)?
             } else if let Some(args) = args.subcommand_matches("new") {
                 super::new::handle(args)?
+            } else if let Some(args) = args.subcommand_matches("quantize") {
+                super::quantize::handle(args)?
             } else if let Some(args) = args.subcommand_matches("run") {
                 super::run::handle(args)?
             } else if let Some(args) = args.subcommand_matches("server") {
                 super::server::handle(args, backend, dtype)?
+            } else if let Some(args) = args.subcommand_matches("tokenize") {
+                super::tokenize::handle(args)?
+            } else if let Some(args) = args.subcommand_matches("train") {
+                super::train::handle(args)?
             } else if let Some(args) = args.subcommand_matches("web") {
                 super::web::handle(args, backend, dtype)?
             } else {
@@ -0,0 +1,71 @@
+use burn_lm_inference::{message::MessageRole, InferenceTask, Message};
+use burn_lm_registry::Registry;
+use yansi::Paint;
+
+pub(crate) fn create() -> clap::Command {
+    let mut root = clap::Command::new("tokenize")
+        .about("Tokenize a prompt with the tokenizer and chat template of chosen model");
+    let registry = Registry::new();
+    // The model weights are not needed, only the tokenizer
+    let mut plugins: Vec<_> = registry.get().iter().collect();
+    plugins.sort_by_key(|(key, ..)| *key);
+    for (_name, plugin) in plugins {
+        let subcommand = clap::Command::new(plugin.model_cli_param_name())
+            .about(format!("Use {} tokenizer", plugin.model_name()))
+            .arg(
+                clap::Arg::new("raw")
+                    .help("Tokenize the prompt as is, without the chat template")
+                    .long("raw")
+                    .action(clap::ArgAction::SetTrue)
+                    .required(false),
+            )
+            .arg(
+                clap::Arg::new("prompt")
+                    .help("The prompt to tokenize")
+                    .required(true)
+                    .index(1),
+            );
+        root = root.subcommand(subcommand);
+    }
+    root
+}
+
+pub(crate) fn handle(args: &clap::ArgMatches) -> super::HandleCommandResult {
+    let plugin_name = match args.subcommand_name() {
+        Some(cmd) => cmd,
+        None => {
+            create().print_help().unwrap();
+            return Ok(None);
+        }
+    };
+    let tokenize_args = args.subcommand_matches(plugin_name).unwrap();
+    let registry = Registry::new();
+    let plugin = registry
+        .get()
+        .iter()
+        .find(|(_, p)| p.model_cli_param_name() == plugin_name.to_lowercase())
+        .map(|(_, plugin)| plugin)
+        .unwrap_or_else(|| panic!("Plugin should be registered: {plugin_name}"));
+
+    let prompt = tokenize_args
+        .get_one::<String>("prompt")
+        .expect("The prompt argument should be set.");
+    let task = if tokenize_args.get_flag("raw") {
+        InferenceTask::Prompt(prompt.clone())
+    } else {
+        InferenceTask::Message(Message {
+            role: MessageRole::User,
+            content: prompt.clone(),
+            refusal: None,
+        })
+    };
+    match plugin.tokenize(task) {
+        Ok(tokenization) => {
+            println!("{}", tokenization.prompt.bright_black());
+            println!("{:?}", tokenization.tokens);
+            println!("{} tokens", tokenization.tokens.len());
+            Ok(None)
+        }
+        Err(err) => anyhow::bail!("An error occurred: {err}"),
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use burn_lm_inference::{
+    InferenceError, LrSchedule, QuantizationScheme, TrainingArgs, LR_SCHEDULES,
+    QUANTIZATION_SCHEMES,
+};
+use burn_lm_registry::Registry;
+
+pub(crate) fn create() -> clap::Command {
+    let mut root = clap::Command::new("train")
+        .about("Train a downloaded model on a dataset and save it as a new model variant");
+    let registry = Registry::new();
+    let defaults = TrainingArgs::default();
+    // Create a a subcommand for each downloaded model that can be trained
+    let mut reg_entries: Vec<_> = registry
+        .get()
+        .iter()
+        .filter(|(_, plugin)| plugin.trainer().is_some() && plugin.is_downloaded())
+        .collect();
+    reg_entries.sort_by_key(|(key, ..)| *key);
+    for (_name, plugin) in reg_entries {
+        let subcommand = clap::Command::new(plugin.model_cli_param_name())
+            .about(format!("Train model '{}'", plugin.model_name()))
+            .args([
+                clap::Arg::new("dataset")
+                    .help("The text (.txt) or JSON lines (.jsonl) dataset file")
+                    .required(true)
+                    .value_parser(clap::value_parser!(PathBuf)),
+                clap::Arg::new("name")
+                    .long("name")
+                    .help("The name of the trained model variant")
+                    .default_value(defaults.name.clone()),
+                clap::Arg::new("text-field")
+                    .long("text-field")
+                    .help("The field holding the document text in JSON lines datasets")
+                    .default_value(defaults.text_field.clone()),
+                clap::Arg::new("chat")
+                    .long("chat")
+                    .help("Fine-tune on a JSON lines dataset of chat conversations, learning only the assistant answers")
+                    .action(clap::ArgAction::SetTrue),
+                clap::Arg::new("messages-field")
+                    .long("messages-field")
+                    .help("The field holding the conversation messages in chat datasets")
+                    .default_value(defaults.messages_field.clone()),
+                clap::Arg::new("seq-len")
+                    .long("seq-len")
+                    .help("The number of tokens per training sequence")
+                    .default_value(defaults.seq_len.to_string())
+                    .value_parser(clap::value_parser!(usize)),
+                clap::Arg::new("packing")
+                    .long("packing")
+                    .help("Pack multiple documents per training sequence instead of padding each document")
+                    .action(clap::ArgAction::SetTrue),
+                clap::Arg::new("batch-size")
+                    .long("batch-size")
+                    .default_value(defaults.batch_size.to_string())
+                    .value_parser(clap::value_parser!(usize)),
+                clap::Arg::new("epochs")
+                    .long("epochs")
+                    .default_value(defaults.num_epochs.to_string())
+                    .value_parser(clap::value_parser!(usize)),
+                clap::Arg::new("lr")
+                    .long("lr")
+                    .help("The peak learning rate")
+                    .default_value(defaults.learning_rate.to_string())
+                    .value_parser(clap::value_parser!(f64)),
+                clap::Arg::new("lr-schedule")
+                    .long("lr-schedule")
+                    .help(format!(
+                        "The learning rate schedule, one of: {}",
+                        LR_SCHEDULES.join(", ")
+                    ))
+                    .default_value(defaults.lr_schedule.to_string())
+                    .value_parser(|s: &str| s.parse::<LrSchedule>()),
+                clap::Arg::new("warmup-steps")
+                    .long("warmup-steps")
+                    .help("The number of optimizer steps to warm up the learning rate")
+                    .default_value(defaults.warmup_steps.to_string())
+                    .value_parser(clap::value_parser!(usize)),
+                clap::Arg::new("grad-accumulation")
+                    .long("grad-accumulation")
+                    .help("The number of batches accumulated per optimizer step")
+                    .default_value(defaults.grad_accumulation.to_string())
+                    .value_parser(clap::value_parser!(usize)),
+                clap::Arg::new("validation-ratio")
+                    .long("validation-ratio")
+                    .help("The fraction of the dataset held out for validation")
+                    .default_value(defaults.validation_ratio.to_string())
+                    .value_parser(clap::value_parser!(f64)),
+                clap::Arg::new("seed")
+                    .long("seed")
+                    .default_value(defaults.seed.to_string())
+                    .value_parser(clap::value_parser!(u64)),
+                clap::Arg::new("resume")
+                    .long("resume")
+                    .help("Resume the training from the last saved checkpoint")
+                    .action(clap::ArgAction::SetTrue),
+                clap::Arg::new("lora-rank")
+                    .long("lora-rank")
+                    .help("Train a LoRA adapter of the given rank instead of all the weights")
+                    .value_parser(clap::value_parser!(usize)),
+                clap::Arg::new("lora-alpha")
+                    .long("lora-alpha")
+                    .help("The scaling numerator of the LoRA adapter")
+                    .default_value(defaults.lora_alpha.to_string())
+                    .value_parser(clap::value_parser!(f64)),
+                clap::Arg::new("qlora")
+                    .long("qlora")
+                    .help(format!(
+                        "Quantize the frozen weights when training a LoRA adapter, one of: {}",
+                        QUANTIZATION_SCHEMES.join(", ")
+                    ))
+                    .requires("lora-rank")
+                    .value_parser(|s: &str| s.parse::<QuantizationScheme>()),
+            ])
+            .args(super::device_args());
+        root = root.subcommand(subcommand);
+    }
+    root
+}
+
+fn parse_args(args: &clap::ArgMatches) -> TrainingArgs {
+    TrainingArgs {
+        dataset: args.get_one::<PathBuf>("dataset").unwrap().clone(),
+        name: args.get_one::<String>("name").unwrap().clone(),
+        text_field: args.get_one::<String>("text-field").unwrap().clone(),
+        chat: args.get_flag("chat"),
+        messages_field: args.get_one::<String>("messages-field").unwrap().clone(),
+        seq_len: *args.get_one::<usize>("seq-len").unwrap(),
+        packing: args.get_flag("packing"),
+        batch_size: *args.get_one::<usize>("batch-size").unwrap(),
+        num_epochs: *args.get_one::<usize>("epochs").unwrap(),
+        learning_rate: *args.get_one::<f64>("lr").unwrap(),
+        lr_schedule: *args.get_one::<LrSchedule>("lr-schedule").unwrap(),
+        warmup_steps: *args.get_one::<usize>("warmup-steps").unwrap(),
+        grad_accumulation: *args.get_one::<usize>("grad-accumulation").unwrap(),
+        validation_ratio: *args.get_one::<f64>("validation-ratio").unwrap(),
+        seed: *args.get_one::<u64>("seed").unwrap(),
+        resume: args.get_flag("resume"),
+        lora_rank: args.get_one::<usize>("lora-rank").copied(),
+        lora_alpha: *args.get_one::<f64>("lora-alpha").unwrap(),
+        qlora: args.get_one::<QuantizationScheme>("qlora").copied(),
+    }
+}
+
+pub(crate) fn handle(args: &clap::ArgMatches) -> super::HandleCommandResult {
+    let model = match args.subcommand_name() {
+        Some(model) => model,
+        None => {
+            create().print_help().unwrap();
+            return Ok(None);
+        }
+    };
+    let train_args = args.subcommand_matches(model).unwrap();
+    let training_args = parse_args(train_args);
+    if let Err(err) = training_args.validate() {
+        anyhow::bail!("Invalid training arguments: {err}");
+    }
+    let registry = Registry::new();
+    let (name, plugin) = registry
+        .get()
+        .iter()
+        .find(|(_, p)| p.model_cli_param_name() == model)
+        .expect("Plugin should be registered");
+    super::set_device(plugin.as_ref(), train_args)?;
+    let trainer = match plugin.trainer() {
+        Some(trainer) => trainer,
+        None => anyhow::bail!(InferenceError::PluginTrainingUnsupportedError(
+            model.to_string()
+        )),
+    };
+
+    // The training renders its own progress and metrics
+    println!(
+        "training model '{name}' on dataset '{}'...",
+        training_args.dataset.display()
+    );
+    let variant = training_args.name.clone();
+    let lora = training_args.lora_rank.is_some();
+    match trainer(plugin.device_settings(), training_args) {
+        Ok(stats) => {
+            if let Some(stats) = stats {
+                crate::utils::display_stats(&stats);
+            }
+            if lora {
+                println!("✅ Trained adapter is available with 'run {model} --adapter {variant}'");
+                Ok(None)
+            } else {
+                println!("✅ Trained model variant is available with 'run {model}-{variant}'");
+                Ok(Some(super::ShellMetaAction::RefreshParser))
+            }
+        }
+        Err(err) => anyhow::bail!("Training error: {err}"),
+    }
+}
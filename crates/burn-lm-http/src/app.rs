@@ -17,8 +17,8 @@ use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
     openapi::ApiDoc,
-    routers::{chat_routers, model_routers},
-    stores::chat_store::ChatStore,
+    routers::{chat_routers, model_routers, tokenize_routers},
+    stores::chat_store::{ChatStore, ModelStoreState},
     trace::{self, Latency},
 };
 
@@ -30,11 +30,15 @@ lazy_static! {
 #[derive(Debug)]
 pub struct App {
     port: u16,
+    workers: bool,
 }
 
 impl Default for App {
     fn default() -> Self {
-        Self { port: 3000 }
+        Self {
+            port: 3000,
+            workers: false,
+        }
     }
 }
 
@@ -42,20 +46,34 @@ impl App {
     pub fn new(port: u16) -> Self {
         dotenvy::from_filename(".env").ok();
         trace::init();
-        Self { port }
+        Self {
+            port,
+            workers: false,
+        }
+    }
+
+    /// Run each model in a worker process, see [ProcessChannel](burn_lm_inference::ProcessChannel).
+    pub fn with_workers(mut self, workers: bool) -> Self {
+        self.workers = workers;
+        self
     }
 }
 
 impl App {
     /// Define application service (router)
     async fn app(&self) -> Router {
+        Self::router(ChatStore::create_state(self.workers))
+    }
+
+    /// Build the application router serving the models of a store.
+    fn router(model_store: ModelStoreState) -> Router {
         let version_prefix = "/v1";
-        let model_store = ChatStore::create_state();
         let openapi = ApiDoc::openapi();
         let public_routes = Router::new()
             .route("/", get(|| async { "Home" }))
             .merge(chat_routers::public_router(model_store.clone()))
-            .merge(model_routers::public_router(model_store.clone()));
+            .merge(model_routers::public_router(model_store.clone()))
+            .merge(tokenize_routers::public_router(model_store.clone()));
         let router = Router::new().merge(public_routes);
         Router::new()
             .nest(version_prefix, router)
@@ -127,6 +145,14 @@ impl App {
         let listener = TcpListener::bind(addr)
             .await
             .expect("Server should bind to address successfully");
+        self.serve_with_listener(listener).await
+    }
+
+    /// Start the application HTTP server on a bound listener
+    pub async fn serve_with_listener(
+        self,
+        listener: TcpListener,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         // Serve the application
         let app = self.app().await;
         info!("Server started! (press CTRL+C to exit)");
@@ -134,3 +160,332 @@ impl App {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::chat_handlers::REPLY_MARKER;
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, Method, StatusCode},
+    };
+    use burn_lm_inference::{
+        remote::remote_models, CommandFactory, InferenceClient, InferenceJob, InferencePlugin,
+        InferenceTask, Message, MessageRole, ServerConfigParsing, TextGenerationListener,
+    };
+    use burn_lm_registry::{Channel, DynClients, ParrotServer, Registry};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    /// Router of an application serving the Parrot model only.
+    fn parrot_router() -> Router {
+        type S = ParrotServer;
+        parrot_router_named(S::model_name(), S::model_cli_param_name())
+    }
+
+    /// Router of an application serving the Parrot model under the given names.
+    fn parrot_router_named(model_name: &'static str, cli_name: &'static str) -> Router {
+        type S = ParrotServer;
+        let client = InferenceClient::<S, Channel<S>>::new(
+            model_name,
+            cli_name,
+            S::model_creation_date(),
+            S::created_by(),
+            <S as ServerConfigParsing>::Config::command,
+            Channel::<S>::new(),
+        );
+        let mut clients = DynClients::new();
+        clients.insert(model_name, Box::new(client));
+        App::router(ChatStore::create_state_with_registry(
+            Registry::from_clients(clients),
+        ))
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    fn post_json(uri: &str, body: Value) -> Request<Body> {
+        Request::post(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn chat_request(model: &str, stream: bool) -> Value {
+        json!({
+            "model": model,
+            "messages": [
+                {"role": "system", "content": "You are a parrot.", "refusal": null},
+                {"role": "user", "content": "Hello Parrot!", "refusal": null},
+            ],
+            "stream": stream,
+        })
+    }
+
+    /// Send a request to the router without binding a port.
+    async fn send(router: &Router, request: Request<Body>) -> Response {
+        router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    async fn body_json(response: Response) -> Value {
+        serde_json::from_str(&body_text(response).await).unwrap()
+    }
+
+    /// Split a server-sent events body into its `data:` payloads.
+    fn sse_data(body: &str) -> Vec<&str> {
+        assert!(
+            body.ends_with("\n\n"),
+            "events should end with a blank line"
+        );
+        body.trim_end_matches("\n\n")
+            .split("\n\n")
+            .map(|event| {
+                event
+                    .strip_prefix("data: ")
+                    .unwrap_or_else(|| panic!("event should be a data event: {event:?}"))
+            })
+            .collect()
+    }
+
+    /// The delta contents of the streamed chunks, checking the `[DONE]` termination.
+    fn streamed_contents(body: &str) -> Vec<String> {
+        let data = sse_data(body);
+        let (done, chunks) = data.split_last().expect("stream should not be empty");
+        assert_eq!(*done, "[DONE]", "stream should end with [DONE]");
+        let mut ids = vec![];
+        let contents = chunks
+            .iter()
+            .map(|data| {
+                let chunk: Value = serde_json::from_str(data).unwrap();
+                assert_eq!(chunk["object"], "chat.completion.chunk");
+                assert_eq!(chunk["model"], "Parrot");
+                ids.push(chunk["id"].clone());
+                chunk["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        ids.dedup();
+        assert_eq!(ids.len(), 1, "chunks should share the completion id");
+        contents
+    }
+
+    /// The streamed reply, between the reply marker and the stats chunk.
+    fn streamed_reply(contents: &[String]) -> String {
+        let marker = format!("\n{REPLY_MARKER}\n");
+        let start = contents
+            .iter()
+            .position(|content| *content == marker)
+            .expect("stream should contain the reply marker");
+        contents[start + 1..contents.len() - 1].concat()
+    }
+
+    #[tokio::test]
+    async fn list_models_returns_registered_models() {
+        let response = send(&parrot_router(), get("/v1/models")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_json(response).await,
+            json!([{
+                "id": "Parrot",
+                "created": 1738022400,
+                "object": "model",
+                "created_by": "Tracel Technologies Inc.",
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn get_model_returns_model_or_not_found() {
+        let router = parrot_router();
+        let response = send(&router, get("/v1/models/Parrot")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_json(response).await["id"], "Parrot");
+
+        let response = send(&router, get("/v1/models/Unknown")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn chat_completion_echoes_last_message() {
+        let request = post_json("/v1/chat/completions", chat_request("Parrot", false));
+        let response = send(&parrot_router(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[CONTENT_TYPE].to_str().unwrap(),
+            "application/json"
+        );
+        let completion = body_json(response).await;
+        assert_eq!(completion["object"], "chat.completion");
+        assert_eq!(completion["model"], "Parrot");
+        assert!(completion["id"].as_str().unwrap().starts_with("chatcmpl-"));
+        let choices = completion["choices"].as_array().unwrap();
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0]["message"]["role"], "assistant");
+        assert_eq!(choices[0]["message"]["content"], "Hello Parrot!");
+        assert_eq!(choices[0]["finish_reason"], "stop");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn chat_completion_forwards_params_to_model() {
+        let mut request = chat_request("Parrot", false);
+        request["max_tokens"] = json!(5);
+        let response = send(&parrot_router(), post_json("/v1/chat/completions", request)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_json(response).await["choices"][0]["message"]["content"],
+            "Hello Parrot! Hello Parrot! Hello "
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn chat_completion_of_model_name_with_colon() {
+        let router = parrot_router_named("Parrot:8b", "parrot-8b");
+        let request = post_json("/v1/chat/completions", chat_request("Parrot:8b", false));
+        let response = send(&router, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let completion = body_json(response).await;
+        assert_eq!(completion["model"], "Parrot:8b");
+        assert_eq!(
+            completion["choices"][0]["message"]["content"],
+            "Hello Parrot!"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn streaming_chat_completion_frames_chunks_until_done() {
+        let router = parrot_router();
+        let request = post_json("/v1/chat/completions", chat_request("Parrot", true));
+        let response = send(&router, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/event-stream");
+        assert_eq!(response.headers()["cache-control"], "no-cache");
+        let contents = streamed_contents(&body_text(response).await);
+        assert!(
+            contents[0].starts_with("```Burn LM\nloading model 'Parrot'... "),
+            "stream should start with the loading feedback, got {:?}",
+            contents[0]
+        );
+        assert_eq!(streamed_reply(&contents), "Hello Parrot!");
+        assert!(contents.last().unwrap().starts_with("\n\n"));
+
+        // the loaded model is not loaded again
+        let request = post_json("/v1/chat/completions", chat_request("parrot", true));
+        let contents = streamed_contents(&body_text(send(&router, request).await).await);
+        assert_eq!(contents[0], format!("\n{REPLY_MARKER}\n"));
+        assert_eq!(streamed_reply(&contents), "Hello Parrot!");
+    }
+
+    #[tokio::test]
+    async fn chat_completion_of_unknown_model_is_not_found() {
+        let router = parrot_router();
+        for stream in [false, true] {
+            let request = post_json("/v1/chat/completions", chat_request("Unknown", stream));
+            let response = send(&router, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "stream: {stream}");
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_requests_have_client_error_status() {
+        let router = parrot_router();
+        let uri = "/v1/chat/completions";
+
+        let request = Request::post(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from("{"))
+            .unwrap();
+        assert_eq!(
+            send(&router, request).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+
+        let request = post_json(uri, json!({"model": "Parrot"}));
+        assert_eq!(
+            send(&router, request).await.status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let request = Request::post(uri)
+            .body(Body::from(chat_request("Parrot", false).to_string()))
+            .unwrap();
+        assert_eq!(
+            send(&router, request).await.status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            send(&router, request).await.status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+
+        let response = send(&router, get("/v1/unknown")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn request_id_is_propagated_to_response() {
+        let router = parrot_router();
+        for uri in ["/v1/models", "/v1/models/Unknown"] {
+            let request = Request::get(uri)
+                .header(X_REQUEST_ID.clone(), "test-request-id")
+                .body(Body::empty())
+                .unwrap();
+            let response = send(&router, request).await;
+            assert_eq!(response.headers()[X_REQUEST_ID.clone()], "test-request-id");
+        }
+    }
+
+    #[tokio::test]
+    async fn request_id_is_generated_when_missing() {
+        let router = parrot_router();
+        let first = send(&router, get("/v1/models")).await;
+        let second = send(&router, get("/v1/models")).await;
+        let first = first.headers()[X_REQUEST_ID.clone()].to_str().unwrap();
+        let second = second.headers()[X_REQUEST_ID.clone()].to_str().unwrap();
+        assert!(!first.is_empty());
+        assert_ne!(first, second, "each request should have its own id");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn remote_plugin_proxies_parrot_of_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { App::default().serve_with_listener(listener).await.ok() });
+
+        let reply = tokio::task::spawn_blocking(move || {
+            let plugin = remote_models(&url, Some("remote"))
+                .unwrap()
+                .into_iter()
+                .find(|plugin| plugin.model_name() == "remote/Parrot")
+                .expect("the remote server should serve Parrot");
+            assert_eq!(plugin.model_creation_date(), "2025/01/28");
+            plugin.load().unwrap();
+            let task = InferenceTask::Context(vec![Message {
+                role: MessageRole::User,
+                content: "Hello remote Parrot!".to_string(),
+                refusal: None,
+            }]);
+            let (job, handle) = InferenceJob::create(task, TextGenerationListener::default());
+            plugin.run_job(job).unwrap();
+            handle.join()
+        })
+        .await
+        .unwrap();
+        assert_eq!(reply, "Hello remote Parrot!");
+    }
+}
@@ -15,18 +15,23 @@ enum Commands {
         /// Listening port for the server.
         #[arg(short, long, default_value_t = 3000)]
         port: u16,
+        /// Run each model in a worker process, restarted when the model crashes.
+        #[arg(long)]
+        workers: bool,
     },
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Model worker processes are started with the server executable
+    burn_lm_registry::run_worker_if_requested();
     let cli = Cli::parse();
+    let runtime = tokio::runtime::Runtime::new()?;
     match cli.command {
-        Commands::Run { port } => run_server(port).await,
+        Commands::Run { port, workers } => runtime.block_on(run_server(port, workers)),
     }
 }
 
-async fn run_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
-    let app = App::new(port);
+async fn run_server(port: u16, workers: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::new(port).with_workers(workers);
     app.serve().await
 }
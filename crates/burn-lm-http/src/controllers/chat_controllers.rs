@@ -8,5 +8,7 @@ pub trait ChatController {
         &mut self,
         name: &str,
     ) -> ServerResult<(Box<dyn InferencePlugin>, Option<String>)>;
+    /// Find a registered plugin without selecting it as the current plugin.
+    async fn find_plugin(&self, name: &str) -> ServerResult<Box<dyn InferencePlugin>>;
     async fn list_models(&self) -> ServerResult<Vec<ModelSchema>>;
 }
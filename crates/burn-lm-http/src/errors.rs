@@ -14,6 +14,10 @@ pub enum ServerError {
     NotFound,
     #[error("Error loading model (reason: {0})")]
     LoadingError(String),
+    #[error("Unsupported operation (reason: {0})")]
+    Unsupported(String),
+    #[error("Error tokenizing (reason: {0})")]
+    TokenizationError(String),
     #[error("")]
     UserRoleExpected(ChoiceMessageRoleSchema),
 }
@@ -24,6 +28,8 @@ impl IntoResponse for ServerError {
             ServerError::NotFound => handle_not_found_error(),
             ServerError::UserRoleExpected(role) => handle_user_role_expected_error(role),
             ServerError::LoadingError(reason) => handle_loading_model_error(reason),
+            ServerError::Unsupported(reason) => handle_unsupported_error(reason),
+            ServerError::TokenizationError(reason) => handle_tokenization_error(reason),
         }
     }
 }
@@ -44,6 +50,20 @@ fn handle_loading_model_error(reason: String) -> Response {
     (status, msg).into_response()
 }
 
+fn handle_unsupported_error(reason: String) -> Response {
+    let msg = format!("Unsupported operation (reason: {reason}).");
+    tracing::error!("{msg}");
+    let status = StatusCode::NOT_IMPLEMENTED;
+    (status, msg).into_response()
+}
+
+fn handle_tokenization_error(reason: String) -> Response {
+    let msg = format!("Error tokenizing (reason: {reason}).");
+    tracing::error!("{msg}");
+    let status = StatusCode::BAD_REQUEST;
+    (status, msg).into_response()
+}
+
 fn handle_user_role_expected_error(role: ChoiceMessageRoleSchema) -> Response {
     let msg = format!("Role should be 'user' and not '{role}'.");
     tracing::error!("{msg}");
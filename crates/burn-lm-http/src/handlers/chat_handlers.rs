@@ -4,6 +4,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+pub use burn_lm_inference::REPLY_MARKER;
 use burn_lm_inference::{
     InferenceJob, InferenceTask, StatEntry, TextGenerationListener, WriteListener,
 };
@@ -23,8 +24,6 @@ use crate::{
     utils::id::ChatCompletionId,
 };
 
-pub const REPLY_MARKER: &str = "##### Model Reply";
-
 struct SseWriter {
     tx: mpsc::Sender<String>,
     id: String,
@@ -56,9 +55,13 @@ impl Write for SseWriter {
 
 pub async fn chat_completions(
     State(state): State<ModelStoreState>,
-    Json(payload): Json<ChatCompletionRequestSchema>,
+    Json(mut payload): Json<ChatCompletionRequestSchema>,
 ) -> ServerResult<Response> {
     tracing::debug!("Received JSON payload: {:?}", payload);
+    {
+        let store = state.lock().await;
+        payload.split_model_adapter(|name| store.has_model(name));
+    }
     if payload.stream {
         handle_streaming_response(state.clone(), payload).await
     } else {
@@ -109,6 +112,8 @@ async fn handle_streaming_response(
     state: ModelStoreState,
     payload: ChatCompletionRequestSchema,
 ) -> ServerResult<Response> {
+    // reply with an error status before starting the stream of an unknown model
+    state.lock().await.find_plugin(&payload.model).await?;
     let (tx, rx) = mpsc::channel(10);
     tokio::spawn({
         async move {
@@ -331,4 +336,34 @@ mod tests {
             .expect("stream should still be open");
         assert!(first.contains("\"content\":\"first\""));
     }
+
+    #[test]
+    fn model_name_suffix_selects_adapter() {
+        let is_model = |name: &str| name == "Llama 3.2 (1B Instruct)";
+        let mut payload: ChatCompletionRequestSchema = serde_json::from_str(
+            r#"{"model": "Llama 3.2 (1B Instruct):french", "messages": [], "seed": 1}"#,
+        )
+        .unwrap();
+        payload.split_model_adapter(is_model);
+        assert_eq!(payload.model, "Llama 3.2 (1B Instruct)");
+        assert_eq!(
+            serde_json::to_string(&payload.params).unwrap(),
+            r#"{"seed":1,"adapter":"french"}"#
+        );
+
+        let mut payload: ChatCompletionRequestSchema = serde_json::from_str(
+            r#"{"model": "Llama 3.2 (1B Instruct):french", "messages": [], "adapter": "german"}"#,
+        )
+        .unwrap();
+        payload.split_model_adapter(is_model);
+        assert_eq!(payload.model, "Llama 3.2 (1B Instruct)");
+        assert_eq!(payload.params.adapter.as_deref(), Some("german"));
+
+        // A suffix of an unknown model is kept in the model name
+        let mut payload: ChatCompletionRequestSchema =
+            serde_json::from_str(r#"{"model": "Unknown:french", "messages": []}"#).unwrap();
+        payload.split_model_adapter(is_model);
+        assert_eq!(payload.model, "Unknown:french");
+        assert_eq!(payload.params.adapter, None);
+    }
 }
@@ -1,2 +1,3 @@
 pub mod chat_handlers;
 pub mod model_handlers;
+pub mod tokenize_handlers;
@@ -0,0 +1,119 @@
+use axum::{extract::State, Json};
+use burn_lm_inference::{InferenceError, InferencePlugin};
+
+use crate::{
+    constants::API_VERSION,
+    controllers::chat_controllers::ChatController,
+    errors::{ServerError, ServerResult},
+    schemas::tokenize_schemas::{
+        DetokenizeRequestSchema, DetokenizeResponseSchema, TokenizeRequestSchema,
+        TokenizeResponseSchema,
+    },
+    stores::chat_store::ModelStoreState,
+};
+
+#[utoipa::path(
+    post,
+    path = format!("/{}/tokenize", API_VERSION),
+    request_body = TokenizeRequestSchema,
+    responses(
+        (status = 200, description = "Tokenize a prompt with the model tokenizer and chat template.", body = TokenizeResponseSchema),
+    )
+)]
+pub async fn tokenize(
+    State(state): State<ModelStoreState>,
+    Json(payload): Json<TokenizeRequestSchema>,
+) -> ServerResult<Json<TokenizeResponseSchema>> {
+    tracing::debug!("Received JSON payload: {:?}", payload);
+    let plugin = find_plugin(&state, &payload.model).await?;
+    let model = plugin.model_name().to_string();
+    let task = payload.task();
+    // the tokenizer might have to be downloaded
+    let tokenization = tokio::task::spawn_blocking(move || plugin.tokenize(task))
+        .await
+        .expect("should complete tokenization")
+        .map_err(tokenization_error)?;
+    Ok(Json(TokenizeResponseSchema {
+        model,
+        count: tokenization.tokens.len(),
+        tokens: tokenization.tokens,
+        prompt: tokenization.prompt,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = format!("/{}/detokenize", API_VERSION),
+    request_body = DetokenizeRequestSchema,
+    responses(
+        (status = 200, description = "Decode token identifiers with the model tokenizer.", body = DetokenizeResponseSchema),
+    )
+)]
+pub async fn detokenize(
+    State(state): State<ModelStoreState>,
+    Json(payload): Json<DetokenizeRequestSchema>,
+) -> ServerResult<Json<DetokenizeResponseSchema>> {
+    tracing::debug!("Received JSON payload: {:?}", payload);
+    let plugin = find_plugin(&state, &payload.model).await?;
+    let model = plugin.model_name().to_string();
+    let tokens = payload.tokens;
+    let text = tokio::task::spawn_blocking(move || plugin.detokenize(tokens))
+        .await
+        .expect("should complete detokenization")
+        .map_err(tokenization_error)?;
+    Ok(Json(DetokenizeResponseSchema { model, text }))
+}
+
+async fn find_plugin(
+    state: &ModelStoreState,
+    model: &str,
+) -> ServerResult<Box<dyn InferencePlugin>> {
+    // Tokenizing must not unload the current model, so the plugin is not selected
+    let store = state.lock().await;
+    store.find_plugin(model).await
+}
+
+fn tokenization_error(error: InferenceError) -> ServerError {
+    match error {
+        InferenceError::PluginTokenizationUnsupportedError => {
+            ServerError::Unsupported(error.to_string())
+        }
+        error => ServerError::TokenizationError(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn_lm_inference::InferenceTask;
+
+    #[test]
+    fn raw_prompt_takes_precedence_over_messages() {
+        let payload: TokenizeRequestSchema = serde_json::from_str(
+            r#"{"model": "Parrot", "messages": [{"role": "user", "content": "hi"}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(payload.task(), InferenceTask::Context(messages) if messages.len() == 1));
+
+        let payload: TokenizeRequestSchema = serde_json::from_str(
+            r#"{"model": "Parrot", "messages": [{"role": "user", "content": "hi"}], "prompt": "raw"}"#,
+        )
+        .unwrap();
+        assert!(matches!(payload.task(), InferenceTask::Prompt(prompt) if prompt == "raw"));
+    }
+
+    #[test]
+    fn unsupported_tokenization_is_not_a_bad_request() {
+        assert!(matches!(
+            tokenization_error(InferenceError::PluginTokenizationUnsupportedError),
+            ServerError::Unsupported(_)
+        ));
+        assert!(matches!(
+            tokenization_error(InferenceError::TokenizationError(
+                "Parrot".to_string(),
+                "unknown token identifier 42".to_string()
+            )),
+            ServerError::TokenizationError(_)
+        ));
+    }
+}
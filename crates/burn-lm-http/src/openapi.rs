@@ -1,12 +1,23 @@
 use utoipa::OpenApi;
 
 use crate::handlers::model_handlers::{__path_get_model, __path_list_models};
+use crate::handlers::tokenize_handlers::{__path_detokenize, __path_tokenize};
 use crate::schemas::model_schemas::ModelResponseSchema;
+use crate::schemas::tokenize_schemas::{
+    DetokenizeRequestSchema, DetokenizeResponseSchema, TokenizeRequestSchema,
+    TokenizeResponseSchema,
+};
 
 /// OpenAPI spec
 #[derive(OpenApi)]
 #[openapi(
-    paths(get_model, list_models),
-    components(schemas(ModelResponseSchema))
+    paths(get_model, list_models, tokenize, detokenize),
+    components(schemas(
+        ModelResponseSchema,
+        TokenizeRequestSchema,
+        TokenizeResponseSchema,
+        DetokenizeRequestSchema,
+        DetokenizeResponseSchema
+    ))
 )]
 pub(crate) struct ApiDoc;
@@ -1,2 +1,3 @@
 pub mod chat_routers;
 pub mod model_routers;
+pub mod tokenize_routers;
@@ -0,0 +1,10 @@
+use crate::{handlers::tokenize_handlers::*, stores::chat_store::ModelStoreState};
+
+use axum::{routing::post, Router};
+
+pub fn public_router(state: ModelStoreState) -> Router {
+    Router::new()
+        .route("/tokenize", post(tokenize))
+        .route("/detokenize", post(detokenize))
+        .with_state(state)
+}
@@ -21,6 +21,31 @@ pub struct ChatCompletionParamsSchema {
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u64>,
+    /// The LoRA adapter to apply, can also be selected with a `<model>:<adapter>` model name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adapter: Option<String>,
+}
+
+impl ChatCompletionRequestSchema {
+    /// Split the `<model>:<adapter>` model name suffix into the adapter parameter.
+    ///
+    /// Model names may contain `:`, so the name is only split when it is not a model name
+    /// itself but its prefix is. An explicit `adapter` parameter takes precedence over the model
+    /// name suffix.
+    pub fn split_model_adapter(&mut self, is_model: impl Fn(&str) -> bool) {
+        if is_model(&self.model) {
+            return;
+        }
+        if let Some((model, adapter)) = self.model.rsplit_once(':') {
+            if !is_model(model) {
+                return;
+            }
+            if !adapter.is_empty() && self.params.adapter.is_none() {
+                self.params.adapter = Some(adapter.to_string());
+            }
+            self.model = model.to_string();
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, ToSchema)]
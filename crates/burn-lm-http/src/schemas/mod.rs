@@ -1,2 +1,3 @@
 pub mod chat_schemas;
 pub mod model_schemas;
+pub mod tokenize_schemas;
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::schemas::chat_schemas::ChoiceMessageSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TokenizeRequestSchema {
+    pub model: String,
+    /// The messages rendered with the model chat template.
+    #[serde(default)]
+    pub messages: Vec<ChoiceMessageSchema>,
+    /// A raw prompt tokenized as is, takes precedence over the messages.
+    pub prompt: Option<String>,
+}
+
+impl TokenizeRequestSchema {
+    pub fn task(self) -> burn_lm_inference::InferenceTask {
+        match self.prompt {
+            Some(prompt) => burn_lm_inference::InferenceTask::Prompt(prompt),
+            None => burn_lm_inference::InferenceTask::Context(
+                self.messages.into_iter().map(Into::into).collect(),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct TokenizeResponseSchema {
+    pub model: String,
+    pub tokens: Vec<u32>,
+    pub count: usize,
+    /// The prompt rendered with the model chat template.
+    pub prompt: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DetokenizeRequestSchema {
+    pub model: String,
+    pub tokens: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct DetokenizeResponseSchema {
+    pub model: String,
+    pub text: String,
+}
@@ -5,7 +5,8 @@ use burn_lm_inference::InferencePlugin;
 use burn_lm_registry::Registry;
 
 use crate::{
-    controllers::chat_controllers::ChatController, errors::ServerResult,
+    controllers::chat_controllers::ChatController,
+    errors::{ServerError, ServerResult},
     schemas::model_schemas::ModelSchema,
 };
 
@@ -19,15 +20,33 @@ pub struct ChatStore {
 pub type ModelStoreState = Arc<tokio::sync::Mutex<ChatStore>>;
 
 impl ChatStore {
-    fn new() -> Self {
+    fn new(registry: Registry) -> Self {
         Self {
-            registry: Registry::new(),
+            registry,
             current_plugin_name: None,
         }
     }
 
-    pub fn create_state() -> ModelStoreState {
-        Arc::new(tokio::sync::Mutex::new(ChatStore::new()))
+    pub fn create_state(workers: bool) -> ModelStoreState {
+        let registry = if workers {
+            Registry::with_workers()
+        } else {
+            Registry::new()
+        };
+        Self::create_state_with_registry(registry)
+    }
+
+    /// Create the state of a store serving the models of the given registry.
+    pub fn create_state_with_registry(registry: Registry) -> ModelStoreState {
+        Arc::new(tokio::sync::Mutex::new(ChatStore::new(registry)))
+    }
+
+    /// Whether a model with the given name is registered, the name is case insensitive.
+    pub fn has_model(&self, name: &str) -> bool {
+        self.registry
+            .get()
+            .keys()
+            .any(|pname| pname.to_lowercase() == name.to_lowercase())
     }
 }
 
@@ -48,6 +67,15 @@ impl ChatController for ChatStore {
         Ok(models)
     }
 
+    async fn find_plugin(&self, name: &str) -> ServerResult<Box<dyn InferencePlugin>> {
+        self.registry
+            .get()
+            .iter()
+            .find(|(pname, _)| (**pname).to_lowercase() == name.to_lowercase())
+            .map(|(_, plugin)| plugin.clone())
+            .ok_or(ServerError::NotFound)
+    }
+
     async fn get_plugin(
         &mut self,
         name: &str,
@@ -58,8 +86,7 @@ impl ChatController for ChatStore {
             .iter()
             .find(|(pname, _)| (**pname).to_lowercase() == name.to_lowercase())
             .map(|(_, plugin)| plugin);
-        let requested_plugin =
-            requested_plugin.unwrap_or_else(|| panic!("plugin '{name}' should be registered"));
+        let requested_plugin = requested_plugin.ok_or(ServerError::NotFound)?;
 
         // unload plugin if we request a different plugin than the current one
         let mut old_model_name = None;
@@ -0,0 +1,65 @@
+use std::{path::Path, process::Command};
+
+fn main() {
+    // Recorded in the plugin declarations, plugins built by another compiler are rejected.
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=BURN_LM_RUSTC_VERSION={}", version.trim());
+    println!("cargo:rerun-if-env-changed=RUSTC");
+
+    // Plugins built with other features or another burn revision are rejected as well.
+    let fingerprint = format!(
+        "target={}; features={}; burn={}",
+        std::env::var("TARGET").unwrap_or_default(),
+        features().join(","),
+        burn_source().unwrap_or_else(|| "unknown".to_string())
+    );
+    println!("cargo:rustc-env=BURN_LM_BUILD_FINGERPRINT={fingerprint}");
+}
+
+/// The enabled features of this crate.
+fn features() -> Vec<String> {
+    let mut features: Vec<_> = std::env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    features
+}
+
+/// The version and source of the burn package in the lock file of the workspace being built,
+/// e.g. `0.21.0 git+https://github.com/tracel-ai/burn?branch=main#<commit>`.
+fn burn_source() -> Option<String> {
+    // The target directory is usually in the workspace, this crate is in this workspace
+    let out_dir = std::env::var("OUT_DIR").ok()?;
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").ok()?;
+    let lock_file = Path::new(&out_dir)
+        .ancestors()
+        .chain(Path::new(&manifest_dir).ancestors())
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())?;
+    println!("cargo:rerun-if-changed={}", lock_file.display());
+
+    let content = std::fs::read_to_string(lock_file).ok()?;
+    content.split("[[package]]").find_map(|package| {
+        let field = |name: &str| {
+            package.lines().find_map(|line| {
+                line.strip_prefix(name)
+                    .and_then(|value| value.trim().strip_prefix('='))
+                    .map(|value| value.trim().trim_matches('"').to_string())
+            })
+        };
+        (field("name")? == "burn").then(|| {
+            let version = field("version").unwrap_or_default();
+            let source = field("source").unwrap_or_else(|| "path".to_string());
+            format!("{version} {source}")
+        })
+    })
+}
@@ -6,9 +6,12 @@ mod elems {
             pub type ElemType = burn::tensor::f16;
             pub const DTYPE_NAME: &str = "f16";
         }
-        else if #[cfg(all(feature = "f16", any(feature = "cuda", feature = "wgpu", feature = "vulkan", feature = "metal", feature = "rocm", feature = "libtorch")))]{
+        // bf16 is only supported by the backends of BackendKind::supports
+        else if #[cfg(all(feature = "bf16", any(feature = "cuda", feature = "rocm", feature = "libtorch", feature = "libtorch-cpu")))]{
             pub type ElemType = burn::tensor::bf16;
             pub const DTYPE_NAME: &str = "bf16";
+        } else if #[cfg(feature = "bf16")] {
+            compile_error!("The bf16 feature requires the cuda, rocm, libtorch or libtorch-cpu backend");
         } else {
             pub type ElemType = f32;
             pub const DTYPE_NAME: &str = "f32";
@@ -18,103 +21,461 @@ mod elems {
 
 pub use elems::*;
 
-/// Configure device default float `ElemType`.
-fn configure(mut device: burn::tensor::Device) -> burn::tensor::Device {
-    use burn::tensor::{DeviceConfig, Element};
-    device
-        .configure(DeviceConfig::default().float_dtype(ElemType::dtype()))
-        .unwrap();
-    device
+use burn::tensor::Device;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+/// A backend which can be selected at runtime when it is included in the build.
+///
+/// Several backend features can be enabled together, the first one of the order of the
+/// variants is the default backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, strum::EnumIter)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    Cuda,
+    Rocm,
+    Libtorch,
+    LibtorchCpu,
+    Metal,
+    Vulkan,
+    Wgpu,
+    WgpuCpu,
+    Ndarray,
 }
 
-use burn::tensor::Device;
-use std::sync::LazyLock;
+impl BackendKind {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Cuda => "cuda",
+            Self::Rocm => "rocm",
+            Self::Libtorch => "libtorch",
+            Self::LibtorchCpu => "libtorch-cpu",
+            Self::Metal => "metal",
+            Self::Vulkan => "vulkan",
+            Self::Wgpu => "wgpu",
+            Self::WgpuCpu => "wgpu-cpu",
+            Self::Ndarray => "ndarray",
+        }
+    }
 
-// Cuda ----------------------------------------------------------------------
+    /// Return true if the backend is included in the build.
+    pub fn is_available(&self) -> bool {
+        match self {
+            Self::Cuda => cfg!(feature = "cuda"),
+            Self::Rocm => cfg!(feature = "rocm"),
+            Self::Libtorch => cfg!(feature = "libtorch"),
+            Self::LibtorchCpu => cfg!(feature = "libtorch-cpu"),
+            Self::Metal => cfg!(feature = "metal"),
+            Self::Vulkan => cfg!(feature = "vulkan"),
+            Self::Wgpu => cfg!(feature = "wgpu"),
+            Self::WgpuCpu => cfg!(feature = "wgpu-cpu"),
+            // ndarray is always included for testing
+            Self::Ndarray => true,
+        }
+    }
 
-#[cfg(feature = "cuda")]
-pub mod burn_backend_types {
-    use super::*;
+    /// The backends included in the build.
+    pub fn available() -> Vec<Self> {
+        use strum::IntoEnumIterator;
 
-    pub type InferenceDevice = Device;
-    pub static INFERENCE_DEVICE: LazyLock<Device> =
-        LazyLock::new(|| configure(Device::cuda(burn::tensor::DeviceIndex::Default)));
-    pub const NAME: &str = "cuda";
+        Self::iter().filter(Self::is_available).collect()
+    }
+
+    /// Return true if the backend supports the float type.
+    pub fn supports(&self, dtype: FloatDtype) -> bool {
+        match (self, dtype) {
+            (_, FloatDtype::F32) => true,
+            (Self::Cuda | Self::Rocm | Self::Libtorch, _) => true,
+            (Self::LibtorchCpu, FloatDtype::Bf16) => true,
+            // f16 support of wgpu depends on the hardware, see the note of `elems`
+            (Self::Metal | Self::Vulkan | Self::Wgpu, FloatDtype::F16) => true,
+            _ => false,
+        }
+    }
+
+    /// Return true if the backend has a single device.
+    fn is_single_device(&self) -> bool {
+        matches!(self, Self::LibtorchCpu | Self::WgpuCpu | Self::Ndarray)
+    }
+
+    /// Create the device of the backend with the given index.
+    // The index is unused when the build only includes single device backends.
+    #[allow(unused_variables)]
+    fn create_device(&self, index: Option<usize>, dtype: FloatDtype) -> Result<Device, String> {
+        match self {
+            #[cfg(feature = "cuda")]
+            Self::Cuda => configure(Device::cuda(device_index(index)), dtype),
+            #[cfg(feature = "rocm")]
+            Self::Rocm => configure(Device::rocm(device_index(index)), dtype),
+            #[cfg(all(feature = "libtorch", not(target_os = "macos")))]
+            Self::Libtorch => configure(Device::libtorch_cuda(device_index(index)), dtype),
+            #[cfg(all(feature = "libtorch", target_os = "macos"))]
+            Self::Libtorch => configure(Device::libtorch_mps(), dtype),
+            #[cfg(feature = "libtorch-cpu")]
+            Self::LibtorchCpu => configure(Device::libtorch(), dtype),
+            #[cfg(any(feature = "wgpu", feature = "vulkan", feature = "metal"))]
+            Self::Metal | Self::Vulkan | Self::Wgpu => {
+                let kind = index.map_or(
+                    burn::tensor::DeviceKind::DefaultDevice,
+                    burn::tensor::DeviceKind::DiscreteGpu,
+                );
+                configure(Device::wgpu(kind), dtype)
+            }
+            #[cfg(feature = "wgpu-cpu")]
+            Self::WgpuCpu => configure(Device::wgpu(burn::tensor::DeviceKind::Cpu), dtype),
+            Self::Ndarray => configure(Device::ndarray(), dtype),
+            #[allow(unreachable_patterns)]
+            backend => Err(format!(
+                "the {backend} backend is not included in this build"
+            )),
+        }
+    }
 }
 
-// ROCm ----------------------------------------------------------------------
+impl std::fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
 
-#[cfg(feature = "rocm")]
-pub mod burn_backend_types {
-    use super::*;
+impl std::str::FromStr for BackendKind {
+    type Err = String;
 
-    pub type InferenceDevice = Device;
-    pub static INFERENCE_DEVICE: LazyLock<Device> =
-        LazyLock::new(|| configure(Device::rocm(burn::tensor::DeviceIndex::Default)));
-    pub const NAME: &str = "rocm";
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        use strum::IntoEnumIterator;
+
+        Self::iter()
+            .find(|backend| backend.name() == name)
+            .ok_or_else(|| format!("unknown backend '{name}'"))
+    }
 }
 
-// ndarray -------------------------------------------------------------------
-// This backend is used for testing and by default when no backend is selected.
+#[cfg(any(
+    feature = "cuda",
+    feature = "rocm",
+    all(feature = "libtorch", not(target_os = "macos"))
+))]
+fn device_index(index: Option<usize>) -> burn::tensor::DeviceIndex {
+    index.map_or(
+        burn::tensor::DeviceIndex::Default,
+        burn::tensor::DeviceIndex::Index,
+    )
+}
 
-#[cfg(any(feature = "ndarray", not(feature = "selected-backend")))]
-pub mod burn_backend_types {
-    use super::*;
+/// A float data type which can be selected at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, strum::EnumIter)]
+#[serde(rename_all = "lowercase")]
+pub enum FloatDtype {
+    F32,
+    F16,
+    Bf16,
+}
 
-    pub type InferenceDevice = Device;
-    pub static INFERENCE_DEVICE: LazyLock<Device> = LazyLock::new(|| configure(Device::ndarray()));
-    pub const NAME: &str = "ndarray";
+impl FloatDtype {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::F32 => "f32",
+            Self::F16 => "f16",
+            Self::Bf16 => "bf16",
+        }
+    }
+
+    pub fn dtype(&self) -> burn::tensor::DType {
+        match self {
+            Self::F32 => burn::tensor::DType::F32,
+            Self::F16 => burn::tensor::DType::F16,
+            Self::Bf16 => burn::tensor::DType::BF16,
+        }
+    }
 }
 
-// LibTorch ------------------------------------------------------------------
+impl std::fmt::Display for FloatDtype {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
 
-#[cfg(feature = "libtorch")]
-pub mod burn_backend_types {
-    use super::*;
+impl std::str::FromStr for FloatDtype {
+    type Err = String;
 
-    pub type InferenceDevice = Device;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        use strum::IntoEnumIterator;
 
-    #[cfg(not(target_os = "macos"))]
-    pub static INFERENCE_DEVICE: LazyLock<Device> =
-        LazyLock::new(|| Device::libtorch_cuda(burn::tensor::DeviceIndex::Default));
-    #[cfg(target_os = "macos")]
-    pub static INFERENCE_DEVICE: LazyLock<Device> =
-        LazyLock::new(|| configure(Device::libtorch_mps()));
-    pub const NAME: &str = "libtorch";
+        Self::iter()
+            .find(|dtype| dtype.name() == name)
+            .ok_or_else(|| format!("unknown float type '{name}'"))
+    }
 }
 
-#[cfg(feature = "libtorch-cpu")]
-pub mod burn_backend_types {
-    use super::*;
+/// Configure device default float type.
+fn configure(mut device: Device, dtype: FloatDtype) -> Result<Device, String> {
+    use burn::tensor::DeviceConfig;
+    device
+        .configure(DeviceConfig::default().float_dtype(dtype.dtype()))
+        .map_err(|err| format!("failed to configure the {dtype} float type: {err:?}"))?;
+    Ok(device)
+}
 
-    pub type InferenceDevice = Device;
-    pub static INFERENCE_DEVICE: LazyLock<Device> = LazyLock::new(|| configure(Device::libtorch()));
-    pub const NAME: &str = "libtorch-cpu";
+cfg_if::cfg_if! {
+    if #[cfg(feature = "cuda")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::Cuda;
+    } else if #[cfg(feature = "rocm")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::Rocm;
+    } else if #[cfg(feature = "libtorch")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::Libtorch;
+    } else if #[cfg(feature = "libtorch-cpu")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::LibtorchCpu;
+    } else if #[cfg(feature = "metal")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::Metal;
+    } else if #[cfg(feature = "vulkan")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::Vulkan;
+    } else if #[cfg(feature = "wgpu")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::Wgpu;
+    } else if #[cfg(feature = "wgpu-cpu")] {
+        const DEFAULT_BACKEND: BackendKind = BackendKind::WgpuCpu;
+    } else {
+        // This backend is used for testing and by default when no backend is selected.
+        const DEFAULT_BACKEND: BackendKind = BackendKind::Ndarray;
+    }
 }
 
-// WebGPU --------------------------------------------------------------------
+/// The backend, device and float type on which a model is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceSettings {
+    #[serde(default = "DeviceSettings::default_backend")]
+    pub backend: BackendKind,
+    /// The device index, the default device of the backend if not set.
+    #[serde(default)]
+    pub index: Option<usize>,
+    #[serde(default = "DeviceSettings::default_dtype")]
+    pub dtype: FloatDtype,
+}
+
+impl Default for DeviceSettings {
+    fn default() -> Self {
+        Self {
+            backend: Self::default_backend(),
+            index: None,
+            dtype: Self::default_dtype(),
+        }
+    }
+}
+
+/// The devices created for each settings, so that the models with the same settings share
+/// their device.
+static DEVICES: LazyLock<Mutex<HashMap<DeviceSettings, Device>>> = LazyLock::new(Default::default);
+
+impl DeviceSettings {
+    fn default_backend() -> BackendKind {
+        DEFAULT_BACKEND
+    }
+
+    fn default_dtype() -> FloatDtype {
+        let dtype = DTYPE_NAME.parse().expect("should be a valid float type");
+        // e.g. a libtorch-cpu and wgpu build with f16, whose default backend is libtorch-cpu
+        if DEFAULT_BACKEND.supports(dtype) {
+            dtype
+        } else {
+            FloatDtype::F32
+        }
+    }
+
+    /// The default device with the preferred float type of a model, or with the default float
+    /// type when the default backend doesn't support it.
+    pub fn with_preferred_dtype(dtype: FloatDtype) -> Self {
+        let settings = Self::default();
+        if settings.backend.supports(dtype) {
+            Self { dtype, ..settings }
+        } else {
+            settings
+        }
+    }
+
+    /// The settings on another backend, keeping the float type when the backend supports it
+    /// and using f32 otherwise.
+    pub fn with_backend(self, backend: BackendKind) -> Self {
+        let dtype = if backend.supports(self.dtype) {
+            self.dtype
+        } else {
+            FloatDtype::F32
+        };
+        Self {
+            backend,
+            dtype,
+            ..self
+        }
+    }
+
+    /// Check that the settings can be used with this build.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.backend.is_available() {
+            let available: Vec<_> = BackendKind::available()
+                .iter()
+                .map(BackendKind::name)
+                .collect();
+            return Err(format!(
+                "the {} backend is not included in this build (available: {})",
+                self.backend,
+                available.join(", ")
+            ));
+        }
+        if self.backend.is_single_device() && self.index.is_some_and(|index| index > 0) {
+            return Err(format!("the {} backend has a single device", self.backend));
+        }
+        if !self.backend.supports(self.dtype) {
+            return Err(format!(
+                "the {} backend does not support the {} float type",
+                self.backend, self.dtype
+            ));
+        }
+        Ok(())
+    }
+
+    /// Return the device of the settings, created and configured on first use.
+    pub fn device(&self) -> Result<InferenceDevice, String> {
+        self.validate()?;
+        let mut devices = DEVICES.lock().unwrap();
+        if let Some(device) = devices.get(self) {
+            return Ok(device.clone());
+        }
+        let device = self.backend.create_device(self.index, self.dtype)?;
+        devices.insert(*self, device.clone());
+        Ok(device)
+    }
+}
+
+impl std::fmt::Display for DeviceSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.backend)?;
+        if let Some(index) = self.index {
+            write!(f, ":{index}")?;
+        }
+        write!(f, " ({})", self.dtype)
+    }
+}
 
-#[cfg(any(feature = "wgpu", feature = "vulkan", feature = "metal"))]
 pub mod burn_backend_types {
     use super::*;
 
     pub type InferenceDevice = Device;
-    pub static INFERENCE_DEVICE: LazyLock<Device> =
-        LazyLock::new(|| configure(Device::wgpu(burn::tensor::DeviceKind::DefaultDevice)));
-    #[cfg(all(feature = "wgpu", not(feature = "vulkan"), not(feature = "metal")))]
-    pub const NAME: &str = "wgpu";
-    #[cfg(feature = "vulkan")]
-    pub const NAME: &str = "vulkan";
-    #[cfg(feature = "metal")]
-    pub const NAME: &str = "metal";
+    /// The device of the default [DeviceSettings].
+    pub static INFERENCE_DEVICE: LazyLock<Device> = LazyLock::new(|| {
+        DeviceSettings::default()
+            .device()
+            .expect("the default device should be available")
+    });
+    pub const NAME: &str = DEFAULT_BACKEND.name();
 }
 
-#[cfg(feature = "wgpu-cpu")]
-pub mod burn_backend_types {
+use burn_backend_types::InferenceDevice;
+
+#[cfg(test)]
+mod tests {
     use super::*;
 
-    pub type InferenceDevice = Device;
-    pub static INFERENCE_DEVICE: LazyLock<Device> =
-        LazyLock::new(|| configure(Device::wgpu(burn::tensor::DeviceKind::Cpu)));
-    pub const NAME: &str = "wgpu-cpu";
+    #[test]
+    fn test_device_settings_default() {
+        let settings = DeviceSettings::default();
+        assert_eq!(settings.backend.name(), burn_backend_types::NAME);
+        assert_eq!(settings.dtype.name(), DTYPE_NAME);
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_device_settings_parse() {
+        let settings: DeviceSettings =
+            serde_json::from_str(r#"{"backend": "wgpu-cpu", "dtype": "bf16"}"#).unwrap();
+        assert_eq!(settings.backend, BackendKind::WgpuCpu);
+        assert_eq!(settings.index, None);
+        assert_eq!(settings.dtype, FloatDtype::Bf16);
+        assert_eq!(settings.to_string(), "wgpu-cpu (bf16)");
+
+        assert_eq!("libtorch-cpu".parse(), Ok(BackendKind::LibtorchCpu));
+        assert!("tpu".parse::<BackendKind>().is_err());
+        assert!(serde_json::from_str::<DeviceSettings>(r#"{"device": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_device_settings_dtype_support() {
+        assert!(BackendKind::Cuda.supports(FloatDtype::Bf16));
+        assert!(BackendKind::Wgpu.supports(FloatDtype::F16));
+        assert!(!BackendKind::Wgpu.supports(FloatDtype::Bf16));
+        assert!(!BackendKind::Ndarray.supports(FloatDtype::F16));
+
+        let settings = DeviceSettings {
+            backend: BackendKind::Cuda,
+            index: Some(1),
+            dtype: FloatDtype::Bf16,
+        };
+        let settings = settings.with_backend(BackendKind::Ndarray);
+        assert_eq!(settings.dtype, FloatDtype::F32);
+        assert_eq!(settings.index, Some(1));
+
+        let settings = DeviceSettings::with_preferred_dtype(FloatDtype::Bf16);
+        assert_eq!(
+            settings.backend.supports(FloatDtype::Bf16),
+            settings.dtype == FloatDtype::Bf16
+        );
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_device_settings_validate() {
+        let settings = DeviceSettings {
+            backend: BackendKind::Ndarray,
+            index: Some(1),
+            dtype: FloatDtype::F32,
+        };
+        assert!(settings.validate().is_err());
+
+        let settings = DeviceSettings {
+            backend: BackendKind::Ndarray,
+            index: None,
+            dtype: FloatDtype::Bf16,
+        };
+        assert!(settings.validate().unwrap_err().contains("bf16"));
+
+        if !BackendKind::Cuda.is_available() {
+            let settings = DeviceSettings {
+                backend: BackendKind::Cuda,
+                ..Default::default()
+            };
+            assert!(settings.device().is_err());
+        }
+    }
+
+    #[test]
+    fn test_device_float_dtype_matches_settings() {
+        use burn::tensor::Tensor;
+        use strum::IntoEnumIterator;
+
+        for backend in BackendKind::available() {
+            // The f16 support of wgpu depends on the adapter of the machine running the tests
+            let hardware_dependent = |dtype: &FloatDtype| {
+                matches!(
+                    (backend, dtype),
+                    (
+                        BackendKind::Metal | BackendKind::Vulkan | BackendKind::Wgpu,
+                        FloatDtype::F16
+                    )
+                )
+            };
+            for dtype in FloatDtype::iter()
+                .filter(|dtype| backend.supports(*dtype) && !hardware_dependent(dtype))
+            {
+                let settings = DeviceSettings {
+                    backend,
+                    index: None,
+                    dtype,
+                };
+                let device = settings.device().unwrap();
+                let tensor = Tensor::<1>::zeros([2], &device);
+                assert_eq!(tensor.dtype(), dtype.dtype(), "device of {settings}");
+            }
+        }
+    }
 }
@@ -1,16 +1,33 @@
 use std::fmt::Debug;
 
-use crate::{errors::InferenceResult, server::InferenceServer, InferenceJob, Stats};
+use crate::{
+    errors::InferenceResult, server::InferenceServer, DeviceSettings, InferenceJob, InferenceTask,
+    Loglikelihood, LoglikelihoodRequest, PerplexityArgs, QuantizationScheme, Stats, Tokenization,
+    TrainingArgs,
+};
 
 pub trait InferenceChannel<Server: InferenceServer>: Clone + Send + Sync + Debug {
-    fn downloader(&self) -> Option<fn() -> InferenceResult<Option<Stats>>>;
+    fn downloader(&self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>>;
     fn is_downloaded(&self) -> bool;
     fn deleter(&self) -> Option<fn() -> InferenceResult<Option<Stats>>>;
+    fn quantizer(
+        &self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>>;
+    fn trainer(&self)
+        -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>>;
     fn parse_cli_config(&self, args: &clap::ArgMatches);
     fn parse_json_config(&self, json: &str);
+    fn set_device(&self, settings: DeviceSettings);
     fn load(&self) -> InferenceResult<Option<Stats>>;
     fn is_loaded(&self) -> bool;
     fn unload(&self) -> InferenceResult<Option<Stats>>;
     fn run_job(&self, job: InferenceJob) -> InferenceResult<Stats>;
     fn clear_state(&self) -> InferenceResult<()>;
+    fn perplexity(&self, args: PerplexityArgs) -> InferenceResult<Stats>;
+    fn loglikelihood(
+        &self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>>;
+    fn tokenize(&self, task: InferenceTask) -> InferenceResult<Tokenization>;
+    fn detokenize(&self, tokens: Vec<u32>) -> InferenceResult<String>;
 }
@@ -2,5 +2,6 @@ pub mod base;
 pub mod passthrough;
 // pub mod mpsc;
 pub mod mutex;
+pub mod process;
 
 pub use base::*;
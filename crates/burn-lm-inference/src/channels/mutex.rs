@@ -1,6 +1,10 @@
 use std::sync::{Arc, Mutex};
 
-use crate::{errors::InferenceResult, server::InferenceServer, InferenceJob, Stats};
+use crate::{
+    errors::InferenceResult, server::InferenceServer, DeviceSettings, InferenceJob, InferenceTask,
+    Loglikelihood, LoglikelihoodRequest, PerplexityArgs, QuantizationScheme, Stats, Tokenization,
+    TrainingArgs,
+};
 
 use super::InferenceChannel;
 
@@ -22,10 +26,18 @@ impl<Server: InferenceServer> MutexChannel<Server> {
             server: Arc::new(Mutex::new(Server::default())),
         }
     }
+
+    /// Create a channel for an already configured server.
+    /// This is used by plugins registered at runtime.
+    pub fn with_server(server: Server) -> Self {
+        Self {
+            server: Arc::new(Mutex::new(server)),
+        }
+    }
 }
 
 impl<Server: InferenceServer> InferenceChannel<Server> for MutexChannel<Server> {
-    fn downloader(&self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+    fn downloader(&self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
         let mut server = self.server.lock().unwrap();
         server.downloader()
     }
@@ -40,6 +52,20 @@ impl<Server: InferenceServer> InferenceChannel<Server> for MutexChannel<Server>
         server.deleter()
     }
 
+    fn quantizer(
+        &self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>> {
+        let mut server = self.server.lock().unwrap();
+        server.quantizer()
+    }
+
+    fn trainer(
+        &self,
+    ) -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>> {
+        let mut server = self.server.lock().unwrap();
+        server.trainer()
+    }
+
     fn parse_cli_config(&self, args: &clap::ArgMatches) {
         let mut server = self.server.lock().unwrap();
         server.parse_cli_config(args);
@@ -50,6 +76,11 @@ impl<Server: InferenceServer> InferenceChannel<Server> for MutexChannel<Server>
         server.parse_json_config(json);
     }
 
+    fn set_device(&self, settings: DeviceSettings) {
+        let mut server = self.server.lock().unwrap();
+        server.set_device(settings);
+    }
+
     fn load(&self) -> InferenceResult<Option<Stats>> {
         let mut server = self.server.lock().unwrap();
         server.load()
@@ -74,4 +105,27 @@ impl<Server: InferenceServer> InferenceChannel<Server> for MutexChannel<Server>
         let mut server = self.server.lock().unwrap();
         server.clear_state()
     }
+
+    fn perplexity(&self, args: PerplexityArgs) -> InferenceResult<Stats> {
+        let mut server = self.server.lock().unwrap();
+        server.perplexity(args)
+    }
+
+    fn loglikelihood(
+        &self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        let mut server = self.server.lock().unwrap();
+        server.loglikelihood(requests)
+    }
+
+    fn tokenize(&self, task: InferenceTask) -> InferenceResult<Tokenization> {
+        let mut server = self.server.lock().unwrap();
+        server.tokenize(task)
+    }
+
+    fn detokenize(&self, tokens: Vec<u32>) -> InferenceResult<String> {
+        let mut server = self.server.lock().unwrap();
+        server.detokenize(tokens)
+    }
 }
@@ -1,6 +1,10 @@
 use std::{cell::RefCell, sync::Arc};
 
-use crate::{errors::InferenceResult, server::InferenceServer, InferenceJob, Stats};
+use crate::{
+    errors::InferenceResult, server::InferenceServer, DeviceSettings, InferenceJob, InferenceTask,
+    Loglikelihood, LoglikelihoodRequest, PerplexityArgs, QuantizationScheme, Stats, Tokenization,
+    TrainingArgs,
+};
 
 use super::InferenceChannel;
 
@@ -28,7 +32,7 @@ impl<Server: InferenceServer> SingleThreadedChannel<Server> {
 }
 
 impl<Server: InferenceServer> InferenceChannel<Server> for SingleThreadedChannel<Server> {
-    fn downloader(&self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+    fn downloader(&self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
         self.server.borrow_mut().downloader()
     }
 
@@ -40,6 +44,18 @@ impl<Server: InferenceServer> InferenceChannel<Server> for SingleThreadedChannel
         self.server.borrow_mut().deleter()
     }
 
+    fn quantizer(
+        &self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>> {
+        self.server.borrow_mut().quantizer()
+    }
+
+    fn trainer(
+        &self,
+    ) -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>> {
+        self.server.borrow_mut().trainer()
+    }
+
     fn parse_cli_config(&self, args: &clap::ArgMatches) {
         self.server.borrow_mut().parse_cli_config(args);
     }
@@ -48,6 +64,10 @@ impl<Server: InferenceServer> InferenceChannel<Server> for SingleThreadedChannel
         self.server.borrow_mut().parse_json_config(json);
     }
 
+    fn set_device(&self, settings: DeviceSettings) {
+        self.server.borrow_mut().set_device(settings);
+    }
+
     fn load(&self) -> InferenceResult<Option<Stats>> {
         self.server.borrow_mut().load()
     }
@@ -67,4 +87,23 @@ impl<Server: InferenceServer> InferenceChannel<Server> for SingleThreadedChannel
     fn clear_state(&self) -> InferenceResult<()> {
         self.server.borrow_mut().clear_state()
     }
+
+    fn perplexity(&self, args: PerplexityArgs) -> InferenceResult<Stats> {
+        self.server.borrow_mut().perplexity(args)
+    }
+
+    fn loglikelihood(
+        &self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        self.server.borrow_mut().loglikelihood(requests)
+    }
+
+    fn tokenize(&self, task: InferenceTask) -> InferenceResult<Tokenization> {
+        self.server.borrow_mut().tokenize(task)
+    }
+
+    fn detokenize(&self, tokens: Vec<u32>) -> InferenceResult<String> {
+        self.server.borrow_mut().detokenize(tokens)
+    }
 }
@@ -0,0 +1,749 @@
+use std::{
+    fmt::Debug,
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    errors::{InferenceError, InferenceResult},
+    job::{GeneratedItem, InferenceJobListener},
+    plugin::InferencePlugin,
+    server::InferenceServer,
+    DeviceSettings, InferenceJob, InferenceTask, Loglikelihood, LoglikelihoodRequest,
+    PerplexityArgs, QuantizationScheme, Stats, Tokenization, TrainingArgs,
+};
+
+use super::InferenceChannel;
+
+/// Environment variable naming the model served by a worker process.
+pub const WORKER_MODEL_ENV: &str = "BURNLM_WORKER_MODEL";
+/// Environment variable giving the address of the parent process to a worker process.
+const WORKER_ADDRESS_ENV: &str = "BURNLM_WORKER_ADDRESS";
+/// Environment variable giving the token a worker process sends back to its parent, so that
+/// the parent doesn't talk to another local process connecting first.
+const WORKER_TOKEN_ENV: &str = "BURNLM_WORKER_TOKEN";
+/// Maximum duration for a worker process to connect to its parent.
+const WORKER_STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A request sent to a worker, one per [InferenceChannel] call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Request {
+    /// The values of the arguments set on the command line, by argument id.
+    ParseCliConfig(Vec<(String, Vec<String>)>),
+    ParseJsonConfig(String),
+    SetDevice(DeviceSettings),
+    Load,
+    IsLoaded,
+    Unload,
+    RunJob(InferenceTask),
+    ClearState,
+    Perplexity(PerplexityArgs),
+    Loglikelihood(Vec<LoglikelihoodRequest>),
+    Tokenize(InferenceTask),
+    Detokenize(Vec<u32>),
+}
+
+/// A response sent by a worker.
+#[derive(Debug, Serialize, Deserialize)]
+enum Response {
+    /// The worker is connected and waits for requests.
+    Ready { token: String },
+    /// Text generated by the running job, sent before the job result.
+    Text(String),
+    /// The result of the request.
+    Done(Result<serde_json::Value, InferenceError>),
+}
+
+/// Channel running the server in a child process.
+///
+/// The child process is the current executable started with the [WORKER_MODEL_ENV]
+/// environment variable, so the executable must call [run_worker] with the model plugin
+/// before anything else when this variable is set. The worker process is started on the first
+/// request which needs the model, and a panic or crash of the model only terminates the worker:
+/// the request fails with [InferenceError::WorkerError] and a new worker is started with the
+/// last configuration.
+///
+/// The download, deletion, quantization and training functions are not serializable, they
+/// are run by the current process.
+#[derive(Debug, Clone)]
+pub struct ProcessChannel<Server: InferenceServer> {
+    server: Arc<Mutex<Server>>,
+    worker: Arc<Mutex<Worker>>,
+}
+
+impl<Server: InferenceServer> ProcessChannel<Server> {
+    /// Create a channel running the model registered as `model_name` in a worker process.
+    pub fn new(model_name: &'static str) -> Self {
+        Self::with_launcher(model_name, Arc::new(spawn_worker))
+    }
+
+    fn with_launcher(model_name: &'static str, launcher: Launcher) -> Self {
+        Self {
+            server: Arc::new(Mutex::new(Server::default())),
+            worker: Arc::new(Mutex::new(Worker {
+                model_name,
+                launcher,
+                connection: None,
+                config: None,
+                device: None,
+            })),
+        }
+    }
+
+    fn request<T: DeserializeOwned>(&self, request: Request) -> InferenceResult<T> {
+        self.request_streaming(request, |_| {})
+    }
+
+    fn request_streaming<T: DeserializeOwned>(
+        &self,
+        request: Request,
+        on_text: impl FnMut(String),
+    ) -> InferenceResult<T> {
+        let mut worker = self.worker.lock().unwrap();
+        let value = worker.request(&request, on_text)?;
+        serde_json::from_value(value)
+            .map_err(|err| InferenceError::WorkerError(worker.model_name.into(), err.to_string()))
+    }
+
+    fn configure(&self, request: Request) {
+        let mut worker = self.worker.lock().unwrap();
+        match request {
+            Request::SetDevice(_) => worker.device = Some(request.clone()),
+            _ => worker.config = Some(request.clone()),
+        }
+        if worker.connection.is_some() {
+            if let Err(err) = worker.request(&request, |_| {}) {
+                tracing::error!("{err}");
+            }
+        }
+    }
+}
+
+impl<Server: InferenceServer> InferenceChannel<Server> for ProcessChannel<Server> {
+    fn downloader(&self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
+        let mut server = self.server.lock().unwrap();
+        server.downloader()
+    }
+
+    fn is_downloaded(&self) -> bool {
+        let mut server = self.server.lock().unwrap();
+        server.is_downloaded()
+    }
+
+    fn deleter(&self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+        let mut server = self.server.lock().unwrap();
+        server.deleter()
+    }
+
+    fn quantizer(
+        &self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>> {
+        let mut server = self.server.lock().unwrap();
+        server.quantizer()
+    }
+
+    fn trainer(
+        &self,
+    ) -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>> {
+        let mut server = self.server.lock().unwrap();
+        server.trainer()
+    }
+
+    fn parse_cli_config(&self, args: &clap::ArgMatches) {
+        self.configure(Request::ParseCliConfig(cli_values(args)));
+    }
+
+    fn parse_json_config(&self, json: &str) {
+        self.configure(Request::ParseJsonConfig(json.to_string()));
+    }
+
+    fn set_device(&self, settings: DeviceSettings) {
+        self.configure(Request::SetDevice(settings));
+    }
+
+    fn load(&self) -> InferenceResult<Option<Stats>> {
+        self.request(Request::Load)
+    }
+
+    fn is_loaded(&self) -> bool {
+        // A worker is only started to load the model
+        if self.worker.lock().unwrap().connection.is_none() {
+            return false;
+        }
+        self.request(Request::IsLoaded).unwrap_or(false)
+    }
+
+    fn unload(&self) -> InferenceResult<Option<Stats>> {
+        if self.worker.lock().unwrap().connection.is_none() {
+            return Ok(None);
+        }
+        self.request(Request::Unload)
+    }
+
+    fn run_job(&self, job: InferenceJob) -> InferenceResult<Stats> {
+        let emitter = job.emitter;
+        self.request_streaming(Request::RunJob(job.task), |text| {
+            emitter.completed(GeneratedItem::Text(text))
+        })
+    }
+
+    fn clear_state(&self) -> InferenceResult<()> {
+        self.request(Request::ClearState)
+    }
+
+    fn perplexity(&self, args: PerplexityArgs) -> InferenceResult<Stats> {
+        self.request(Request::Perplexity(args))
+    }
+
+    fn loglikelihood(
+        &self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        self.request(Request::Loglikelihood(requests))
+    }
+
+    fn tokenize(&self, task: InferenceTask) -> InferenceResult<Tokenization> {
+        self.request(Request::Tokenize(task))
+    }
+
+    fn detokenize(&self, tokens: Vec<u32>) -> InferenceResult<String> {
+        self.request(Request::Detokenize(tokens))
+    }
+}
+
+/// Start a worker for a model and return its connection.
+type Launcher = Arc<dyn Fn(&'static str) -> std::io::Result<Connection> + Send + Sync>;
+
+/// The worker of a [ProcessChannel].
+struct Worker {
+    model_name: &'static str,
+    launcher: Launcher,
+    connection: Option<Connection>,
+    /// The last configuration and device requests, replayed on new workers.
+    config: Option<Request>,
+    device: Option<Request>,
+}
+
+impl Debug for Worker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Worker")
+            .field("model_name", &self.model_name)
+            .field("connection", &self.connection)
+            .finish()
+    }
+}
+
+impl Worker {
+    fn request(
+        &mut self,
+        request: &Request,
+        on_text: impl FnMut(String),
+    ) -> InferenceResult<serde_json::Value> {
+        let model_name = self.model_name;
+        let error = |reason: String| InferenceError::WorkerError(model_name.into(), reason);
+        if self.connection.is_none() {
+            let connection = self.start().map_err(|err| error(err.to_string()))?;
+            self.connection = Some(connection);
+        }
+        let connection = self.connection.as_mut().expect("worker should be started");
+        match connection.exchange(request, on_text) {
+            Ok(result) => result,
+            Err(err) => {
+                let reason = match connection.terminate() {
+                    Some(status) => format!("worker exited with {status}"),
+                    None => err.to_string(),
+                };
+                self.connection = None;
+                tracing::error!("Model worker of '{model_name}' failed: {reason}");
+                // Restart the worker right away so that it is ready for the next request
+                match self.start() {
+                    Ok(connection) => self.connection = Some(connection),
+                    Err(err) => tracing::error!("Failed to restart model worker: {err}"),
+                }
+                Err(error(reason))
+            }
+        }
+    }
+
+    /// Start a worker with the current configuration.
+    fn start(&self) -> std::io::Result<Connection> {
+        let mut connection = (self.launcher)(self.model_name)?;
+        for request in self.config.iter().chain(&self.device) {
+            connection
+                .exchange(request, |_| {})?
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(connection)
+    }
+}
+
+/// The connection to a worker.
+#[derive(Debug)]
+struct Connection {
+    child: Option<Child>,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Accept the connection of a worker and check its token.
+    fn accept(
+        listener: &TcpListener,
+        token: &str,
+        mut child: Option<Child>,
+    ) -> std::io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        let started = Instant::now();
+        let stream = loop {
+            match listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    if let Some(child) = child.as_mut() {
+                        if let Some(status) = child.try_wait()? {
+                            return Err(std::io::Error::other(format!(
+                                "worker exited during startup with {status}"
+                            )));
+                        }
+                    }
+                    if started.elapsed() > WORKER_STARTUP_TIMEOUT {
+                        if let Some(child) = child.as_mut() {
+                            child.kill().ok();
+                            child.wait().ok();
+                        }
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            "worker didn't connect",
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        stream.set_nonblocking(false)?;
+        let mut connection = Self {
+            child,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        match connection.receive()? {
+            Response::Ready { token: received } if received == token => Ok(connection),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid worker handshake",
+            )),
+        }
+    }
+
+    /// Send a request and wait for its result, forwarding the generated text.
+    ///
+    /// An I/O error means that the worker is gone.
+    fn exchange(
+        &mut self,
+        request: &Request,
+        mut on_text: impl FnMut(String),
+    ) -> std::io::Result<Result<serde_json::Value, InferenceError>> {
+        send(&mut self.writer, request)?;
+        loop {
+            match self.receive()? {
+                Response::Text(text) => on_text(text),
+                Response::Done(result) => return Ok(result),
+                Response::Ready { .. } => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "unexpected worker handshake",
+                    ))
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self) -> std::io::Result<Response> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "worker closed the connection",
+            ));
+        }
+        serde_json::from_str(&line).map_err(std::io::Error::other)
+    }
+
+    /// Terminate the worker process and return its exit status.
+    fn terminate(&mut self) -> Option<std::process::ExitStatus> {
+        self.writer.shutdown(std::net::Shutdown::Both).ok();
+        let child = self.child.as_mut()?;
+        // Give a crashing worker some time to exit by itself to report its status
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(1) {
+            if let Ok(Some(status)) = child.try_wait() {
+                return Some(status);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        child.kill().ok();
+        child.wait().ok()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.writer.shutdown(std::net::Shutdown::Both).ok();
+        if let Some(child) = self.child.as_mut() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+fn send<T: Serialize>(writer: &mut impl Write, message: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(message).map_err(std::io::Error::other)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+/// Start a worker process running the current executable.
+fn spawn_worker(model_name: &'static str) -> std::io::Result<Connection> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let token = worker_token();
+    let child = Command::new(std::env::current_exe()?)
+        .env(WORKER_MODEL_ENV, model_name)
+        .env(WORKER_ADDRESS_ENV, listener.local_addr()?.to_string())
+        .env(WORKER_TOKEN_ENV, &token)
+        .stdin(Stdio::null())
+        .spawn()?;
+    Connection::accept(&listener, &token, Some(child))
+}
+
+fn worker_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    format!("{:016x}", hasher.finish())
+}
+
+/// The values of the arguments set on the command line, by argument id.
+fn cli_values(args: &clap::ArgMatches) -> Vec<(String, Vec<String>)> {
+    args.ids()
+        .filter(|id| args.value_source(id.as_str()) == Some(clap::parser::ValueSource::CommandLine))
+        .map(|id| {
+            let values = args
+                .get_raw(id.as_str())
+                .map(|values| {
+                    values
+                        .map(|value| value.to_string_lossy().into_owned())
+                        .collect()
+                })
+                .unwrap_or_default();
+            (id.to_string(), values)
+        })
+        .collect()
+}
+
+/// Parse the command line values of a [Request::ParseCliConfig] with the plugin arguments.
+fn cli_matches(
+    plugin: &dyn InferencePlugin,
+    values: Vec<(String, Vec<String>)>,
+) -> Result<clap::ArgMatches, clap::Error> {
+    let command = (plugin.create_cli_flags_fn())();
+    let mut cli_args = vec![command.get_name().to_string()];
+    for (id, values) in values {
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == id.as_str())
+        else {
+            continue;
+        };
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if arg.get_action().takes_values() {
+            cli_args.extend(values.iter().map(|value| format!("--{long}={value}")));
+        } else {
+            cli_args.push(format!("--{long}"));
+        }
+    }
+    command.try_get_matches_from(cli_args)
+}
+
+/// Return the model to serve when the current process is a worker started by a
+/// [ProcessChannel].
+pub fn worker_model() -> Option<String> {
+    std::env::var(WORKER_MODEL_ENV).ok()
+}
+
+/// Serve the requests of the parent process with the model plugin until the parent closes the
+/// connection.
+///
+/// This is the entry point of the worker processes, see [ProcessChannel].
+pub fn run_worker(plugin: Box<dyn InferencePlugin>) -> std::io::Result<()> {
+    let env = |name: &str| {
+        std::env::var(name).map_err(|_| std::io::Error::other(format!("{name} is not set")))
+    };
+    let stream = TcpStream::connect(env(WORKER_ADDRESS_ENV)?)?;
+    serve(plugin.as_ref(), stream, &env(WORKER_TOKEN_ENV)?)
+}
+
+fn serve(plugin: &dyn InferencePlugin, stream: TcpStream, token: &str) -> std::io::Result<()> {
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let response = Response::Ready {
+        token: token.to_string(),
+    };
+    send(&mut *writer.lock().unwrap(), &response)?;
+    for line in BufReader::new(stream).lines() {
+        let request: Request = serde_json::from_str(&line?).map_err(std::io::Error::other)?;
+        let result = handle(plugin, request, &writer);
+        send(&mut *writer.lock().unwrap(), &Response::Done(result))?;
+    }
+    Ok(())
+}
+
+fn handle(
+    plugin: &dyn InferencePlugin,
+    request: Request,
+    writer: &Arc<Mutex<TcpStream>>,
+) -> Result<serde_json::Value, InferenceError> {
+    fn value<T: Serialize>(value: T) -> serde_json::Value {
+        serde_json::to_value(value).expect("should serialize the result")
+    }
+
+    match request {
+        Request::ParseCliConfig(values) => {
+            let matches = cli_matches(plugin, values).map_err(|err| {
+                InferenceError::WorkerError(plugin.model_name().into(), err.to_string())
+            })?;
+            plugin.parse_cli_config(&matches);
+            Ok(value(()))
+        }
+        Request::ParseJsonConfig(json) => {
+            plugin.parse_json_config(&json);
+            Ok(value(()))
+        }
+        Request::SetDevice(settings) => plugin.set_device(settings).map(value),
+        Request::Load => plugin.load().map(value),
+        Request::IsLoaded => Ok(value(plugin.is_loaded())),
+        Request::Unload => plugin.unload().map(value),
+        Request::RunJob(task) => {
+            let listener = StreamListener {
+                writer: writer.clone(),
+            };
+            let (job, handle) = InferenceJob::create(task, listener);
+            let result = plugin.run_job(job).map(value);
+            // Wait for the generated text to be sent before the result
+            handle.join();
+            result
+        }
+        Request::ClearState => plugin.clear_state().map(value),
+        Request::Perplexity(args) => plugin.perplexity(args).map(value),
+        Request::Loglikelihood(requests) => plugin.loglikelihood(requests).map(value),
+        Request::Tokenize(task) => plugin.tokenize(task).map(value),
+        Request::Detokenize(tokens) => plugin.detokenize(tokens).map(value),
+    }
+}
+
+/// Listener sending the generated text to the parent process.
+struct StreamListener {
+    writer: Arc<Mutex<TcpStream>>,
+}
+
+impl InferenceJobListener for StreamListener {
+    type CompletedItem = ();
+
+    fn on_text(&mut self, text: String) {
+        // A failure means that the parent is gone, which also ends the request loop
+        send(&mut *self.writer.lock().unwrap(), &Response::Text(text)).ok();
+    }
+
+    fn on_finished(self) -> Self::CompletedItem {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inference_server_config, CommandFactory, FromArgMatches, InferenceClient,
+        InferenceServerConfig, MutexChannel, Parser, ServerConfigParsing, StatEntry,
+        TextGenerationListener,
+    };
+
+    #[inference_server_config]
+    pub struct EchoServerConfig {
+        /// Repeat the prompt.
+        #[config(default = 1)]
+        pub repeat: usize,
+    }
+
+    /// Server echoing its prompt, panicking on the `panic` prompt.
+    #[derive(Debug, Clone, Default)]
+    struct EchoServer {
+        config: EchoServerConfig,
+        loaded: bool,
+    }
+
+    impl ServerConfigParsing for EchoServer {
+        type Config = EchoServerConfig;
+
+        fn parse_cli_config(&mut self, args: &clap::ArgMatches) {
+            self.config = Self::Config::from_arg_matches(args).unwrap();
+        }
+
+        fn parse_json_config(&mut self, json: &str) {
+            self.config = serde_json::from_str(json).unwrap();
+        }
+    }
+
+    impl InferenceServer for EchoServer {
+        fn load(&mut self) -> InferenceResult<Option<Stats>> {
+            self.loaded = true;
+            Ok(None)
+        }
+
+        fn is_loaded(&mut self) -> bool {
+            self.loaded
+        }
+
+        fn unload(&mut self) -> InferenceResult<Option<Stats>> {
+            self.loaded = false;
+            Ok(None)
+        }
+
+        fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
+            let InferenceTask::Prompt(prompt) = job.task else {
+                return Err(InferenceError::GenerationError(
+                    "Echo".to_string(),
+                    "only prompts are echoed".to_string(),
+                ));
+            };
+            assert_ne!(prompt, "panic", "the model panicked");
+            for _ in 0..self.config.repeat {
+                for word in prompt.split_inclusive(' ') {
+                    job.emitter.completed(GeneratedItem::Text(word.to_string()));
+                }
+            }
+            let mut stats = Stats::new();
+            stats
+                .entries
+                .insert(StatEntry::TokensCount(prompt.split(' ').count()));
+            Ok(stats)
+        }
+
+        fn clear_state(&mut self) -> InferenceResult<()> {
+            Err(InferenceError::ModelNotLoaded)
+        }
+    }
+
+    /// Launch the workers on threads of the current process, a panic of the model ends the
+    /// thread and closes the connection like a crash of a worker process.
+    fn thread_launcher(starts: Arc<Mutex<usize>>) -> Launcher {
+        Arc::new(move |model_name| {
+            *starts.lock().unwrap() += 1;
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+            let address = listener.local_addr()?;
+            std::thread::spawn(move || {
+                let plugin = InferenceClient::new(
+                    model_name,
+                    "echo",
+                    "",
+                    "",
+                    EchoServerConfig::command,
+                    MutexChannel::<EchoServer>::new(),
+                );
+                let stream = TcpStream::connect(address).unwrap();
+                serve(&plugin, stream, "token").ok();
+            });
+            Connection::accept(&listener, "token", None)
+        })
+    }
+
+    fn run(channel: &ProcessChannel<EchoServer>, prompt: &str) -> InferenceResult<String> {
+        let (job, handle) = InferenceJob::create(
+            InferenceTask::Prompt(prompt.to_string()),
+            TextGenerationListener::default(),
+        );
+        channel.run_job(job)?;
+        Ok(handle.join())
+    }
+
+    #[test]
+    fn test_process_channel_run_job() {
+        let channel = ProcessChannel::<EchoServer>::with_launcher(
+            "Echo",
+            thread_launcher(Default::default()),
+        );
+        assert!(!channel.is_loaded());
+        assert!(channel.load().unwrap().is_none());
+        assert!(channel.is_loaded());
+
+        let (job, handle) = InferenceJob::create(
+            InferenceTask::Prompt("hello streaming world".to_string()),
+            TextGenerationListener::default(),
+        );
+        let stats = channel.run_job(job).unwrap();
+        assert_eq!(handle.join(), "hello streaming world");
+        assert!(stats.entries.contains(&StatEntry::TokensCount(3)));
+
+        assert!(matches!(
+            channel.clear_state(),
+            Err(InferenceError::ModelNotLoaded)
+        ));
+        channel.unload().unwrap();
+        assert!(!channel.is_loaded());
+    }
+
+    #[test]
+    fn test_process_channel_config() {
+        let channel = ProcessChannel::<EchoServer>::with_launcher(
+            "Echo",
+            thread_launcher(Default::default()),
+        );
+        channel.parse_json_config(r#"{"repeat": 2}"#);
+        assert_eq!(run(&channel, "echo ").unwrap(), "echo echo ");
+
+        let args = EchoServerConfig::command()
+            .try_get_matches_from(["echo", "--repeat", "3"])
+            .unwrap();
+        channel.parse_cli_config(&args);
+        assert_eq!(run(&channel, "echo ").unwrap(), "echo echo echo ");
+    }
+
+    #[test]
+    fn test_process_channel_restarts_crashed_worker() {
+        let starts = Arc::new(Mutex::new(0));
+        let channel =
+            ProcessChannel::<EchoServer>::with_launcher("Echo", thread_launcher(starts.clone()));
+        channel.parse_json_config(r#"{"repeat": 2}"#);
+        channel.load().unwrap();
+
+        let result = run(&channel, "panic");
+        assert!(matches!(result, Err(InferenceError::WorkerError(..))));
+        assert_eq!(*starts.lock().unwrap(), 2);
+
+        // The restarted worker has the same configuration but the model isn't loaded
+        assert!(!channel.is_loaded());
+        assert_eq!(run(&channel, "echo ").unwrap(), "echo echo ");
+    }
+
+    #[test]
+    fn test_cli_values() {
+        let args = EchoServerConfig::command()
+            .try_get_matches_from(["echo", "--repeat", "3"])
+            .unwrap();
+        assert!(cli_values(&args).contains(&("repeat".to_string(), vec!["3".to_string()])));
+
+        let args = EchoServerConfig::command()
+            .try_get_matches_from(["echo"])
+            .unwrap();
+        assert!(cli_values(&args).is_empty());
+    }
+}
@@ -1,11 +1,15 @@
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
 
 use crate::{
     channels::InferenceChannel,
     errors::InferenceResult,
     plugin::{CreateCliFlagsFn, InferencePlugin},
     server::InferenceServer,
-    InferenceJob, Stats,
+    DeviceSettings, InferenceError, InferenceJob, InferenceTask, Loglikelihood,
+    LoglikelihoodRequest, PerplexityArgs, QuantizationScheme, Stats, Tokenization, TrainingArgs,
 };
 
 #[derive(Debug, Clone)]
@@ -16,6 +20,7 @@ pub struct InferenceClient<Server: InferenceServer + 'static, Channel: 'static>
     created_by: &'static str,
     create_cli_flags_fn: CreateCliFlagsFn,
     channel: Channel,
+    device: Arc<Mutex<DeviceSettings>>,
     _phantom_server: PhantomData<Server>,
 }
 
@@ -33,6 +38,13 @@ where
         create_cli_flags_fn: CreateCliFlagsFn,
         channel: Channel,
     ) -> Self {
+        let device = Server::PREFERRED_DTYPE.map_or_else(
+            DeviceSettings::default,
+            DeviceSettings::with_preferred_dtype,
+        );
+        if device != DeviceSettings::default() {
+            channel.set_device(device);
+        }
         Self {
             model_name,
             model_cli_param_name,
@@ -40,9 +52,23 @@ where
             created_by,
             create_cli_flags_fn,
             channel,
+            device: Arc::new(Mutex::new(device)),
             _phantom_server: PhantomData,
         }
     }
+
+    /// Release the memory of the model device.
+    fn memory_cleanup(&self, sync: bool) {
+        // A device which can't be created holds no memory
+        let Ok(device) = self.device_settings().device() else {
+            return;
+        };
+        device.memory_cleanup();
+        if sync {
+            // Force pending deallocations to complete
+            device.sync().unwrap();
+        }
+    }
 }
 
 impl<Server, Channel> InferencePlugin for InferenceClient<Server, Channel>
@@ -54,7 +80,7 @@ where
         Box::new(self.clone())
     }
 
-    fn downloader(&self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+    fn downloader(&self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
         self.channel.downloader()
     }
 
@@ -65,11 +91,23 @@ where
     fn deleter(&self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
         let result = self.channel.deleter();
 
-        crate::INFERENCE_DEVICE.memory_cleanup();
+        self.memory_cleanup(false);
 
         result
     }
 
+    fn quantizer(
+        &self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>> {
+        self.channel.quantizer()
+    }
+
+    fn trainer(
+        &self,
+    ) -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>> {
+        self.channel.trainer()
+    }
+
     fn parse_cli_config(&self, args: &clap::ArgMatches) {
         self.channel.parse_cli_config(args);
     }
@@ -78,6 +116,19 @@ where
         self.channel.parse_json_config(json);
     }
 
+    fn set_device(&self, settings: DeviceSettings) -> InferenceResult<()> {
+        settings
+            .validate()
+            .map_err(|reason| InferenceError::DeviceError(self.model_name.to_string(), reason))?;
+        *self.device.lock().unwrap() = settings;
+        self.channel.set_device(settings);
+        Ok(())
+    }
+
+    fn device_settings(&self) -> DeviceSettings {
+        *self.device.lock().unwrap()
+    }
+
     fn load(&self) -> InferenceResult<Option<Stats>> {
         self.channel.load()
     }
@@ -89,10 +140,7 @@ where
     fn unload(&self) -> InferenceResult<Option<Stats>> {
         let result = self.channel.unload();
 
-        let device = &*crate::INFERENCE_DEVICE;
-        device.memory_cleanup();
-        // Force pending deallocations to complete
-        device.sync().unwrap();
+        self.memory_cleanup(true);
 
         result
     }
@@ -124,4 +172,23 @@ where
     fn clear_state(&self) -> InferenceResult<()> {
         self.channel.clear_state()
     }
+
+    fn perplexity(&self, args: PerplexityArgs) -> InferenceResult<Stats> {
+        self.channel.perplexity(args)
+    }
+
+    fn loglikelihood(
+        &self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        self.channel.loglikelihood(requests)
+    }
+
+    fn tokenize(&self, task: InferenceTask) -> InferenceResult<Tokenization> {
+        self.channel.tokenize(task)
+    }
+
+    fn detokenize(&self, tokens: Vec<u32>) -> InferenceResult<String> {
+        self.channel.detokenize(tokens)
+    }
 }
@@ -0,0 +1,341 @@
+//! Conformance tests of [InferenceServer] implementations.
+//!
+//! A model crate runs the tests of its server with
+//! [inference_server_conformance_tests](crate::inference_server_conformance_tests):
+//!
+//! ```ignore
+//! burn_lm_inference::inference_server_conformance_tests!(MyModelServer);
+//! ```
+//!
+//! The streaming test runs the same prompt twice and compares the streamed chunks with the
+//! generated text, so a JSON configuration making the generation deterministic can be given,
+//! e.g. with a temperature of 0:
+//!
+//! ```ignore
+//! burn_lm_inference::inference_server_conformance_tests!(
+//!     MyModelServer,
+//!     config = r#"{"temperature": 0.0, "sample_len": 8}"#
+//! );
+//! ```
+//!
+//! The checks are also available as functions to write custom tests, e.g. for servers
+//! without a `Default` configuration which can run.
+
+use crate::{
+    CommandFactory, FromArgMatches, InferenceError, InferenceJob, InferenceJobListener,
+    InferenceResult, InferenceServer, InferenceTask, Message, MessageRole, ServerConfigParsing,
+    TextGenerationListener,
+};
+
+/// Listener collecting the generated chunks in their emission order.
+#[derive(Default)]
+struct ChunksListener {
+    chunks: Vec<String>,
+}
+
+impl InferenceJobListener for ChunksListener {
+    type CompletedItem = Vec<String>;
+
+    fn on_text(&mut self, text: String) {
+        self.chunks.push(text);
+    }
+
+    fn on_finished(self) -> Self::CompletedItem {
+        self.chunks
+    }
+}
+
+fn server_name<S>() -> &'static str {
+    std::any::type_name::<S>()
+}
+
+fn message(role: MessageRole, content: &str) -> Message {
+    Message {
+        role,
+        content: content.to_string(),
+        refusal: None,
+    }
+}
+
+/// The tasks of each [InferenceTask] variant.
+fn tasks() -> Vec<InferenceTask> {
+    vec![
+        InferenceTask::Message(message(MessageRole::User, "Hello!")),
+        InferenceTask::Context(vec![
+            message(MessageRole::System, "You are a helpful assistant."),
+            message(MessageRole::User, "Hello!"),
+            message(MessageRole::Assistant, "Hello, how can I help you?"),
+            message(MessageRole::User, "Say hello again."),
+        ]),
+        InferenceTask::Prompt("Once upon a time".to_string()),
+    ]
+}
+
+fn load<S: InferenceServer>(server: &mut S) {
+    if let Err(err) = server.load() {
+        panic!("{} should load: {err}", server_name::<S>());
+    }
+}
+
+/// Check that the default configuration of the server parses from the CLI and from JSON with
+/// the same values.
+pub fn check_config_parsing<S>()
+where
+    S: InferenceServer,
+    S::Config: CommandFactory,
+{
+    let name = server_name::<S>();
+    let command = S::Config::command();
+    let matches = command
+        .clone()
+        .try_get_matches_from([command.get_name().to_string()])
+        .unwrap_or_else(|err| panic!("{name} config should parse without CLI arguments: {err}"));
+    let cli_config = S::Config::from_arg_matches(&matches)
+        .unwrap_or_else(|err| panic!("{name} config should parse from CLI arguments: {err}"));
+    let json_config: S::Config = serde_json::from_str("{}")
+        .unwrap_or_else(|err| panic!("{name} config should have JSON default values: {err}"));
+    assert_eq!(
+        format!("{cli_config:?}"),
+        format!("{json_config:?}"),
+        "{name} config should have the same CLI and JSON default values"
+    );
+
+    let mut server = S::default();
+    server.parse_cli_config(&matches);
+    server.parse_json_config("{}");
+}
+
+/// Check the [is_loaded](InferenceServer::is_loaded) transitions and that loading a loaded
+/// model and unloading an unloaded model have no effect.
+pub fn check_load_unload<S: InferenceServer>(server: &mut S) {
+    let name = server_name::<S>();
+    assert!(!server.is_loaded(), "{name} should not be loaded initially");
+
+    load(server);
+    assert!(server.is_loaded(), "{name} should be loaded after load");
+    let stats = server
+        .load()
+        .unwrap_or_else(|err| panic!("{name} should load a loaded model: {err}"));
+    assert!(
+        stats.is_none(),
+        "{name} should not reload a loaded model, got {stats:?}"
+    );
+    assert!(server.is_loaded(), "{name} should stay loaded");
+
+    server
+        .unload()
+        .unwrap_or_else(|err| panic!("{name} should unload: {err}"));
+    assert!(
+        !server.is_loaded(),
+        "{name} should not be loaded after unload"
+    );
+    server
+        .unload()
+        .unwrap_or_else(|err| panic!("{name} should unload an unloaded model: {err}"));
+    assert!(!server.is_loaded(), "{name} should stay unloaded");
+
+    load(server);
+    assert!(server.is_loaded(), "{name} should load again after unload");
+    server.unload().unwrap();
+}
+
+/// Check that [clear_state](InferenceServer::clear_state) fails with
+/// [ModelNotLoaded](InferenceError::ModelNotLoaded) when the model is not loaded.
+pub fn check_clear_state<S: InferenceServer>(server: &mut S) {
+    let name = server_name::<S>();
+    let not_loaded =
+        |result: InferenceResult<()>| matches!(result, Err(InferenceError::ModelNotLoaded));
+    assert!(
+        not_loaded(server.clear_state()),
+        "{name} should fail to clear the state of a model which is not loaded"
+    );
+
+    load(server);
+    server
+        .clear_state()
+        .unwrap_or_else(|err| panic!("{name} should clear the state of a loaded model: {err}"));
+
+    server.unload().unwrap();
+    assert!(
+        not_loaded(server.clear_state()),
+        "{name} should fail to clear the state of an unloaded model"
+    );
+}
+
+/// Check that the loaded model runs a job of each [InferenceTask] variant.
+pub fn check_run_job<S: InferenceServer>(server: &mut S) {
+    let name = server_name::<S>();
+    load(server);
+    for task in tasks() {
+        let description = format!("{task:?}");
+        let (job, handle) = InferenceJob::create(task, TextGenerationListener::default());
+        if let Err(err) = server.run_job(job) {
+            panic!("{name} should run the task {description}: {err}");
+        }
+        handle.join();
+        server.clear_state().unwrap_or_else(|err| {
+            panic!("{name} should clear the state after the task {description}: {err}")
+        });
+    }
+    server.unload().unwrap();
+}
+
+/// Check that the streamed chunks of a job are emitted in their generation order, by
+/// comparing them with the text of a second run.
+///
+/// The configuration of the server must make the generation deterministic.
+pub fn check_streaming<S: InferenceServer>(server: &mut S) {
+    let name = server_name::<S>();
+    let task = InferenceTask::Prompt("Once upon a time".to_string());
+    load(server);
+
+    let (job, handle) = InferenceJob::create(task.clone(), ChunksListener::default());
+    server
+        .run_job(job)
+        .unwrap_or_else(|err| panic!("{name} should run the streaming job: {err}"));
+    let chunks = handle.join();
+    assert!(!chunks.is_empty(), "{name} should emit the generated text");
+    server.clear_state().unwrap();
+
+    let (job, handle) = InferenceJob::create(task, TextGenerationListener::default());
+    server
+        .run_job(job)
+        .unwrap_or_else(|err| panic!("{name} should run the job again: {err}"));
+    assert_eq!(
+        chunks.concat(),
+        handle.join(),
+        "{name} should stream the chunks in their generation order (the generation must be \
+         deterministic with the test configuration)"
+    );
+    server.unload().unwrap();
+}
+
+/// Generate the conformance tests of an [InferenceServer](crate::InferenceServer) in an
+/// `inference_server_conformance` test module, see the [conformance](crate::conformance)
+/// module.
+///
+/// The server is created with `Default` and configured with the optional JSON `config`.
+#[macro_export]
+macro_rules! inference_server_conformance_tests {
+    ($server:ty) => {
+        $crate::inference_server_conformance_tests!($server, config = "{}");
+    };
+    ($server:ty, config = $config:expr) => {
+        #[cfg(test)]
+        mod inference_server_conformance {
+            #[allow(unused_imports)]
+            use super::*;
+
+            fn server() -> $server {
+                let mut server = <$server as ::std::default::Default>::default();
+                $crate::ServerConfigParsing::parse_json_config(&mut server, $config);
+                server
+            }
+
+            #[test]
+            fn config_parsing() {
+                $crate::conformance::check_config_parsing::<$server>();
+            }
+
+            #[test]
+            fn load_unload() {
+                $crate::conformance::check_load_unload(&mut server());
+            }
+
+            #[test]
+            fn clear_state() {
+                $crate::conformance::check_clear_state(&mut server());
+            }
+
+            #[test]
+            fn run_job() {
+                $crate::conformance::check_run_job(&mut server());
+            }
+
+            #[test]
+            fn streaming() {
+                $crate::conformance::check_streaming(&mut server());
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inference_server_config, GeneratedItem, InferenceServerConfig, Parser, Stats};
+
+    #[inference_server_config]
+    pub struct WordsServerConfig {
+        /// Number of words to generate.
+        #[config(default = 3)]
+        pub words: usize,
+    }
+
+    /// Server generating numbered words, which can't be unloaded when `sticky` is set.
+    #[derive(Debug, Clone, Default)]
+    struct WordsServer {
+        config: WordsServerConfig,
+        loaded: bool,
+        sticky: bool,
+    }
+
+    impl ServerConfigParsing for WordsServer {
+        type Config = WordsServerConfig;
+
+        fn parse_cli_config(&mut self, args: &clap::ArgMatches) {
+            self.config = Self::Config::from_arg_matches(args).unwrap();
+        }
+
+        fn parse_json_config(&mut self, json: &str) {
+            self.config = serde_json::from_str(json).unwrap();
+        }
+    }
+
+    impl InferenceServer for WordsServer {
+        fn load(&mut self) -> InferenceResult<Option<Stats>> {
+            if self.loaded {
+                return Ok(None);
+            }
+            self.loaded = true;
+            Ok(Some(Stats::new()))
+        }
+
+        fn is_loaded(&mut self) -> bool {
+            self.loaded
+        }
+
+        fn unload(&mut self) -> InferenceResult<Option<Stats>> {
+            self.loaded = self.sticky;
+            Ok(None)
+        }
+
+        fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
+            for word in 0..self.config.words {
+                job.emitter
+                    .completed(GeneratedItem::Text(format!("word{word} ")));
+            }
+            Ok(Stats::new())
+        }
+
+        fn clear_state(&mut self) -> InferenceResult<()> {
+            if self.loaded {
+                Ok(())
+            } else {
+                Err(InferenceError::ModelNotLoaded)
+            }
+        }
+    }
+
+    inference_server_conformance_tests!(WordsServer, config = r#"{"words": 5}"#);
+
+    #[test]
+    #[should_panic(expected = "should not be loaded after unload")]
+    fn test_conformance_sticky_server() {
+        let mut server = WordsServer {
+            sticky: true,
+            ..Default::default()
+        };
+        check_load_unload(&mut server);
+    }
+}
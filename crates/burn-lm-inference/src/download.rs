@@ -0,0 +1,669 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+/// Environment variable overriding the local cache directory of the models.
+pub const CACHE_DIR_ENV: &str = "BURNLM_CACHE_DIR";
+/// Environment variable overriding the base URL of the model hub, e.g. to use a mirror.
+pub const MIRROR_ENV: &str = "BURNLM_MIRROR";
+/// Base URL of the model hub.
+pub const DEFAULT_ENDPOINT: &str = "https://huggingface.co";
+
+const PARTIAL_EXTENSION: &str = "partial";
+const MAX_REDIRECTS: usize = 10;
+const CHUNK_SIZE: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+pub type DownloadResult<T> = Result<T, DownloadError>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum DownloadError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Request to '{0}' failed: {1}")]
+    Http(String, String),
+    #[error("File not found: {0}")]
+    NotFound(String),
+    #[error("Checksum mismatch for '{file}': expected {expected}, got {actual}")]
+    Checksum {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("Invalid manifest '{0}': {1}")]
+    Manifest(String, String),
+}
+
+impl From<DownloadError> for std::io::Error {
+    fn from(err: DownloadError) -> Self {
+        match err {
+            DownloadError::Io(err) => err,
+            err => std::io::Error::other(err),
+        }
+    }
+}
+
+/// An event reported while downloading a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadEvent {
+    /// The download of a file started, possibly resuming a previous download.
+    Started {
+        file: String,
+        resumed_from: u64,
+        total: Option<u64>,
+    },
+    /// Bytes were downloaded. Reported at most every few hundred milliseconds.
+    Progress {
+        file: String,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// The file has been downloaded and verified.
+    Finished { file: String },
+}
+
+pub type DownloadListener = Box<dyn Fn(&DownloadEvent) + Send + Sync>;
+
+static LISTENER: RwLock<Option<DownloadListener>> = RwLock::new(None);
+
+/// Set the listener notified of the progress of all the downloads of the process.
+///
+/// Plugins download their files from their own downloader functions, this lets a front-end
+/// like the CLI display their progress.
+pub fn set_download_listener(listener: Option<DownloadListener>) {
+    *LISTENER.write().unwrap() = listener;
+}
+
+fn notify(event: DownloadEvent) {
+    if let Some(listener) = LISTENER.read().unwrap().as_ref() {
+        listener(&event);
+    }
+}
+
+/// Return the cache directory override set with the `BURNLM_CACHE_DIR` environment variable.
+pub fn cache_dir_override() -> Option<PathBuf> {
+    std::env::var_os(CACHE_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// A downloaded file recorded in a [manifest](Manifest).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The URL the file was downloaded from.
+    pub url: String,
+    /// The SHA-256 digest of the file content.
+    pub sha256: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The modification time of the file when it was recorded, in nanoseconds since the Unix
+    /// epoch. Missing from the manifests of older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+}
+
+/// The files downloaded to a directory.
+///
+/// A file is only recorded once it has been completely downloaded and verified, so a
+/// download interrupted midway is never considered as done.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// The file name of the manifest in the download directory.
+    pub const FILE_NAME: &'static str = "manifest.json";
+
+    /// Load the manifest of a directory, empty when there is none.
+    pub fn load<P: AsRef<Path>>(dir: P) -> DownloadResult<Self> {
+        let path = dir.as_ref().join(Self::FILE_NAME);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|err| DownloadError::Manifest(path.display().to_string(), err.to_string()))
+    }
+
+    /// Save the manifest to a directory.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> DownloadResult<()> {
+        let path = dir.as_ref().join(Self::FILE_NAME);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|err| DownloadError::Manifest(path.display().to_string(), err.to_string()))?;
+        write_atomic(&path, content.as_bytes())
+    }
+
+    /// Return true if the file is recorded and still present with its recorded size and
+    /// modification time.
+    ///
+    /// This is a cheap best-effort check which catches truncated, replaced or edited files
+    /// without hashing them, use [verify](Self::verify) to check the content itself.
+    pub fn contains<P: AsRef<Path>>(&self, dir: P, file_name: &str) -> bool {
+        self.files.get(file_name).is_some_and(|entry| {
+            std::fs::metadata(dir.as_ref().join(file_name)).is_ok_and(|metadata| {
+                metadata.len() == entry.size
+                    && entry
+                        .modified
+                        .is_none_or(|modified| modified_nanos(&metadata) == Some(modified))
+            })
+        })
+    }
+
+    /// Check the content of a recorded file against its SHA-256 digest.
+    pub fn verify<P: AsRef<Path>>(&self, dir: P, file_name: &str) -> DownloadResult<()> {
+        let entry = self.files.get(file_name).ok_or_else(|| {
+            DownloadError::Manifest(
+                Self::FILE_NAME.to_string(),
+                format!("'{file_name}' has not been downloaded"),
+            )
+        })?;
+        let mut hasher = Sha256::new();
+        hash_file(&dir.as_ref().join(file_name), &mut hasher)?;
+        check_digest(file_name, &entry.sha256, hasher)
+    }
+
+    /// Record a file created in the directory from the downloaded files, e.g. converted weights.
+    pub fn record<P: AsRef<Path>>(dir: P, file_name: &str, url: &str) -> DownloadResult<()> {
+        let dir = dir.as_ref();
+        let path = dir.join(file_name);
+        let mut hasher = Sha256::new();
+        hash_file(&path, &mut hasher)?;
+        let metadata = std::fs::metadata(&path)?;
+        let entry = ManifestEntry {
+            url: url.to_string(),
+            sha256: check_digest(file_name, "", hasher)?,
+            size: metadata.len(),
+            modified: modified_nanos(&metadata),
+        };
+        let mut manifest = Self::load(dir)?;
+        manifest.files.insert(file_name.to_string(), entry);
+        manifest.save(dir)
+    }
+
+    /// Remove a file and its manifest record.
+    pub fn remove<P: AsRef<Path>>(dir: P, file_name: &str) -> DownloadResult<()> {
+        let dir = dir.as_ref();
+        let mut manifest = Self::load(dir)?;
+        for path in [dir.join(file_name), partial_path(&dir.join(file_name))] {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        if manifest.files.remove(file_name).is_some() {
+            manifest.save(dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// Downloads files to a local directory.
+///
+/// Files are streamed to a `.partial` file which is renamed once complete and verified, an
+/// interrupted download is resumed with an HTTP range request. The SHA-256 digest of each file
+/// is checked against the digest advertised by the hub, when available, and recorded in the
+/// [manifest](Manifest) of the directory.
+#[derive(Debug, Clone)]
+pub struct Downloader {
+    mirror: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Downloader {
+    /// Create a downloader using the mirror set with the `BURNLM_MIRROR` environment variable.
+    pub fn new() -> Self {
+        Self {
+            mirror: std::env::var(MIRROR_ENV).ok().filter(|url| !url.is_empty()),
+            agent: ureq::AgentBuilder::new().redirects(0).build(),
+        }
+    }
+
+    /// Download the hub files from a mirror instead.
+    pub fn with_mirror(mut self, mirror: impl Into<String>) -> Self {
+        self.mirror = Some(mirror.into());
+        self
+    }
+
+    /// Return the URL to download, pointing to the mirror if any.
+    pub fn url(&self, url: &str) -> String {
+        match (&self.mirror, url.strip_prefix(DEFAULT_ENDPOINT)) {
+            (Some(mirror), Some(path)) => format!("{}{path}", mirror.trim_end_matches('/')),
+            _ => url.to_string(),
+        }
+    }
+
+    /// Download a file to a directory unless the manifest records it as already downloaded.
+    pub fn download<P: AsRef<Path>>(
+        &self,
+        url: &str,
+        dir: P,
+        file_name: &str,
+    ) -> DownloadResult<PathBuf> {
+        let dir = dir.as_ref();
+        let path = dir.join(file_name);
+        let mut manifest = Manifest::load(dir)?;
+        if manifest.contains(dir, file_name) {
+            return Ok(path);
+        }
+        std::fs::create_dir_all(dir)?;
+
+        // A file without a manifest record may be incomplete, so it is resumed like a
+        // partial download which only costs a request when it is complete.
+        let partial = partial_path(&path);
+        let unrecorded = path.is_file();
+        if unrecorded {
+            std::fs::rename(&path, &partial)?;
+        }
+
+        let url = self.url(url);
+        let (sha256, size) = match self.fetch(&url, &partial, file_name) {
+            Ok(download) => download,
+            Err(err @ (DownloadError::Http(..) | DownloadError::NotFound(_))) if unrecorded => {
+                // Keep working offline with the files downloaded before the manifest existed
+                std::fs::rename(&partial, &path)?;
+                tracing::warn!("Could not check '{}': {err}", path.display());
+                return Ok(path);
+            }
+            Err(err) => return Err(err),
+        };
+        std::fs::rename(&partial, &path)?;
+
+        let modified = modified_nanos(&std::fs::metadata(&path)?);
+        manifest.files.insert(
+            file_name.to_string(),
+            ManifestEntry {
+                url,
+                sha256,
+                size,
+                modified,
+            },
+        );
+        manifest.save(dir)?;
+        notify(DownloadEvent::Finished {
+            file: file_name.to_string(),
+        });
+        Ok(path)
+    }
+
+    /// Download a URL to the partial file and return its SHA-256 digest and size.
+    fn fetch(&self, url: &str, partial: &Path, file_name: &str) -> DownloadResult<(String, u64)> {
+        let offset = std::fs::metadata(partial).map_or(0, |metadata| metadata.len());
+        let (response, expected) = self.request(url, offset)?;
+
+        let mut hasher = Sha256::new();
+        let Some(response) = response else {
+            // The partial file is already complete
+            hash_file(partial, &mut hasher)?;
+            let sha256 = verify_partial(partial, file_name, expected.as_deref(), hasher)?;
+            return Ok((sha256, offset));
+        };
+
+        let resumed = response.status() == 206;
+        let mut file = if resumed {
+            hash_file(partial, &mut hasher)?;
+            OpenOptions::new().append(true).open(partial)?
+        } else {
+            File::create(partial)?
+        };
+        let mut downloaded = if resumed { offset } else { 0 };
+        let total = response
+            .header("Content-Length")
+            .and_then(|length| length.parse::<u64>().ok())
+            .map(|length| length + downloaded);
+        notify(DownloadEvent::Started {
+            file: file_name.to_string(),
+            resumed_from: downloaded,
+            total,
+        });
+
+        let mut reader = response.into_reader();
+        let mut buffer = vec![0; CHUNK_SIZE];
+        let mut last_progress = Instant::now();
+        loop {
+            let count = reader.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            file.write_all(&buffer[..count])?;
+            hasher.update(&buffer[..count]);
+            downloaded += count as u64;
+            if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                notify(DownloadEvent::Progress {
+                    file: file_name.to_string(),
+                    downloaded,
+                    total,
+                });
+            }
+        }
+        file.sync_all()?;
+
+        if let Some(total) = total.filter(|total| *total != downloaded) {
+            // Keep the partial file to resume the download later
+            return Err(DownloadError::Http(
+                url.to_string(),
+                format!("connection closed after {downloaded} of {total} bytes"),
+            ));
+        }
+        let sha256 = verify_partial(partial, file_name, expected.as_deref(), hasher)?;
+        Ok((sha256, downloaded))
+    }
+
+    /// Request the content of a URL starting at the offset, following redirects.
+    ///
+    /// Return `None` when the range starts at the end of the file and the SHA-256 digest
+    /// advertised along the redirects, if any.
+    fn request(
+        &self,
+        url: &str,
+        offset: u64,
+    ) -> DownloadResult<(Option<ureq::Response>, Option<String>)> {
+        let mut url = url.to_string();
+        let mut expected = None;
+        for _ in 0..MAX_REDIRECTS {
+            let mut request = self.agent.get(&url);
+            if offset > 0 {
+                request = request.set("Range", &format!("bytes={offset}-"));
+            }
+            let response = match request.call() {
+                Ok(response) => response,
+                Err(ureq::Error::Status(416, _)) if offset > 0 => return Ok((None, expected)),
+                Err(ureq::Error::Status(404, _)) => return Err(DownloadError::NotFound(url)),
+                Err(err) => return Err(DownloadError::Http(url, err.to_string())),
+            };
+
+            // The hub advertises the digest of the files stored with Git LFS in the ETag
+            if let Some(etag) = response
+                .header("X-Linked-Etag")
+                .or_else(|| response.header("ETag"))
+                .and_then(sha256_etag)
+            {
+                expected = Some(etag);
+            }
+
+            if !(300..400).contains(&response.status()) {
+                return Ok((Some(response), expected));
+            }
+            let location = response.header("Location").ok_or_else(|| {
+                DownloadError::Http(url.clone(), "redirect without location".to_string())
+            })?;
+            url = redirect_url(&url, location);
+        }
+        Err(DownloadError::Http(url, "too many redirects".to_string()))
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(PARTIAL_EXTENSION);
+    path.with_file_name(file_name)
+}
+
+fn write_atomic(path: &Path, content: &[u8]) -> DownloadResult<()> {
+    let partial = partial_path(path);
+    let mut file = File::create(&partial)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(partial, path)?;
+    Ok(())
+}
+
+fn hash_file(path: &Path, hasher: &mut Sha256) -> DownloadResult<()> {
+    let mut reader = BufReader::with_capacity(CHUNK_SIZE, File::open(path)?);
+    std::io::copy(&mut reader, hasher)?;
+    Ok(())
+}
+
+/// The modification time of a file in nanoseconds since the Unix epoch, if available.
+fn modified_nanos(metadata: &std::fs::Metadata) -> Option<u64> {
+    let elapsed = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(elapsed.as_nanos()).ok()
+}
+
+/// Check the digest of a partial download, deleting it when corrupted so that it is not resumed.
+fn verify_partial(
+    partial: &Path,
+    file_name: &str,
+    expected: Option<&str>,
+    hasher: Sha256,
+) -> DownloadResult<String> {
+    check_digest(file_name, expected.unwrap_or_default(), hasher).inspect_err(|_| {
+        let _ = std::fs::remove_file(partial);
+    })
+}
+
+/// Return the hex digest, checking it against the expected one when not empty.
+fn check_digest(file_name: &str, expected: &str, hasher: Sha256) -> DownloadResult<String> {
+    let actual = format!("{:x}", hasher.finalize());
+    if !expected.is_empty() && !expected.eq_ignore_ascii_case(&actual) {
+        return Err(DownloadError::Checksum {
+            file: file_name.to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(actual)
+}
+
+fn sha256_etag(etag: &str) -> Option<String> {
+    let etag = etag.trim_start_matches("W/").trim_matches('"');
+    (etag.len() == 64 && etag.chars().all(|c| c.is_ascii_hexdigit())).then(|| etag.to_lowercase())
+}
+
+fn redirect_url(url: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    // Relative redirects are resolved against the origin of the URL
+    let origin_end = url
+        .find("://")
+        .and_then(|scheme_end| {
+            url[scheme_end + 3..]
+                .find('/')
+                .map(|path_start| scheme_end + 3 + path_start)
+        })
+        .unwrap_or(url.len());
+    format!(
+        "{}/{}",
+        &url[..origin_end],
+        location.trim_start_matches('/')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Request, Response, TestServer};
+
+    /// The offset of a range request, 0 when the whole file is requested.
+    fn offset(request: &Request) -> u64 {
+        request
+            .header("range")
+            .and_then(|range| range.strip_prefix("bytes="))
+            .map_or(0, |range| range.trim_end_matches('-').parse().unwrap())
+    }
+
+    /// Serve a file over HTTP with range requests support.
+    fn serve(content: Vec<u8>, etag: Option<String>) -> TestServer {
+        TestServer::start(move |request| {
+            let offset = offset(request) as usize;
+            if offset >= content.len() {
+                return Response::status(416);
+            }
+            let response = Response::ok(&content[offset..]);
+            let response = match &etag {
+                Some(etag) => response.with_header("X-Linked-Etag", &format!("\"{etag}\"")),
+                None => response,
+            };
+            if offset > 0 {
+                response.with_status(206)
+            } else {
+                response
+            }
+        })
+    }
+
+    fn ranges(server: &TestServer) -> Vec<u64> {
+        server.requests().iter().map(offset).collect()
+    }
+
+    fn content() -> (Vec<u8>, String) {
+        let content = (0..100_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let sha256 = format!("{:x}", Sha256::digest(&content));
+        (content, sha256)
+    }
+
+    const URL: &str = "https://huggingface.co/org/repo/resolve/main/model.bin";
+
+    #[test]
+    fn test_download_through_mirror() {
+        let (content, sha256) = content();
+        let server = serve(content.clone(), Some(sha256.clone()));
+        let mirror = server.url();
+        let dir = tempfile::tempdir().unwrap();
+
+        let downloader = Downloader::new().with_mirror(mirror);
+        let path = downloader.download(URL, dir.path(), "model.bin").unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+        assert!(!partial_path(&path).exists());
+        let manifest = Manifest::load(dir.path()).unwrap();
+        let entry = &manifest.files["model.bin"];
+        assert_eq!(
+            entry.url,
+            format!("{mirror}/org/repo/resolve/main/model.bin")
+        );
+        assert_eq!(entry.sha256, sha256);
+        assert_eq!(entry.size, content.len() as u64);
+        assert!(manifest.contains(dir.path(), "model.bin"));
+        manifest.verify(dir.path(), "model.bin").unwrap();
+    }
+
+    #[test]
+    fn test_download_resumes_partial_file() {
+        let (content, sha256) = content();
+        let server = serve(content.clone(), Some(sha256));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.bin");
+        std::fs::write(partial_path(&path), &content[..40_000]).unwrap();
+
+        let downloader = Downloader::new().with_mirror(server.url());
+        downloader.download(URL, dir.path(), "model.bin").unwrap();
+
+        assert_eq!(ranges(&server), vec![40_000]);
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+
+        // Already downloaded files are not requested again
+        downloader.download(URL, dir.path(), "model.bin").unwrap();
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_download_adopts_complete_file_without_manifest() {
+        let (content, sha256) = content();
+        let server = serve(content.clone(), None);
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("model.bin"), &content).unwrap();
+
+        let downloader = Downloader::new().with_mirror(server.url());
+        downloader.download(URL, dir.path(), "model.bin").unwrap();
+
+        assert_eq!(ranges(&server), vec![content.len() as u64]);
+        let manifest = Manifest::load(dir.path()).unwrap();
+        assert_eq!(manifest.files["model.bin"].sha256, sha256);
+    }
+
+    #[test]
+    fn test_download_checksum_mismatch() {
+        let (content, _) = content();
+        let server = serve(content, Some("0".repeat(64)));
+        let dir = tempfile::tempdir().unwrap();
+
+        let downloader = Downloader::new().with_mirror(server.url());
+        let result = downloader.download(URL, dir.path(), "model.bin");
+
+        assert!(matches!(result, Err(DownloadError::Checksum { .. })));
+        let path = dir.path().join("model.bin");
+        assert!(!path.exists() && !partial_path(&path).exists());
+        assert!(Manifest::load(dir.path()).unwrap().files.is_empty());
+    }
+
+    #[test]
+    fn test_manifest_detects_modified_file() {
+        let (content, _) = content();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.bin");
+        std::fs::write(&path, &content).unwrap();
+        Manifest::record(dir.path(), "model.bin", URL).unwrap();
+        let manifest = Manifest::load(dir.path()).unwrap();
+        assert!(manifest.contains(dir.path(), "model.bin"));
+
+        // Same size, different content and modification time
+        let mut edited = content.clone();
+        edited[0] ^= 1;
+        std::fs::write(&path, &edited).unwrap();
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+
+        assert!(!manifest.contains(dir.path(), "model.bin"));
+        assert!(matches!(
+            manifest.verify(dir.path(), "model.bin"),
+            Err(DownloadError::Checksum { .. })
+        ));
+    }
+
+    #[test]
+    fn test_manifest_remove() {
+        let (content, sha256) = content();
+        let server = serve(content, Some(sha256));
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new().with_mirror(server.url());
+        downloader.download(URL, dir.path(), "model.bin").unwrap();
+
+        Manifest::remove(dir.path(), "model.bin").unwrap();
+
+        assert!(!dir.path().join("model.bin").exists());
+        let manifest = Manifest::load(dir.path()).unwrap();
+        assert!(!manifest.contains(dir.path(), "model.bin"));
+    }
+
+    #[test]
+    fn test_manifest_record() {
+        let (content, sha256) = content();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("model.safetensors"), &content).unwrap();
+
+        Manifest::record(dir.path(), "model.safetensors", URL).unwrap();
+
+        let manifest = Manifest::load(dir.path()).unwrap();
+        assert!(manifest.contains(dir.path(), "model.safetensors"));
+        assert_eq!(manifest.files["model.safetensors"].sha256, sha256);
+        assert_eq!(manifest.files["model.safetensors"].url, URL);
+    }
+
+    #[test]
+    fn test_redirect_url() {
+        assert_eq!(
+            redirect_url("https://hub.example/org/repo/file", "/api/file"),
+            "https://hub.example/api/file"
+        );
+        assert_eq!(
+            redirect_url("https://hub.example/file", "https://cdn.example/file"),
+            "https://cdn.example/file"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Inference plugins built as shared libraries and loaded at runtime.
+//!
+//! A plugin crate is built as a `cdylib` and declares its servers with
+//! [declare_inference_plugins](crate::declare_inference_plugins):
+//!
+//! ```ignore
+//! burn_lm_inference::declare_inference_plugins!(MyModelServer, MyOtherModelServer);
+//! ```
+//!
+//! The library exports a [PluginDeclaration] with a `#[repr(C)]` layout. The plugins themselves
+//! are Rust trait objects and vectors, which have no stable ABI: they can only be shared by
+//! builds which lay them out the same way. The declaration records the Rust compiler, the
+//! `burn-lm-inference` version, backend and float type, and a build fingerprint made of the
+//! target, the enabled features and the burn revision of the lock file. A library is only
+//! loaded when they all match the host, so plugins should be built with the `Cargo.lock` of
+//! the host. Other dependencies are not checked.
+//!
+//! The library has its own copies of the statics of `burn-lm-inference` and burn: the devices
+//! of [DeviceSettings](crate::DeviceSettings), including
+//! [INFERENCE_DEVICE](crate::INFERENCE_DEVICE), are created again by the
+//! library rather than shared with the host. The device settings given to
+//! [set_device](crate::InferencePlugin::set_device) apply to the plugin models, but their
+//! devices don't share memory pools or caches with the models of the host.
+
+use std::borrow::Cow;
+#[cfg(feature = "dylib")]
+use std::path::Path;
+
+use crate::InferencePlugin;
+
+/// Version of the [PluginDeclaration] layout, incremented on any change of the layout.
+pub const PLUGIN_ABI_VERSION: u32 = 2;
+/// Name of the exported [PluginDeclaration] symbol.
+pub const PLUGIN_DECLARATION_SYMBOL: &str = "burn_lm_plugin_declaration";
+
+const RUSTC_VERSION: &str = env!("BURN_LM_RUSTC_VERSION");
+const BURN_LM_VERSION: &str = env!("CARGO_PKG_VERSION");
+const BUILD_FINGERPRINT: &str = env!("BURN_LM_BUILD_FINGERPRINT");
+
+/// Function returning the inference plugins of a library.
+pub type PluginsFn = fn() -> Vec<Box<dyn InferencePlugin>>;
+
+/// A static string shared with the host, as a pointer and a length.
+#[repr(C)]
+pub struct AbiStr {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl AbiStr {
+    pub const fn new(value: &'static str) -> Self {
+        Self {
+            ptr: value.as_ptr(),
+            len: value.len(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The string must point to `len` readable bytes, e.g. a string of a loaded library.
+    unsafe fn as_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(std::slice::from_raw_parts(self.ptr, self.len))
+    }
+}
+
+/// Declaration exported by a plugin library.
+///
+/// The fields are only read by the host after checking that `abi_version` matches
+/// [PLUGIN_ABI_VERSION].
+#[repr(C)]
+pub struct PluginDeclaration {
+    pub abi_version: u32,
+    pub rustc_version: AbiStr,
+    pub burn_lm_version: AbiStr,
+    pub backend: AbiStr,
+    pub dtype: AbiStr,
+    pub build_fingerprint: AbiStr,
+    pub plugins: PluginsFn,
+}
+
+// The declaration only points to static strings.
+unsafe impl Sync for PluginDeclaration {}
+
+impl PluginDeclaration {
+    /// Declaration of the plugins returned by `plugins`, built with the current versions.
+    pub const fn new(plugins: PluginsFn) -> Self {
+        Self {
+            abi_version: PLUGIN_ABI_VERSION,
+            rustc_version: AbiStr::new(RUSTC_VERSION),
+            burn_lm_version: AbiStr::new(BURN_LM_VERSION),
+            backend: AbiStr::new(crate::NAME),
+            dtype: AbiStr::new(crate::DTYPE_NAME),
+            build_fingerprint: AbiStr::new(BUILD_FINGERPRINT),
+            plugins,
+        }
+    }
+
+    /// Check that the plugins of the declaration can be used by the host.
+    ///
+    /// # Safety
+    ///
+    /// The strings of the declaration must be valid, which is the case for the declarations
+    /// of a loaded library with the current ABI version.
+    pub unsafe fn check(&self) -> Result<(), String> {
+        let incompatible = |field: &str, expected: &str, actual: &str| {
+            Err(format!(
+                "{field} mismatch, expected '{expected}' but got '{actual}'"
+            ))
+        };
+        if self.abi_version != PLUGIN_ABI_VERSION {
+            return incompatible(
+                "ABI version",
+                &PLUGIN_ABI_VERSION.to_string(),
+                &self.abi_version.to_string(),
+            );
+        }
+        let fields = [
+            ("Rust compiler", RUSTC_VERSION, &self.rustc_version),
+            (
+                "burn-lm-inference version",
+                BURN_LM_VERSION,
+                &self.burn_lm_version,
+            ),
+            ("backend", crate::NAME, &self.backend),
+            ("float type", crate::DTYPE_NAME, &self.dtype),
+            ("build", BUILD_FINGERPRINT, &self.build_fingerprint),
+        ];
+        for (field, expected, actual) in fields {
+            let actual = actual.as_str();
+            if actual != expected {
+                return incompatible(field, expected, &actual);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Load the inference plugins of a shared library.
+///
+/// The library is never unloaded since the plugins run its code for the lifetime of the
+/// process.
+#[cfg(feature = "dylib")]
+pub fn load_plugins<P: AsRef<Path>>(path: P) -> Result<Vec<Box<dyn InferencePlugin>>, PluginError> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    // SAFETY: the declaration fields are only read after checking its layout version. The
+    // plugins are only called when the library was built like the host, which rules out the
+    // usual layout differences of the Rust types but can't prove that they are identical,
+    // e.g. with different versions of other dependencies.
+    unsafe {
+        let library = libloading::Library::new(path)
+            .map_err(|err| PluginError::Library(name.clone(), err.to_string()))?;
+        let declaration: *const PluginDeclaration = *library
+            .get::<*const PluginDeclaration>(PLUGIN_DECLARATION_SYMBOL.as_bytes())
+            .map_err(|_| PluginError::MissingDeclaration(name.clone()))?;
+        let declaration = &*declaration;
+        declaration
+            .check()
+            .map_err(|reason| PluginError::Incompatible(name.clone(), reason))?;
+        let plugins = (declaration.plugins)();
+        std::mem::forget(library);
+        Ok(plugins)
+    }
+}
+
+/// Load the inference plugins of all the shared libraries of a directory.
+///
+/// Libraries which can't be loaded are reported and skipped.
+#[cfg(feature = "dylib")]
+pub fn load_plugins_dir<P: AsRef<Path>>(dir: P) -> Vec<Box<dyn InferencePlugin>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut libraries: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION)
+        })
+        .collect();
+    libraries.sort();
+    libraries
+        .iter()
+        .flat_map(|path| match load_plugins(path) {
+            Ok(plugins) => plugins,
+            Err(err) => {
+                tracing::error!("{err}");
+                vec![]
+            }
+        })
+        .collect()
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PluginError {
+    #[error("Failed to load plugin library '{0}': {1}")]
+    Library(String, String),
+    #[error("Plugin library '{0}' does not export a `{PLUGIN_DECLARATION_SYMBOL}` declaration")]
+    MissingDeclaration(String),
+    #[error("Incompatible plugin library '{0}': {1}")]
+    Incompatible(String, String),
+}
+
+/// Declare the inference plugins exported by a plugin library.
+///
+/// The servers are given as types implementing [InferenceServer](crate::InferenceServer)
+/// with the `InferenceServer` derive, or as a function returning the plugins with
+/// `provider = path::to::function`.
+#[macro_export]
+macro_rules! declare_inference_plugins {
+    (provider = $provider:path) => {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static burn_lm_plugin_declaration: $crate::dylib::PluginDeclaration =
+            $crate::dylib::PluginDeclaration::new($provider);
+    };
+    ($($server:ty),+ $(,)?) => {
+        fn __burn_lm_plugins() -> ::std::vec::Vec<::std::boxed::Box<dyn $crate::InferencePlugin>> {
+            ::std::vec![$(
+                ::std::boxed::Box::new($crate::InferenceClient::new(
+                    <$server>::model_name(),
+                    <$server>::model_cli_param_name(),
+                    <$server>::model_creation_date(),
+                    <$server>::created_by(),
+                    <<$server as $crate::ServerConfigParsing>::Config as $crate::CommandFactory>::command,
+                    $crate::MutexChannel::<$server>::new(),
+                )) as ::std::boxed::Box<dyn $crate::InferencePlugin>
+            ),+]
+        }
+        $crate::declare_inference_plugins!(provider = __burn_lm_plugins);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugins() -> Vec<Box<dyn InferencePlugin>> {
+        vec![]
+    }
+
+    #[test]
+    fn test_plugin_declaration_check() {
+        let declaration = PluginDeclaration::new(plugins);
+        assert!(unsafe { declaration.check() }.is_ok());
+    }
+
+    #[test]
+    fn test_plugin_declaration_check_mismatch() {
+        let declaration = PluginDeclaration {
+            abi_version: PLUGIN_ABI_VERSION + 1,
+            ..PluginDeclaration::new(plugins)
+        };
+        let err = unsafe { declaration.check() }.unwrap_err();
+        assert!(err.starts_with("ABI version mismatch"));
+
+        let declaration = PluginDeclaration {
+            rustc_version: AbiStr::new("rustc 1.0.0"),
+            ..PluginDeclaration::new(plugins)
+        };
+        let err = unsafe { declaration.check() }.unwrap_err();
+        assert!(err.contains("'rustc 1.0.0'"));
+
+        let declaration = PluginDeclaration {
+            backend: AbiStr::new("unknown"),
+            ..PluginDeclaration::new(plugins)
+        };
+        let err = unsafe { declaration.check() }.unwrap_err();
+        assert!(err.starts_with("backend mismatch"));
+
+        let declaration = PluginDeclaration {
+            build_fingerprint: AbiStr::new("target=unknown; features=; burn=unknown"),
+            ..PluginDeclaration::new(plugins)
+        };
+        let err = unsafe { declaration.check() }.unwrap_err();
+        assert!(err.starts_with("build mismatch"));
+    }
+
+    #[cfg(feature = "dylib")]
+    #[test]
+    fn test_load_plugins_invalid_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join(format!("plugin.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&path, b"not a library").unwrap();
+
+        assert!(matches!(load_plugins(&path), Err(PluginError::Library(..))));
+        assert!(load_plugins_dir(dir.path()).is_empty());
+    }
+}
@@ -1,7 +1,7 @@
 pub type InferenceResult<T> = Result<T, InferenceError>;
 pub type InferenceOptionalResult<T> = Result<Option<T>, InferenceError>;
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
 pub enum InferenceError {
     #[error("Error deleting model: {0} (reason: {1})")]
     DeleteError(String, String),
@@ -13,8 +13,32 @@ pub enum InferenceError {
     ModelNotLoaded,
     #[error("The plugin '{0}' does not support downloading.")]
     PluginDownloadUnsupportedError(String),
+    #[error("The plugin '{0}' does not support quantization.")]
+    PluginQuantizationUnsupportedError(String),
+    #[error("Error quantizing model: {0} (reason: {1})")]
+    QuantizationError(String, String),
+    #[error("The plugin '{0}' does not support training.")]
+    PluginTrainingUnsupportedError(String),
+    #[error("Error training model: {0} (reason: {1})")]
+    TrainingError(String, String),
+    #[error("The plugin does not support evaluation.")]
+    PluginEvaluationUnsupportedError,
+    #[error("Error evaluating model: {0} (reason: {1})")]
+    EvaluationError(String, String),
+    #[error("The plugin does not support tokenization.")]
+    PluginTokenizationUnsupportedError,
+    #[error("Error tokenizing: {0} (reason: {1})")]
+    TokenizationError(String, String),
     #[error("Error unloading model: {0} (reason: {1})")]
     UnloadError(String, String),
+    #[error("Error generating text with model: {0} (reason: {1})")]
+    GenerationError(String, String),
     #[error("Input sequence length ({0} tokens) exceeds maximum context window ({1} tokens). Please shorten your input or increase the maximum context window.")]
     ContextLengthExceeded(usize, usize),
+    #[error("Error selecting the device of model: {0} (reason: {1})")]
+    DeviceError(String, String),
+    #[error("Model worker of '{0}' failed: {1}")]
+    WorkerError(String, String),
+    #[error("Remote server '{0}' failed: {1}")]
+    RemoteError(String, String),
 }
@@ -0,0 +1,385 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+use crate::{InferenceResult, StatEntry, Stats};
+
+/// Length normalizations listed in the CLI help.
+pub const NORMALIZATIONS: [&str; 3] = ["none", "tokens", "bytes"];
+
+/// Arguments of a perplexity evaluation of a loaded model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerplexityArgs {
+    /// The evaluated text.
+    pub text: String,
+    /// Number of tokens between the starts of two consecutive windows of the maximum sequence
+    /// length. Windows overlap when the stride is smaller than the sequence length, giving
+    /// more context to the predictions.
+    pub stride: usize,
+}
+
+/// A continuation to score given a context.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoglikelihoodRequest {
+    pub context: String,
+    pub continuation: String,
+}
+
+/// Log-likelihood of a continuation under the model.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Loglikelihood {
+    /// Sum of the log-probabilities of the continuation tokens, in nats.
+    pub logprob: f64,
+    /// The number of continuation tokens.
+    pub num_tokens: usize,
+}
+
+/// Length normalization of the continuation log-likelihoods, so that longer choices aren't
+/// penalized for having more tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Normalization {
+    /// Compare the raw log-likelihoods.
+    None,
+    /// Divide the log-likelihoods by the number of tokens.
+    Tokens,
+    /// Divide the log-likelihoods by the number of UTF-8 bytes, which doesn't depend on the
+    /// tokenizer.
+    #[default]
+    Bytes,
+}
+
+impl Normalization {
+    /// The normalized score of a continuation.
+    pub fn score(&self, continuation: &str, loglikelihood: &Loglikelihood) -> f64 {
+        let length = match self {
+            Normalization::None => return loglikelihood.logprob,
+            Normalization::Tokens => loglikelihood.num_tokens,
+            Normalization::Bytes => continuation.len(),
+        };
+        loglikelihood.logprob / length.max(1) as f64
+    }
+}
+
+impl Display for Normalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Normalization::None => "none",
+            Normalization::Tokens => "tokens",
+            Normalization::Bytes => "bytes",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Normalization::None),
+            "tokens" => Ok(Normalization::Tokens),
+            "bytes" => Ok(Normalization::Bytes),
+            _ => Err(format!(
+                "Invalid normalization '{s}', expected one of: {}",
+                NORMALIZATIONS.join(", ")
+            )),
+        }
+    }
+}
+
+/// A multiple-choice question with a single correct answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipleChoiceTask {
+    pub query: String,
+    pub choices: Vec<String>,
+    /// Index of the correct choice.
+    pub answer: usize,
+}
+
+/// Fields of the multiple-choice tasks in JSON lines files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipleChoiceFields {
+    /// Field holding the question or the context to complete.
+    pub query: String,
+    /// Field holding the list of candidate continuations.
+    pub choices: String,
+    /// Field holding the correct choice, either as an index or as a letter (`A` for the first
+    /// choice).
+    pub answer: String,
+}
+
+impl Default for MultipleChoiceFields {
+    fn default() -> Self {
+        Self {
+            query: "query".to_string(),
+            choices: "choices".to_string(),
+            answer: "answer".to_string(),
+        }
+    }
+}
+
+impl MultipleChoiceTask {
+    /// Parse the tasks of a JSON lines file, one task per line.
+    pub fn parse_json_lines(
+        content: &str,
+        fields: &MultipleChoiceFields,
+    ) -> Result<Vec<Self>, String> {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                Self::parse_json(line, fields).map_err(|err| format!("Line {}: {err}", i + 1))
+            })
+            .collect()
+    }
+
+    fn parse_json(line: &str, fields: &MultipleChoiceFields) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+        let field = |name: &str| {
+            value
+                .get(name)
+                .ok_or_else(|| format!("missing field '{name}'"))
+        };
+        let query = field(&fields.query)?
+            .as_str()
+            .ok_or_else(|| format!("field '{}' should be a string", fields.query))?
+            .to_string();
+        let choices: Vec<String> = serde_json::from_value(field(&fields.choices)?.clone())
+            .map_err(|_| format!("field '{}' should be a list of strings", fields.choices))?;
+        let answer = match field(&fields.answer)? {
+            serde_json::Value::Number(index) => index.as_u64().map(|index| index as usize),
+            serde_json::Value::String(label) => Self::parse_label(label),
+            _ => None,
+        }
+        .filter(|index| *index < choices.len())
+        .ok_or_else(|| format!("field '{}' should select one of the choices", fields.answer))?;
+
+        Ok(Self {
+            query,
+            choices,
+            answer,
+        })
+    }
+
+    /// Parse an answer label, either an index or a letter.
+    fn parse_label(label: &str) -> Option<usize> {
+        let label = label.trim();
+        if let Ok(index) = label.parse::<usize>() {
+            return Some(index);
+        }
+        match label.as_bytes() {
+            [letter @ b'A'..=b'Z'] => Some((letter - b'A') as usize),
+            _ => None,
+        }
+    }
+}
+
+/// Arguments of a multiple-choice evaluation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipleChoiceArgs {
+    pub normalization: Normalization,
+    /// Number of solved tasks prepended to each query as examples. They are taken from the
+    /// start of the tasks and aren't evaluated.
+    pub num_fewshot: usize,
+    /// Separator between a query and its answer.
+    pub delimiter: String,
+    /// Separator between the few-shot examples and the query.
+    pub fewshot_delimiter: String,
+}
+
+impl Default for MultipleChoiceArgs {
+    fn default() -> Self {
+        Self {
+            normalization: Normalization::default(),
+            num_fewshot: 0,
+            delimiter: " ".to_string(),
+            fewshot_delimiter: "\n\n".to_string(),
+        }
+    }
+}
+
+/// Result of a multiple-choice evaluation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MultipleChoiceOutput {
+    /// Number of evaluated tasks.
+    pub num_tasks: usize,
+    /// Number of tasks whose best scored choice is the correct one.
+    pub num_correct: usize,
+    /// Number of scored continuation tokens.
+    pub num_tokens: usize,
+}
+
+impl MultipleChoiceOutput {
+    pub fn accuracy(&self) -> f64 {
+        self.num_correct as f64 / self.num_tasks.max(1) as f64
+    }
+
+    /// Return the evaluation as stats.
+    pub fn stats(&self, args: &MultipleChoiceArgs) -> Stats {
+        let mut stats = Stats::new();
+        stats.entries.extend(vec![
+            StatEntry::Named(
+                "Accuracy".to_string(),
+                format!("{:.2}%", self.accuracy() * 100.0),
+            ),
+            StatEntry::Named(
+                "Correct Answers".to_string(),
+                format!("{}/{}", self.num_correct, self.num_tasks),
+            ),
+            StatEntry::Named("Normalization".to_string(), args.normalization.to_string()),
+            StatEntry::Named(
+                "Few-shot Examples".to_string(),
+                args.num_fewshot.to_string(),
+            ),
+            StatEntry::TokensCount(self.num_tokens),
+        ]);
+        stats
+    }
+}
+
+impl MultipleChoiceArgs {
+    /// The scoring requests of the choices of a task, given the few-shot prefix.
+    pub fn requests(&self, prefix: &str, task: &MultipleChoiceTask) -> Vec<LoglikelihoodRequest> {
+        task.choices
+            .iter()
+            .map(|choice| LoglikelihoodRequest {
+                context: format!("{prefix}{}", task.query),
+                continuation: format!("{}{choice}", self.delimiter),
+            })
+            .collect()
+    }
+
+    /// The prompt prefix made of the solved few-shot examples.
+    pub fn fewshot_prefix(&self, examples: &[MultipleChoiceTask]) -> String {
+        examples
+            .iter()
+            .map(|task| {
+                format!(
+                    "{}{}{}{}",
+                    task.query, self.delimiter, task.choices[task.answer], self.fewshot_delimiter
+                )
+            })
+            .collect()
+    }
+
+    /// Evaluate the tasks with a function scoring the log-likelihood of continuations, such as
+    /// [InferencePlugin::loglikelihood](crate::InferencePlugin::loglikelihood).
+    pub fn evaluate<F>(
+        &self,
+        tasks: &[MultipleChoiceTask],
+        mut loglikelihood: F,
+    ) -> InferenceResult<MultipleChoiceOutput>
+    where
+        F: FnMut(Vec<LoglikelihoodRequest>) -> InferenceResult<Vec<Loglikelihood>>,
+    {
+        let num_fewshot = self.num_fewshot.min(tasks.len());
+        let (examples, tasks) = tasks.split_at(num_fewshot);
+        let prefix = self.fewshot_prefix(examples);
+
+        let mut output = MultipleChoiceOutput::default();
+        for task in tasks {
+            let requests = self.requests(&prefix, task);
+            let results = loglikelihood(requests.clone())?;
+            let best = requests
+                .iter()
+                .zip(results.iter())
+                .map(|(request, result)| self.normalization.score(&request.continuation, result))
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(index, _)| index);
+
+            output.num_tasks += 1;
+            output.num_tokens += results.iter().map(|r| r.num_tokens).sum::<usize>();
+            if best == Some(task.answer) {
+                output.num_correct += 1;
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn task(query: &str, choices: &[&str], answer: usize) -> MultipleChoiceTask {
+        MultipleChoiceTask {
+            query: query.to_string(),
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            answer,
+        }
+    }
+
+    #[rstest]
+    #[case(r#"{"query": "2 + 2 =", "choices": ["3", "4"], "answer": 1}"#)]
+    #[case(r#"{"query": "2 + 2 =", "choices": ["3", "4"], "answer": "1"}"#)]
+    #[case(r#"{"query": "2 + 2 =", "choices": ["3", "4"], "answer": "B"}"#)]
+    fn test_parse_multiple_choice_task(#[case] line: &str) {
+        let tasks =
+            MultipleChoiceTask::parse_json_lines(line, &MultipleChoiceFields::default()).unwrap();
+
+        assert_eq!(tasks, vec![task("2 + 2 =", &["3", "4"], 1)]);
+    }
+
+    #[rstest]
+    #[case(r#"{"query": "2 + 2 =", "choices": ["3", "4"], "answer": 2}"#)]
+    #[case(r#"{"query": "2 + 2 =", "choices": ["3", "4"], "answer": "C"}"#)]
+    #[case(r#"{"query": "2 + 2 =", "choices": "4", "answer": 0}"#)]
+    #[case(r#"{"question": "2 + 2 =", "choices": ["3", "4"], "answer": 0}"#)]
+    fn test_parse_invalid_multiple_choice_task(#[case] line: &str) {
+        let result = MultipleChoiceTask::parse_json_lines(line, &MultipleChoiceFields::default());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_fewshot_requests() {
+        let args = MultipleChoiceArgs {
+            num_fewshot: 1,
+            ..Default::default()
+        };
+        let prefix = args.fewshot_prefix(&[task("Sky is", &["green", "blue"], 1)]);
+        let requests = args.requests(&prefix, &task("Grass is", &["green", "blue"], 0));
+
+        assert_eq!(
+            requests[0],
+            LoglikelihoodRequest {
+                context: "Sky is blue\n\nGrass is".to_string(),
+                continuation: " green".to_string(),
+            }
+        );
+        assert_eq!(requests[1].continuation, " blue");
+    }
+
+    #[rstest]
+    #[case(Normalization::None, 0)]
+    #[case(Normalization::Tokens, 1)]
+    #[case(Normalization::Bytes, 1)]
+    fn test_evaluate_normalization(#[case] normalization: Normalization, #[case] correct: usize) {
+        let args = MultipleChoiceArgs {
+            normalization,
+            ..Default::default()
+        };
+        let tasks = [task("The answer is", &["no", "a longer answer"], 1)];
+        // The longer answer is less likely in total but more likely per token
+        let scorer = |requests: Vec<LoglikelihoodRequest>| {
+            Ok(requests
+                .iter()
+                .map(|request| {
+                    let num_tokens = request.continuation.split_whitespace().count();
+                    Loglikelihood {
+                        logprob: -(num_tokens as f64) - 1.0,
+                        num_tokens,
+                    }
+                })
+                .collect())
+        };
+
+        let output = args.evaluate(&tasks, scorer).unwrap();
+
+        assert_eq!(output.num_tasks, 1);
+        assert_eq!(output.num_correct, correct);
+        assert_eq!(output.num_tokens, 4);
+    }
+}
@@ -9,6 +9,8 @@ use std::{
     },
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{Message, Prompt};
 
 /// Defines a job to be run during inference.
@@ -28,6 +30,7 @@ pub struct GeneratedItemEmitter {
 
 /// The potential tasks that can be executed by an [inference job](InferenceJob) using the
 /// [inference server](crate::InferenceServer).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InferenceTask {
     /// A single message to be processed by the server.
     Message(Message),
@@ -5,25 +5,46 @@ pub use job::*;
 pub mod backends;
 pub mod channels;
 pub mod client;
+pub mod conformance;
+pub mod download;
+pub mod dylib;
 pub mod errors;
+pub mod evaluation;
+pub mod memory;
 pub mod message;
 pub mod plugin;
+pub mod quantization;
+pub mod remote;
 pub mod server;
 pub mod stats;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_server;
+pub mod tokenization;
+pub mod training;
 pub mod utils;
 
 // ---------------------------------------------------------------------------
 // Re-exports for convenience so plugins implementors can just do:
 pub use crate::channels::mutex::MutexChannel;
 pub use crate::channels::passthrough::SingleThreadedChannel;
+pub use crate::channels::process::ProcessChannel;
 pub use crate::client::InferenceClient;
 pub use crate::errors::*;
-pub use crate::message::{Message, MessageRole};
-pub use crate::plugin::InferencePlugin;
+pub use crate::evaluation::{
+    Loglikelihood, LoglikelihoodRequest, MultipleChoiceArgs, MultipleChoiceFields,
+    MultipleChoiceTask, Normalization, PerplexityArgs, NORMALIZATIONS,
+};
+pub use crate::memory::PeakMemoryTracker;
+pub use crate::message::{Message, MessageRole, REPLY_MARKER};
+pub use crate::plugin::{register_plugin, InferencePlugin};
+pub use crate::quantization::{QuantizationScheme, QUANTIZATION_SCHEMES};
+pub use crate::remote::RemotePlugin;
 pub use crate::server::{InferenceServer, InferenceServerConfig, ServerConfigParsing};
 pub use crate::stats::{StatEntry, Stats, STATS_MARKER};
+pub use crate::tokenization::Tokenization;
+pub use crate::training::{LrSchedule, TrainingArgs, LR_SCHEDULES};
 pub use backends::burn_backend_types::*;
-pub use backends::DTYPE_NAME;
+pub use backends::{BackendKind, DeviceSettings, FloatDtype, DTYPE_NAME};
 pub use burn_lm_macros::inference_server_config;
 pub use burn_lm_macros::InferenceServer;
 // external re-export
@@ -0,0 +1,100 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Interval between two samples of the resident memory.
+const SAMPLING_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Return the resident memory of the current process in bytes.
+///
+/// Only available on Linux, returns `None` on other platforms.
+pub fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_resident_memory(&status)
+}
+
+fn parse_resident_memory(status: &str) -> Option<u64> {
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Track the peak resident memory of the process while an operation runs.
+///
+/// The resident memory is sampled in a background thread until [stop](Self::stop) is called.
+pub struct PeakMemoryTracker {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Option<u64>>>,
+}
+
+impl PeakMemoryTracker {
+    /// Start sampling the resident memory.
+    pub fn start() -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = {
+            let running = running.clone();
+            std::thread::spawn(move || {
+                let mut peak = resident_memory()?;
+                while running.load(Ordering::Relaxed) {
+                    std::thread::sleep(SAMPLING_INTERVAL);
+                    peak = peak.max(resident_memory().unwrap_or_default());
+                }
+                Some(peak)
+            })
+        };
+        Self {
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop sampling and return the peak resident memory in bytes, if available.
+    pub fn stop(mut self) -> Option<u64> {
+        self.running.store(false, Ordering::Relaxed);
+        let peak = self.handle.take()?.join().ok()??;
+        // Take a last sample in case the peak happened after the last interval
+        Some(peak.max(resident_memory().unwrap_or_default()))
+    }
+}
+
+impl Drop for PeakMemoryTracker {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resident_memory() {
+        let status = "Name:\tburn-lm\nVmPeak:\t  204800 kB\nVmRSS:\t   10240 kB\nThreads:\t4\n";
+        assert_eq!(parse_resident_memory(status), Some(10240 * 1024));
+        assert_eq!(parse_resident_memory("Name:\tburn-lm\n"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_peak_memory_tracker() {
+        let size = 64 * 1024 * 1024;
+        let tracker = PeakMemoryTracker::start();
+        let buffer = std::hint::black_box(vec![1u8; size]);
+        std::thread::sleep(SAMPLING_INTERVAL * 5);
+        drop(buffer);
+
+        let peak = tracker.stop().unwrap();
+        assert!(peak >= size as u64);
+    }
+}
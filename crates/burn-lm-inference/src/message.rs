@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+/// Marker of the start of the model reply in the streamed chat completions.
+pub const REPLY_MARKER: &str = "##### Model Reply";
+
 #[derive(Clone, Debug, Serialize, Deserialize, strum::Display, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum MessageRole {
@@ -1,6 +1,9 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 
-use crate::{InferenceJob, InferenceResult, Stats};
+use crate::{
+    DeviceSettings, InferenceJob, InferenceResult, InferenceTask, Loglikelihood,
+    LoglikelihoodRequest, PerplexityArgs, QuantizationScheme, Stats, Tokenization, TrainingArgs,
+};
 
 pub type CreateCliFlagsFn = fn() -> clap::Command;
 
@@ -11,16 +14,31 @@ pub trait InferencePlugin: Send + Sync + Debug {
     fn model_creation_date(&self) -> &'static str;
     fn created_by(&self) -> &'static str;
     fn create_cli_flags_fn(&self) -> CreateCliFlagsFn;
-    fn downloader(&self) -> Option<fn() -> InferenceResult<Option<Stats>>>;
+    fn downloader(&self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>>;
     fn is_downloaded(&self) -> bool;
     fn deleter(&self) -> Option<fn() -> InferenceResult<Option<Stats>>>;
+    fn quantizer(
+        &self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>>;
+    fn trainer(&self)
+        -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>>;
     fn parse_cli_config(&self, args: &clap::ArgMatches);
     fn parse_json_config(&self, json: &str);
+    /// Select the device on which the model is loaded, used from the next load.
+    fn set_device(&self, settings: DeviceSettings) -> InferenceResult<()>;
+    fn device_settings(&self) -> DeviceSettings;
     fn load(&self) -> InferenceResult<Option<Stats>>;
     fn is_loaded(&self) -> bool;
     fn unload(&self) -> InferenceResult<Option<Stats>>;
     fn run_job(&self, job: InferenceJob) -> InferenceResult<Stats>;
     fn clear_state(&self) -> InferenceResult<()>;
+    fn perplexity(&self, args: PerplexityArgs) -> InferenceResult<Stats>;
+    fn loglikelihood(
+        &self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>>;
+    fn tokenize(&self, task: InferenceTask) -> InferenceResult<Tokenization>;
+    fn detokenize(&self, tokens: Vec<u32>) -> InferenceResult<String>;
 }
 
 impl Clone for Box<dyn InferencePlugin> {
@@ -28,3 +46,30 @@ impl Clone for Box<dyn InferencePlugin> {
         self.clone_box()
     }
 }
+
+/// Register a plugin in the clients of a registry, keyed by model name, and return true if it
+/// has been registered.
+///
+/// A plugin with the model name or the CLI name of a registered plugin is reported and
+/// skipped, so that it neither replaces a model nor makes the lookups by name ambiguous.
+pub fn register_plugin(
+    clients: &mut HashMap<&'static str, Box<dyn InferencePlugin>>,
+    plugin: Box<dyn InferencePlugin>,
+) -> bool {
+    let registered = clients.values().find(|registered| {
+        registered.model_name() == plugin.model_name()
+            || registered.model_cli_param_name() == plugin.model_cli_param_name()
+    });
+    if let Some(registered) = registered {
+        tracing::error!(
+            "Skipping model '{}' ('{}'), its name is already used by the model '{}' ('{}')",
+            plugin.model_name(),
+            plugin.model_cli_param_name(),
+            registered.model_name(),
+            registered.model_cli_param_name()
+        );
+        return false;
+    }
+    clients.insert(plugin.model_name(), plugin);
+    true
+}
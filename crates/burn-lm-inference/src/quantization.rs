@@ -0,0 +1,138 @@
+use std::{fmt::Display, str::FromStr};
+
+use burn::tensor::quantization::{QuantLevel, QuantParam, QuantScheme, QuantValue};
+
+/// Quantization schemes listed in the CLI help.
+pub const QUANTIZATION_SCHEMES: [&str; 6] = [
+    "q4-block32",
+    "q4-block64",
+    "q4-block128",
+    "q8",
+    "q8-block32",
+    "q8-block128",
+];
+
+/// Bit width of the quantized weights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantizationBits {
+    Q4,
+    Q8,
+}
+
+/// Weight quantization scheme used to produce quantized model variants.
+///
+/// Schemes are written as `q<bits>` for per-tensor quantization or
+/// `q<bits>-block<size>` for block quantization, e.g. `q4-block32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuantizationScheme {
+    pub bits: QuantizationBits,
+    /// Block size, or `None` for per-tensor quantization.
+    pub block_size: Option<u8>,
+}
+
+impl QuantizationScheme {
+    /// The equivalent burn quantization scheme.
+    pub fn quant_scheme(&self) -> QuantScheme {
+        let value = match self.bits {
+            QuantizationBits::Q4 => QuantValue::Q4F,
+            QuantizationBits::Q8 => QuantValue::Q8F,
+        };
+        let scheme = QuantScheme::default().with_value(value);
+        match self.block_size {
+            Some(size) => scheme
+                .with_level(QuantLevel::block([size]))
+                .with_param(QuantParam::F16),
+            None => scheme,
+        }
+    }
+}
+
+impl Display for QuantizationScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bits = match self.bits {
+            QuantizationBits::Q4 => "q4",
+            QuantizationBits::Q8 => "q8",
+        };
+        match self.block_size {
+            Some(size) => write!(f, "{bits}-block{size}"),
+            None => write!(f, "{bits}"),
+        }
+    }
+}
+
+impl FromStr for QuantizationScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid quantization scheme '{s}', expected one of: {}",
+                QUANTIZATION_SCHEMES.join(", ")
+            )
+        };
+        let lowercase = s.to_lowercase();
+        let (bits, block) = match lowercase.split_once('-') {
+            Some((bits, block)) => (bits, Some(block)),
+            None => (lowercase.as_str(), None),
+        };
+        let bits = match bits {
+            "q4" => QuantizationBits::Q4,
+            "q8" => QuantizationBits::Q8,
+            _ => return Err(invalid()),
+        };
+        let block_size = match block {
+            Some(block) => {
+                let size = block
+                    .strip_prefix("block")
+                    .and_then(|size| size.parse::<u8>().ok())
+                    .ok_or_else(invalid)?;
+                if size == 0 || !size.is_power_of_two() {
+                    return Err(invalid());
+                }
+                Some(size)
+            }
+            None => None,
+        };
+        Ok(Self { bits, block_size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("q4-block32", QuantizationBits::Q4, Some(32))]
+    #[case("Q8", QuantizationBits::Q8, None)]
+    #[case("q8-block128", QuantizationBits::Q8, Some(128))]
+    fn test_parse_quantization_scheme(
+        #[case] input: &str,
+        #[case] bits: QuantizationBits,
+        #[case] block_size: Option<u8>,
+    ) {
+        let scheme: QuantizationScheme = input.parse().unwrap();
+        assert_eq!(scheme, QuantizationScheme { bits, block_size });
+        assert_eq!(scheme.to_string(), input.to_lowercase());
+    }
+
+    #[rstest]
+    #[case("q3")]
+    #[case("q4-block")]
+    #[case("q4-block48")]
+    #[case("q4-block512")]
+    #[case("q4-tensor")]
+    fn test_parse_invalid_quantization_scheme(#[case] input: &str) {
+        assert!(input.parse::<QuantizationScheme>().is_err());
+    }
+
+    #[test]
+    fn test_block_scheme_to_quant_scheme() {
+        let scheme: QuantizationScheme = "q4-block32".parse().unwrap();
+        let expected = QuantScheme::default()
+            .with_value(QuantValue::Q4F)
+            .with_level(QuantLevel::block([32]))
+            .with_param(QuantParam::F16);
+        assert_eq!(scheme.quant_scheme(), expected);
+    }
+}
@@ -0,0 +1,610 @@
+//! Inference plugins proxying the models of another `burn-lm-http` server.
+//!
+//! The models listed by the `/v1/models` endpoint of a remote server are exposed as local
+//! plugins whose jobs are streamed from its `/v1/chat/completions` endpoint, so a front-end
+//! server can route models to several inference machines.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::BufRead,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    errors::{InferenceError, InferenceResult},
+    job::{GeneratedItem, InferenceJob, InferenceTask},
+    plugin::{CreateCliFlagsFn, InferencePlugin},
+    DeviceSettings, Loglikelihood, LoglikelihoodRequest, PerplexityArgs, QuantizationScheme,
+    StatEntry, Stats, Tokenization, TrainingArgs, REPLY_MARKER, STATS_MARKER,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Timeout of the whole `/v1/models` request, the registry waits for it.
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The chat completion parameters which can be set on the command line, with their flag.
+const CLI_PARAMS: [(&str, &str, &str); 4] = [
+    ("seed", "seed", "Random seed of the sampling."),
+    (
+        "temperature",
+        "temperature",
+        "Temperature value for controlling randomness in sampling.",
+    ),
+    (
+        "top_p",
+        "top-p",
+        "Top-p probability threshold of the sampling.",
+    ),
+    (
+        "max_tokens",
+        "max-tokens",
+        "Maximum number of generated tokens.",
+    ),
+];
+
+fn remote_cli_flags() -> clap::Command {
+    CLI_PARAMS.iter().fold(
+        clap::Command::new("remote"),
+        |command, (param, long, help)| {
+            command.arg(
+                clap::Arg::new(*param)
+                    .long(*long)
+                    .required(false)
+                    .help(*help),
+            )
+        },
+    )
+}
+
+/// A model of the `/v1/models` response of a remote server.
+#[derive(Debug, Clone, Deserialize)]
+struct RemoteModel {
+    id: String,
+    created: i64,
+    #[serde(default)]
+    created_by: String,
+}
+
+/// A chunk of the `/v1/chat/completions` event stream.
+#[derive(Debug, Deserialize)]
+struct CompletionChunk {
+    choices: Vec<CompletionChunkChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChunkChoice {
+    delta: Option<CompletionChunkDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChunkDelta {
+    content: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct RemoteState {
+    loaded: bool,
+    /// The chat completion parameters sent with each request.
+    params: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Plugin running the jobs of a model on a remote `burn-lm-http` server.
+///
+/// The remote server loads the model on the first request, so loading and unloading the
+/// plugin only tracks whether the model is in use.
+#[derive(Debug, Clone)]
+pub struct RemotePlugin {
+    model_name: &'static str,
+    model_cli_param_name: &'static str,
+    model_creation_date: &'static str,
+    created_by: &'static str,
+    /// The model name on the remote server.
+    remote_model: String,
+    url: String,
+    agent: ureq::Agent,
+    state: Arc<Mutex<RemoteState>>,
+}
+
+/// Return the `'static` name of the plugins with the given value.
+///
+/// Each distinct value is allocated once for the whole process, so listing the models of a
+/// server again, e.g. each time the registry is created, doesn't allocate their names again.
+fn intern(value: String) -> &'static str {
+    static NAMES: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+    let mut names = NAMES.lock().unwrap();
+    match names.get(value.as_str()) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(value.into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum ReplySection {
+    #[default]
+    Loading,
+    Reply,
+    Stats,
+}
+
+/// Extract the model reply from the streamed content of a remote server.
+///
+/// The reply is streamed between the reply and the statistics markers, which can be split
+/// across several chunks, so the text which could be the start of a marker is held back
+/// until the next chunk.
+#[derive(Debug, Default)]
+struct ReplyReader {
+    section: ReplySection,
+    pending: String,
+}
+
+impl ReplyReader {
+    /// Add the content of a chunk and return the text of the reply it completes.
+    fn push(&mut self, content: &str) -> String {
+        let reply_marker = format!("\n{REPLY_MARKER}\n");
+        let stats_marker = format!("\n\n\n{STATS_MARKER}");
+        self.pending.push_str(content);
+        if self.section == ReplySection::Loading {
+            match self.pending.find(&reply_marker) {
+                Some(index) => {
+                    self.pending.drain(..index + reply_marker.len());
+                    self.section = ReplySection::Reply;
+                }
+                None => {
+                    self.pending
+                        .drain(..held_back(&self.pending, &reply_marker));
+                    return String::new();
+                }
+            }
+        }
+        if self.section == ReplySection::Stats {
+            self.pending.clear();
+            return String::new();
+        }
+        match self.pending.find(&stats_marker) {
+            Some(index) => {
+                self.section = ReplySection::Stats;
+                let reply = self.pending[..index].to_string();
+                self.pending.clear();
+                reply
+            }
+            None => {
+                let rest = self
+                    .pending
+                    .split_off(held_back(&self.pending, &stats_marker));
+                std::mem::replace(&mut self.pending, rest)
+            }
+        }
+    }
+}
+
+/// The index of the longest end of `text` which is the start of `marker`.
+fn held_back(text: &str, marker: &str) -> usize {
+    (text.len().saturating_sub(marker.len() - 1)..text.len())
+        .filter(|index| text.is_char_boundary(*index))
+        .find(|index| marker.starts_with(&text[*index..]))
+        .unwrap_or(text.len())
+}
+
+impl RemotePlugin {
+    fn new(url: &str, prefix: Option<&str>, model: RemoteModel, agent: ureq::Agent) -> Self {
+        let model_name = match prefix {
+            Some(prefix) => format!("{prefix}/{}", model.id),
+            None => model.id.clone(),
+        };
+        let creation_date = chrono::DateTime::from_timestamp(model.created, 0)
+            .unwrap_or_default()
+            .format("%Y/%m/%d")
+            .to_string();
+        Self {
+            model_cli_param_name: intern(model_name.to_lowercase().replace([' ', '/'], "-")),
+            model_name: intern(model_name),
+            model_creation_date: intern(creation_date),
+            created_by: intern(model.created_by),
+            remote_model: model.id,
+            url: url.trim_end_matches('/').to_string(),
+            agent,
+            state: Default::default(),
+        }
+    }
+
+    fn error(&self, reason: impl ToString) -> InferenceError {
+        InferenceError::RemoteError(self.url.clone(), reason.to_string())
+    }
+
+    /// Send the chat completion request of a task and return the event stream.
+    fn request(&self, task: InferenceTask) -> InferenceResult<ureq::Response> {
+        let messages = match task {
+            InferenceTask::Message(message) => vec![message],
+            InferenceTask::Context(messages) => messages,
+            // The remote server applies the chat template of its model to the messages, a
+            // formatted prompt can't be sent as is.
+            InferenceTask::Prompt(_) => {
+                return Err(InferenceError::GenerationError(
+                    self.model_name.to_string(),
+                    "remote models only accept messages, not prompts".to_string(),
+                ))
+            }
+        };
+        let mut body = self.state.lock().unwrap().params.clone();
+        body.insert("model".into(), self.remote_model.clone().into());
+        body.insert("stream".into(), true.into());
+        body.insert(
+            "messages".into(),
+            serde_json::to_value(messages).map_err(|err| self.error(err))?,
+        );
+        let url = format!("{}/v1/chat/completions", self.url);
+        match self.agent.post(&url).send_json(body) {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                Err(self.error(format!("{url} returned {status}: {body}")))
+            }
+            Err(err) => Err(self.error(err)),
+        }
+    }
+}
+
+impl InferencePlugin for RemotePlugin {
+    fn clone_box(&self) -> Box<dyn InferencePlugin> {
+        Box::new(self.clone())
+    }
+
+    fn model_name(&self) -> &'static str {
+        self.model_name
+    }
+
+    fn model_cli_param_name(&self) -> &'static str {
+        self.model_cli_param_name
+    }
+
+    fn model_creation_date(&self) -> &'static str {
+        self.model_creation_date
+    }
+
+    fn created_by(&self) -> &'static str {
+        self.created_by
+    }
+
+    fn create_cli_flags_fn(&self) -> CreateCliFlagsFn {
+        remote_cli_flags
+    }
+
+    fn downloader(&self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
+        None
+    }
+
+    fn is_downloaded(&self) -> bool {
+        // The remote server only lists its installed models
+        true
+    }
+
+    fn deleter(&self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+        None
+    }
+
+    fn quantizer(
+        &self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>> {
+        None
+    }
+
+    fn trainer(
+        &self,
+    ) -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>> {
+        None
+    }
+
+    fn parse_cli_config(&self, args: &clap::ArgMatches) {
+        let mut state = self.state.lock().unwrap();
+        for (param, ..) in CLI_PARAMS {
+            if let Some(value) = args.get_one::<String>(param) {
+                // Numbers are sent as JSON numbers
+                let value = serde_json::from_str(value).unwrap_or_else(|_| value.clone().into());
+                state.params.insert(param.to_string(), value);
+            }
+        }
+    }
+
+    fn parse_json_config(&self, json: &str) {
+        match serde_json::from_str::<serde_json::Map<_, _>>(json) {
+            Ok(params) => self.state.lock().unwrap().params = params,
+            Err(err) => tracing::error!("Invalid configuration of '{}': {err}", self.model_name),
+        }
+    }
+
+    fn set_device(&self, _settings: DeviceSettings) -> InferenceResult<()> {
+        Err(InferenceError::DeviceError(
+            self.model_name.to_string(),
+            "the device is selected by the remote server".to_string(),
+        ))
+    }
+
+    fn device_settings(&self) -> DeviceSettings {
+        DeviceSettings::default()
+    }
+
+    fn load(&self) -> InferenceResult<Option<Stats>> {
+        self.state.lock().unwrap().loaded = true;
+        Ok(None)
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.state.lock().unwrap().loaded
+    }
+
+    fn unload(&self) -> InferenceResult<Option<Stats>> {
+        self.state.lock().unwrap().loaded = false;
+        Ok(None)
+    }
+
+    fn run_job(&self, job: InferenceJob) -> InferenceResult<Stats> {
+        let now = Instant::now();
+        let response = self.request(job.task)?;
+        // The remote server reports the model loading before its reply and its statistics
+        // after it, only the reply is forwarded.
+        let mut reader = ReplyReader::default();
+        for line in std::io::BufReader::new(response.into_reader()).lines() {
+            let line = line.map_err(|err| self.error(err))?;
+            let Some(data) = line.strip_prefix("data: ") else {
+                continue;
+            };
+            if data == "[DONE]" {
+                let mut stats = Stats::new();
+                stats
+                    .entries
+                    .insert(StatEntry::InferenceDuration(now.elapsed()));
+                return Ok(stats);
+            }
+            let chunk: CompletionChunk =
+                serde_json::from_str(data).map_err(|err| self.error(err))?;
+            let content = chunk
+                .choices
+                .into_iter()
+                .filter_map(|choice| choice.delta.and_then(|delta| delta.content))
+                .collect::<String>();
+            let reply = reader.push(&content);
+            if !reply.is_empty() {
+                job.emitter.completed(GeneratedItem::Text(reply));
+            }
+        }
+        Err(self.error("the event stream ended before completion"))
+    }
+
+    fn clear_state(&self) -> InferenceResult<()> {
+        // Each chat completion request sends the whole context
+        Ok(())
+    }
+
+    fn perplexity(&self, _args: PerplexityArgs) -> InferenceResult<Stats> {
+        Err(InferenceError::PluginEvaluationUnsupportedError)
+    }
+
+    fn loglikelihood(
+        &self,
+        _requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        Err(InferenceError::PluginEvaluationUnsupportedError)
+    }
+
+    fn tokenize(&self, _task: InferenceTask) -> InferenceResult<Tokenization> {
+        Err(InferenceError::PluginTokenizationUnsupportedError)
+    }
+
+    fn detokenize(&self, _tokens: Vec<u32>) -> InferenceResult<String> {
+        Err(InferenceError::PluginTokenizationUnsupportedError)
+    }
+}
+
+/// Request the models listed by the `/v1/models` endpoint of the server at `url`.
+fn list_models(agent: &ureq::Agent, url: &str) -> Result<Vec<RemoteModel>, String> {
+    agent
+        .get(&format!("{url}/v1/models"))
+        .timeout(LIST_TIMEOUT)
+        .call()
+        .map_err(|err| err.to_string())?
+        .into_json()
+        .map_err(|err| err.to_string())
+}
+
+/// Return the plugins of the models served by the `burn-lm-http` server at `url`.
+///
+/// The plugins are named after the remote models, prefixed with `<prefix>/` when a prefix is
+/// given to tell apart the models of several servers.
+///
+/// The models of a server, or the failure to list them, are only requested once per process,
+/// later calls, e.g. each time the registry is created, return new plugins of the same models.
+pub fn remote_models(url: &str, prefix: Option<&str>) -> InferenceResult<Vec<RemotePlugin>> {
+    static LISTED_MODELS: LazyLock<Mutex<HashMap<String, Result<Vec<RemoteModel>, String>>>> =
+        LazyLock::new(Default::default);
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .build();
+    let url = url.trim_end_matches('/');
+    let models = LISTED_MODELS
+        .lock()
+        .unwrap()
+        .entry(url.to_string())
+        .or_insert_with(|| list_models(&agent, url))
+        .clone()
+        .map_err(|reason| InferenceError::RemoteError(url.to_string(), reason))?;
+    Ok(models
+        .into_iter()
+        .map(|model| RemotePlugin::new(url, prefix, model, agent.clone()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_server::{Response, TestServer},
+        Message, MessageRole, TextGenerationListener,
+    };
+
+    fn message(content: &str) -> InferenceTask {
+        InferenceTask::Message(Message {
+            role: MessageRole::User,
+            content: content.to_string(),
+            refusal: None,
+        })
+    }
+
+    fn chunk(content: &str) -> String {
+        let chunk = serde_json::json!({
+            "choices": [{"index": 0, "delta": {"role": null, "content": content}}]
+        });
+        format!("data: {chunk}\n\n")
+    }
+
+    /// Serve the given response bodies to successive requests.
+    fn serve(bodies: Vec<String>) -> TestServer {
+        TestServer::sequence(bodies.into_iter().map(Response::ok).collect())
+    }
+
+    #[test]
+    fn test_remote_plugin_streams_reply() {
+        let models = r#"[{"id": "Parrot", "created": 1738022400, "object": "model", "created_by": "Tracel"}]"#;
+        let stream = [
+            chunk("```Burn LM\nloading model 'Parrot'... "),
+            chunk("model loaded ! ✓\n```\n\n"),
+            chunk(&format!("\n{REPLY_MARKER}\n")),
+            chunk("Hello "),
+            chunk("world"),
+            chunk(&format!("\n\n\n{STATS_MARKER}\n| stats |")),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+        let server = serve(vec![models.to_string(), stream]);
+
+        let plugins = remote_models(server.url(), Some("gpu")).unwrap();
+        assert_eq!(plugins.len(), 1);
+        let plugin = &plugins[0];
+        assert_eq!(plugin.model_name(), "gpu/Parrot");
+        assert_eq!(plugin.model_cli_param_name(), "gpu-parrot");
+        assert_eq!(plugin.model_creation_date(), "2025/01/28");
+
+        plugin.parse_json_config(r#"{"temperature": 0.5}"#);
+        let (job, handle) =
+            InferenceJob::create(message("Hello world"), TextGenerationListener::default());
+        let stats = plugin.run_job(job).unwrap();
+        assert_eq!(handle.join(), "Hello world");
+        assert!(stats
+            .entries
+            .iter()
+            .any(|entry| matches!(entry, StatEntry::InferenceDuration(_))));
+
+        let request = &server.requests()[1];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/chat/completions");
+        assert!(request.body_text().contains(r#""model":"Parrot""#));
+        assert!(request.body_text().contains(r#""temperature":0.5"#));
+    }
+
+    #[test]
+    fn test_remote_models_listed_once() {
+        let models = r#"[{"id": "Parrot", "created": 0, "object": "model"}]"#;
+        let server = serve(vec![models.to_string()]);
+        let url = server.url();
+
+        let plugins = remote_models(url, None).unwrap();
+        // The server only answers one request, the models are not requested again
+        let prefixed = remote_models(&format!("{url}/"), Some("gpu")).unwrap();
+        assert_eq!(plugins[0].model_name(), "Parrot");
+        assert_eq!(prefixed[0].model_name(), "gpu/Parrot");
+        assert_eq!(server.requests().len(), 1);
+
+        // The plugins don't share their state
+        plugins[0].load().unwrap();
+        assert!(!prefixed[0].is_loaded());
+    }
+
+    #[test]
+    fn test_remote_plugin_interrupted_stream() {
+        let models = r#"[{"id": "Parrot", "created": 0, "object": "model"}]"#;
+        let stream = [chunk(&format!("\n{REPLY_MARKER}\n")), chunk("Hello")].concat();
+        let server = serve(vec![models.to_string(), stream]);
+
+        let plugin = remote_models(server.url(), None).unwrap().remove(0);
+        let (job, _handle) =
+            InferenceJob::create(message("Hello"), TextGenerationListener::default());
+        assert!(matches!(
+            plugin.run_job(job),
+            Err(InferenceError::RemoteError(..))
+        ));
+    }
+
+    #[test]
+    fn test_remote_plugin_rejects_prompts() {
+        let models = r#"[{"id": "Parrot", "created": 0, "object": "model"}]"#;
+        let server = serve(vec![models.to_string()]);
+
+        let plugin = remote_models(server.url(), None).unwrap().remove(0);
+        let (job, _handle) = InferenceJob::create(
+            InferenceTask::Prompt("Hello".to_string()),
+            TextGenerationListener::default(),
+        );
+        assert!(matches!(
+            plugin.run_job(job),
+            Err(InferenceError::GenerationError(..))
+        ));
+    }
+
+    #[test]
+    fn test_reply_reader_split_markers() {
+        let stream = format!(
+            "```Burn LM\nloading model 'Parrot'... model loaded ! ✓\n```\n\n\n{REPLY_MARKER}\nHello\n\nworld ##### \n\n\n{STATS_MARKER}\n| stats |"
+        );
+        // Split the stream in chunks of every length, including in the markers
+        for size in 1..stream.len() {
+            let mut reader = ReplyReader::default();
+            let mut reply = String::new();
+            let mut start = 0;
+            while start < stream.len() {
+                let mut end = (start + size).min(stream.len());
+                while !stream.is_char_boundary(end) {
+                    end += 1;
+                }
+                reply.push_str(&reader.push(&stream[start..end]));
+                start = end;
+            }
+            assert_eq!(reply, "Hello\n\nworld ##### ", "chunks of {size} bytes");
+        }
+    }
+
+    #[test]
+    fn test_remote_plugin_names_are_interned() {
+        let name = intern("gpu/Parrot".to_string());
+        assert!(std::ptr::eq(name, intern("gpu/Parrot".to_string())));
+    }
+
+    #[test]
+    fn test_remote_plugin_cli_config() {
+        let plugin = RemotePlugin::new(
+            "http://localhost:3000/",
+            None,
+            RemoteModel {
+                id: "Parrot".to_string(),
+                created: 0,
+                created_by: String::new(),
+            },
+            ureq::agent(),
+        );
+        let args = (plugin.create_cli_flags_fn())()
+            .try_get_matches_from(["remote", "--top-p", "0.9", "--seed", "42"])
+            .unwrap();
+        plugin.parse_cli_config(&args);
+
+        let params = &plugin.state.lock().unwrap().params;
+        assert_eq!(params["top_p"], serde_json::json!(0.9));
+        assert_eq!(params["seed"], serde_json::json!(42));
+        assert_eq!(plugin.url, "http://localhost:3000");
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{errors::InferenceResult, InferenceJob, Stats};
+use crate::{
+    errors::InferenceResult, DeviceSettings, FloatDtype, InferenceError, InferenceJob,
+    InferenceTask, Loglikelihood, LoglikelihoodRequest, PerplexityArgs, QuantizationScheme, Stats,
+    Tokenization, TrainingArgs,
+};
 use std::fmt::Debug;
 
 /// Marker trait for server configurations.
@@ -19,8 +23,9 @@ pub trait ServerConfigParsing {
 /// Inference server interface aimed to be implemented to be able to register a
 /// model in Burn LM registry.
 pub trait InferenceServer: ServerConfigParsing + Clone + Default + Send + Sync + Debug {
-    /// Return closure of a function to download the model
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+    /// Return closure of a function to download the model, the device settings of the plugin
+    /// are given for models which are prepared on the device once downloaded.
+    fn downloader(&mut self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
         None
     }
 
@@ -35,6 +40,33 @@ pub trait InferenceServer: ServerConfigParsing + Clone + Default + Send + Sync +
         None
     }
 
+    /// Return closure of a function to quantize the downloaded model on the device of the
+    /// given settings and save it as a new model variant.
+    fn quantizer(
+        &mut self,
+    ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>> {
+        None
+    }
+
+    /// Return closure of a function to train the downloaded model on a dataset on the device
+    /// of the given settings and save it as a new model variant.
+    fn trainer(
+        &mut self,
+    ) -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>> {
+        None
+    }
+
+    /// Float type the model runs best with, e.g. f32 for quantized models whose activations
+    /// lose precision in f16. It is used instead of the default float type when the default
+    /// backend supports it.
+    const PREFERRED_DTYPE: Option<FloatDtype> = None;
+
+    /// Select the device on which the model is loaded, used from the next load.
+    ///
+    /// Servers which don't support the device selection load their model on the
+    /// [default device](crate::INFERENCE_DEVICE).
+    fn set_device(&mut self, _settings: DeviceSettings) {}
+
     /// Load the model.
     fn load(&mut self) -> InferenceResult<Option<Stats>>;
 
@@ -49,4 +81,28 @@ pub trait InferenceServer: ServerConfigParsing + Clone + Default + Send + Sync +
 
     /// Clear the model state
     fn clear_state(&mut self) -> InferenceResult<()>;
+
+    /// Evaluate the perplexity of the loaded model on a text.
+    fn perplexity(&mut self, _args: PerplexityArgs) -> InferenceResult<Stats> {
+        Err(InferenceError::PluginEvaluationUnsupportedError)
+    }
+
+    /// Score the log-likelihood of each continuation given its context with the loaded model.
+    fn loglikelihood(
+        &mut self,
+        _requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        Err(InferenceError::PluginEvaluationUnsupportedError)
+    }
+
+    /// Tokenize the prompt of a task with the model tokenizer and chat template, without
+    /// loading the model weights.
+    fn tokenize(&mut self, _task: InferenceTask) -> InferenceResult<Tokenization> {
+        Err(InferenceError::PluginTokenizationUnsupportedError)
+    }
+
+    /// Decode token identifiers with the model tokenizer, without loading the model weights.
+    fn detokenize(&mut self, _tokens: Vec<u32>) -> InferenceResult<String> {
+        Err(InferenceError::PluginTokenizationUnsupportedError)
+    }
 }
@@ -1,10 +1,11 @@
 use comfy_table::{Cell, CellAlignment, Table};
+use serde::{Deserialize, Serialize};
 use std::{collections::BTreeSet, time::Duration};
 
 pub const STATS_MARKER: &str = "##### BurnLM Stats";
 
 /// A statistic entry returned by a Completion
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum StatEntry {
     /// Total inference duration
     InferenceDuration(Duration),
@@ -14,6 +15,8 @@ pub enum StatEntry {
     ModelLoadingDuration(Duration),
     /// A named stat
     Named(String, String),
+    /// Peak resident memory in bytes
+    PeakMemory(u64),
     /// Total number of tokens
     TokensCount(usize),
     /// The number of tokens per second
@@ -36,7 +39,7 @@ impl StatEntry {
     }
 }
 
-#[derive(Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Stats {
     pub entries: BTreeSet<StatEntry>,
 }
@@ -83,6 +86,10 @@ impl Stats {
                     format!("{:.2}s", duration.as_secs_f64()),
                 ),
                 StatEntry::Named(name, val) => (name.clone(), val.clone()),
+                StatEntry::PeakMemory(bytes) => (
+                    "Peak Memory".to_string(),
+                    format!("{:.2} GiB", *bytes as f64 / (1024.0 * 1024.0 * 1024.0)),
+                ),
             };
 
             table.add_row(vec![
@@ -0,0 +1,187 @@
+//! A minimal HTTP server to test the clients of remote services, e.g. the hub downloads or
+//! the remote inference plugins, without network access.
+//!
+//! ```ignore
+//! let server = TestServer::start(|request| match request.path.as_str() {
+//!     "/config.json" => Response::ok("{}"),
+//!     _ => Response::status(404),
+//! });
+//! let config = ureq::get(&format!("{}/config.json", server.url())).call();
+//! assert_eq!(server.requests().len(), 1);
+//! ```
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+};
+
+/// A request received by a [TestServer].
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The value of a header, the name is case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// A response sent by a [TestServer], the connection is closed after each response.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// A successful response with the given body.
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: vec![],
+            body: body.into(),
+        }
+    }
+
+    /// An empty response with the given status.
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn write(&self, stream: &mut impl Write) -> std::io::Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason(self.status),
+            self.body.len()
+        );
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+}
+
+/// An HTTP server answering the requests with a handler from a background thread, until the
+/// end of the test process.
+pub struct TestServer {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl TestServer {
+    /// Start a server on a free local port.
+    pub fn start(mut handler: impl FnMut(&Request) -> Response + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("should bind a local port");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let Ok(request) = read_request(&stream) else {
+                    continue;
+                };
+                let response = handler(&request);
+                // Recorded before answering, so the request is listed once its client returns
+                received.lock().unwrap().push(request);
+                // The client may have dropped the connection
+                let _ = response.write(&mut stream);
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// Serve the given responses to successive requests, the next requests are not found.
+    pub fn sequence(responses: Vec<Response>) -> Self {
+        let mut responses = responses.into_iter();
+        Self::start(move |_| responses.next().unwrap_or_else(|| Response::status(404)))
+    }
+
+    /// The base URL of the server, e.g. `http://127.0.0.1:4242`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The requests received so far.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> std::io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: vec![],
+    };
+    let length = request
+        .header("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(request)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        206 => "Partial Content",
+        404 => "Not Found",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
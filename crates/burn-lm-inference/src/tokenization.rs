@@ -0,0 +1,8 @@
+/// The prompt of a task tokenized with the model tokenizer and chat template.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Tokenization {
+    /// The token identifiers of the prompt.
+    pub tokens: Vec<u32>,
+    /// The prompt rendered with the chat template.
+    pub prompt: String,
+}
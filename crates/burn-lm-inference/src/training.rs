@@ -0,0 +1,191 @@
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+use crate::QuantizationScheme;
+
+/// Learning rate schedules listed in the CLI help.
+pub const LR_SCHEDULES: [&str; 3] = ["constant", "linear", "cosine"];
+
+/// Learning rate schedule applied after the optional warmup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LrSchedule {
+    /// Keep the learning rate constant.
+    Constant,
+    /// Decay the learning rate linearly to zero.
+    Linear,
+    /// Decay the learning rate following a cosine to zero.
+    #[default]
+    Cosine,
+}
+
+impl Display for LrSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LrSchedule::Constant => "constant",
+            LrSchedule::Linear => "linear",
+            LrSchedule::Cosine => "cosine",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for LrSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "constant" => Ok(LrSchedule::Constant),
+            "linear" => Ok(LrSchedule::Linear),
+            "cosine" => Ok(LrSchedule::Cosine),
+            _ => Err(format!(
+                "Invalid learning rate schedule '{s}', expected one of: {}",
+                LR_SCHEDULES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Arguments of a training run producing a new model variant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingArgs {
+    /// Text (`.txt`) or JSON lines (`.jsonl`) dataset file.
+    pub dataset: PathBuf,
+    /// Name of the trained model variant.
+    pub name: String,
+    /// Field holding the document text in JSON lines datasets.
+    pub text_field: String,
+    /// Train on chat conversations, learning only the assistant answers.
+    pub chat: bool,
+    /// Field holding the conversation messages in chat datasets.
+    pub messages_field: String,
+    /// Number of tokens per training sequence.
+    pub seq_len: usize,
+    /// Pack multiple documents per training sequence instead of padding each document.
+    pub packing: bool,
+    pub batch_size: usize,
+    pub num_epochs: usize,
+    /// Peak learning rate.
+    pub learning_rate: f64,
+    pub lr_schedule: LrSchedule,
+    /// Number of optimizer steps to linearly increase the learning rate.
+    pub warmup_steps: usize,
+    /// Number of batches whose gradients are accumulated before an optimizer step.
+    pub grad_accumulation: usize,
+    /// Fraction of the dataset held out for validation.
+    pub validation_ratio: f64,
+    pub seed: u64,
+    /// Resume the training from the last saved checkpoint.
+    pub resume: bool,
+    /// Rank of the LoRA adapter trained on top of the frozen weights, or `None` to train all
+    /// the weights.
+    pub lora_rank: Option<usize>,
+    /// Scaling numerator of the LoRA adapter.
+    pub lora_alpha: f64,
+    /// Quantization scheme of the frozen weights when training a LoRA adapter (QLoRA).
+    pub qlora: Option<QuantizationScheme>,
+}
+
+impl Default for TrainingArgs {
+    fn default() -> Self {
+        Self {
+            dataset: PathBuf::new(),
+            name: "finetuned".to_string(),
+            text_field: "text".to_string(),
+            chat: false,
+            messages_field: "messages".to_string(),
+            seq_len: 512,
+            packing: false,
+            batch_size: 1,
+            num_epochs: 1,
+            learning_rate: 2e-5,
+            lr_schedule: LrSchedule::default(),
+            warmup_steps: 0,
+            grad_accumulation: 1,
+            validation_ratio: 0.05,
+            seed: 42,
+            resume: false,
+            lora_rank: None,
+            lora_alpha: 16.0,
+            qlora: None,
+        }
+    }
+}
+
+impl TrainingArgs {
+    /// Check the consistency of the arguments.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.seq_len < 2 {
+            return Err("The sequence length must be at least 2".to_string());
+        }
+        if self.batch_size == 0 || self.num_epochs == 0 || self.grad_accumulation == 0 {
+            return Err(
+                "The batch size, number of epochs and gradient accumulation must be positive"
+                    .to_string(),
+            );
+        }
+        if self.learning_rate <= 0.0 || self.learning_rate > 1.0 {
+            return Err("The learning rate must be in (0, 1]".to_string());
+        }
+        if self.lora_rank == Some(0) || self.lora_alpha <= 0.0 {
+            return Err("The LoRA rank and alpha must be positive".to_string());
+        }
+        if self.qlora.is_some() && self.lora_rank.is_none() {
+            return Err("QLoRA requires a LoRA rank".to_string());
+        }
+        if !(0.0..1.0).contains(&self.validation_ratio) {
+            return Err("The validation ratio must be in [0, 1)".to_string());
+        }
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid variant name '{}', only alphanumeric characters, '-' and '_' are allowed",
+                self.name
+            ));
+        }
+        if self.name.parse::<QuantizationScheme>().is_ok() {
+            return Err(format!(
+                "Invalid variant name '{}', it is the name of a quantization scheme",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("constant", LrSchedule::Constant)]
+    #[case("linear", LrSchedule::Linear)]
+    #[case("Cosine", LrSchedule::Cosine)]
+    fn test_parse_lr_schedule(#[case] input: &str, #[case] expected: LrSchedule) {
+        let schedule: LrSchedule = input.parse().unwrap();
+        assert_eq!(schedule, expected);
+        assert_eq!(schedule.to_string(), input.to_lowercase());
+    }
+
+    #[rstest]
+    #[case(TrainingArgs { seq_len: 1, ..Default::default() })]
+    #[case(TrainingArgs { grad_accumulation: 0, ..Default::default() })]
+    #[case(TrainingArgs { learning_rate: 0.0, ..Default::default() })]
+    #[case(TrainingArgs { validation_ratio: 1.0, ..Default::default() })]
+    #[case(TrainingArgs { name: "my model".to_string(), ..Default::default() })]
+    #[case(TrainingArgs { name: "q4-block32".to_string(), ..Default::default() })]
+    #[case(TrainingArgs { lora_rank: Some(0), ..Default::default() })]
+    #[case(TrainingArgs { qlora: Some("q4-block32".parse().unwrap()), ..Default::default() })]
+    fn test_invalid_training_args(#[case] args: TrainingArgs) {
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_default_training_args_are_valid() {
+        assert!(TrainingArgs::default().validate().is_ok());
+        assert!("step".parse::<LrSchedule>().is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{inference::Llama, tokenizer::Tokenizer};
+
+/// Log-likelihood of a continuation given a context.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoglikelihoodOutput {
+    /// Sum of the log-probabilities of the continuation tokens, in nats.
+    pub logprob: f64,
+    /// The number of continuation tokens.
+    pub num_tokens: usize,
+}
+
+impl<T: Tokenizer> Llama<T> {
+    /// Score the log-likelihood of a continuation given a context.
+    ///
+    /// The context and the continuation are tokenized separately so that the continuation
+    /// tokens don't depend on the context. When both don't fit in the maximum sequence length,
+    /// the context is truncated from the start.
+    pub fn loglikelihood(
+        &mut self,
+        context: &str,
+        continuation: &str,
+    ) -> Result<LoglikelihoodOutput, String> {
+        let mut tokens = self.tokenizer.encode_text(context, true, false);
+        let continuation = self.tokenizer.encode_text(continuation, false, false);
+        let num_tokens = continuation.len();
+        if num_tokens == 0 {
+            return Err("The continuation to score is empty".to_string());
+        }
+        let window = self.cache.max_seq_len();
+        if num_tokens >= window {
+            return Err(format!(
+                "The continuation ({num_tokens} tokens) doesn't fit in the maximum sequence length ({window} tokens)"
+            ));
+        }
+        tokens.extend(continuation);
+
+        let tokens = &tokens[tokens.len().saturating_sub(window)..];
+        let nll = self.window_nll(tokens, tokens.len() - num_tokens)?;
+        self.reset();
+
+        Ok(LoglikelihoodOutput {
+            logprob: -nll,
+            num_tokens,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::Reinitializer, tokenizer::byte::ByteTokenizer, LlamaConfig};
+    use burn::prelude::*;
+
+    #[test]
+    fn test_loglikelihood_truncates_context() {
+        let device: Device = Default::default();
+        let mut llama = LlamaConfig::llama3_2_1b_test()
+            .with_max_seq_len(16)
+            .init::<ByteTokenizer>(&device)
+            .unwrap();
+        llama.model = Reinitializer::default()
+            .random_float(0, -0.1, 0.1)
+            .apply(llama.model);
+
+        let short = llama.loglikelihood("Hi", " there").unwrap();
+        let long = llama
+            .loglikelihood("A context longer than the window says hi", " there")
+            .unwrap();
+
+        assert_eq!(short.num_tokens, 6);
+        assert_eq!(long.num_tokens, 6);
+        assert!(short.logprob < 0.0 && long.logprob < 0.0);
+        assert!(llama.loglikelihood("Hi", "").is_err());
+        assert!(llama
+            .loglikelihood("Hi", " a continuation longer than the window")
+            .is_err());
+    }
+}
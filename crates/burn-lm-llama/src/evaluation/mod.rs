@@ -0,0 +1,5 @@
+mod loglikelihood;
+mod perplexity;
+
+pub use loglikelihood::*;
+pub use perplexity::*;
@@ -0,0 +1,142 @@
+use burn::{
+    prelude::*,
+    tensor::{activation::log_softmax, TensorData},
+};
+
+use crate::{inference::Llama, tokenizer::Tokenizer};
+
+/// Negative log-likelihood of a text evaluated with a sliding window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerplexityOutput {
+    /// Sum of the negative log-likelihoods of the predicted tokens, in nats.
+    pub nll: f64,
+    /// The number of predicted tokens.
+    pub num_tokens: usize,
+    /// The number of UTF-8 bytes of the text.
+    pub num_bytes: usize,
+    /// The number of forward passes.
+    pub num_windows: usize,
+}
+
+impl PerplexityOutput {
+    /// The exponential of the mean negative log-likelihood per token.
+    pub fn perplexity(&self) -> f64 {
+        (self.nll / self.num_tokens as f64).exp()
+    }
+
+    /// The negative log-likelihood in bits per byte of text, which doesn't depend on the
+    /// tokenizer.
+    pub fn bits_per_byte(&self) -> f64 {
+        self.nll / (self.num_bytes as f64 * std::f64::consts::LN_2)
+    }
+}
+
+impl<T: Tokenizer> Llama<T> {
+    /// Evaluate the perplexity of a text with a sliding window over the tokens.
+    ///
+    /// Windows span the maximum sequence length of the model and start every `stride` tokens.
+    /// Each window is a forward pass on a fresh cache that only scores the tokens not predicted
+    /// by the previous windows, so a smaller stride gives more context to the predictions at
+    /// the cost of more forward passes.
+    pub fn perplexity(&mut self, text: &str, stride: usize) -> Result<PerplexityOutput, String> {
+        let tokens = self.tokenizer.encode_text(text, true, false);
+        if tokens.len() < 2 {
+            return Err("The text is too short to evaluate the perplexity".to_string());
+        }
+        let window = self.cache.max_seq_len();
+        let stride = stride.clamp(1, window);
+
+        let mut output = PerplexityOutput {
+            nll: 0.0,
+            num_tokens: 0,
+            num_bytes: text.len(),
+            num_windows: 0,
+        };
+        // The first token is never predicted
+        let mut scored_end = 1;
+        let mut begin = 0;
+        loop {
+            let end = (begin + window).min(tokens.len());
+            // The first token of a window has no context to be predicted from
+            let start = scored_end.max(begin + 1);
+            if start < end {
+                output.nll += self.window_nll(&tokens[begin..end], start - begin)?;
+                output.num_tokens += end - start;
+                output.num_windows += 1;
+            }
+            scored_end = end;
+            if end == tokens.len() {
+                break;
+            }
+            begin += stride;
+        }
+        self.reset();
+
+        Ok(output)
+    }
+
+    /// Negative log-likelihood of the window tokens from the `start` position.
+    pub(super) fn window_nll(&mut self, tokens: &[u32], start: usize) -> Result<f64, String> {
+        self.reset();
+        let seq_len = tokens.len();
+        let input = Tensor::<2, Int>::from_data(
+            TensorData::new(tokens.to_vec(), [1, seq_len]),
+            &self.device,
+        );
+
+        let mask = self
+            .cache
+            .prepare(seq_len)
+            .map_err(|err| format!("Failed to evaluate the window.\nError: {err:?}"))?;
+        self.pos_encoding.prepare(seq_len);
+        let logits = self
+            .model
+            .forward(input.clone(), &mut self.cache, &self.pos_encoding, mask);
+
+        // The logits at position i predict the token at position i + 1
+        let [_, _, vocab_size] = logits.dims();
+        let num_targets = seq_len - start;
+        let logits = logits
+            .slice([0..1, start - 1..seq_len - 1])
+            .reshape([num_targets, vocab_size]);
+        let targets = input
+            .slice([0..1, start..seq_len])
+            .reshape([num_targets, 1]);
+        let log_probs = log_softmax(logits, 1).gather(1, targets);
+
+        Ok(-log_probs.into_data().iter::<f64>().sum::<f64>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::Reinitializer, tokenizer::byte::ByteTokenizer, LlamaConfig};
+
+    #[test]
+    fn test_perplexity_windows() {
+        let device: Device = Default::default();
+        let mut llama = LlamaConfig::llama3_2_1b_test()
+            .with_max_seq_len(16)
+            .init::<ByteTokenizer>(&device)
+            .unwrap();
+        llama.model = Reinitializer::default()
+            .random_float(0, -0.1, 0.1)
+            .apply(llama.model);
+        let text = "The quick brown fox jumps over the lazy dog";
+        // [bos] is 5 bytes, each byte of the text is one token
+        let num_tokens = 5 + text.len();
+
+        let full = llama.perplexity(text, 16).unwrap();
+        let overlapping = llama.perplexity(text, 4).unwrap();
+
+        // Non-overlapping windows can't predict their first token
+        assert_eq!(full.num_windows, 3);
+        assert_eq!(full.num_tokens, num_tokens - 3);
+        assert_eq!(overlapping.num_tokens, num_tokens - 1);
+        assert!(overlapping.num_windows > full.num_windows);
+        assert!(full.perplexity().is_finite() && full.perplexity() > 1.0);
+        assert!(overlapping.bits_per_byte() > 0.0);
+        assert!(llama.perplexity("", 4).is_err());
+    }
+}
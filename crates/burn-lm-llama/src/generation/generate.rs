@@ -41,11 +41,28 @@ impl<T: Tokenizer + 'static> Llama<T> {
         temperature: f64,
         sampler: &mut Sampler,
         emitter: GeneratedItemEmitter,
+    ) -> Result<GenerationOutput, GenerationError> {
+        let tokens = self.tokenizer.encode(prompt, false, false);
+        self.generate_tokens(tokens, sample_len, temperature, sampler, emitter)
+    }
+
+    /// Generate text sample based on the provided prompt tokens, such as an encoded
+    /// [ChatPrompt](crate::tokenizer::ChatPrompt).
+    ///
+    /// See [generate](Self::generate) for the other arguments.
+    pub fn generate_tokens(
+        &mut self,
+        tokens: Vec<u32>,
+        sample_len: usize,
+        temperature: f64,
+        sampler: &mut Sampler,
+        emitter: GeneratedItemEmitter,
     ) -> Result<GenerationOutput, GenerationError> {
         self.reset();
 
-        let input_tokens = self.tokenize(prompt);
-        let prompt_len = input_tokens.dims()[0];
+        let prompt_len = tokens.len();
+        let input_tokens =
+            Tensor::<1, Int>::from_data(TensorData::new(tokens, [prompt_len]), &self.device);
 
         let mut state = GenerationContext::new(
             prompt_len + sample_len,
@@ -162,10 +179,7 @@ mod tests {
 
     #[test]
     fn llama_generate_leaks_autoregressive_kv_cache_across_independent_generations() {
-        fn run_once(
-            llama: &mut crate::inference::Llama<ByteTokenizer>,
-            prompt: &str,
-        ) -> String {
+        fn run_once(llama: &mut crate::inference::Llama<ByteTokenizer>, prompt: &str) -> String {
             let (emitter, handle) = GeneratedItemEmitter::init(TextGenerationListener::default());
 
             // This observes streamed text, which can race with the decoder thread.
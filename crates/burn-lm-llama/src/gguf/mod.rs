@@ -0,0 +1,12 @@
+//! Reader for the [GGUF](https://github.com/ggml-org/ggml/blob/master/docs/gguf.md) file format.
+mod quant;
+mod reader;
+
+pub use quant::*;
+pub use reader::*;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    pub(crate) use super::quant::tests::*;
+    pub(crate) use super::reader::tests::*;
+}
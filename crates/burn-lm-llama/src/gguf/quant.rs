@@ -0,0 +1,459 @@
+use burn::tensor::{
+    bf16, f16,
+    quantization::{QuantLevel, QuantScheme, QuantValue},
+    TensorData,
+};
+
+/// Number of elements in a K-quant super-block.
+const QK_K: usize = 256;
+
+/// GGML tensor data types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum GgmlType {
+    F32 = 0,
+    F16 = 1,
+    Q4_0 = 2,
+    Q4_1 = 3,
+    Q5_0 = 6,
+    Q5_1 = 7,
+    Q8_0 = 8,
+    Q8_1 = 9,
+    Q2K = 10,
+    Q3K = 11,
+    Q4K = 12,
+    Q5K = 13,
+    Q6K = 14,
+    Q8K = 15,
+    BF16 = 30,
+}
+
+impl TryFrom<u32> for GgmlType {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        let dtype = match value {
+            0 => Self::F32,
+            1 => Self::F16,
+            2 => Self::Q4_0,
+            3 => Self::Q4_1,
+            6 => Self::Q5_0,
+            7 => Self::Q5_1,
+            8 => Self::Q8_0,
+            9 => Self::Q8_1,
+            10 => Self::Q2K,
+            11 => Self::Q3K,
+            12 => Self::Q4K,
+            13 => Self::Q5K,
+            14 => Self::Q6K,
+            15 => Self::Q8K,
+            30 => Self::BF16,
+            _ => return Err(format!("Unsupported GGML tensor type {value}")),
+        };
+
+        Ok(dtype)
+    }
+}
+
+impl GgmlType {
+    /// The number of elements per block.
+    pub fn block_size(&self) -> usize {
+        match self {
+            Self::F32 | Self::F16 | Self::BF16 => 1,
+            Self::Q4_0 | Self::Q4_1 | Self::Q5_0 | Self::Q5_1 | Self::Q8_0 | Self::Q8_1 => 32,
+            Self::Q2K | Self::Q3K | Self::Q4K | Self::Q5K | Self::Q6K | Self::Q8K => QK_K,
+        }
+    }
+
+    /// The number of bytes per block.
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F16 | Self::BF16 => 2,
+            Self::Q4_0 => 18,
+            Self::Q4_1 => 20,
+            Self::Q5_0 => 22,
+            Self::Q5_1 => 24,
+            Self::Q8_0 => 34,
+            Self::Q8_1 => 36,
+            Self::Q2K => 84,
+            Self::Q3K => 110,
+            Self::Q4K => 144,
+            Self::Q5K => 176,
+            Self::Q6K => 210,
+            Self::Q8K => 292,
+        }
+    }
+
+    /// The size in bytes of a tensor with the given number of elements.
+    pub fn size_in_bytes(&self, num_elements: usize) -> usize {
+        num_elements / self.block_size() * self.block_bytes()
+    }
+
+    /// The equivalent burn quantization scheme, when the GGML blocks can be represented as is.
+    ///
+    /// `Q4_0` and `Q8_0` are symmetric with a single scale per block of 32 values, which maps
+    /// directly to burn's block quantization.
+    pub fn quant_scheme(&self) -> Option<QuantScheme> {
+        let value = match self {
+            Self::Q4_0 => QuantValue::Q4F,
+            Self::Q8_0 => QuantValue::Q8F,
+            _ => return None,
+        };
+
+        Some(
+            QuantScheme::default()
+                .with_value(value)
+                .with_level(QuantLevel::block([32])),
+        )
+    }
+}
+
+fn read_f16(bytes: &[u8]) -> f32 {
+    f16::from_le_bytes([bytes[0], bytes[1]]).to_f32()
+}
+
+/// Convert raw GGML tensor data into burn [tensor data](TensorData) with the given shape.
+///
+/// `Q4_0` and `Q8_0` blocks are kept quantized, other types are dequantized to `f32`.
+pub fn tensor_data(bytes: &[u8], dtype: GgmlType, shape: Vec<usize>) -> Result<TensorData, String> {
+    let num_elements = shape
+        .iter()
+        .try_fold(1usize, |count, dim| count.checked_mul(*dim));
+    match num_elements {
+        Some(count)
+            if count % dtype.block_size() == 0 && bytes.len() == dtype.size_in_bytes(count) => {}
+        _ => {
+            return Err(format!(
+                "Invalid {dtype:?} data length {} for shape {shape:?}, expected whole blocks of {} \
+                 values",
+                bytes.len(),
+                dtype.block_size()
+            ))
+        }
+    }
+
+    if let Some(scheme) = dtype.quant_scheme() {
+        let (values, scales) = match dtype {
+            GgmlType::Q4_0 => unpack_q4_0(bytes),
+            _ => unpack_q8_0(bytes),
+        };
+        return Ok(TensorData::quantized(values, shape, scheme, &scales));
+    }
+
+    Ok(TensorData::new(dequantize(bytes, dtype)?, shape))
+}
+
+/// Dequantize raw GGML tensor data to `f32` values.
+pub fn dequantize(bytes: &[u8], dtype: GgmlType) -> Result<Vec<f32>, String> {
+    if bytes.len() % dtype.block_bytes() != 0 {
+        return Err(format!(
+            "Invalid {dtype:?} data length {}, expected a multiple of {}",
+            bytes.len(),
+            dtype.block_bytes()
+        ));
+    }
+
+    let blocks = bytes.chunks_exact(dtype.block_bytes());
+    let values = match dtype {
+        GgmlType::F32 => bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        GgmlType::F16 => bytes.chunks_exact(2).map(read_f16).collect(),
+        GgmlType::BF16 => bytes
+            .chunks_exact(2)
+            .map(|b| bf16::from_le_bytes([b[0], b[1]]).to_f32())
+            .collect(),
+        GgmlType::Q4_0 => {
+            let (values, scales) = unpack_q4_0(bytes);
+            scale_blocks(values, &scales)
+        }
+        GgmlType::Q8_0 => {
+            let (values, scales) = unpack_q8_0(bytes);
+            scale_blocks(values, &scales)
+        }
+        GgmlType::Q4_1 => blocks.flat_map(dequantize_q4_1).collect(),
+        GgmlType::Q4K => blocks.flat_map(dequantize_q4_k).collect(),
+        GgmlType::Q5K => blocks.flat_map(dequantize_q5_k).collect(),
+        GgmlType::Q6K => blocks.flat_map(dequantize_q6_k).collect(),
+        _ => return Err(format!("Unsupported GGML tensor type {dtype:?}")),
+    };
+
+    Ok(values)
+}
+
+fn scale_blocks(values: Vec<i8>, scales: &[f32]) -> Vec<f32> {
+    values
+        .chunks_exact(32)
+        .zip(scales)
+        .flat_map(|(block, d)| block.iter().map(move |q| *q as f32 * d))
+        .collect()
+}
+
+/// Unpack `Q4_0` blocks: `{ d: f16, qs: [u8; 16] }` with `x = d * (q - 8)`.
+///
+/// The low nibbles hold the first 16 values and the high nibbles the last 16.
+fn unpack_q4_0(bytes: &[u8]) -> (Vec<i8>, Vec<f32>) {
+    let num_blocks = bytes.len() / GgmlType::Q4_0.block_bytes();
+    let mut values = Vec::with_capacity(num_blocks * 32);
+    let mut scales = Vec::with_capacity(num_blocks);
+
+    for block in bytes.chunks_exact(GgmlType::Q4_0.block_bytes()) {
+        scales.push(read_f16(&block[0..2]));
+        let qs = &block[2..];
+        values.extend(qs.iter().map(|q| (q & 0x0F) as i8 - 8));
+        values.extend(qs.iter().map(|q| (q >> 4) as i8 - 8));
+    }
+
+    (values, scales)
+}
+
+/// Unpack `Q8_0` blocks: `{ d: f16, qs: [i8; 32] }` with `x = d * q`.
+fn unpack_q8_0(bytes: &[u8]) -> (Vec<i8>, Vec<f32>) {
+    let num_blocks = bytes.len() / GgmlType::Q8_0.block_bytes();
+    let mut values = Vec::with_capacity(num_blocks * 32);
+    let mut scales = Vec::with_capacity(num_blocks);
+
+    for block in bytes.chunks_exact(GgmlType::Q8_0.block_bytes()) {
+        scales.push(read_f16(&block[0..2]));
+        values.extend(block[2..].iter().map(|q| *q as i8));
+    }
+
+    (values, scales)
+}
+
+/// Dequantize a `Q4_1` block: `{ d: f16, m: f16, qs: [u8; 16] }` with `x = d * q + m`.
+fn dequantize_q4_1(block: &[u8]) -> Vec<f32> {
+    let d = read_f16(&block[0..2]);
+    let m = read_f16(&block[2..4]);
+    let qs = &block[4..];
+
+    qs.iter()
+        .map(|q| (q & 0x0F) as f32 * d + m)
+        .chain(qs.iter().map(|q| (q >> 4) as f32 * d + m))
+        .collect()
+}
+
+/// Returns the 6-bit scale and min of sub-block `j` of a `Q4_K`/`Q5_K` super-block.
+fn scale_min_k4(j: usize, scales: &[u8]) -> (f32, f32) {
+    let (d, m) = if j < 4 {
+        (scales[j] & 63, scales[j + 4] & 63)
+    } else {
+        (
+            (scales[j + 4] & 0x0F) | ((scales[j - 4] >> 6) << 4),
+            (scales[j + 4] >> 4) | ((scales[j] >> 6) << 4),
+        )
+    };
+    (d as f32, m as f32)
+}
+
+/// Dequantize a `Q4_K` super-block:
+/// `{ d: f16, dmin: f16, scales: [u8; 12], qs: [u8; 128] }`.
+fn dequantize_q4_k(block: &[u8]) -> Vec<f32> {
+    let d = read_f16(&block[0..2]);
+    let min = read_f16(&block[2..4]);
+    let scales = &block[4..16];
+    let qs = &block[16..];
+
+    let mut values = Vec::with_capacity(QK_K);
+    for (i, q) in qs.chunks_exact(32).enumerate() {
+        let (sc, m) = scale_min_k4(2 * i, scales);
+        let (d1, m1) = (d * sc, min * m);
+        let (sc, m) = scale_min_k4(2 * i + 1, scales);
+        let (d2, m2) = (d * sc, min * m);
+
+        values.extend(q.iter().map(|q| d1 * (q & 0x0F) as f32 - m1));
+        values.extend(q.iter().map(|q| d2 * (q >> 4) as f32 - m2));
+    }
+
+    values
+}
+
+/// Dequantize a `Q5_K` super-block:
+/// `{ d: f16, dmin: f16, scales: [u8; 12], qh: [u8; 32], qs: [u8; 128] }`.
+fn dequantize_q5_k(block: &[u8]) -> Vec<f32> {
+    let d = read_f16(&block[0..2]);
+    let min = read_f16(&block[2..4]);
+    let scales = &block[4..16];
+    let qh = &block[16..48];
+    let qs = &block[48..];
+
+    let mut values = Vec::with_capacity(QK_K);
+    for (i, ql) in qs.chunks_exact(32).enumerate() {
+        let (sc, m) = scale_min_k4(2 * i, scales);
+        let (d1, m1) = (d * sc, min * m);
+        let (sc, m) = scale_min_k4(2 * i + 1, scales);
+        let (d2, m2) = (d * sc, min * m);
+        let u1 = 1 << (2 * i);
+        let u2 = 2 << (2 * i);
+
+        values.extend(ql.iter().zip(qh).map(|(q, h)| {
+            let high = if h & u1 != 0 { 16 } else { 0 };
+            d1 * ((q & 0x0F) + high) as f32 - m1
+        }));
+        values.extend(ql.iter().zip(qh).map(|(q, h)| {
+            let high = if h & u2 != 0 { 16 } else { 0 };
+            d2 * ((q >> 4) + high) as f32 - m2
+        }));
+    }
+
+    values
+}
+
+/// Dequantize a `Q6_K` super-block:
+/// `{ ql: [u8; 128], qh: [u8; 64], scales: [i8; 16], d: f16 }`.
+fn dequantize_q6_k(block: &[u8]) -> Vec<f32> {
+    let ql = &block[0..128];
+    let qh = &block[128..192];
+    let scales = &block[192..208];
+    let d = read_f16(&block[208..210]);
+
+    let mut values = vec![0.0; QK_K];
+    for n in 0..2 {
+        let ql = &ql[n * 64..];
+        let qh = &qh[n * 32..];
+        let sc = &scales[n * 8..];
+        let y = &mut values[n * 128..];
+
+        for l in 0..32 {
+            let is = l / 16;
+            let q1 = ((ql[l] & 0x0F) | ((qh[l] & 3) << 4)) as i32 - 32;
+            let q2 = ((ql[l + 32] & 0x0F) | (((qh[l] >> 2) & 3) << 4)) as i32 - 32;
+            let q3 = ((ql[l] >> 4) | (((qh[l] >> 4) & 3) << 4)) as i32 - 32;
+            let q4 = ((ql[l + 32] >> 4) | (((qh[l] >> 6) & 3) << 4)) as i32 - 32;
+
+            y[l] = d * (sc[is] as i8) as f32 * q1 as f32;
+            y[l + 32] = d * (sc[is + 2] as i8) as f32 * q2 as f32;
+            y[l + 64] = d * (sc[is + 4] as i8) as f32 * q3 as f32;
+            y[l + 96] = d * (sc[is + 6] as i8) as f32 * q4 as f32;
+        }
+    }
+
+    values
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Quantize values to `Q8_0` blocks.
+    pub(crate) fn quantize_q8_0(values: &[f32]) -> Vec<u8> {
+        values
+            .chunks_exact(32)
+            .flat_map(|block| {
+                let amax = block.iter().fold(0f32, |acc, v| acc.max(v.abs()));
+                let d = amax / 127.;
+                let id = if d != 0. { 1. / d } else { 0. };
+                let mut bytes = f16::from_f32(d).to_le_bytes().to_vec();
+                bytes.extend(block.iter().map(|v| (v * id).round() as i8 as u8));
+                bytes
+            })
+            .collect()
+    }
+
+    /// Quantize values to `Q4_0` blocks.
+    pub(crate) fn quantize_q4_0(values: &[f32]) -> Vec<u8> {
+        values
+            .chunks_exact(32)
+            .flat_map(|block| {
+                let max = block
+                    .iter()
+                    .fold(0f32, |acc, v| if v.abs() > acc.abs() { *v } else { acc });
+                let d = max / -8.;
+                let id = if d != 0. { 1. / d } else { 0. };
+                let q = |v: f32| ((v * id + 8.5) as u8).min(15);
+                let mut bytes = f16::from_f32(d).to_le_bytes().to_vec();
+                bytes.extend((0..16).map(|j| q(block[j]) | (q(block[j + 16]) << 4)));
+                bytes
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() <= tolerance, "{a} != {e}");
+        }
+    }
+
+    #[test]
+    fn test_dequantize_q8_0() {
+        let values = (0..64).map(|i| (i as f32 - 32.) / 8.).collect::<Vec<_>>();
+        let bytes = quantize_q8_0(&values);
+        assert_eq!(bytes.len(), GgmlType::Q8_0.size_in_bytes(values.len()));
+
+        let output = dequantize(&bytes, GgmlType::Q8_0).unwrap();
+
+        assert_close(&output, &values, 0.02);
+    }
+
+    #[test]
+    fn test_dequantize_q4_0() {
+        let values = (0..32).map(|i| i as f32 - 16.).collect::<Vec<_>>();
+        let bytes = quantize_q4_0(&values);
+        assert_eq!(bytes.len(), GgmlType::Q4_0.size_in_bytes(values.len()));
+
+        let output = dequantize(&bytes, GgmlType::Q4_0).unwrap();
+
+        // 4-bit steps of `16 / 8 = 2`
+        assert_close(&output, &values, 1.0);
+        assert_eq!(output[0], -16.);
+    }
+
+    #[test]
+    fn test_dequantize_q4_k() {
+        // d = 1, dmin = 0.5, all sub-block scales = 2 and mins = 1
+        let mut block = f16::from_f32(1.).to_le_bytes().to_vec();
+        block.extend(f16::from_f32(0.5).to_le_bytes());
+        block.extend([2, 2, 2, 2, 1, 1, 1, 1, 0x12, 0x12, 0x12, 0x12]);
+        block.extend([0x31; 128]);
+
+        let output = dequantize(&block, GgmlType::Q4K).unwrap();
+
+        assert_eq!(output.len(), QK_K);
+        // Low nibbles: 2 * 1 - 0.5, high nibbles: 2 * 3 - 0.5
+        assert_eq!(&output[0..32], &[1.5; 32]);
+        assert_eq!(&output[32..64], &[5.5; 32]);
+        assert_eq!(&output[224..256], &[5.5; 32]);
+    }
+
+    #[test]
+    fn test_dequantize_q6_k() {
+        let mut block = vec![0x21; 128]; // ql: low = 1, high = 2
+        block.extend([0; 64]); // qh
+        block.extend([1; 16]); // scales
+        block.extend(f16::from_f32(0.5).to_le_bytes());
+
+        let output = dequantize(&block, GgmlType::Q6K).unwrap();
+
+        assert_eq!(output.len(), QK_K);
+        assert_eq!(&output[0..32], &[0.5 * (1. - 32.); 32]);
+        assert_eq!(&output[64..96], &[0.5 * (2. - 32.); 32]);
+    }
+
+    #[test]
+    fn test_quantized_tensor_data() {
+        let values = (0..64).map(|i| (i as f32 - 32.) / 8.).collect::<Vec<_>>();
+        let bytes = quantize_q8_0(&values);
+
+        let data = tensor_data(&bytes, GgmlType::Q8_0, vec![2, 32]).unwrap();
+
+        assert_eq!(data.shape, vec![2, 32]);
+        assert!(matches!(data.dtype, burn::tensor::DType::QFloat(_)));
+    }
+
+    #[test]
+    fn test_tensor_data_invalid_length() {
+        let values = (0..64).map(|i| (i as f32 - 32.) / 8.).collect::<Vec<_>>();
+        let bytes = quantize_q8_0(&values);
+
+        // truncated buffer
+        assert!(tensor_data(&bytes[..bytes.len() - 1], GgmlType::Q8_0, vec![2, 32]).is_err());
+        assert!(tensor_data(&bytes[..34], GgmlType::Q8_0, vec![2, 32]).is_err());
+        // shape which is not made of whole blocks
+        assert!(tensor_data(&bytes, GgmlType::Q8_0, vec![2, 30]).is_err());
+        assert!(tensor_data(&bytes, GgmlType::Q4_0, vec![2, 32]).is_err());
+        assert!(tensor_data(&bytes[..12], GgmlType::F32, vec![4]).is_err());
+    }
+}
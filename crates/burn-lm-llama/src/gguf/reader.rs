@@ -0,0 +1,626 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use super::GgmlType;
+
+/// GGUF magic number (`GGUF` in little-endian).
+pub const GGUF_MAGIC: u32 = 0x4655_4747;
+/// Default alignment of the tensor data section.
+pub const GGUF_DEFAULT_ALIGNMENT: u64 = 32;
+
+/// A GGUF metadata value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    Bool(bool),
+    String(String),
+    Array(Vec<GgufValue>),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+}
+
+impl GgufValue {
+    /// Returns the value as an unsigned integer, if it is an integer.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            GgufValue::U8(v) => Some(*v as u64),
+            GgufValue::U16(v) => Some(*v as u64),
+            GgufValue::U32(v) => Some(*v as u64),
+            GgufValue::U64(v) => Some(*v),
+            GgufValue::I8(v) => u64::try_from(*v).ok(),
+            GgufValue::I16(v) => u64::try_from(*v).ok(),
+            GgufValue::I32(v) => u64::try_from(*v).ok(),
+            GgufValue::I64(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a float, if it is numeric.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            GgufValue::F32(v) => Some(*v as f64),
+            GgufValue::F64(v) => Some(*v),
+            _ => self.as_u64().map(|v| v as f64),
+        }
+    }
+
+    /// Returns the value as a string slice, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            GgufValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a slice of values, if it is an array.
+    pub fn as_array(&self) -> Option<&[GgufValue]> {
+        match self {
+            GgufValue::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Information about a tensor stored in a GGUF file.
+#[derive(Debug, Clone)]
+pub struct GgufTensorInfo {
+    /// The tensor name.
+    pub name: String,
+    /// The tensor dimensions, innermost first (GGML order).
+    pub dims: Vec<usize>,
+    /// The tensor data type.
+    pub dtype: GgmlType,
+    /// The offset of the tensor data, relative to the start of the data section.
+    pub offset: u64,
+}
+
+impl GgufTensorInfo {
+    /// The tensor shape, outermost first (row-major order).
+    pub fn shape(&self) -> Vec<usize> {
+        self.dims.iter().rev().copied().collect()
+    }
+
+    /// The number of elements in the tensor.
+    pub fn num_elements(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    /// The size of the tensor data in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.dtype.size_in_bytes(self.num_elements())
+    }
+}
+
+/// A GGUF file header with its metadata and tensor information.
+///
+/// The tensor data is only read on demand with [GgufFile::tensor_data].
+#[derive(Debug, Clone)]
+pub struct GgufFile {
+    /// The file path.
+    pub path: PathBuf,
+    /// The GGUF format version.
+    pub version: u32,
+    /// The metadata key-value pairs.
+    pub metadata: HashMap<String, GgufValue>,
+    /// The tensors stored in the file.
+    pub tensors: Vec<GgufTensorInfo>,
+    /// The offset of the tensor data section.
+    pub data_offset: u64,
+}
+
+impl GgufFile {
+    /// Read the GGUF header (metadata and tensor information) from the provided path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| {
+            format!(
+                "Failed to open GGUF file '{}'.\nError: {err}",
+                path.display()
+            )
+        })?;
+        let mut reader = BufReader::new(file);
+
+        Self::read_header(&mut reader, path).map_err(|err| {
+            format!(
+                "Failed to read GGUF file '{}'.\nError: {err}",
+                path.display()
+            )
+        })
+    }
+
+    fn read_header<R: Read + Seek>(reader: &mut R, path: &Path) -> Result<Self, String> {
+        // The counts and lengths of the header are checked against the file length before
+        // allocating, a truncated or corrupted file must not allocate huge buffers
+        let file_len = reader
+            .seek(SeekFrom::End(0))
+            .and_then(|len| reader.seek(SeekFrom::Start(0)).map(|_| len))
+            .map_err(|err| err.to_string())?;
+        let reader = &mut LimitedReader { reader, file_len };
+
+        let magic = read_u32(reader)?;
+        if magic != GGUF_MAGIC {
+            return Err("Invalid GGUF magic number".to_string());
+        }
+        let version = read_u32(reader)?;
+        if !(2..=3).contains(&version) {
+            return Err(format!("Unsupported GGUF version {version}"));
+        }
+
+        let num_tensors = read_u64(reader)?;
+        let num_metadata = read_u64(reader)?;
+        // key length, value type and a value of at least one byte
+        reader.check_remaining(num_metadata, 8 + 4 + 1, "metadata")?;
+
+        let mut metadata = HashMap::new();
+        for _ in 0..num_metadata {
+            let key = read_string(reader)?;
+            let value_type = read_u32(reader)?;
+            let value = read_value(reader, value_type)?;
+            metadata.insert(key, value);
+        }
+
+        // name length, number of dimensions, type and offset
+        reader.check_remaining(num_tensors, 8 + 4 + 4 + 8, "tensors")?;
+        let mut tensors = Vec::with_capacity(num_tensors as usize);
+        for _ in 0..num_tensors {
+            let name = read_string(reader)?;
+            let num_dims = read_u32(reader)?;
+            reader.check_remaining(num_dims as u64, 8, "tensor dimensions")?;
+            let dims = (0..num_dims)
+                .map(|_| read_u64(reader).map(|dim| dim as usize))
+                .collect::<Result<Vec<_>, _>>()?;
+            if dims
+                .iter()
+                .try_fold(1usize, |count, dim| count.checked_mul(*dim))
+                .is_none()
+            {
+                return Err(format!(
+                    "Invalid dimensions {dims:?} of GGUF tensor '{name}'"
+                ));
+            }
+            let dtype = GgmlType::try_from(read_u32(reader)?)?;
+            let offset = read_u64(reader)?;
+            tensors.push(GgufTensorInfo {
+                name,
+                dims,
+                dtype,
+                offset,
+            });
+        }
+
+        let alignment = metadata
+            .get("general.alignment")
+            .and_then(GgufValue::as_u64)
+            .unwrap_or(GGUF_DEFAULT_ALIGNMENT);
+        if !alignment.is_power_of_two() {
+            return Err(format!("Invalid GGUF alignment {alignment}"));
+        }
+        let position = reader.stream_position().map_err(|err| err.to_string())?;
+        let data_offset = position.div_ceil(alignment) * alignment;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            metadata,
+            tensors,
+            data_offset,
+        })
+    }
+
+    /// Returns the metadata value for the given key.
+    pub fn get(&self, key: &str) -> Option<&GgufValue> {
+        self.metadata.get(key)
+    }
+
+    /// Returns the required unsigned integer metadata value for the given key.
+    pub fn get_u64(&self, key: &str) -> Result<u64, String> {
+        self.get(key)
+            .and_then(GgufValue::as_u64)
+            .ok_or_else(|| format!("Missing or invalid GGUF metadata '{key}'"))
+    }
+
+    /// Returns the required float metadata value for the given key.
+    pub fn get_f64(&self, key: &str) -> Result<f64, String> {
+        self.get(key)
+            .and_then(GgufValue::as_f64)
+            .ok_or_else(|| format!("Missing or invalid GGUF metadata '{key}'"))
+    }
+
+    /// Returns the required string metadata value for the given key.
+    pub fn get_str(&self, key: &str) -> Result<&str, String> {
+        self.get(key)
+            .and_then(GgufValue::as_str)
+            .ok_or_else(|| format!("Missing or invalid GGUF metadata '{key}'"))
+    }
+
+    /// The model architecture (e.g., `llama`).
+    pub fn architecture(&self) -> Result<&str, String> {
+        self.get_str("general.architecture")
+    }
+
+    /// Returns the tensor information for the given name.
+    pub fn tensor(&self, name: &str) -> Option<&GgufTensorInfo> {
+        self.tensors.iter().find(|info| info.name == name)
+    }
+
+    /// Read the raw data of a tensor.
+    pub fn tensor_data(&self, info: &GgufTensorInfo) -> Result<Vec<u8>, String> {
+        let mut file = File::open(&self.path).map_err(|err| err.to_string())?;
+        let file_len = file.metadata().map_err(|err| err.to_string())?.len();
+        let start = self.data_offset.saturating_add(info.offset);
+        let size = info.size_in_bytes();
+        if start.saturating_add(size as u64) > file_len {
+            return Err(format!(
+                "Failed to read GGUF tensor '{}'.\nError: {size} bytes at offset {start} exceed \
+                 the file length {file_len}",
+                info.name
+            ));
+        }
+        file.seek(SeekFrom::Start(start))
+            .map_err(|err| err.to_string())?;
+
+        let mut data = vec![0; size];
+        file.read_exact(&mut data)
+            .map_err(|err| format!("Failed to read GGUF tensor '{}'.\nError: {err}", info.name))?;
+
+        Ok(data)
+    }
+}
+
+/// A reader of a GGUF header which knows the length of the file.
+struct LimitedReader<'a, R> {
+    reader: &'a mut R,
+    file_len: u64,
+}
+
+impl<R: Read + Seek> LimitedReader<'_, R> {
+    /// Check that `count` items of at least `item_size` bytes fit in the rest of the file.
+    fn check_remaining(&mut self, count: u64, item_size: u64, what: &str) -> Result<(), String> {
+        let position = self.stream_position().map_err(|err| err.to_string())?;
+        let remaining = self.file_len.saturating_sub(position);
+        match count.checked_mul(item_size) {
+            Some(size) if size <= remaining => Ok(()),
+            _ => Err(format!(
+                "Invalid GGUF {what} count {count}, it exceeds the {remaining} remaining bytes"
+            )),
+        }
+    }
+}
+
+impl<R: Read> Read for LimitedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for LimitedReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.reader.seek(pos)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        self.reader.stream_position()
+    }
+}
+
+/// The minimum size in bytes of a metadata value of the given type.
+fn value_min_size(value_type: u32) -> u64 {
+    match value_type {
+        2 | 3 => 2,
+        4..=6 => 4,
+        // strings start with their length, arrays with their item type and length
+        8 | 10..=12 => 8,
+        9 => 12,
+        _ => 1,
+    }
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], String> {
+    let mut bytes = [0; N];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, String> {
+    read_bytes(reader).map(u32::from_le_bytes)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, String> {
+    read_bytes(reader).map(u64::from_le_bytes)
+}
+
+fn read_string<R: Read + Seek>(reader: &mut LimitedReader<R>) -> Result<String, String> {
+    let len = read_u64(reader)?;
+    reader.check_remaining(len, 1, "string bytes")?;
+    let mut bytes = vec![0; len as usize];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    // Some vocabularies contain invalid UTF-8 sequences
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn read_value<R: Read + Seek>(
+    reader: &mut LimitedReader<R>,
+    value_type: u32,
+) -> Result<GgufValue, String> {
+    let value = match value_type {
+        0 => GgufValue::U8(u8::from_le_bytes(read_bytes(reader)?)),
+        1 => GgufValue::I8(i8::from_le_bytes(read_bytes(reader)?)),
+        2 => GgufValue::U16(u16::from_le_bytes(read_bytes(reader)?)),
+        3 => GgufValue::I16(i16::from_le_bytes(read_bytes(reader)?)),
+        4 => GgufValue::U32(read_u32(reader)?),
+        5 => GgufValue::I32(i32::from_le_bytes(read_bytes(reader)?)),
+        6 => GgufValue::F32(f32::from_le_bytes(read_bytes(reader)?)),
+        7 => GgufValue::Bool(u8::from_le_bytes(read_bytes(reader)?) != 0),
+        8 => GgufValue::String(read_string(reader)?),
+        9 => {
+            let item_type = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            reader.check_remaining(len, value_min_size(item_type), "array items")?;
+            let items = (0..len)
+                .map(|_| read_value(reader, item_type))
+                .collect::<Result<Vec<_>, _>>()?;
+            GgufValue::Array(items)
+        }
+        10 => GgufValue::U64(read_u64(reader)?),
+        11 => GgufValue::I64(i64::from_le_bytes(read_bytes(reader)?)),
+        12 => GgufValue::F64(f64::from_le_bytes(read_bytes(reader)?)),
+        _ => return Err(format!("Unknown GGUF metadata value type {value_type}")),
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Minimal GGUF writer to create synthetic test files.
+    #[derive(Default)]
+    pub(crate) struct GgufWriter {
+        metadata: Vec<(String, GgufValue)>,
+        tensors: Vec<(String, Vec<usize>, GgmlType, Vec<u8>)>,
+    }
+
+    impl GgufWriter {
+        pub(crate) fn metadata(mut self, key: &str, value: GgufValue) -> Self {
+            self.metadata.push((key.to_string(), value));
+            self
+        }
+
+        /// Add a tensor with its dimensions in GGML order (innermost first).
+        pub(crate) fn tensor(
+            mut self,
+            name: &str,
+            dims: &[usize],
+            dtype: GgmlType,
+            data: Vec<u8>,
+        ) -> Self {
+            self.tensors
+                .push((name.to_string(), dims.to_vec(), dtype, data));
+            self
+        }
+
+        pub(crate) fn tensor_f32(self, name: &str, dims: &[usize], values: &[f32]) -> Self {
+            let data = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+            self.tensor(name, dims, GgmlType::F32, data)
+        }
+
+        pub(crate) fn write(self, path: &Path) {
+            let mut bytes = Vec::new();
+            bytes.extend(GGUF_MAGIC.to_le_bytes());
+            bytes.extend(3u32.to_le_bytes());
+            bytes.extend((self.tensors.len() as u64).to_le_bytes());
+            bytes.extend((self.metadata.len() as u64).to_le_bytes());
+
+            for (key, value) in self.metadata.iter() {
+                write_string(&mut bytes, key);
+                bytes.extend(value_type(value).to_le_bytes());
+                write_value(&mut bytes, value);
+            }
+
+            let mut offset = 0u64;
+            let mut offsets = Vec::new();
+            for (name, dims, dtype, data) in self.tensors.iter() {
+                write_string(&mut bytes, name);
+                bytes.extend((dims.len() as u32).to_le_bytes());
+                for dim in dims {
+                    bytes.extend((*dim as u64).to_le_bytes());
+                }
+                bytes.extend((*dtype as u32).to_le_bytes());
+                bytes.extend(offset.to_le_bytes());
+                offsets.push(offset);
+                offset = (offset + data.len() as u64).div_ceil(GGUF_DEFAULT_ALIGNMENT)
+                    * GGUF_DEFAULT_ALIGNMENT;
+            }
+
+            let data_offset =
+                (bytes.len() as u64).div_ceil(GGUF_DEFAULT_ALIGNMENT) * GGUF_DEFAULT_ALIGNMENT;
+            for ((_, _, _, data), offset) in self.tensors.iter().zip(offsets) {
+                bytes.resize((data_offset + offset) as usize, 0);
+                bytes.extend(data);
+            }
+
+            std::fs::write(path, bytes).unwrap();
+        }
+    }
+
+    fn write_string(bytes: &mut Vec<u8>, value: &str) {
+        bytes.extend((value.len() as u64).to_le_bytes());
+        bytes.extend(value.as_bytes());
+    }
+
+    fn value_type(value: &GgufValue) -> u32 {
+        match value {
+            GgufValue::U8(_) => 0,
+            GgufValue::I8(_) => 1,
+            GgufValue::U16(_) => 2,
+            GgufValue::I16(_) => 3,
+            GgufValue::U32(_) => 4,
+            GgufValue::I32(_) => 5,
+            GgufValue::F32(_) => 6,
+            GgufValue::Bool(_) => 7,
+            GgufValue::String(_) => 8,
+            GgufValue::Array(_) => 9,
+            GgufValue::U64(_) => 10,
+            GgufValue::I64(_) => 11,
+            GgufValue::F64(_) => 12,
+        }
+    }
+
+    fn write_value(bytes: &mut Vec<u8>, value: &GgufValue) {
+        match value {
+            GgufValue::U8(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::I8(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::U16(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::I16(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::U32(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::I32(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::F32(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::Bool(v) => bytes.push(*v as u8),
+            GgufValue::String(v) => write_string(bytes, v),
+            GgufValue::Array(items) => {
+                let item_type = items.first().map(value_type).unwrap_or(0);
+                bytes.extend(item_type.to_le_bytes());
+                bytes.extend((items.len() as u64).to_le_bytes());
+                for item in items {
+                    write_value(bytes, item);
+                }
+            }
+            GgufValue::U64(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::I64(v) => bytes.extend(v.to_le_bytes()),
+            GgufValue::F64(v) => bytes.extend(v.to_le_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_gguf_read_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("header.gguf");
+        GgufWriter::default()
+            .metadata("general.architecture", GgufValue::String("llama".into()))
+            .metadata("llama.block_count", GgufValue::U32(2))
+            .metadata(
+                "tokenizer.ggml.tokens",
+                GgufValue::Array(vec![
+                    GgufValue::String("a".into()),
+                    GgufValue::String("b".into()),
+                ]),
+            )
+            .tensor_f32("first", &[3], &[1., 2., 3.])
+            .tensor_f32("second", &[2, 2], &[4., 5., 6., 7.])
+            .write(&path);
+
+        let file = GgufFile::open(&path).unwrap();
+
+        assert_eq!(file.version, 3);
+        assert_eq!(file.architecture().unwrap(), "llama");
+        assert_eq!(file.get_u64("llama.block_count").unwrap(), 2);
+        assert_eq!(
+            file.get("tokenizer.ggml.tokens")
+                .and_then(GgufValue::as_array)
+                .map(|tokens| tokens.len()),
+            Some(2)
+        );
+        assert_eq!(file.tensors.len(), 2);
+
+        let second = file.tensor("second").unwrap();
+        assert_eq!(second.shape(), vec![2, 2]);
+        let data = file.tensor_data(second).unwrap();
+        let values = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![4., 5., 6., 7.]);
+    }
+
+    /// A GGUF header with the given tensor and metadata counts, followed by `rest`.
+    fn header_bytes(num_tensors: u64, num_metadata: u64, rest: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(GGUF_MAGIC.to_le_bytes());
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(num_tensors.to_le_bytes());
+        bytes.extend(num_metadata.to_le_bytes());
+        bytes.extend(rest);
+        bytes
+    }
+
+    #[test]
+    fn test_gguf_invalid_counts_and_lengths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("invalid.gguf");
+        let mut huge_string = Vec::new();
+        write_string(&mut huge_string, "key");
+        huge_string.extend(8u32.to_le_bytes());
+        huge_string.extend(u64::MAX.to_le_bytes());
+        let mut huge_array = Vec::new();
+        write_string(&mut huge_array, "key");
+        huge_array.extend(9u32.to_le_bytes());
+        huge_array.extend(10u32.to_le_bytes());
+        huge_array.extend((u64::MAX / 8).to_le_bytes());
+
+        for bytes in [
+            header_bytes(u64::MAX, 0, &[]),
+            header_bytes(0, u64::MAX, &[]),
+            header_bytes(0, 1, &huge_string),
+            header_bytes(0, 1, &huge_array),
+        ] {
+            std::fs::write(&path, bytes).unwrap();
+            let err = GgufFile::open(&path).unwrap_err();
+            assert!(err.contains("remaining bytes"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_gguf_truncated_tensor_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("truncated.gguf");
+        GgufWriter::default()
+            .tensor_f32("tensor", &[4], &[1., 2., 3., 4.])
+            .write(&path);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 4]).unwrap();
+
+        let file = GgufFile::open(&path).unwrap();
+        let err = file
+            .tensor_data(file.tensor("tensor").unwrap())
+            .unwrap_err();
+        assert!(err.contains("exceed the file length"), "{err}");
+    }
+
+    #[test]
+    fn test_gguf_invalid_alignment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alignment.gguf");
+        for alignment in [0, 12] {
+            GgufWriter::default()
+                .metadata("general.alignment", GgufValue::U64(alignment))
+                .write(&path);
+            let err = GgufFile::open(&path).unwrap_err();
+            assert!(err.contains("alignment"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_gguf_invalid_magic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("invalid.gguf");
+        std::fs::write(&path, b"GGML\x03\x00\x00\x00").unwrap();
+
+        assert!(GgufFile::open(&path).is_err());
+    }
+}
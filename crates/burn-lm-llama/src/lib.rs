@@ -8,6 +8,12 @@ pub mod nn;
 /// Text generation components.
 pub mod generation;
 
+/// Model evaluation components.
+pub mod evaluation;
+
+#[cfg(feature = "gguf")]
+pub mod gguf;
+
 #[cfg(feature = "inference-server")]
 pub mod server;
 
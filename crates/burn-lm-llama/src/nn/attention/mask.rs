@@ -0,0 +1,79 @@
+use burn::tensor::{Bool, Device, Int, Tensor};
+
+/// Creates the attention mask of training sequences, where `true` values are masked.
+///
+/// Tokens attend causally to the previous tokens, excluding the padding tokens and, for packed
+/// sequences, the tokens of other documents. Padding tokens keep the causal mask so that no
+/// attention row is fully masked.
+///
+/// # Shapes
+///
+/// - mask_pad: `[batch_size, seq_length]`
+/// - document_ids: `[batch_size, seq_length]`
+/// - output: `[batch_size, 1, seq_length, seq_length]`
+pub fn attention_mask_train(
+    batch_size: usize,
+    seq_len: usize,
+    mask_pad: Option<Tensor<2, Bool>>,
+    document_ids: Option<Tensor<2, Int>>,
+    device: &Device,
+) -> Tensor<4, Bool> {
+    let shape = [batch_size, seq_len, seq_len];
+    let mut mask = Tensor::<2, Bool>::tril_mask([seq_len, seq_len], 0, device)
+        .unsqueeze::<3>()
+        .expand(shape);
+
+    if let Some(mask_pad) = mask_pad {
+        let keys = mask_pad.clone().unsqueeze_dim::<3>(1).expand(shape);
+        let queries = mask_pad.unsqueeze_dim::<3>(2).expand(shape);
+        mask = mask.bool_or(keys.bool_and(queries.bool_not()));
+    }
+
+    if let Some(document_ids) = document_ids {
+        let keys = document_ids.clone().unsqueeze_dim::<3>(1).expand(shape);
+        let queries = document_ids.unsqueeze_dim::<3>(2).expand(shape);
+        mask = mask.bool_or(queries.not_equal(keys));
+    }
+
+    mask.unsqueeze_dim(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::tensor::TensorData;
+
+    #[test]
+    fn test_attention_mask_padding() {
+        let device = Default::default();
+        let mask_pad = Tensor::<2, Bool>::from_data([[false, false, true]], &device);
+
+        let mask = attention_mask_train(1, 3, Some(mask_pad), None, &device);
+
+        mask.into_data().assert_eq(
+            &TensorData::from([[[
+                [false, true, true],
+                [false, false, true],
+                [false, false, false],
+            ]]]),
+            false,
+        );
+    }
+
+    #[test]
+    fn test_attention_mask_documents() {
+        let device = Default::default();
+        let document_ids = Tensor::<2, Int>::from_data([[0, 0, 1]], &device);
+
+        let mask = attention_mask_train(1, 3, None, Some(document_ids), &device);
+
+        mask.into_data().assert_eq(
+            &TensorData::from([[[
+                [false, true, true],
+                [false, false, true],
+                [true, true, false],
+            ]]]),
+            false,
+        );
+    }
+}
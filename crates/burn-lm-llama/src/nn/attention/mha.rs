@@ -4,7 +4,10 @@ use burn::{
     tensor::activation::softmax,
 };
 
-use crate::nn::pos_encoding::PositionalEncodingState;
+use crate::nn::{
+    lora::{forward_linear, merge_linear, AttentionLora, LoraConfig, LoraTarget},
+    pos_encoding::PositionalEncodingState,
+};
 
 use super::kv_cache::KeyValueCache;
 
@@ -29,6 +32,8 @@ pub struct MultiHeadAttention {
     wv: Linear,
     /// Output projection.
     wo: Linear,
+    /// Low-rank adapter of the projections, applied unmerged.
+    lora: Option<AttentionLora>,
 
     n_heads: usize,
     n_kv_heads: usize,
@@ -39,7 +44,7 @@ impl MultiHeadAttention {
     /// Applies masked self-attention in a non-cached (non-incremental) setting.
     ///
     /// This function is intended for scenarios where the entire input sequence
-    /// is available.
+    /// is available. Without a mask, a causal mask is applied.
     ///
     /// # Shapes
     ///
@@ -47,7 +52,12 @@ impl MultiHeadAttention {
     /// - key: `[batch_size, seq_length_2, d_model]`
     /// - value: `[batch_size, seq_length_2, d_model]`
     /// - output: `[batch_size, seq_length_1, d_model]`
-    pub fn forward_masked(&self, input: Tensor<3>, rope: &RotaryEncoding) -> Tensor<3> {
+    pub fn forward_masked(
+        &self,
+        input: Tensor<3>,
+        rope: &RotaryEncoding,
+        mask: Option<Tensor<4, Bool>>,
+    ) -> Tensor<3> {
         let device = input.device();
         let [batch_size, seq_len, hidden_size] = input.dims();
 
@@ -57,15 +67,17 @@ impl MultiHeadAttention {
         let q = rope.forward(q);
         let k = rope.forward(k);
 
-        let mask = if seq_len > 1 {
-            let mask = Tensor::<2, Bool>::tril_mask([seq_len, seq_len], 0, &device);
-            Some(mask.unsqueeze::<4>())
-        } else {
-            None
+        let mask = match mask {
+            Some(mask) => Some(mask),
+            None if seq_len > 1 => {
+                let mask = Tensor::<2, Bool>::tril_mask([seq_len, seq_len], 0, &device);
+                Some(mask.unsqueeze::<4>())
+            }
+            None => None,
         };
 
         let output = self.forward_attention(q, k, v, mask, batch_size, seq_len, hidden_size);
-        self.wo.forward(output)
+        self.forward_output(output)
     }
 
     /// Applies the forward pass on the input tensors.
@@ -115,15 +127,16 @@ impl MultiHeadAttention {
 
         let output = self.forward_attention(q, k, v, mask, batch_size, seq_len, hidden_size);
 
-        self.wo.forward(output)
+        self.forward_output(output)
     }
 
     fn forward_projection(&self, input: Tensor<3>) -> (Tensor<4>, Tensor<4>, Tensor<4>) {
         let [batch_size, seq_len, _hidden_size] = input.dims();
 
-        let q = self.wq.forward(input.clone());
-        let k = self.wk.forward(input.clone());
-        let v = self.wv.forward(input);
+        let lora = self.lora.as_ref();
+        let q = forward_linear(&self.wq, lora.and_then(|l| l.wq.as_ref()), input.clone());
+        let k = forward_linear(&self.wk, lora.and_then(|l| l.wk.as_ref()), input.clone());
+        let v = forward_linear(&self.wv, lora.and_then(|l| l.wv.as_ref()), input);
 
         // [batch_size, num_heads, seq_len, head_dim]
         let q = q
@@ -139,6 +152,11 @@ impl MultiHeadAttention {
         (q, k, v)
     }
 
+    fn forward_output(&self, input: Tensor<3>) -> Tensor<3> {
+        let lora = self.lora.as_ref().and_then(|l| l.wo.as_ref());
+        forward_linear(&self.wo, lora, input)
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn forward_attention(
         &self,
@@ -185,6 +203,46 @@ impl MultiHeadAttention {
     }
 }
 
+impl MultiHeadAttention {
+    /// The number of query and key-value heads.
+    pub fn num_heads(&self) -> (usize, usize) {
+        (self.n_heads, self.n_kv_heads)
+    }
+
+    /// Initialize a new low-rank adapter of the attention projections.
+    pub fn init_lora(&self, config: &LoraConfig, device: &Device) -> AttentionLora {
+        let [d_model, d_query] = self.wq.weight.lazy_shape().dims();
+        let [_, d_kv] = self.wk.weight.lazy_shape().dims();
+
+        AttentionLora {
+            wq: config.init_linear(LoraTarget::Wq, d_model, d_query, device),
+            wk: config.init_linear(LoraTarget::Wk, d_model, d_kv, device),
+            wv: config.init_linear(LoraTarget::Wv, d_model, d_kv, device),
+            wo: config.init_linear(LoraTarget::Wo, d_query, d_model, device),
+        }
+    }
+
+    /// The low-rank adapter applied unmerged to the projections.
+    pub fn lora(&self) -> Option<&AttentionLora> {
+        self.lora.as_ref()
+    }
+
+    /// Set the low-rank adapter applied unmerged to the projections.
+    pub fn with_lora(mut self, lora: Option<AttentionLora>) -> Self {
+        self.lora = lora;
+        self
+    }
+
+    /// Merge the low-rank adapter into the projection weights.
+    pub fn merge_lora(mut self, lora: &AttentionLora) -> Result<Self, String> {
+        self.wq = merge_linear(self.wq, lora.wq.as_ref())?;
+        self.wk = merge_linear(self.wk, lora.wk.as_ref())?;
+        self.wv = merge_linear(self.wv, lora.wv.as_ref())?;
+        self.wo = merge_linear(self.wo, lora.wo.as_ref())?;
+        Ok(self)
+    }
+}
+
 impl MultiHeadAttentionConfig {
     /// Initialize a new [multi-head attention](MultiHeadAttention) module.
     pub fn init(&self, device: &Device) -> MultiHeadAttention {
@@ -208,6 +266,7 @@ impl MultiHeadAttentionConfig {
             wk,
             wv,
             wo,
+            lora: None,
             n_heads: self.n_heads,
             n_kv_heads: self.n_kv_heads,
             head_dim,
@@ -278,7 +337,7 @@ mod tests {
         let rope = RotaryEncodingConfig::new(seq_length * 2, config.d_model / config.n_heads)
             .init(&device);
 
-        let output = mha.forward_masked(input, &rope);
+        let output = mha.forward_masked(input, &rope, None);
         let expected = arange_mha_expected_value();
 
         output
@@ -1,6 +1,8 @@
 mod cache;
 mod kv_cache;
+mod mask;
 mod mha;
 
 pub use kv_cache::*;
+pub use mask::*;
 pub use mha::*;
@@ -1,7 +1,9 @@
 use burn::config::Config;
 use burn::module::Module;
 use burn::nn::{Linear, LinearConfig, SwiGlu, SwiGluConfig};
-use burn::tensor::{Device, Tensor};
+use burn::tensor::{activation::silu, Device, Tensor};
+
+use crate::nn::lora::{forward_linear, merge_linear, FeedForwardLora, LoraConfig, LoraTarget};
 
 #[derive(Config, Debug)]
 /// Configuration to create a [feed-forward transformation network](FeedForward).
@@ -19,6 +21,8 @@ pub struct FeedForward {
     swiglu: SwiGlu,
     /// Outer linear.
     w2: Linear,
+    /// Low-rank adapter of the projections, applied unmerged.
+    lora: Option<FeedForwardLora>,
 }
 
 impl FeedForwardConfig {
@@ -31,7 +35,11 @@ impl FeedForwardConfig {
             .with_bias(false)
             .init(device);
 
-        FeedForward { swiglu, w2 }
+        FeedForward {
+            swiglu,
+            w2,
+            lora: None,
+        }
     }
 }
 impl FeedForward {
@@ -42,7 +50,52 @@ impl FeedForward {
     /// - input: `[batch_size, seq_length, d_model]`
     /// - output: `[batch_size, seq_length, d_model]`
     pub fn forward(&self, input: Tensor<3>) -> Tensor<3> {
-        self.w2.forward(self.swiglu.forward(input))
+        match &self.lora {
+            Some(lora) => {
+                // SwiGLU with the low-rank updates of its inner and outer projections
+                let inner = forward_linear(
+                    &self.swiglu.linear_inner,
+                    lora.linear_inner.as_ref(),
+                    input.clone(),
+                );
+                let outer =
+                    forward_linear(&self.swiglu.linear_outer, lora.linear_outer.as_ref(), input);
+                forward_linear(&self.w2, lora.w2.as_ref(), silu(inner).mul(outer))
+            }
+            None => self.w2.forward(self.swiglu.forward(input)),
+        }
+    }
+
+    /// Initialize a new low-rank adapter of the feed-forward projections.
+    pub fn init_lora(&self, config: &LoraConfig, device: &Device) -> FeedForwardLora {
+        let [d_model, hidden_size] = self.swiglu.linear_inner.weight.lazy_shape().dims();
+
+        FeedForwardLora {
+            linear_inner: config.init_linear(LoraTarget::SwigluInner, d_model, hidden_size, device),
+            linear_outer: config.init_linear(LoraTarget::SwigluOuter, d_model, hidden_size, device),
+            w2: config.init_linear(LoraTarget::W2, hidden_size, d_model, device),
+        }
+    }
+
+    /// The low-rank adapter applied unmerged to the projections.
+    pub fn lora(&self) -> Option<&FeedForwardLora> {
+        self.lora.as_ref()
+    }
+
+    /// Set the low-rank adapter applied unmerged to the projections.
+    pub fn with_lora(mut self, lora: Option<FeedForwardLora>) -> Self {
+        self.lora = lora;
+        self
+    }
+
+    /// Merge the low-rank adapter into the projection weights.
+    pub fn merge_lora(mut self, lora: &FeedForwardLora) -> Result<Self, String> {
+        self.swiglu.linear_inner =
+            merge_linear(self.swiglu.linear_inner, lora.linear_inner.as_ref())?;
+        self.swiglu.linear_outer =
+            merge_linear(self.swiglu.linear_outer, lora.linear_outer.as_ref())?;
+        self.w2 = merge_linear(self.w2, lora.w2.as_ref())?;
+        Ok(self)
     }
 }
 
@@ -12,8 +12,28 @@ use burn::{config::Config, nn::RotaryEncodingConfig, tensor::Device};
 use crate::tokenizer::SentencePieceTokenizer;
 #[cfg(feature = "llama3")]
 use crate::tokenizer::Tiktoken;
-#[cfg(feature = "llama3")]
-use burn::record::HalfPrecisionSettings;
+#[cfg(any(feature = "llama3", feature = "tiny"))]
+use burn::record::{HalfPrecisionSettings, NamedMpkFileRecorder};
+
+/// Load the checkpoint weights into the model.
+///
+/// Safetensors checkpoints are memory-mapped and materialized layer by layer on the model device,
+/// other checkpoints are read with the named MessagePack recorder.
+#[cfg(any(feature = "llama3", feature = "tiny"))]
+fn load_checkpoint<T: Tokenizer>(
+    llama: inference::Llama<T>,
+    checkpoint: &str,
+) -> Result<inference::Llama<T>, String> {
+    #[cfg(feature = "safetensors")]
+    if checkpoint.ends_with(".safetensors") {
+        return llama.load_streaming(checkpoint);
+    }
+
+    let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
+    llama
+        .load(checkpoint, &recorder)
+        .map_err(|err| format!("Failed to load pre-trained Llama model.\nError: {err}"))
+}
 
 #[derive(Clone, Debug, Default)]
 /// Llama-3 model variants to load.
@@ -31,6 +51,33 @@ pub enum LlamaVersion {
     Llama321bInstructQ4FB32,
 }
 
+impl LlamaVersion {
+    /// Load the model variant from a local checkpoint with [Tiktoken](https://github.com/openai/tiktoken) tokenizer.
+    #[cfg(feature = "llama3")]
+    pub fn load(
+        &self,
+        checkpoint: &str,
+        tokenizer_path: &str,
+        max_seq_len: usize,
+        device: &Device,
+    ) -> Result<inference::Llama<Tiktoken>, String> {
+        match self {
+            Self::Llama3Instruct => {
+                LlamaConfig::load_llama3_8b(checkpoint, tokenizer_path, max_seq_len, device)
+            }
+            Self::Llama31Instruct => {
+                LlamaConfig::load_llama3_1_8b(checkpoint, tokenizer_path, max_seq_len, device)
+            }
+            Self::Llama323bInstruct => {
+                LlamaConfig::load_llama3_2_3b(checkpoint, tokenizer_path, max_seq_len, device)
+            }
+            Self::Llama321bInstruct | Self::Llama321bInstructQ4FB32 => {
+                LlamaConfig::load_llama3_2_1b(checkpoint, tokenizer_path, max_seq_len, device)
+            }
+        }
+    }
+}
+
 /// Tiny Llama model variants to load.
 pub enum TinyLlamaVersion {
     /// TinyLlama-1.1B-Chat-v1.0
@@ -57,6 +104,9 @@ pub struct LlamaConfig {
     /// RMSNorm epsilon
     #[config(default = "1e-5")]
     pub norm_eps: f64,
+    /// Whether the output layer shares its weights with the token embeddings.
+    #[config(default = "false")]
+    pub tie_word_embeddings: bool,
     /// Rotary positional encoding (RoPE).
     #[config(default = "RopeConfig::new(10000.0)")]
     pub rope: RopeConfig,
@@ -146,18 +196,11 @@ impl LlamaConfig {
         max_seq_len: usize,
         device: &Device,
     ) -> Result<inference::Llama<Tiktoken>, String> {
-        use burn::record::NamedMpkFileRecorder;
-
         let llama = Self::llama3_2_3b(tokenizer_path)
             .with_max_seq_len(max_seq_len)
             .init::<Tiktoken>(device)?;
 
-        let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
-        let llama = llama
-            .load(checkpoint, &recorder)
-            .map_err(|err| format!("Failed to load pre-trained Llama model.\nError: {err}"))?;
-
-        Ok(llama)
+        load_checkpoint(llama, checkpoint)
     }
 
     /// Load pre-trained Llama-3.2-1B model with [Tiktoken](https://github.com/openai/tiktoken) tokenizer.
@@ -168,18 +211,11 @@ impl LlamaConfig {
         max_seq_len: usize,
         device: &Device,
     ) -> Result<inference::Llama<Tiktoken>, String> {
-        use burn::record::NamedMpkFileRecorder;
-
         let llama = Self::llama3_2_1b(tokenizer_path)
             .with_max_seq_len(max_seq_len)
             .init::<Tiktoken>(device)?;
 
-        let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
-        let llama = llama
-            .load(checkpoint, &recorder)
-            .map_err(|err| format!("Failed to load pre-trained Llama model.\nError: {err}"))?;
-
-        Ok(llama)
+        load_checkpoint(llama, checkpoint)
     }
 
     /// Load pre-trained Llama-3.1-8B model with [Tiktoken](https://github.com/openai/tiktoken) tokenizer.
@@ -190,18 +226,11 @@ impl LlamaConfig {
         max_seq_len: usize,
         device: &Device,
     ) -> Result<inference::Llama<Tiktoken>, String> {
-        use burn::record::NamedMpkFileRecorder;
-
         let llama = Self::llama3_1_8b(tokenizer_path)
             .with_max_seq_len(max_seq_len)
             .init::<Tiktoken>(device)?;
 
-        let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
-        let llama = llama
-            .load(checkpoint, &recorder)
-            .map_err(|err| format!("Failed to load pre-trained Llama model.\nError: {err}"))?;
-
-        Ok(llama)
+        load_checkpoint(llama, checkpoint)
     }
 
     /// Load pre-trained Llama-3-8B model with [Tiktoken](https://github.com/openai/tiktoken) tokenizer.
@@ -212,18 +241,11 @@ impl LlamaConfig {
         max_seq_len: usize,
         device: &Device,
     ) -> Result<inference::Llama<Tiktoken>, String> {
-        use burn::record::NamedMpkFileRecorder;
-
         let llama = Self::llama3_8b(tokenizer_path)
             .with_max_seq_len(max_seq_len)
             .init::<Tiktoken>(device)?;
 
-        let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
-        let llama = llama
-            .load(checkpoint, &recorder)
-            .map_err(|err| format!("Failed to load pre-trained Llama model.\nError: {err}"))?;
-
-        Ok(llama)
+        load_checkpoint(llama, checkpoint)
     }
 
     /// Load pre-trained TinyLlama-1.1B Chat v1.0 model with [SentenciePiece](https://github.com/google/sentencepiece) tokenizer.
@@ -234,18 +256,11 @@ impl LlamaConfig {
         max_seq_len: usize,
         device: &Device,
     ) -> Result<inference::Llama<SentencePieceTokenizer>, String> {
-        use burn::record::NamedMpkFileRecorder;
-
         let llama = Self::tiny_llama(tokenizer_path)
             .with_max_seq_len(max_seq_len)
             .init::<SentencePieceTokenizer>(device)?;
 
-        let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
-        let llama = llama
-            .load(checkpoint, &recorder)
-            .map_err(|err| format!("Failed to load pre-trained Llama model.\nError: {err}"))?;
-
-        Ok(llama)
+        load_checkpoint(llama, checkpoint)
     }
 
     /// Initialize a new [Llama](Llama) module.
@@ -276,14 +291,26 @@ impl LlamaConfig {
         )
         .with_theta(self.rope.theta);
 
-        let rope = if let Some(scaling) = &self.rope.scaled {
-            let freq_scaling_fn = move |x| scaling.freq_scaling_by_parts(x);
-            rope.init_with_frequency_scaling(freq_scaling_fn, device)
-        } else {
-            rope.init(device)
-        };
-
-        let pos_encoding = PositionalEncodingState::new(rope);
+        let rope =
+            match (&self.rope.scaled, &self.rope.scaling) {
+                (Some(_), Some(_)) => return Err(
+                    "RoPE frequency scaling by parts can't be combined with another RoPE scaling"
+                        .to_string(),
+                ),
+                (Some(scaling), None) => {
+                    let freq_scaling_fn = move |x| scaling.freq_scaling_by_parts(x);
+                    rope.init_with_frequency_scaling(freq_scaling_fn, device)
+                }
+                (None, Some(scaling)) => {
+                    let (theta, max_seq_len) = (self.rope.theta, self.max_seq_len);
+                    let freq_scaling_fn = move |x| scaling.scale_frequencies(x, theta, max_seq_len);
+                    rope.init_with_frequency_scaling(freq_scaling_fn, device)
+                }
+                (None, None) => rope.init(device),
+            };
+
+        let pos_encoding =
+            PositionalEncodingState::new(rope).with_attention_factor(self.rope.attention_factor());
 
         Ok(inference::Llama {
             tokenizer,
@@ -0,0 +1,533 @@
+//! Tokenized text and chat datasets for training.
+
+use std::path::Path;
+
+use burn::{
+    data::{dataloader::batcher::Batcher, dataset::Dataset},
+    tensor::{Bool, Device, Int, Tensor, TensorData},
+};
+use serde::Deserialize;
+
+use crate::tokenizer::{ChatFormat, Tokenizer};
+
+use super::training::{LlamaInput, IGNORE_INDEX};
+
+/// Read the documents of a text dataset file.
+///
+/// A `.jsonl` file contains one JSON object per line with the document text in `text_field`,
+/// any other file is read as a single plain text document.
+pub fn read_documents<P: AsRef<Path>>(path: P, text_field: &str) -> Result<Vec<String>, String> {
+    let path = path.as_ref();
+    let content = read_dataset(path)?;
+
+    if path.extension().is_some_and(|ext| ext == "jsonl") {
+        read_json_lines(&content, text_field, |text: serde_json::Value| {
+            text.as_str().map(|text| text.to_string())
+        })
+    } else {
+        Ok(vec![content])
+    }
+}
+
+/// Read the conversations of a chat dataset file.
+///
+/// The `.jsonl` file contains one JSON object per line with the conversation messages in
+/// `messages_field`, each message having a `role` and a `content`.
+pub fn read_conversations<P: AsRef<Path>>(
+    path: P,
+    messages_field: &str,
+) -> Result<Vec<Vec<ChatMessage>>, String> {
+    let content = read_dataset(path.as_ref())?;
+
+    read_json_lines(&content, messages_field, |messages| {
+        serde_json::from_value(messages).ok()
+    })
+}
+
+fn read_dataset(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read dataset '{}'.\nError: {err}", path.display()))
+}
+
+fn read_json_lines<T>(
+    content: &str,
+    field: &str,
+    parse: impl Fn(serde_json::Value) -> Option<T>,
+) -> Result<Vec<T>, String> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let mut value: serde_json::Value = serde_json::from_str(line)
+                .map_err(|err| format!("Failed to parse dataset line {}.\nError: {err}", i + 1))?;
+            value
+                .get_mut(field)
+                .map(|value| value.take())
+                .and_then(&parse)
+                .ok_or_else(|| {
+                    format!(
+                        "Missing or invalid field '{field}' on dataset line {}",
+                        i + 1
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Split off the last items of a dataset for validation.
+///
+/// At least one item is held out when the ratio is positive, and at least one is kept.
+fn split_validation<T>(items: &mut Vec<T>, validation_ratio: f64) -> Vec<T> {
+    let num_valid = match validation_ratio > 0.0 {
+        true => ((items.len() as f64 * validation_ratio).ceil() as usize)
+            .min(items.len().saturating_sub(1)),
+        false => 0,
+    };
+    items.split_off(items.len() - num_valid)
+}
+
+/// Tokenized training sequence holding the inputs and the next token targets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingSequence {
+    pub tokens: Vec<u32>,
+    /// Whether each token is learned as a target.
+    pub trained: Vec<bool>,
+    /// Document of each token, for sequences packing multiple documents.
+    pub document_ids: Vec<u32>,
+}
+
+/// Dataset of text documents split into sequences of at most `seq_len + 1` tokens.
+///
+/// Documents are tokenized and delimited with the beginning and end of sentence tokens. With
+/// packing, documents are concatenated before being split into sequences, and tokens only attend
+/// to the tokens of their own document. Otherwise, each document is split on its own and its
+/// last shorter sequence is padded when batched.
+#[derive(Debug, Clone)]
+pub struct TextDataset {
+    sequences: Vec<TrainingSequence>,
+}
+
+impl TextDataset {
+    /// Tokenize the documents into sequences of `seq_len` input tokens.
+    pub fn new<T: Tokenizer>(
+        documents: &[String],
+        tokenizer: &T,
+        seq_len: usize,
+        packing: bool,
+    ) -> Self {
+        let documents = documents
+            .iter()
+            .map(|document| tokenizer.encode(document, true, true))
+            .collect();
+
+        Self::from_documents(documents, seq_len, packing)
+    }
+
+    /// Split tokenized documents into sequences of `seq_len` input tokens.
+    ///
+    /// Consecutive sequences overlap by one token since the last target of a sequence is the
+    /// first input of the next one. With packing, remaining tokens that don't fill a sequence
+    /// are dropped.
+    pub fn from_documents(documents: Vec<Vec<u32>>, seq_len: usize, packing: bool) -> Self {
+        let documents = documents
+            .into_iter()
+            .enumerate()
+            .map(|(id, tokens)| {
+                // The first token of a document isn't predicted from the previous document
+                let mut trained = vec![true; tokens.len()];
+                if let Some(first) = trained.first_mut() {
+                    *first = false;
+                }
+                let document_ids = vec![id as u32; tokens.len()];
+                TrainingSequence {
+                    tokens,
+                    trained,
+                    document_ids,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let sequences = if packing {
+            let stream = TrainingSequence {
+                tokens: documents.iter().flat_map(|d| d.tokens.clone()).collect(),
+                trained: documents.iter().flat_map(|d| d.trained.clone()).collect(),
+                document_ids: documents
+                    .iter()
+                    .flat_map(|d| d.document_ids.clone())
+                    .collect(),
+            };
+            split_sequence(&stream, seq_len, false)
+        } else {
+            documents
+                .iter()
+                .flat_map(|document| split_sequence(document, seq_len, true))
+                .collect()
+        };
+
+        Self { sequences }
+    }
+
+    /// Split the dataset into a training and a validation dataset.
+    ///
+    /// The validation dataset holds the last sequences, with at least one sequence when the
+    /// ratio is positive.
+    pub fn split(mut self, validation_ratio: f64) -> (Self, Self) {
+        let valid = split_validation(&mut self.sequences, validation_ratio);
+
+        (self, Self { sequences: valid })
+    }
+}
+
+impl Dataset<TrainingSequence> for TextDataset {
+    fn get(&self, index: usize) -> Option<TrainingSequence> {
+        self.sequences.get(index).cloned()
+    }
+
+    fn len(&self) -> usize {
+        self.sequences.len()
+    }
+}
+
+/// Split a sequence into overlapping sequences of `seq_len + 1` tokens, keeping the last
+/// shorter sequence when `partial` is set.
+fn split_sequence(
+    sequence: &TrainingSequence,
+    seq_len: usize,
+    partial: bool,
+) -> Vec<TrainingSequence> {
+    let len = sequence.tokens.len();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start + seq_len < len {
+        ranges.push(start..start + seq_len + 1);
+        start += seq_len;
+    }
+    if partial && start + 1 < len {
+        ranges.push(start..len);
+    }
+
+    ranges
+        .into_iter()
+        .map(|range| TrainingSequence {
+            tokens: sequence.tokens[range.clone()].to_vec(),
+            trained: sequence.trained[range.clone()].to_vec(),
+            document_ids: sequence.document_ids[range].to_vec(),
+        })
+        .collect()
+}
+
+/// Batch training sequences into [training inputs](LlamaInput) with next token targets.
+///
+/// Sequences are padded to the longest one of the batch. Padding tokens and untrained targets
+/// are excluded from the loss with the [ignore index](IGNORE_INDEX), and the padding and
+/// document masks are only set when needed.
+#[derive(Debug, Clone, Default)]
+pub struct LlamaBatcher;
+
+impl Batcher<TrainingSequence, LlamaInput> for LlamaBatcher {
+    fn batch(&self, items: Vec<TrainingSequence>, device: &Device) -> LlamaInput {
+        let batch_size = items.len();
+        let seq_len = items
+            .iter()
+            .map(|item| item.tokens.len().saturating_sub(1))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let mut tokens = Vec::with_capacity(batch_size * seq_len);
+        let mut targets = Vec::with_capacity(batch_size * seq_len);
+        let mut mask_pad = Vec::with_capacity(batch_size * seq_len);
+        let mut document_ids = Vec::with_capacity(batch_size * seq_len);
+        for item in items.iter() {
+            let len = item.tokens.len().saturating_sub(1);
+            tokens.extend_from_slice(&item.tokens[..len]);
+            targets.extend((1..len + 1).map(|i| match item.trained[i] {
+                true => item.tokens[i] as i64,
+                false => IGNORE_INDEX,
+            }));
+            mask_pad.extend(std::iter::repeat_n(false, len));
+            document_ids.extend_from_slice(&item.document_ids[..len]);
+
+            // Padding tokens get their own document so that they never attend to other tokens
+            let padding = seq_len - len;
+            let padding_id = item.document_ids.iter().max().map_or(0, |id| id + 1);
+            tokens.extend(std::iter::repeat_n(0, padding));
+            targets.extend(std::iter::repeat_n(IGNORE_INDEX, padding));
+            mask_pad.extend(std::iter::repeat_n(true, padding));
+            document_ids.extend(std::iter::repeat_n(padding_id, padding));
+        }
+
+        let shape = [batch_size, seq_len];
+        let padded = mask_pad.iter().any(|pad| *pad);
+        let packed = items.iter().any(|item| {
+            item.document_ids
+                .iter()
+                .any(|id| Some(id) != item.document_ids.first())
+        });
+        LlamaInput {
+            tokens: Tensor::<2, Int>::from_data(TensorData::new(tokens, shape), device),
+            targets: Tensor::<2, Int>::from_data(TensorData::new(targets, shape), device),
+            mask_pad: padded
+                .then(|| Tensor::<2, Bool>::from_data(TensorData::new(mask_pad, shape), device)),
+            document_ids: packed
+                .then(|| Tensor::<2, Int>::from_data(TensorData::new(document_ids, shape), device)),
+        }
+    }
+}
+
+/// Message of a chat conversation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ChatMessage {
+    /// The author role, e.g. `system`, `user` or `assistant`.
+    pub role: String,
+    pub content: String,
+}
+
+/// Dataset of chat conversations for supervised fine-tuning (SFT).
+///
+/// Only the assistant answers are learned, the prompt tokens (system and user messages and
+/// all the message headers) are excluded from the loss.
+#[derive(Debug, Clone)]
+pub struct ChatDataset {
+    sequences: Vec<TrainingSequence>,
+}
+
+impl ChatDataset {
+    /// Tokenize the conversations, truncated to `seq_len` input tokens.
+    ///
+    /// Conversations without any assistant answer token left are dropped.
+    pub fn new<T: Tokenizer>(
+        conversations: &[Vec<ChatMessage>],
+        tokenizer: &T,
+        format: ChatFormat,
+        seq_len: usize,
+    ) -> Self {
+        let sequences = conversations
+            .iter()
+            .filter_map(|messages| {
+                let mut tokens = vec![tokenizer.bos_id()];
+                let mut trained = vec![false];
+                for message in messages {
+                    let (header, content) = format.render(&message.role, &message.content);
+                    let header = header.encode(tokenizer, false);
+                    let content = content.encode(tokenizer, false);
+                    trained.extend(std::iter::repeat_n(false, header.len()));
+                    trained.extend(std::iter::repeat_n(
+                        message.role.eq_ignore_ascii_case("assistant"),
+                        content.len(),
+                    ));
+                    tokens.extend(header);
+                    tokens.extend(content);
+                }
+                tokens.truncate(seq_len + 1);
+                trained.truncate(seq_len + 1);
+                let document_ids = vec![0; tokens.len()];
+
+                trained
+                    .iter()
+                    .any(|trained| *trained)
+                    .then_some(TrainingSequence {
+                        tokens,
+                        trained,
+                        document_ids,
+                    })
+            })
+            .collect();
+
+        Self { sequences }
+    }
+
+    /// Split the dataset into a training and a validation dataset.
+    pub fn split(mut self, validation_ratio: f64) -> (Self, Self) {
+        let valid = split_validation(&mut self.sequences, validation_ratio);
+
+        (self, Self { sequences: valid })
+    }
+}
+
+impl Dataset<TrainingSequence> for ChatDataset {
+    fn get(&self, index: usize) -> Option<TrainingSequence> {
+        self.sequences.get(index).cloned()
+    }
+
+    fn len(&self) -> usize {
+        self.sequences.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::byte::ByteTokenizer;
+
+    fn sequence(tokens: Vec<u32>, trained: Vec<bool>, document_ids: Vec<u32>) -> TrainingSequence {
+        TrainingSequence {
+            tokens,
+            trained,
+            document_ids,
+        }
+    }
+
+    #[test]
+    fn test_dataset_sequences() {
+        let dataset = TextDataset::from_documents(vec![(0..10).collect()], 3, true);
+
+        assert_eq!(dataset.len(), 3);
+        let tokens = |index| dataset.get(index).map(|sequence| sequence.tokens);
+        assert_eq!(tokens(0), Some(vec![0, 1, 2, 3]));
+        assert_eq!(tokens(1), Some(vec![3, 4, 5, 6]));
+        assert_eq!(tokens(2), Some(vec![6, 7, 8, 9]));
+
+        let (train, valid) = dataset.split(0.1);
+        assert_eq!(train.len(), 2);
+        assert_eq!(valid.get(0).unwrap().tokens, vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_dataset_packing() {
+        let documents = vec![vec![0, 1, 2], vec![3, 4, 5, 6, 7]];
+
+        let packed = TextDataset::from_documents(documents.clone(), 3, true);
+        assert_eq!(packed.len(), 2);
+        assert_eq!(
+            packed.get(0),
+            Some(sequence(
+                vec![0, 1, 2, 3],
+                vec![false, true, true, false],
+                vec![0, 0, 0, 1]
+            ))
+        );
+
+        let unpacked = TextDataset::from_documents(documents, 3, false);
+        assert_eq!(unpacked.len(), 3);
+        assert_eq!(
+            unpacked.get(0),
+            Some(sequence(vec![0, 1, 2], vec![false, true, true], vec![0; 3]))
+        );
+        assert_eq!(
+            unpacked.get(2),
+            Some(sequence(vec![6, 7], vec![true, true], vec![1; 2]))
+        );
+    }
+
+    #[test]
+    fn test_batcher_shifts_targets() {
+        let device = Default::default();
+        let input = LlamaBatcher.batch(
+            vec![
+                sequence(vec![1, 2, 3], vec![true; 3], vec![0; 3]),
+                sequence(vec![4, 5, 6], vec![true; 3], vec![0; 3]),
+            ],
+            &device,
+        );
+
+        input
+            .tokens
+            .into_data()
+            .assert_eq(&TensorData::from([[1, 2], [4, 5]]), false);
+        input
+            .targets
+            .into_data()
+            .assert_eq(&TensorData::from([[2, 3], [5, 6]]), false);
+        assert!(input.mask_pad.is_none());
+        assert!(input.document_ids.is_none());
+    }
+
+    #[test]
+    fn test_batcher_pads_and_ignores_targets() {
+        let device = Default::default();
+        let input = LlamaBatcher.batch(
+            vec![
+                sequence(
+                    vec![1, 2, 3, 4],
+                    vec![false, false, true, true],
+                    vec![0, 0, 1, 1],
+                ),
+                sequence(vec![5, 6, 7], vec![false, true, true], vec![0; 3]),
+            ],
+            &device,
+        );
+
+        let ignore = IGNORE_INDEX;
+        input
+            .tokens
+            .into_data()
+            .assert_eq(&TensorData::from([[1, 2, 3], [5, 6, 0]]), false);
+        input
+            .targets
+            .into_data()
+            .assert_eq(&TensorData::from([[ignore, 3, 4], [6, 7, ignore]]), false);
+        input.mask_pad.unwrap().into_data().assert_eq(
+            &TensorData::from([[false, false, false], [false, false, true]]),
+            false,
+        );
+        input
+            .document_ids
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([[0, 0, 1], [0, 0, 1]]), false);
+    }
+
+    #[test]
+    fn test_read_jsonl_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dataset.jsonl");
+        std::fs::write(
+            &path,
+            "{\"text\": \"first\", \"id\": 1}\n\n{\"text\": \"second\"}\n",
+        )
+        .unwrap();
+
+        let documents = read_documents(&path, "text").unwrap();
+        let missing = read_documents(&path, "content");
+
+        assert_eq!(documents, vec!["first".to_string(), "second".to_string()]);
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_chat_dataset_masks_prompt() {
+        let message = |role: &str, content: &str| ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        };
+        let conversations = vec![
+            vec![message("user", "hi"), message("assistant", "ok")],
+            vec![message("system", "be nice"), message("user", "hello")],
+        ];
+        let dataset = ChatDataset::new(&conversations, &ByteTokenizer, ChatFormat::Zephyr, 64);
+
+        // The conversation without an assistant answer is dropped
+        assert_eq!(dataset.len(), 1);
+        let sequence = dataset.get(0).unwrap();
+        let answer = "ok</s>\n".len();
+        assert_eq!(sequence.tokens.len(), 1 + 9 + 7 + 14 + answer);
+        assert_eq!(sequence.trained.iter().filter(|t| **t).count(), answer);
+        assert!(sequence.trained[sequence.trained.len() - answer..]
+            .iter()
+            .all(|t| *t));
+
+        // The answer is truncated away
+        let truncated = ChatDataset::new(&conversations, &ByteTokenizer, ChatFormat::Zephyr, 20);
+        assert_eq!(truncated.len(), 0);
+    }
+
+    #[test]
+    fn test_read_conversations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chat-dataset.jsonl");
+        std::fs::write(
+            &path,
+            "{\"messages\": [{\"role\": \"user\", \"content\": \"hi\"}]}\n{\"messages\": \"hi\"}\n",
+        )
+        .unwrap();
+
+        let conversations = read_conversations(&path, "messages");
+        std::fs::write(&path, "{\"messages\": []}\n").unwrap();
+        let empty = read_conversations(&path, "messages").unwrap();
+
+        assert!(conversations.is_err());
+        assert_eq!(empty, vec![Vec::<ChatMessage>::new()]);
+    }
+}
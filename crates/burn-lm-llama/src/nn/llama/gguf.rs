@@ -0,0 +1,406 @@
+use std::path::Path;
+
+use burn::{
+    module::{Module, Param},
+    tensor::{Device, Tensor},
+};
+
+use crate::{
+    gguf::{dequantize, tensor_data, GgufFile},
+    inference::Llama,
+    nn::pos_encoding::{yarn_mscale, RopeConfig, RopeFrequencyScaling, RopeScaling, YarnScaling},
+    tokenizer::{GgufTokenizer, Tokenizer},
+};
+
+use super::LlamaConfig;
+
+/// Supported GGUF model architectures.
+const GGUF_ARCHITECTURE: &str = "llama";
+
+impl LlamaConfig {
+    /// Create a Llama configuration from the metadata of a GGUF file.
+    pub fn from_gguf(file: &GgufFile, tokenizer_path: &str) -> Result<Self, String> {
+        let arch = file.architecture()?;
+        if arch != GGUF_ARCHITECTURE {
+            return Err(format!("Unsupported GGUF model architecture '{arch}'"));
+        }
+        let key = |name: &str| format!("{arch}.{name}");
+
+        let d_model = file.get_u64(&key("embedding_length"))? as usize;
+        let num_attention_heads = file.get_u64(&key("attention.head_count"))? as usize;
+        let num_key_value_heads = file
+            .get_u64(&key("attention.head_count_kv"))
+            .ok()
+            .map(|heads| heads as usize);
+        let vocab_size = match file.get_u64(&key("vocab_size")) {
+            Ok(size) => size as usize,
+            Err(_) => file
+                .get("tokenizer.ggml.tokens")
+                .and_then(|tokens| tokens.as_array())
+                .map(|tokens| tokens.len())
+                .ok_or("Missing GGUF vocabulary size")?,
+        };
+
+        let head_dim = d_model / num_attention_heads;
+        if let Ok(rope_dim) = file.get_u64(&key("rope.dimension_count")) {
+            if rope_dim as usize != head_dim {
+                return Err(format!(
+                    "Unsupported RoPE dimension {rope_dim}, expected the attention head dimension {head_dim}"
+                ));
+            }
+        }
+
+        let theta = file.get_f64(&key("rope.freq_base")).unwrap_or(10000.) as f32;
+        let rope = RopeConfig::new(theta);
+        let factor = || {
+            file.get_f64(&key("rope.scaling.factor"))
+                .map(|factor| factor as f32)
+        };
+        let rope = match file.get_str(&key("rope.scaling.type")) {
+            Ok("linear") => rope.with_scaling(Some(RopeScaling::Linear(factor()?))),
+            Ok("yarn") => {
+                let factor = factor()?;
+                let original_max_seq_len =
+                    file.get_u64(&key("rope.scaling.original_context_length"))? as usize;
+                // The attention factor multiplies the default YaRN attention temperature
+                let attention_factor = file
+                    .get_f64(&key("rope.scaling.attn_factor"))
+                    .ok()
+                    .map(|attn_factor| attn_factor as f32 * yarn_mscale(factor, 1.));
+                rope.with_scaling(Some(RopeScaling::Yarn(
+                    YarnScaling::new(factor, original_max_seq_len)
+                        .with_attention_factor(attention_factor),
+                )))
+            }
+            Ok("none") => rope.with_scaled(rope_freq_scaling(file)?),
+            Ok(kind) => return Err(format!("Unsupported RoPE scaling type '{kind}'")),
+            // Legacy linear scaling key
+            Err(_) => match file.get_f64(&key("rope.scale_linear")) {
+                Ok(factor) if factor != 1. => {
+                    rope.with_scaling(Some(RopeScaling::Linear(factor as f32)))
+                }
+                _ => rope.with_scaled(rope_freq_scaling(file)?),
+            },
+        };
+
+        let mut config = Self::new(
+            file.get_u64(&key("feed_forward_length"))? as usize,
+            vocab_size,
+            tokenizer_path.to_string(),
+        )
+        .with_d_model(d_model)
+        .with_num_hidden_layers(file.get_u64(&key("block_count"))? as usize)
+        .with_num_attention_heads(num_attention_heads)
+        .with_num_key_value_heads(num_key_value_heads)
+        .with_rope(rope)
+        .with_tie_word_embeddings(file.tensor("output.weight").is_none());
+
+        if let Ok(eps) = file.get_f64(&key("attention.layer_norm_rms_epsilon")) {
+            config = config.with_norm_eps(eps);
+        }
+
+        Ok(config)
+    }
+
+    /// Load a Llama model from a GGUF file, using the tokenizer embedded in the file.
+    pub fn load_gguf<P: AsRef<Path>>(
+        path: P,
+        max_seq_len: usize,
+        device: &Device,
+    ) -> Result<Llama<GgufTokenizer>, String> {
+        let path = path.as_ref();
+        let file = GgufFile::open(path)?;
+        let tokenizer_path = path
+            .to_str()
+            .ok_or_else(|| format!("Invalid GGUF file path '{}'", path.display()))?;
+
+        Self::from_gguf(&file, tokenizer_path)?
+            .with_max_seq_len(max_seq_len)
+            .load_gguf_weights(&file, device)
+    }
+
+    /// Load the weights of a GGUF file into a new Llama model.
+    ///
+    /// `Q4_0` and `Q8_0` weights are loaded as block-quantized tensors, other quantized types
+    /// are dequantized.
+    pub fn load_gguf_weights<T: Tokenizer>(
+        &self,
+        file: &GgufFile,
+        device: &Device,
+    ) -> Result<Llama<T>, String> {
+        let mut llama = self.init::<T>(device)?;
+
+        let d_model = self.d_model;
+        let n_kv_heads = self.num_key_value_heads.unwrap_or(self.num_attention_heads);
+        let kv_dim = d_model * n_kv_heads / self.num_attention_heads;
+        let hidden = self.hidden_size;
+
+        // GGML stores the linear weights as [d_output, d_input]
+        let linear = |name: String, d_input: usize, d_output: usize| {
+            load_tensor::<2>(file, &name, [d_output, d_input], device).map(|w| w.transpose())
+        };
+        let norm = |name: String| load_tensor::<1>(file, &name, [d_model], device);
+
+        let mut record = llama.model.clone().into_record();
+        set(
+            &mut record.tok_embeddings.weight,
+            load_tensor(
+                file,
+                "token_embd.weight",
+                [self.vocab_size, d_model],
+                device,
+            )?,
+        );
+
+        for (i, layer) in record.layers.iter_mut().enumerate() {
+            let name = |tensor: &str| format!("blk.{i}.{tensor}.weight");
+            let attention = &mut layer.attention;
+            set(
+                &mut attention.wq.weight,
+                linear(name("attn_q"), d_model, d_model)?,
+            );
+            set(
+                &mut attention.wk.weight,
+                linear(name("attn_k"), d_model, kv_dim)?,
+            );
+            set(
+                &mut attention.wv.weight,
+                linear(name("attn_v"), d_model, kv_dim)?,
+            );
+            set(
+                &mut attention.wo.weight,
+                linear(name("attn_output"), d_model, d_model)?,
+            );
+
+            let feed_forward = &mut layer.feed_forward;
+            set(
+                &mut feed_forward.swiglu.linear_inner.weight,
+                linear(name("ffn_gate"), d_model, hidden)?,
+            );
+            set(
+                &mut feed_forward.swiglu.linear_outer.weight,
+                linear(name("ffn_up"), d_model, hidden)?,
+            );
+            set(
+                &mut feed_forward.w2.weight,
+                linear(name("ffn_down"), hidden, d_model)?,
+            );
+
+            set(&mut layer.attention_norm.gamma, norm(name("attn_norm"))?);
+            set(&mut layer.ffn_norm.gamma, norm(name("ffn_norm"))?);
+        }
+
+        set(&mut record.norm.gamma, norm("output_norm.weight".into())?);
+        let output = if self.tie_word_embeddings {
+            record.tok_embeddings.weight.val().transpose()
+        } else {
+            linear("output.weight".into(), d_model, self.vocab_size)?
+        };
+        set(&mut record.output.weight, output);
+
+        llama.model = llama.model.load_record(record);
+
+        Ok(llama)
+    }
+}
+
+fn set<const D: usize>(param: &mut Param<Tensor<D>>, tensor: Tensor<D>) {
+    *param = Param::initialized(param.id, tensor);
+}
+
+fn load_tensor<const D: usize>(
+    file: &GgufFile,
+    name: &str,
+    shape: [usize; D],
+    device: &Device,
+) -> Result<Tensor<D>, String> {
+    let info = file
+        .tensor(name)
+        .ok_or_else(|| format!("Missing GGUF tensor '{name}'"))?;
+    if info.shape() != shape {
+        return Err(format!(
+            "Invalid shape {:?} for GGUF tensor '{name}', expected {shape:?}",
+            info.shape()
+        ));
+    }
+
+    let data = tensor_data(&file.tensor_data(info)?, info.dtype, info.shape())?;
+    Ok(Tensor::from_data(data, device))
+}
+
+/// Llama 3.1 frequency scaling, stored by llama.cpp as per-dimension factors in `rope_freqs.weight`.
+///
+/// The largest factor corresponds to the scale factor applied to the low frequencies, the other
+/// parameters are the same for all Llama 3.1 and 3.2 models.
+fn rope_freq_scaling(file: &GgufFile) -> Result<Option<RopeFrequencyScaling>, String> {
+    let Some(info) = file.tensor("rope_freqs.weight") else {
+        return Ok(None);
+    };
+
+    let factors = dequantize(&file.tensor_data(info)?, info.dtype)?;
+    let scale_factor = factors.into_iter().fold(1f32, f32::max);
+
+    Ok(Some(
+        RopeFrequencyScaling::new().with_scale_factor(scale_factor),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gguf::{
+        tests::{quantize_q8_0, GgufWriter},
+        GgmlType, GgufValue,
+    };
+    use burn::tensor::{TensorData, Tolerance};
+
+    const D_MODEL: usize = 32;
+    const HIDDEN: usize = 64;
+    const VOCAB: usize = 8;
+
+    fn values(len: usize, offset: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| ((i + offset) % 17) as f32 / 17. - 0.5)
+            .collect()
+    }
+
+    fn write_model(path: &Path, tied: bool) {
+        let tokens = ["<unk>", "<s>", "</s>", "▁", "a", "b", "▁a", "▁b"];
+        let mut writer = GgufWriter::default()
+            .metadata("general.architecture", GgufValue::String("llama".into()))
+            .metadata("llama.embedding_length", GgufValue::U32(D_MODEL as u32))
+            .metadata("llama.feed_forward_length", GgufValue::U32(HIDDEN as u32))
+            .metadata("llama.block_count", GgufValue::U32(1))
+            .metadata("llama.attention.head_count", GgufValue::U32(4))
+            .metadata("llama.attention.head_count_kv", GgufValue::U32(2))
+            .metadata(
+                "llama.attention.layer_norm_rms_epsilon",
+                GgufValue::F32(1e-6),
+            )
+            .metadata("llama.rope.freq_base", GgufValue::F32(500000.))
+            .metadata("tokenizer.ggml.model", GgufValue::String("llama".into()))
+            .metadata(
+                "tokenizer.ggml.tokens",
+                GgufValue::Array(
+                    tokens
+                        .iter()
+                        .map(|t| GgufValue::String(t.to_string()))
+                        .collect(),
+                ),
+            )
+            .metadata(
+                "tokenizer.ggml.scores",
+                GgufValue::Array((0..VOCAB).map(|i| GgufValue::F32(-(i as f32))).collect()),
+            )
+            .metadata("tokenizer.ggml.bos_token_id", GgufValue::U32(1))
+            .metadata("tokenizer.ggml.eos_token_id", GgufValue::U32(2))
+            .tensor_f32(
+                "token_embd.weight",
+                &[D_MODEL, VOCAB],
+                &values(D_MODEL * VOCAB, 0),
+            )
+            .tensor_f32("output_norm.weight", &[D_MODEL], &[1.; D_MODEL])
+            .tensor_f32("blk.0.attn_norm.weight", &[D_MODEL], &[1.; D_MODEL])
+            .tensor_f32("blk.0.ffn_norm.weight", &[D_MODEL], &[1.; D_MODEL])
+            .tensor(
+                "blk.0.attn_q.weight",
+                &[D_MODEL, D_MODEL],
+                GgmlType::Q8_0,
+                quantize_q8_0(&values(D_MODEL * D_MODEL, 1)),
+            )
+            .tensor_f32(
+                "blk.0.attn_k.weight",
+                &[D_MODEL, 16],
+                &values(D_MODEL * 16, 2),
+            )
+            .tensor_f32(
+                "blk.0.attn_v.weight",
+                &[D_MODEL, 16],
+                &values(D_MODEL * 16, 3),
+            )
+            .tensor_f32(
+                "blk.0.attn_output.weight",
+                &[D_MODEL, D_MODEL],
+                &values(D_MODEL * D_MODEL, 4),
+            )
+            .tensor_f32(
+                "blk.0.ffn_gate.weight",
+                &[D_MODEL, HIDDEN],
+                &values(D_MODEL * HIDDEN, 5),
+            )
+            .tensor_f32(
+                "blk.0.ffn_up.weight",
+                &[D_MODEL, HIDDEN],
+                &values(D_MODEL * HIDDEN, 6),
+            )
+            .tensor_f32(
+                "blk.0.ffn_down.weight",
+                &[HIDDEN, D_MODEL],
+                &values(D_MODEL * HIDDEN, 7),
+            );
+        if !tied {
+            writer = writer.tensor_f32(
+                "output.weight",
+                &[D_MODEL, VOCAB],
+                &values(D_MODEL * VOCAB, 8),
+            );
+        }
+        writer.write(path);
+    }
+
+    #[test]
+    fn test_llama_config_from_gguf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.gguf");
+        write_model(&path, false);
+
+        let file = GgufFile::open(&path).unwrap();
+        let config = LlamaConfig::from_gguf(&file, "tokenizer").unwrap();
+
+        assert_eq!(config.d_model, D_MODEL);
+        assert_eq!(config.hidden_size, HIDDEN);
+        assert_eq!(config.num_hidden_layers, 1);
+        assert_eq!(config.num_attention_heads, 4);
+        assert_eq!(config.num_key_value_heads, Some(2));
+        assert_eq!(config.vocab_size, VOCAB);
+        assert_eq!(config.rope.theta, 500000.);
+        assert!(config.rope.scaled.is_none());
+        assert!(!config.tie_word_embeddings);
+    }
+
+    #[test]
+    fn test_load_gguf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.gguf");
+        write_model(&path, true);
+        let device = Default::default();
+
+        let llama = LlamaConfig::load_gguf(&path, 16, &device).unwrap();
+        assert_eq!(llama.tokenizer.encode("a", true, false), vec![1, 6]);
+
+        let record = llama.model.into_record();
+        let embeddings = record.tok_embeddings.weight.val();
+        embeddings.to_data().assert_approx_eq::<f32>(
+            &TensorData::new(values(D_MODEL * VOCAB, 0), [VOCAB, D_MODEL]),
+            Tolerance::default(),
+        );
+        // Tied output weights
+        record
+            .output
+            .weight
+            .val()
+            .into_data()
+            .assert_approx_eq::<f32>(&embeddings.transpose().into_data(), Tolerance::default());
+
+        // Q8_0 weights are loaded as quantized tensors
+        let wq = record.layers[0].attention.wq.weight.val();
+        assert_eq!(wq.dims(), [D_MODEL, D_MODEL]);
+        wq.dequantize()
+            .transpose()
+            .into_data()
+            .assert_approx_eq::<f32>(
+                &TensorData::new(values(D_MODEL * D_MODEL, 1), [D_MODEL, D_MODEL]),
+                Tolerance::absolute(0.01),
+            );
+    }
+}
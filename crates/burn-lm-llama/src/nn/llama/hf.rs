@@ -0,0 +1,479 @@
+use std::path::Path;
+
+use burn::{module::Param, tensor::Tensor};
+use serde::Deserialize;
+
+use crate::nn::{
+    pos_encoding::{
+        yarn_mscale, DynamicNtkScaling, RopeConfig, RopeFrequencyScaling, RopeScaling, YarnScaling,
+    },
+    transformer::TransformerRecord,
+};
+
+use super::LlamaConfig;
+
+/// Key remapping from Hugging Face Llama parameter names to the [transformer](crate::nn::transformer::Transformer) module.
+pub(crate) const HF_KEY_REMAPS: [(&str, &str); 13] = [
+    // Map lm_head.* -> output.*
+    ("lm_head\\.(.+)", "output.$1"),
+    // Remove model. prefix
+    ("model\\.(.+)", "$1"),
+    // Map embed_tokens.* -> tok_embeddings.*
+    ("embed_tokens\\.(.+)", "tok_embeddings.$1"),
+    // Map layers.[i].input_layernorm.* -> layers.[i].attention_norm.*
+    (
+        "(layers\\.[0-9]+)\\.input_layernorm\\.(.+)",
+        "$1.attention_norm.$2",
+    ),
+    // Map layers.[i].post_attention_layernorm.* -> layers.[i].ffn_norm.*
+    (
+        "(layers\\.[0-9]+)\\.post_attention_layernorm\\.(.+)",
+        "$1.ffn_norm.$2",
+    ),
+    // Map layers.[i].mlp.down_proj.* -> layers.[i].feed_forward.w2.*
+    (
+        "(layers\\.[0-9]+)\\.mlp\\.down_proj\\.(.+)",
+        "$1.feed_forward.w2.$2",
+    ),
+    // Map layers.[i].mlp.gate_proj.* -> layers.[i].feed_forward.swiglu.linear_inner.*
+    (
+        "(layers\\.[0-9]+)\\.mlp\\.gate_proj\\.(.+)",
+        "$1.feed_forward.swiglu.linear_inner.$2",
+    ),
+    // Map layers.[i].mlp.up_proj.* -> layers.[i].feed_forward.swiglu.linear_outer.*
+    (
+        "(layers\\.[0-9]+)\\.mlp\\.up_proj\\.(.+)",
+        "$1.feed_forward.swiglu.linear_outer.$2",
+    ),
+    // Map layers.[i].self_attn.k_proj.* -> layers.[i].attention.wk.*
+    (
+        "(layers\\.[0-9]+)\\.self_attn\\.k_proj\\.(.+)",
+        "$1.attention.wk.$2",
+    ),
+    // Map layers.[i].self_attn.o_proj.* -> layers.[i].attention.wo.*
+    (
+        "(layers\\.[0-9]+)\\.self_attn\\.o_proj\\.(.+)",
+        "$1.attention.wo.$2",
+    ),
+    // Map layers.[i].self_attn.q_proj.* -> layers.[i].attention.wq.*
+    (
+        "(layers\\.[0-9]+)\\.self_attn\\.q_proj\\.(.+)",
+        "$1.attention.wq.$2",
+    ),
+    // Map layers.[i].self_attn.v_proj.* -> layers.[i].attention.wv.*
+    (
+        "(layers\\.[0-9]+)\\.self_attn\\.v_proj\\.(.+)",
+        "$1.attention.wv.$2",
+    ),
+    // Map norm.weight -> norm.gamma for all layers
+    ("(.*)norm\\.weight", "${1}norm.gamma"),
+];
+
+/// Hugging Face `config.json` of a Llama-architecture checkpoint.
+///
+/// Only the fields required to build a [LlamaConfig] are deserialized.
+#[derive(Deserialize, Debug, Clone)]
+pub struct HfLlamaConfig {
+    /// The size of the model.
+    pub hidden_size: usize,
+    /// The size of the feed-forward hidden inner features.
+    pub intermediate_size: usize,
+    /// The number of transformer blocks.
+    pub num_hidden_layers: usize,
+    /// The number of attention heads.
+    pub num_attention_heads: usize,
+    /// The number of key-value heads.
+    pub num_key_value_heads: Option<usize>,
+    /// The attention head dimension.
+    pub head_dim: Option<usize>,
+    /// The vocabulary size.
+    pub vocab_size: usize,
+    /// RMSNorm epsilon.
+    #[serde(default = "HfLlamaConfig::default_rms_norm_eps")]
+    pub rms_norm_eps: f64,
+    /// RoPE base frequency.
+    #[serde(default = "HfLlamaConfig::default_rope_theta")]
+    pub rope_theta: f32,
+    /// RoPE frequency scaling.
+    pub rope_scaling: Option<HfRopeScaling>,
+    /// The maximum sequence length, the pre-training context length of the RoPE scaling when
+    /// not given.
+    pub max_position_embeddings: Option<usize>,
+    /// Whether the output layer shares its weights with the token embeddings.
+    #[serde(default)]
+    pub tie_word_embeddings: bool,
+}
+
+/// Hugging Face `rope_scaling` entry of a `config.json` file.
+#[derive(Deserialize, Debug, Clone)]
+pub struct HfRopeScaling {
+    /// The RoPE scaling type.
+    pub rope_type: Option<String>,
+    /// Legacy name of the RoPE scaling type.
+    #[serde(rename = "type")]
+    pub legacy_type: Option<String>,
+    /// The scaling factor.
+    pub factor: Option<f32>,
+    /// Llama 3 low frequency factor.
+    pub low_freq_factor: Option<f32>,
+    /// Llama 3 high frequency factor.
+    pub high_freq_factor: Option<f32>,
+    /// The context length used during pre-training.
+    pub original_max_position_embeddings: Option<usize>,
+    /// YaRN attention temperature.
+    pub attention_factor: Option<f32>,
+    /// YaRN number of rotations of the extrapolated frequencies.
+    pub beta_fast: Option<f32>,
+    /// YaRN number of rotations of the interpolated frequencies.
+    pub beta_slow: Option<f32>,
+    /// YaRN attention temperature multiplier.
+    pub mscale: Option<f32>,
+    /// YaRN attention temperature multiplier of all the dimensions.
+    pub mscale_all_dim: Option<f32>,
+}
+
+impl HfLlamaConfig {
+    fn default_rms_norm_eps() -> f64 {
+        1e-6
+    }
+
+    fn default_rope_theta() -> f32 {
+        10000.0
+    }
+
+    /// Load a Hugging Face `config.json` file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|err| {
+            format!(
+                "Failed to read Hugging Face config '{}'.\nError: {err}",
+                path.display()
+            )
+        })?;
+
+        serde_json::from_str(&content).map_err(|err| {
+            format!(
+                "Failed to parse Hugging Face config '{}'.\nError: {err}",
+                path.display()
+            )
+        })
+    }
+
+    /// Convert the Hugging Face configuration into a [LlamaConfig].
+    pub fn into_llama_config(self, tokenizer_path: &str) -> Result<LlamaConfig, String> {
+        let default_head_dim = self.hidden_size / self.num_attention_heads;
+        if let Some(head_dim) = self.head_dim {
+            if head_dim != default_head_dim {
+                return Err(format!(
+                    "Unsupported attention head dimension {head_dim}, expected hidden_size / num_attention_heads = {default_head_dim}"
+                ));
+            }
+        }
+
+        let rope = match self.rope_scaling {
+            Some(scaling) => {
+                scaling.into_rope_config(self.rope_theta, self.max_position_embeddings)?
+            }
+            None => RopeConfig::new(self.rope_theta),
+        };
+
+        Ok(LlamaConfig::new(
+            self.intermediate_size,
+            self.vocab_size,
+            tokenizer_path.to_string(),
+        )
+        .with_d_model(self.hidden_size)
+        .with_num_hidden_layers(self.num_hidden_layers)
+        .with_num_attention_heads(self.num_attention_heads)
+        .with_num_key_value_heads(self.num_key_value_heads)
+        .with_norm_eps(self.rms_norm_eps)
+        .with_rope(rope)
+        .with_tie_word_embeddings(self.tie_word_embeddings))
+    }
+}
+
+impl HfRopeScaling {
+    /// The RoPE scaling type.
+    pub fn kind(&self) -> &str {
+        self.rope_type
+            .as_deref()
+            .or(self.legacy_type.as_deref())
+            .unwrap_or("default")
+    }
+
+    fn into_rope_config(
+        self,
+        theta: f32,
+        max_position_embeddings: Option<usize>,
+    ) -> Result<RopeConfig, String> {
+        let rope = RopeConfig::new(theta);
+        let kind = self.kind().to_string();
+        let factor = || {
+            self.factor
+                .ok_or_else(|| format!("Missing factor of the '{kind}' RoPE scaling"))
+        };
+        let original_max_seq_len = || {
+            self.original_max_position_embeddings
+                .or(max_position_embeddings)
+                .ok_or_else(|| format!("Missing context length of the '{kind}' RoPE scaling"))
+        };
+        match self.kind() {
+            "default" => Ok(rope),
+            "llama3" => {
+                let mut scaling = RopeFrequencyScaling::new();
+                if let Some(factor) = self.factor {
+                    scaling = scaling.with_scale_factor(factor);
+                }
+                if let Some(factor) = self.low_freq_factor {
+                    scaling = scaling.with_low_freq_factor(factor);
+                }
+                if let Some(factor) = self.high_freq_factor {
+                    scaling = scaling.with_high_freq_factor(factor);
+                }
+                if let Some(len) = self.original_max_position_embeddings {
+                    scaling = scaling.with_old_context_len(len as f32);
+                }
+                Ok(rope.with_scaled(Some(scaling)))
+            }
+            "linear" => Ok(rope.with_scaling(Some(RopeScaling::Linear(factor()?)))),
+            "dynamic" => Ok(rope.with_scaling(Some(RopeScaling::DynamicNtk(
+                DynamicNtkScaling::new(factor()?, original_max_seq_len()?),
+            )))),
+            "yarn" => {
+                let factor = factor()?;
+                let mut scaling = YarnScaling::new(factor, original_max_seq_len()?);
+                if let Some(beta) = self.beta_fast {
+                    scaling = scaling.with_beta_fast(beta);
+                }
+                if let Some(beta) = self.beta_slow {
+                    scaling = scaling.with_beta_slow(beta);
+                }
+                let attention_factor =
+                    match (self.attention_factor, self.mscale, self.mscale_all_dim) {
+                        (Some(attention_factor), ..) => Some(attention_factor),
+                        (None, Some(mscale), Some(mscale_all_dim)) => {
+                            Some(yarn_mscale(factor, mscale) / yarn_mscale(factor, mscale_all_dim))
+                        }
+                        _ => None,
+                    };
+                Ok(rope.with_scaling(Some(RopeScaling::Yarn(
+                    scaling.with_attention_factor(attention_factor),
+                ))))
+            }
+            kind => Err(format!("Unsupported RoPE scaling type '{kind}'")),
+        }
+    }
+}
+
+impl LlamaConfig {
+    /// Create a Llama configuration from a Hugging Face `config.json` file.
+    pub fn from_hf_config<P: AsRef<Path>>(
+        config_path: P,
+        tokenizer_path: &str,
+    ) -> Result<Self, String> {
+        HfLlamaConfig::load(config_path)?.into_llama_config(tokenizer_path)
+    }
+}
+
+/// Permute the query and key projection weights of a Hugging Face checkpoint.
+///
+/// Llama weights from Hugging Face use a different rotary positional encoding layout
+/// which requires weight permutation:
+/// https://github.com/huggingface/transformers/issues/25199#issuecomment-1687720247
+/// https://github.com/jzhang38/TinyLlama/issues/24
+pub(crate) fn permute_rotary_weights(
+    mut record: TransformerRecord,
+    config: &LlamaConfig,
+) -> TransformerRecord {
+    let n_heads = config.num_attention_heads;
+    let n_kv_heads = config.num_key_value_heads.unwrap_or(n_heads);
+
+    record.layers = record
+        .layers
+        .into_iter()
+        .map(|mut layer| {
+            layer.attention.wq.weight = layer
+                .attention
+                .wq
+                .weight
+                .map(|w| permute_rotary(w, n_heads));
+            layer.attention.wk.weight = layer
+                .attention
+                .wk
+                .weight
+                .map(|w| permute_rotary(w, n_kv_heads));
+            layer
+        })
+        .collect::<Vec<_>>();
+
+    record
+}
+
+/// Permute the output features of a query or key projection weight from the Hugging Face
+/// rotary layout, see [permute_rotary_weights].
+pub(crate) fn permute_rotary(w: Tensor<2>, n_heads: usize) -> Tensor<2> {
+    let [dim1, dim2] = w.dims();
+    w // [2048, 256]
+        .reshape([dim1, n_heads, 2, dim2 / n_heads / 2]) // [2048, 4, 2, 32]
+        .swap_dims(2, 3) // [2048, 4, 32, 2]
+        .reshape([dim1, dim2])
+}
+
+/// Tie the output layer weights to the token embeddings.
+pub(crate) fn tie_word_embeddings(mut record: TransformerRecord) -> TransformerRecord {
+    // Embedding weights are [vocab_size, d_model] while linear weights are [d_in, d_out]
+    let weight = record.tok_embeddings.weight.val().transpose();
+    record.output.weight = Param::from_tensor(weight);
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LLAMA_3_2_1B_CONFIG: &str = r#"{
+        "architectures": ["LlamaForCausalLM"],
+        "bos_token_id": 128000,
+        "eos_token_id": 128001,
+        "head_dim": 64,
+        "hidden_size": 2048,
+        "intermediate_size": 8192,
+        "max_position_embeddings": 131072,
+        "model_type": "llama",
+        "num_attention_heads": 32,
+        "num_hidden_layers": 16,
+        "num_key_value_heads": 8,
+        "rms_norm_eps": 1e-05,
+        "rope_scaling": {
+            "factor": 32.0,
+            "high_freq_factor": 4.0,
+            "low_freq_factor": 1.0,
+            "original_max_position_embeddings": 8192,
+            "rope_type": "llama3"
+        },
+        "rope_theta": 500000.0,
+        "tie_word_embeddings": true,
+        "torch_dtype": "bfloat16",
+        "vocab_size": 128256
+    }"#;
+
+    #[test]
+    fn test_from_hf_config_llama3() {
+        let hf_config: HfLlamaConfig = serde_json::from_str(LLAMA_3_2_1B_CONFIG).unwrap();
+        let config = hf_config.into_llama_config("tokenizer.model").unwrap();
+        let expected = LlamaConfig::llama3_2_1b("tokenizer.model");
+
+        assert_eq!(config.d_model, expected.d_model);
+        assert_eq!(config.hidden_size, expected.hidden_size);
+        assert_eq!(config.num_hidden_layers, expected.num_hidden_layers);
+        assert_eq!(config.num_attention_heads, expected.num_attention_heads);
+        assert_eq!(config.num_key_value_heads, expected.num_key_value_heads);
+        assert_eq!(config.vocab_size, expected.vocab_size);
+        assert_eq!(config.rope.theta, expected.rope.theta);
+        assert!(config.tie_word_embeddings);
+
+        let scaling = config.rope.scaled.unwrap();
+        let expected = expected.rope.scaled.unwrap();
+        assert_eq!(scaling.scale_factor, expected.scale_factor);
+        assert_eq!(scaling.low_freq_factor, expected.low_freq_factor);
+        assert_eq!(scaling.high_freq_factor, expected.high_freq_factor);
+        assert_eq!(scaling.old_context_len, expected.old_context_len);
+    }
+
+    #[test]
+    fn test_from_hf_config_defaults() {
+        let hf_config: HfLlamaConfig = serde_json::from_str(
+            r#"{
+                "hidden_size": 2048,
+                "intermediate_size": 5632,
+                "num_attention_heads": 32,
+                "num_hidden_layers": 22,
+                "num_key_value_heads": 4,
+                "rope_scaling": null,
+                "vocab_size": 32000
+            }"#,
+        )
+        .unwrap();
+        let config = hf_config.into_llama_config("tokenizer.json").unwrap();
+
+        assert_eq!(config.rope.theta, 10000.0);
+        assert!(config.rope.scaled.is_none());
+        assert_eq!(config.norm_eps, 1e-6);
+        assert!(!config.tie_word_embeddings);
+    }
+
+    #[test]
+    fn test_from_hf_config_rope_scaling() {
+        let config = |rope_scaling: &str| {
+            let hf_config: HfLlamaConfig = serde_json::from_str(&format!(
+                r#"{{
+                    "hidden_size": 64,
+                    "intermediate_size": 128,
+                    "max_position_embeddings": 4096,
+                    "num_attention_heads": 4,
+                    "num_hidden_layers": 2,
+                    "vocab_size": 256,
+                    "rope_scaling": {rope_scaling}
+                }}"#
+            ))
+            .unwrap();
+            hf_config.into_llama_config("tokenizer.json")
+        };
+
+        let rope = config(r#"{ "type": "linear", "factor": 2.0 }"#)
+            .unwrap()
+            .rope;
+        assert!(matches!(rope.scaling, Some(RopeScaling::Linear(factor)) if factor == 2.));
+
+        let rope = config(r#"{ "rope_type": "dynamic", "factor": 4.0 }"#)
+            .unwrap()
+            .rope;
+        match rope.scaling {
+            Some(RopeScaling::DynamicNtk(scaling)) => {
+                assert_eq!(scaling.factor, 4.);
+                assert_eq!(scaling.original_max_seq_len, 4096);
+            }
+            scaling => panic!("Expected dynamic NTK scaling, got {scaling:?}"),
+        }
+
+        let rope = config(
+            r#"{
+                "rope_type": "yarn",
+                "factor": 4.0,
+                "original_max_position_embeddings": 2048,
+                "beta_fast": 16.0
+            }"#,
+        )
+        .unwrap()
+        .rope;
+        match rope.scaling {
+            Some(RopeScaling::Yarn(scaling)) => {
+                assert_eq!(scaling.factor, 4.);
+                assert_eq!(scaling.original_max_seq_len, 2048);
+                assert_eq!(scaling.beta_fast, 16.);
+                assert_eq!(scaling.beta_slow, 1.);
+                assert_eq!(scaling.attention_factor(), 0.1 * 4f32.ln() + 1.);
+            }
+            scaling => panic!("Expected YaRN scaling, got {scaling:?}"),
+        }
+
+        assert!(config(r#"{ "rope_type": "yarn" }"#).is_err());
+    }
+
+    #[test]
+    fn test_from_hf_config_unsupported() {
+        let hf_config: HfLlamaConfig = serde_json::from_str(
+            r#"{
+                "hidden_size": 64,
+                "intermediate_size": 128,
+                "num_attention_heads": 4,
+                "num_hidden_layers": 2,
+                "vocab_size": 256,
+                "rope_scaling": { "type": "unknown", "factor": 2.0 }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(hf_config.into_llama_config("tokenizer.json").is_err());
+    }
+}
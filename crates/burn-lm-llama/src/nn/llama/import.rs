@@ -3,7 +3,10 @@ use burn_import::pytorch::{LoadArgs, PyTorchFileRecorder};
 
 use crate::{nn::transformer::TransformerRecord, tokenizer::Tokenizer};
 
-use super::{Llama, LlamaConfig};
+use super::{
+    hf::{permute_rotary_weights, HF_KEY_REMAPS},
+    Llama, LlamaConfig,
+};
 
 impl LlamaConfig {
     /// Load pre-trained Llama checkpoint.
@@ -32,60 +35,9 @@ impl LlamaConfig {
                 // Map norm.weight -> norm.gamma for all layers
                 .with_key_remap("(.*)norm\\.weight", "${1}norm.gamma");
         } else {
-            load_args = load_args
-                // Map lm_head.* -> output.*
-                .with_key_remap("lm_head\\.(.+)", "output.$1")
-                // Remove model. prefix
-                .with_key_remap("model\\.(.+)", "$1")
-                // Map embed_tokens.* -> tok_embeddings.*
-                .with_key_remap("embed_tokens\\.(.+)", "tok_embeddings.$1")
-                // Map layers.[i].input_layernorm.* -> layers.[i].attention_norm.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.input_layernorm\\.(.+)",
-                    "$1.attention_norm.$2",
-                )
-                // Map layers.[i].post_attention_layernorm.* -> layers.[i].ffn_norm.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.post_attention_layernorm\\.(.+)",
-                    "$1.ffn_norm.$2",
-                )
-                // Map layers.[i].mlp.down_proj.* -> layers.[i].feed_forward.w2.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.mlp\\.down_proj\\.(.+)",
-                    "$1.feed_forward.w2.$2",
-                )
-                // Map layers.[i].mlp.gate_proj.* -> layers.[i].feed_forward.swiglu.linear_inner.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.mlp\\.gate_proj\\.(.+)",
-                    "$1.feed_forward.swiglu.linear_inner.$2",
-                )
-                // Map layers.[i].mlp.up_proj.* -> layers.[i].feed_forward.swiglu.linear_outer.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.mlp\\.up_proj\\.(.+)",
-                    "$1.feed_forward.swiglu.linear_outer.$2",
-                )
-                // Map layers.[i].self_attn.k_proj.* -> layers.[i].attention.wk.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.self_attn\\.k_proj\\.(.+)",
-                    "$1.attention.wk.$2",
-                )
-                // Map layers.[i].self_attn.o_proj.* -> layers.[i].attention.wo.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.self_attn\\.o_proj\\.(.+)",
-                    "$1.attention.wo.$2",
-                )
-                // Map layers.[i].self_attn.q_proj.* -> layers.[i].attention.wq.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.self_attn\\.q_proj\\.(.+)",
-                    "$1.attention.wq.$2",
-                )
-                // Map layers.[i].self_attn.v_proj.* -> layers.[i].attention.wv.*
-                .with_key_remap(
-                    "(layers\\.[0-9]+)\\.self_attn\\.v_proj\\.(.+)",
-                    "$1.attention.wv.$2",
-                )
-                // Map norm.weight -> norm.gamma for all layers
-                .with_key_remap("(.*)norm\\.weight", "${1}norm.gamma");
+            for (from, to) in HF_KEY_REMAPS {
+                load_args = load_args.with_key_remap(from, to);
+            }
         }
         let mut record: TransformerRecord = PyTorchFileRecorder::<HalfPrecisionSettings>::new()
             .load(load_args, device)
@@ -93,37 +45,7 @@ impl LlamaConfig {
 
         if cfg!(feature = "tiny") {
             // TinyLlama weights from HuggingFace use a different rotary positional encoding
-            // which requires weight permutation:
-            // https://github.com/huggingface/transformers/issues/25199#issuecomment-1687720247
-            // https://github.com/jzhang38/TinyLlama/issues/24
-            let n_heads = self.num_attention_heads;
-            let n_kv_heads = self.num_key_value_heads.unwrap_or(n_heads);
-            let wk_dim = self.d_model * n_kv_heads / n_heads;
-            let permute = |w: Tensor<2>, n_heads: usize, dim1: usize, dim2: usize| {
-                let w = w // [2048, 256]
-                    .reshape([dim1, n_heads, 2, dim2 / n_heads / 2]) // [2048, 4, 2, 32]
-                    .swap_dims(2, 3) // [2048, 4, 32, 2]
-                    .reshape([dim1, dim2]);
-                w
-            };
-
-            record.layers = record
-                .layers
-                .into_iter()
-                .map(|mut layer| {
-                    layer.attention.wq.weight = layer
-                        .attention
-                        .wq
-                        .weight
-                        .map(|w| permute(w, n_heads, self.d_model, self.d_model));
-                    layer.attention.wk.weight = layer
-                        .attention
-                        .wk
-                        .weight
-                        .map(|w| permute(w, n_kv_heads, self.d_model, wk_dim));
-                    layer
-                })
-                .collect::<Vec<_>>();
+            record = permute_rotary_weights(record, self);
         }
 
         llama.model = llama.model.load_record(record);
@@ -6,10 +6,11 @@ use burn::{
         Device, Int, Shape, Tensor, TensorData,
     },
 };
-use std::time::Instant;
+use std::{path::Path, time::Instant};
 
 use crate::{
     nn::{
+        lora::LoraAdapter,
         pos_encoding::PositionalEncodingState,
         transformer::{Transformer, TransformerCache},
     },
@@ -66,6 +67,33 @@ impl<T: Tokenizer> Llama<T> {
         self.pos_encoding.reset();
     }
 
+    /// Load a LoRA adapter directory for the model.
+    ///
+    /// The directory either holds a PEFT adapter, which requires the `safetensors` feature,
+    /// or an adapter [saved](LoraAdapter::save) by a LoRA training run.
+    pub fn load_adapter<P: AsRef<Path>>(&self, adapter_dir: P) -> Result<LoraAdapter, String> {
+        let adapter_dir = adapter_dir.as_ref();
+        #[cfg(feature = "safetensors")]
+        if adapter_dir.join(super::peft::PEFT_WEIGHTS_FILE).is_file() {
+            return super::peft::load_peft_adapter(&self.model, adapter_dir, &self.device);
+        }
+
+        LoraAdapter::load(&self.model, adapter_dir, &self.device)
+    }
+
+    /// Set the low-rank adapter applied unmerged to the model, or remove it with `None`.
+    pub fn set_adapter(&mut self, adapter: Option<LoraAdapter>) {
+        self.model = self.model.clone().with_adapter(adapter);
+    }
+
+    /// Merge the low-rank adapter into the model weights, which must not be quantized.
+    ///
+    /// The base weights are modified, the model must be reloaded to remove the adapter.
+    pub fn merge_adapter(&mut self, adapter: &LoraAdapter) -> Result<(), String> {
+        self.model = self.model.clone().merge_adapter(adapter)?;
+        Ok(())
+    }
+
     /// Quantize the model weights.
     pub fn quantize(mut self, scheme: QuantScheme) -> Self {
         let calibration = Calibration::MinMax;
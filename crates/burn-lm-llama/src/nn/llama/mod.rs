@@ -1,11 +1,31 @@
 mod base;
 pub use base::*;
 
+pub mod hf;
+
 #[cfg(feature = "pretrained")]
 pub mod pretrained;
 
 #[cfg(feature = "import")]
 pub mod import;
 
+#[cfg(feature = "safetensors")]
+pub mod safetensors;
+
+#[cfg(feature = "safetensors")]
+pub mod peft;
+
+#[cfg(feature = "safetensors")]
+pub mod streaming;
+
+#[cfg(feature = "gguf")]
+pub mod gguf;
+
 pub mod inference;
 pub mod training;
+
+#[cfg(feature = "training")]
+pub mod dataset;
+
+#[cfg(feature = "training")]
+pub mod train;
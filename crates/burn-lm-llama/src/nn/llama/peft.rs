@@ -0,0 +1,218 @@
+use std::path::Path;
+
+use burn::tensor::Device;
+use burn_store::{ModuleSnapshot, PyTorchToBurnAdapter, SafetensorsStore};
+use serde::Deserialize;
+
+use crate::nn::{
+    lora::{LoraAdapter, LoraConfig, LoraLinear, LoraTarget},
+    transformer::Transformer,
+};
+
+use super::hf::{permute_rotary, HF_KEY_REMAPS};
+
+/// Configuration file of a PEFT adapter.
+pub const PEFT_CONFIG_FILE: &str = "adapter_config.json";
+/// Weights file of a PEFT adapter.
+pub const PEFT_WEIGHTS_FILE: &str = "adapter_model.safetensors";
+
+/// Map PEFT adapter keys to the [LoRA adapter](LoraAdapter) module, applied after removing
+/// the PEFT `base_model.model.` prefix and the Hugging Face Llama key remapping.
+const PEFT_KEY_REMAPS: [(&str, &str); 3] = [
+    // Map layers.[i].feed_forward.swiglu.* -> layers.[i].feed_forward.*
+    (
+        "(layers\\.[0-9]+)\\.feed_forward\\.swiglu\\.(.+)",
+        "$1.feed_forward.$2",
+    ),
+    // Map *.lora_A[.default].weight -> *.a.weight
+    ("(.+)\\.lora_A(\\.default)?\\.weight", "$1.a.weight"),
+    // Map *.lora_B[.default].weight -> *.b.weight
+    ("(.+)\\.lora_B(\\.default)?\\.weight", "$1.b.weight"),
+];
+
+/// Target modules of a PEFT adapter, either a list of module names or a pattern.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PeftTargetModules {
+    Modules(Vec<String>),
+    Pattern(String),
+}
+
+/// PEFT LoRA adapter configuration (`adapter_config.json`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct PeftAdapterConfig {
+    /// The PEFT method, only `LORA` is supported.
+    #[serde(default)]
+    pub peft_type: Option<String>,
+    /// The rank of the low-rank update.
+    pub r: usize,
+    /// The scaling numerator of the low-rank update.
+    pub lora_alpha: f64,
+    /// Whether the update is scaled with rsLoRA.
+    #[serde(default)]
+    pub use_rslora: bool,
+    /// Whether the adapter uses weight-decomposed LoRA (DoRA), which isn't supported.
+    #[serde(default)]
+    pub use_dora: bool,
+    /// The adapted modules.
+    pub target_modules: PeftTargetModules,
+}
+
+impl PeftAdapterConfig {
+    /// Load a PEFT `adapter_config.json` file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|err| {
+            format!(
+                "Failed to read adapter config '{}'.\nError: {err}",
+                path.display()
+            )
+        })?;
+
+        serde_json::from_str(&content).map_err(|err| {
+            format!(
+                "Failed to parse adapter config '{}'.\nError: {err}",
+                path.display()
+            )
+        })
+    }
+
+    /// Convert the PEFT configuration into a [LoraConfig].
+    ///
+    /// Target module patterns (e.g. `all-linear`) adapt all the supported projections, the
+    /// updates missing from the checkpoint are left to zero.
+    pub fn into_lora_config(self) -> Result<LoraConfig, String> {
+        if let Some(peft_type) = self.peft_type.as_deref() {
+            if !peft_type.eq_ignore_ascii_case("lora") {
+                return Err(format!("Unsupported PEFT adapter type '{peft_type}'"));
+            }
+        }
+        if self.use_dora {
+            return Err("Unsupported DoRA adapter".to_string());
+        }
+
+        let targets = match self.target_modules {
+            PeftTargetModules::Modules(modules) => modules
+                .iter()
+                .map(|module| {
+                    LoraTarget::from_hf_module(module)
+                        .ok_or_else(|| format!("Unsupported adapter target module '{module}'"))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            PeftTargetModules::Pattern(_) => LoraTarget::all(),
+        };
+
+        Ok(LoraConfig::new()
+            .with_rank(self.r)
+            .with_alpha(self.lora_alpha)
+            .with_rank_stabilized(self.use_rslora)
+            .with_targets(targets))
+    }
+}
+
+/// Load a PEFT LoRA adapter directory for the given transformer.
+///
+/// The directory must contain the `adapter_config.json` file and the
+/// `adapter_model.safetensors` weights.
+pub fn load_peft_adapter<P: AsRef<Path>>(
+    model: &Transformer,
+    adapter_dir: P,
+    device: &Device,
+) -> Result<LoraAdapter, String> {
+    let adapter_dir = adapter_dir.as_ref();
+    let config = PeftAdapterConfig::load(adapter_dir.join(PEFT_CONFIG_FILE))?;
+    let partial = matches!(config.target_modules, PeftTargetModules::Pattern(_));
+    let mut adapter = model.init_lora(&config.into_lora_config()?, device);
+
+    let file = adapter_dir.join(PEFT_WEIGHTS_FILE);
+    let mut store = SafetensorsStore::from_file(&file)
+        .with_from_adapter(PyTorchToBurnAdapter)
+        .allow_partial(partial)
+        .with_key_remapping("^base_model\\.model\\.(.+)", "$1");
+    for (from, to) in HF_KEY_REMAPS.into_iter().chain(PEFT_KEY_REMAPS) {
+        store = store.with_key_remapping(from, to);
+    }
+
+    let result = adapter.load_from(&mut store).map_err(|err| {
+        format!(
+            "Failed to load adapter weights '{}'.\nError: {err}",
+            file.display()
+        )
+    })?;
+    if !result.errors.is_empty() || !result.unused.is_empty() || result.applied.is_empty() {
+        return Err(format!(
+            "Failed to load adapter weights '{}'.\nError: {result}",
+            file.display()
+        ));
+    }
+
+    // The query and key updates need the same rotary permutation as the base weights
+    let (n_heads, n_kv_heads) = model
+        .layers
+        .first()
+        .map(|layer| layer.num_heads())
+        .ok_or_else(|| "Cannot load an adapter for a model without layers".to_string())?;
+    for layer in adapter.layers.iter_mut() {
+        permute_lora_rotary(&mut layer.attention.wq, n_heads);
+        permute_lora_rotary(&mut layer.attention.wk, n_kv_heads);
+    }
+
+    Ok(adapter)
+}
+
+fn permute_lora_rotary(lora: &mut Option<LoraLinear>, n_heads: usize) {
+    if let Some(mut linear) = lora.take() {
+        linear.b.weight = linear.b.weight.map(|w| permute_rotary(w, n_heads));
+        *lora = Some(linear);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peft_config_modules() {
+        let config: PeftAdapterConfig = serde_json::from_str(
+            r#"{
+                "peft_type": "LORA",
+                "r": 16,
+                "lora_alpha": 32,
+                "lora_dropout": 0.05,
+                "target_modules": ["q_proj", "v_proj", "down_proj"],
+                "task_type": "CAUSAL_LM"
+            }"#,
+        )
+        .unwrap();
+        let config = config.into_lora_config().unwrap();
+
+        assert_eq!(config.rank, 16);
+        assert_eq!(config.scaling(), 2.0);
+        assert_eq!(
+            config.targets,
+            vec![LoraTarget::Wq, LoraTarget::Wv, LoraTarget::W2]
+        );
+    }
+
+    #[test]
+    fn test_peft_config_pattern() {
+        let config: PeftAdapterConfig = serde_json::from_str(
+            r#"{ "r": 4, "lora_alpha": 8, "use_rslora": true, "target_modules": "all-linear" }"#,
+        )
+        .unwrap();
+        let config = config.into_lora_config().unwrap();
+
+        assert_eq!(config.scaling(), 4.0);
+        assert_eq!(config.targets, LoraTarget::all());
+    }
+
+    #[test]
+    fn test_peft_config_unsupported() {
+        let config: PeftAdapterConfig = serde_json::from_str(
+            r#"{ "r": 4, "lora_alpha": 8, "target_modules": ["q_proj", "lm_head"] }"#,
+        )
+        .unwrap();
+
+        assert!(config.into_lora_config().is_err());
+    }
+}
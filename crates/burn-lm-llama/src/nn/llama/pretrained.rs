@@ -1,6 +1,7 @@
 use burn::prelude::*;
 
 use super::{inference::Llama, LlamaConfig, LlamaVersion, TinyLlamaVersion};
+use crate::tokenizer::Tokenizer;
 
 #[cfg(feature = "llama3")]
 use crate::tokenizer::Tiktoken;
@@ -15,20 +16,37 @@ pub struct Pretrained {
     pub(super) tokenizer: &'static str,
 }
 
+/// Local cache directory of the Llama models.
+///
+/// Defaults to `~/.cache/llama` and can be overridden with the `BURNLM_CACHE_DIR` environment
+/// variable.
+pub fn cache_dir() -> std::path::PathBuf {
+    burn_lm_inference::download::cache_dir_override().unwrap_or_else(|| {
+        dirs::home_dir()
+            .expect("Should be able to get home directory")
+            .join(".cache")
+            .join("llama")
+    })
+}
+
 mod downloader {
     use super::*;
-    use burn::data::network::downloader;
-    use std::fs::{create_dir_all, File};
-    use std::io::Write;
+    use burn_lm_inference::download::{Downloader, Manifest};
     use std::path::PathBuf;
 
     impl Pretrained {
         fn model_dir(&self) -> PathBuf {
-            dirs::home_dir()
-                .expect("Should be able to get home directory")
-                .join(".cache")
-                .join("llama")
-                .join(self.name)
+            cache_dir().join(self.name)
+        }
+
+        /// Local cache directory of a model variant derived from the pre-trained model.
+        pub fn variant_dir(&self, variant: &str) -> PathBuf {
+            cache_dir().join(format!("{}-{variant}", self.name))
+        }
+
+        /// Local directory of the LoRA adapters of the pre-trained model.
+        pub fn adapters_dir(&self) -> PathBuf {
+            self.model_dir().join("adapters")
         }
 
         fn model_file_name(&self, url: &str) -> String {
@@ -38,33 +56,45 @@ mod downloader {
                 .replace("?download=true", "")
         }
 
-        pub fn is_downloaded(&self) -> bool {
-            let model_name = self.model_dir().join(self.model_file_name(self.model));
-            let tokenizer_name = self.model_dir().join(self.model_file_name(self.tokenizer));
-            model_name.exists() && tokenizer_name.exists()
+        /// File name of the weights converted to safetensors with
+        /// [convert_weights](Pretrained::convert_weights).
+        fn converted_weights_file_name(&self) -> String {
+            PathBuf::from(self.model_file_name(self.model))
+                .with_extension("safetensors")
+                .to_string_lossy()
+                .into_owned()
         }
 
-        /// Download the file to the local cache directory.
-        fn download(&self, url: &str) -> Result<PathBuf, std::io::Error> {
-            // Model cache directory
+        /// The path of the converted weights, if the weights have been converted.
+        fn converted_weights(&self) -> Option<PathBuf> {
             let model_dir = self.model_dir();
+            let file_name = self.converted_weights_file_name();
+            Manifest::load(&model_dir)
+                .is_ok_and(|manifest| manifest.contains(&model_dir, &file_name))
+                .then(|| model_dir.join(file_name))
+        }
 
-            if !model_dir.exists() {
-                create_dir_all(&model_dir)?;
-            }
-
-            let file_base_name = self.model_file_name(url);
-            let file_name = model_dir.join(&file_base_name);
-            if !file_name.exists() {
-                // Download file content
-                let bytes = downloader::download_file_as_bytes(url, &file_base_name);
-
-                // Write content to file
-                let mut output_file = File::create(&file_name)?;
-                output_file.write_all(&bytes)?; // write_all is not OS limited (files over 2GB)
-            }
+        /// Return true if the weights and tokenizer downloads completed.
+        pub fn is_downloaded(&self) -> bool {
+            let model_dir = self.model_dir();
+            let Ok(manifest) = Manifest::load(&model_dir) else {
+                return false;
+            };
+            let weights = [
+                self.model_file_name(self.model),
+                self.converted_weights_file_name(),
+            ];
+            weights
+                .iter()
+                .any(|file_name| manifest.contains(&model_dir, file_name))
+                && manifest.contains(&model_dir, &self.model_file_name(self.tokenizer))
+        }
 
-            Ok(file_name)
+        /// Download the file to the local cache directory.
+        fn download(&self, url: &str) -> Result<PathBuf, std::io::Error> {
+            let path =
+                Downloader::new().download(url, self.model_dir(), &self.model_file_name(url))?;
+            Ok(path)
         }
 
         /// Delete the file to the local cache directory.
@@ -73,18 +103,51 @@ mod downloader {
             if !model_dir.exists() {
                 return Ok(());
             }
-            let file_base_name = self.model_file_name(url);
-            let file_name = model_dir.join(&file_base_name);
-            if file_name.exists() {
-                std::fs::remove_file(file_name)
-                    .unwrap_or_else(|_| panic!("should delete model file '{file_base_name}'"));
-            }
+            Manifest::remove(model_dir, &self.model_file_name(url))?;
             Ok(())
         }
 
         /// Download the pre-trained model weights to the local cache directory.
+        ///
+        /// Return the converted weights instead when they have been converted with
+        /// [convert_weights](Pretrained::convert_weights).
         pub fn download_weights(&self) -> Result<PathBuf, std::io::Error> {
-            self.download(self.model)
+            match self.converted_weights() {
+                Some(weights) => Ok(weights),
+                None => self.download(self.model),
+            }
+        }
+
+        /// Convert the downloaded MessagePack weights to a safetensors file which replaces them
+        /// in the local cache directory, and return its path.
+        ///
+        /// The MessagePack record is read into memory all at once by `load`, this happens only
+        /// once at download time. The safetensors weights are then memory-mapped and loaded
+        /// layer by layer, see [load_streaming](Llama::load_streaming).
+        #[cfg(feature = "safetensors")]
+        pub fn convert_weights<T: Tokenizer>(
+            &self,
+            load: impl FnOnce(&str) -> Result<Llama<T>, String>,
+        ) -> Result<PathBuf, String> {
+            if let Some(weights) = self.converted_weights() {
+                return Ok(weights);
+            }
+            let error = |err: String| format!("Could not convert weights.\nError: {err}");
+            let checkpoint = self
+                .download(self.model)
+                .map_err(|err| error(err.to_string()))?;
+            let llama = load(checkpoint.to_str().unwrap())?;
+
+            let model_dir = self.model_dir();
+            let file_name = self.converted_weights_file_name();
+            let weights = model_dir.join(&file_name);
+            llama.save_safetensors(&weights)?;
+            drop(llama);
+            Manifest::record(&model_dir, &file_name, self.model)
+                .map_err(|err| error(err.to_string()))?;
+            Manifest::remove(&model_dir, &self.model_file_name(self.model))
+                .map_err(|err| error(err.to_string()))?;
+            Ok(weights)
         }
 
         /// Delete the tokenizer to the local cache directory.
@@ -94,6 +157,10 @@ mod downloader {
 
         /// Delete the pre-trained model weights from the local cache directory.
         pub fn delete_weights(&self) -> Result<(), std::io::Error> {
+            let model_dir = self.model_dir();
+            if model_dir.exists() {
+                Manifest::remove(&model_dir, &self.converted_weights_file_name())?;
+            }
             self.delete(self.model)
         }
 
@@ -152,6 +219,58 @@ impl ModelMeta for TinyLlamaVersion {
     }
 }
 
+/// The sequence length of the models loaded to convert their weights, it only sizes the
+/// key-value cache which is not saved.
+#[cfg(feature = "safetensors")]
+const CONVERSION_MAX_SEQ_LEN: usize = 128;
+
+#[cfg(all(feature = "llama3", feature = "safetensors"))]
+impl LlamaVersion {
+    /// Convert the downloaded weights of the model variant on the device, see
+    /// [convert_weights](Pretrained::convert_weights).
+    ///
+    /// The weights of the quantized variants are kept in their MessagePack record.
+    pub fn convert_weights(&self, device: &Device) -> Result<(), String> {
+        if matches!(self, Self::Llama321bInstructQ4FB32) {
+            return Ok(());
+        }
+        let model = self.pretrained();
+        let tokenizer = model
+            .download_tokenizer()
+            .map_err(|err| format!("Could not download tokenizer.\nError: {err}"))?;
+        model.convert_weights(|checkpoint| {
+            self.load(
+                checkpoint,
+                tokenizer.to_str().unwrap(),
+                CONVERSION_MAX_SEQ_LEN,
+                device,
+            )
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "tiny", feature = "safetensors"))]
+impl TinyLlamaVersion {
+    /// Convert the downloaded weights of the model variant on the device, see
+    /// [convert_weights](Pretrained::convert_weights).
+    pub fn convert_weights(&self, device: &Device) -> Result<(), String> {
+        let model = self.pretrained();
+        let tokenizer = model
+            .download_tokenizer()
+            .map_err(|err| format!("Could not download tokenizer.\nError: {err}"))?;
+        model.convert_weights(|checkpoint| {
+            LlamaConfig::load_tiny_llama(
+                checkpoint,
+                tokenizer.to_str().unwrap(),
+                CONVERSION_MAX_SEQ_LEN,
+                device,
+            )
+        })?;
+        Ok(())
+    }
+}
+
 fn check_context_length(max_seq_len: usize, max_context_len: usize) {
     assert!(
         max_seq_len <= max_context_len,
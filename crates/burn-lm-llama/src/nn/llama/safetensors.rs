@@ -0,0 +1,137 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use burn::{module::Module, tensor::Device};
+use burn_store::{ModuleSnapshot, PyTorchToBurnAdapter, SafetensorsStore};
+use serde::Deserialize;
+
+use crate::{inference::Llama, tokenizer::Tokenizer};
+
+use super::{
+    hf::{permute_rotary_weights, tie_word_embeddings, HF_KEY_REMAPS},
+    LlamaConfig,
+};
+
+/// Index file of a sharded safetensors checkpoint.
+#[derive(Deserialize)]
+pub struct SafetensorsIndex {
+    weight_map: std::collections::HashMap<String, String>,
+}
+
+impl SafetensorsIndex {
+    /// The shard files of the checkpoint, sorted by name.
+    pub fn shards(&self) -> Vec<String> {
+        self.weight_map
+            .values()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Returns the safetensors files of a checkpoint.
+///
+/// The checkpoint can either be a single `.safetensors` file or a directory containing a
+/// `model.safetensors` file or a sharded checkpoint with its `model.safetensors.index.json`.
+pub fn safetensors_files<P: AsRef<Path>>(checkpoint: P) -> Result<Vec<PathBuf>, String> {
+    let checkpoint = checkpoint.as_ref();
+    if checkpoint.is_file() {
+        return Ok(vec![checkpoint.to_path_buf()]);
+    }
+
+    let index = checkpoint.join("model.safetensors.index.json");
+    if index.is_file() {
+        let content = std::fs::read_to_string(&index)
+            .map_err(|err| format!("Failed to read safetensors index.\nError: {err}"))?;
+        let index: SafetensorsIndex = serde_json::from_str(&content)
+            .map_err(|err| format!("Failed to parse safetensors index.\nError: {err}"))?;
+        return Ok(index
+            .shards()
+            .into_iter()
+            .map(|shard| checkpoint.join(shard))
+            .collect());
+    }
+
+    let single = checkpoint.join("model.safetensors");
+    if single.is_file() {
+        return Ok(vec![single]);
+    }
+
+    Err(format!(
+        "No safetensors weights found in '{}'",
+        checkpoint.display()
+    ))
+}
+
+impl LlamaConfig {
+    /// Load a Llama model from a Hugging Face checkpoint directory.
+    ///
+    /// The directory must contain the `config.json` file and the safetensors weights.
+    pub fn load_hf_checkpoint<T: Tokenizer, P: AsRef<Path>>(
+        checkpoint_dir: P,
+        tokenizer_path: &str,
+        max_seq_len: usize,
+        device: &Device,
+    ) -> Result<Llama<T>, String> {
+        let checkpoint_dir = checkpoint_dir.as_ref();
+        Self::from_hf_config(checkpoint_dir.join("config.json"), tokenizer_path)?
+            .with_max_seq_len(max_seq_len)
+            .load_safetensors(checkpoint_dir, device)
+    }
+
+    /// Load Hugging Face Llama weights from a safetensors file or checkpoint directory.
+    pub fn load_safetensors<T: Tokenizer, P: AsRef<Path>>(
+        &self,
+        checkpoint: P,
+        device: &Device,
+    ) -> Result<Llama<T>, String> {
+        let mut llama = self.init::<T>(device)?;
+        let mut applied = BTreeSet::new();
+
+        for file in safetensors_files(checkpoint)? {
+            let mut store = SafetensorsStore::from_file(&file)
+                .with_from_adapter(PyTorchToBurnAdapter)
+                .allow_partial(true);
+            for (from, to) in HF_KEY_REMAPS {
+                store = store.with_key_remapping(from, to);
+            }
+
+            let result = llama.model.load_from(&mut store).map_err(|err| {
+                format!(
+                    "Failed to load safetensors weights '{}'.\nError: {err}",
+                    file.display()
+                )
+            })?;
+            if !result.errors.is_empty() {
+                return Err(format!(
+                    "Failed to load safetensors weights '{}'.\nError: {result}",
+                    file.display()
+                ));
+            }
+            applied.extend(result.applied);
+        }
+
+        let num_layers = applied
+            .iter()
+            .filter(|path| path.ends_with("attention.wq.weight"))
+            .count();
+        if num_layers != self.num_hidden_layers || !applied.contains("tok_embeddings.weight") {
+            return Err(format!(
+                "Incomplete safetensors checkpoint: found weights for {num_layers} of {} layers",
+                self.num_hidden_layers
+            ));
+        }
+
+        let mut record = permute_rotary_weights(llama.model.clone().into_record(), self);
+        // The output layer is missing from checkpoints with tied embeddings
+        if self.tie_word_embeddings || !applied.contains("output.weight") {
+            record = tie_word_embeddings(record);
+        }
+        llama.model = llama.model.load_record(record);
+
+        Ok(llama)
+    }
+}
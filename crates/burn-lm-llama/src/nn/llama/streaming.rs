@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use burn_store::{ModuleSnapshot, SafetensorsStore};
+
+use crate::{inference::Llama, tokenizer::Tokenizer};
+
+impl<T: Tokenizer> Llama<T> {
+    /// Save the model weights to a safetensors file that can be loaded with
+    /// [load_streaming](Self::load_streaming).
+    ///
+    /// The weights are written to a temporary file which is renamed once complete, so an
+    /// interrupted save never leaves a truncated checkpoint behind.
+    pub fn save_safetensors<P: AsRef<Path>>(&self, file_path: P) -> Result<(), String> {
+        let file_path = file_path.as_ref();
+        let error = |err: String| {
+            format!(
+                "Failed to save safetensors weights '{}'.\nError: {err}",
+                file_path.display()
+            )
+        };
+
+        let partial = file_path.with_extension("safetensors.partial");
+        let mut store = SafetensorsStore::from_file(&partial).overwrite(true);
+        self.model
+            .save_into(&mut store)
+            .map_err(|err| error(err.to_string()))?;
+        std::fs::rename(&partial, file_path).map_err(|err| error(err.to_string()))
+    }
+
+    /// Load the model weights from a safetensors file saved with
+    /// [save_safetensors](Self::save_safetensors).
+    ///
+    /// The file is memory-mapped and the tensors are materialized on the model device one
+    /// transformer block at a time, so the checkpoint is never read into memory all at once.
+    pub fn load_streaming<P: AsRef<Path>>(mut self, file_path: P) -> Result<Self, String> {
+        let file_path = file_path.as_ref();
+        let error = |err: String| {
+            format!(
+                "Failed to load safetensors weights '{}'.\nError: {err}",
+                file_path.display()
+            )
+        };
+
+        let stages = std::iter::once(r"^tok_embeddings\.".to_string())
+            .chain((0..self.model.layers.len()).map(|i| format!(r"^layers\.{i}\.")))
+            .chain(std::iter::once(r"^(norm|output)\.".to_string()));
+
+        for pattern in stages {
+            let mut store = SafetensorsStore::from_file(file_path)
+                .with_regex(&pattern)
+                .allow_partial(true);
+            let result = self
+                .model
+                .load_from(&mut store)
+                .map_err(|err| error(err.to_string()))?;
+            if !result.errors.is_empty() {
+                return Err(error(result.to_string()));
+            }
+            if result.applied.is_empty() {
+                return Err(error(format!("no weights matching '{pattern}'")));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::Reinitializer, tokenizer::byte::ByteTokenizer, LlamaConfig};
+    use burn::{prelude::*, tensor::Tolerance};
+
+    #[test]
+    fn test_load_streaming_matches_saved_model() {
+        let device: Device = Default::default();
+        let config = LlamaConfig::llama3_2_1b_test();
+        let mut llama = config.init::<ByteTokenizer>(&device).unwrap();
+        llama.model = Reinitializer::default()
+            .random_float(0, -0.1, 0.1)
+            .apply(llama.model);
+
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("model.safetensors");
+        llama.save_safetensors(&file_path).unwrap();
+        assert!(!file_path.with_extension("safetensors.partial").exists());
+
+        let mut loaded = config
+            .init::<ByteTokenizer>(&device)
+            .unwrap()
+            .load_streaming(&file_path)
+            .unwrap();
+
+        let tokens = Tensor::<2, Int>::from_data([[1, 2, 3, 4]], &device);
+        let expected =
+            llama
+                .model
+                .forward(tokens.clone(), &mut llama.cache, &llama.pos_encoding, None);
+        let output = loaded
+            .model
+            .forward(tokens, &mut loaded.cache, &loaded.pos_encoding, None);
+
+        output
+            .into_data()
+            .assert_approx_eq::<f32>(&expected.into_data(), Tolerance::default());
+    }
+
+    #[test]
+    fn test_load_streaming_missing_weights() {
+        let device: Device = Default::default();
+        let config = LlamaConfig::llama3_2_1b_test();
+        let llama = config.init::<ByteTokenizer>(&device).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("model.safetensors");
+        llama.save_safetensors(&file_path).unwrap();
+
+        // A deeper model expects blocks that are not in the checkpoint
+        let result = config
+            .clone()
+            .with_num_hidden_layers(config.num_hidden_layers + 1)
+            .init::<ByteTokenizer>(&device)
+            .unwrap()
+            .load_streaming(&file_path);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! Training loop for continued pre-training, full fine-tuning and LoRA fine-tuning.
+
+use std::path::Path;
+
+use burn::{
+    config::Config,
+    data::{
+        dataloader::{batcher::Batcher, DataLoaderBuilder},
+        dataset::Dataset,
+    },
+    module::Module,
+    optim::{
+        grad_clipping::GradientClippingConfig,
+        lr_scheduler::{
+            composed::{ComposedLrScheduler, ComposedLrSchedulerConfig},
+            cosine::CosineAnnealingLrSchedulerConfig,
+            linear::LinearLrSchedulerConfig,
+        },
+        AdamWConfig,
+    },
+    record::CompactRecorder,
+    tensor::Device,
+    train::{
+        metric::{AccuracyMetric, LearningRateMetric, LossMetric},
+        Learner, SupervisedTraining,
+    },
+};
+
+use crate::{
+    inference,
+    nn::{lora::LoraConfig, transformer::Transformer},
+    tokenizer::Tokenizer,
+};
+
+use super::training::{Llama, LlamaInput};
+
+/// Directory of the training checkpoints, relative to the artifact directory.
+const CHECKPOINT_DIR: &str = "checkpoint";
+
+/// Learning rate schedule applied after the warmup.
+#[derive(Config, Debug, Copy)]
+pub enum LrSchedule {
+    /// Keep the learning rate constant.
+    Constant,
+    /// Decay the learning rate linearly to zero.
+    Linear,
+    /// Decay the learning rate following a cosine to zero.
+    Cosine,
+}
+
+/// Configuration of a Llama training run.
+#[derive(Config, Debug)]
+pub struct LlamaTrainingConfig {
+    /// The optimizer configuration.
+    pub optimizer: AdamWConfig,
+    /// Number of tokens per training sequence.
+    #[config(default = 512)]
+    pub seq_len: usize,
+    #[config(default = 1)]
+    pub batch_size: usize,
+    #[config(default = 1)]
+    pub num_epochs: usize,
+    #[config(default = 1)]
+    pub num_workers: usize,
+    /// Peak learning rate.
+    #[config(default = 2e-5)]
+    pub learning_rate: f64,
+    #[config(default = "LrSchedule::Cosine")]
+    pub lr_schedule: LrSchedule,
+    /// Number of optimizer steps to linearly increase the learning rate up to its peak.
+    #[config(default = 0)]
+    pub warmup_steps: usize,
+    /// Number of batches whose gradients are accumulated before an optimizer step.
+    #[config(default = 1)]
+    pub grad_accumulation: usize,
+    #[config(default = 42)]
+    pub seed: u64,
+    /// Train a low-rank adapter on top of the frozen weights instead of all the weights.
+    pub lora: Option<LoraConfig>,
+}
+
+impl LlamaTrainingConfig {
+    /// Create a training configuration with the default AdamW optimizer used for fine-tuning.
+    pub fn fine_tuning() -> Self {
+        Self::new(
+            AdamWConfig::new()
+                .with_weight_decay(0.0)
+                .with_grad_clipping(Some(GradientClippingConfig::Norm(1.0))),
+        )
+    }
+
+    /// Initialize the learning rate scheduler for the given number of training iterations.
+    ///
+    /// The scheduler is stepped for each batch, so the warmup accounts for the gradient
+    /// accumulation. Since accumulated gradients are summed, the learning rate is divided by
+    /// the number of accumulated batches.
+    pub fn init_lr_scheduler(&self, num_iters: usize) -> Result<ComposedLrScheduler, String> {
+        let num_iters = num_iters.max(1);
+        let learning_rate = self.learning_rate / self.grad_accumulation as f64;
+        let warmup_iters = (self.warmup_steps * self.grad_accumulation).min(num_iters);
+
+        let mut config = ComposedLrSchedulerConfig::new();
+        if warmup_iters > 0 {
+            // Scales the scheduled learning rate from 1 / warmup_iters to 1
+            config = config.linear(LinearLrSchedulerConfig::new(
+                1.0 / warmup_iters as f64,
+                1.0,
+                warmup_iters,
+            ));
+        }
+        config = match self.lr_schedule {
+            LrSchedule::Constant => config.linear(LinearLrSchedulerConfig::new(
+                learning_rate,
+                learning_rate,
+                1,
+            )),
+            LrSchedule::Linear => {
+                config.linear(LinearLrSchedulerConfig::new(learning_rate, 0.0, num_iters))
+            }
+            LrSchedule::Cosine => config.cosine(CosineAnnealingLrSchedulerConfig::new(
+                learning_rate,
+                num_iters,
+            )),
+        };
+
+        config.init()
+    }
+}
+
+/// Return the last epoch saved in the checkpoint directory of a training run.
+pub fn last_checkpoint<P: AsRef<Path>>(artifact_dir: P) -> Option<usize> {
+    std::fs::read_dir(artifact_dir.as_ref().join(CHECKPOINT_DIR))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let (stem, _ext) = name.split_once('.')?;
+            stem.strip_prefix("model-")?.parse::<usize>().ok()
+        })
+        .max()
+}
+
+/// Train the Llama model on a dataset and return the trained transformer.
+///
+/// With a [LoRA configuration](LlamaTrainingConfig::lora), the returned transformer holds the
+/// trained [adapter](Transformer::adapter) on top of the unchanged weights.
+///
+/// Checkpoints, logs and the configuration are saved into the artifact directory, and the
+/// training resumes from the given checkpoint epoch if any.
+pub fn train<T, I, D, B>(
+    llama: inference::Llama<T>,
+    dataset_train: D,
+    dataset_valid: D,
+    batcher: B,
+    config: &LlamaTrainingConfig,
+    artifact_dir: &Path,
+    checkpoint: Option<usize>,
+) -> Result<Transformer, String>
+where
+    T: Tokenizer,
+    I: Send + Sync + Clone + std::fmt::Debug + 'static,
+    D: Dataset<I> + 'static,
+    B: Batcher<I, LlamaInput> + Clone + 'static,
+{
+    if dataset_train.is_empty() {
+        return Err(format!(
+            "The training dataset is too small for sequences of {} tokens",
+            config.seq_len
+        ));
+    }
+    std::fs::create_dir_all(artifact_dir)
+        .map_err(|err| format!("Failed to create training directory.\nError: {err}"))?;
+    config
+        .save(artifact_dir.join("config.json"))
+        .map_err(|err| format!("Failed to save training config.\nError: {err}"))?;
+
+    let device = autodiff_device(&llama.device);
+    let mut model = Llama::from(llama).fork(&device);
+    if let Some(lora) = &config.lora {
+        model = model.with_lora(lora);
+    }
+
+    let num_iters = dataset_train.len().div_ceil(config.batch_size) * config.num_epochs;
+    let dataloader_train = DataLoaderBuilder::new(batcher.clone())
+        .batch_size(config.batch_size)
+        .shuffle(config.seed)
+        .num_workers(config.num_workers)
+        .build(dataset_train);
+    let dataloader_valid = DataLoaderBuilder::new(batcher)
+        .batch_size(config.batch_size)
+        .num_workers(config.num_workers)
+        .build(dataset_valid);
+
+    let lr_scheduler = config.init_lr_scheduler(num_iters)?;
+    let learner = Learner::new(model, config.optimizer.init(), lr_scheduler);
+
+    let mut training = SupervisedTraining::new(artifact_dir, dataloader_train, dataloader_valid)
+        .metric_train_numeric(LossMetric::new())
+        .metric_valid_numeric(LossMetric::new())
+        .metric_train_numeric(AccuracyMetric::new())
+        .metric_valid_numeric(AccuracyMetric::new())
+        .metric_train_numeric(LearningRateMetric::new())
+        .with_file_checkpointer(CompactRecorder::new())
+        .num_epochs(config.num_epochs)
+        .summary();
+    if config.grad_accumulation > 1 {
+        training = training.grads_accumulation(config.grad_accumulation);
+    }
+    if let Some(epoch) = checkpoint {
+        training = training.checkpoint(epoch);
+    }
+
+    let result = training.launch(learner);
+    Ok(result.model.model)
+}
+
+/// The training device with automatic differentiation enabled.
+fn autodiff_device(device: &Device) -> Device {
+    device.clone().autodiff()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::optim::lr_scheduler::LrScheduler;
+
+    #[test]
+    fn test_lr_scheduler_warmup_and_decay() {
+        let config = LlamaTrainingConfig::fine_tuning()
+            .with_learning_rate(0.1)
+            .with_lr_schedule(LrSchedule::Linear)
+            .with_warmup_steps(2);
+        let mut scheduler = config.init_lr_scheduler(10).unwrap();
+        let lrs = (0..10).map(|_| scheduler.step()).collect::<Vec<_>>();
+
+        let peak = lrs.iter().cloned().fold(f64::MIN, f64::max);
+        assert!(peak <= 0.1);
+        assert!(lrs[0] < lrs[1]);
+        assert!(lrs[9] < lrs[2]);
+        assert!(lrs[9].abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lr_scheduler_grad_accumulation() {
+        let config = LlamaTrainingConfig::fine_tuning()
+            .with_learning_rate(0.1)
+            .with_lr_schedule(LrSchedule::Constant)
+            .with_grad_accumulation(4);
+        let mut scheduler = config.init_lr_scheduler(10).unwrap();
+
+        assert!((scheduler.step() - 0.025).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_last_checkpoint() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_path_buf();
+        let checkpoint_dir = dir.join(CHECKPOINT_DIR);
+        std::fs::create_dir_all(&checkpoint_dir).unwrap();
+        for file in [
+            "model-1.mpk",
+            "model-3.mpk",
+            "optim-4.mpk",
+            "scheduler-3.mpk",
+        ] {
+            std::fs::write(checkpoint_dir.join(file), []).unwrap();
+        }
+
+        let last = last_checkpoint(&dir);
+        temp.close().unwrap();
+
+        assert_eq!(last, Some(3));
+        assert_eq!(last_checkpoint(&dir), None);
+    }
+}
@@ -1,10 +1,14 @@
 //! Training requirements for Llama 3.
 
-use crate::{inference, nn::transformer::Transformer, tokenizer::Tokenizer};
+use crate::{
+    inference,
+    nn::{attention::attention_mask_train, lora::LoraConfig, transformer::Transformer},
+    tokenizer::Tokenizer,
+};
 use burn::{
     module::Module,
-    nn::{loss::CrossEntropyLossConfig, RotaryEncoding},
-    tensor::{Device, Int, Tensor, Transaction},
+    nn::RotaryEncoding,
+    tensor::{activation::log_softmax, Bool, Device, Int, Tensor, Transaction},
     train::{
         metric::{AccuracyInput, Adaptor, LossInput},
         InferenceStep, ItemLazy, TrainOutput, TrainStep,
@@ -12,6 +16,9 @@ use burn::{
 };
 use tracing::debug;
 
+/// Target value excluded from the loss and the metrics, e.g. for padding and prompt tokens.
+pub const IGNORE_INDEX: i64 = -100;
+
 /// Meta Llama large language model and tokenizer. For training uses only.
 #[derive(Module, Debug)]
 pub struct Llama {
@@ -25,8 +32,18 @@ pub struct Llama {
 pub struct LlamaInput {
     /// [batch_size, seq_len]
     pub tokens: Tensor<2, Int>,
+    /// Next token targets, or [IGNORE_INDEX] for the targets excluded from the loss.
+    ///
     /// [batch_size, seq_len]
     pub targets: Tensor<2, Int>,
+    /// Padding tokens, which aren't attended to.
+    ///
+    /// [batch_size, seq_len]
+    pub mask_pad: Option<Tensor<2, Bool>>,
+    /// Document of each token of packed sequences, tokens only attend to their own document.
+    ///
+    /// [batch_size, seq_len]
+    pub document_ids: Option<Tensor<2, Int>>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,16 +56,35 @@ pub struct LlamaOutput {
 }
 
 impl Llama {
+    /// Freeze the transformer weights and add a new trainable low-rank adapter.
+    ///
+    /// Only the adapter weights receive gradients, the frozen weights can be quantized.
+    pub fn with_lora(mut self, config: &LoraConfig) -> Self {
+        let device = self.model.devices()[0].clone();
+        let adapter = self.model.init_lora(config, &device);
+        self.model = self.model.no_grad().with_adapter(Some(adapter));
+        self
+    }
+
     pub fn forward(&self, item: LlamaInput) -> LlamaOutput {
-        let logits = self.model.forward_train(item.tokens, &self.rope);
+        let [batch_size, seq_len] = item.tokens.dims();
+        let mask = match item.mask_pad.is_some() || item.document_ids.is_some() {
+            true => Some(attention_mask_train(
+                batch_size,
+                seq_len,
+                item.mask_pad,
+                item.document_ids,
+                &item.tokens.device(),
+            )),
+            false => None,
+        };
+        let logits = self.model.forward_train(item.tokens, &self.rope, mask);
         let [batch_size, seq_len, vocab_size] = logits.dims();
 
         let logits_flattened = logits.clone().reshape([batch_size * seq_len, vocab_size]);
         let targets_flattened = item.targets.clone().reshape([batch_size * seq_len]);
 
-        let loss = CrossEntropyLossConfig::new()
-            .init(&logits.device())
-            .forward(logits_flattened, targets_flattened);
+        let loss = cross_entropy_loss(logits_flattened, targets_flattened);
 
         debug!(
             "logits dims {:?}, loss dims {:?}",
@@ -64,6 +100,28 @@ impl Llama {
     }
 }
 
+/// Cross-entropy loss averaged over the targets that aren't the [ignore index](IGNORE_INDEX).
+///
+/// # Shapes
+///
+/// - logits: `[num_targets, vocab_size]`
+/// - targets: `[num_targets]`
+pub fn cross_entropy_loss(logits: Tensor<2>, targets: Tensor<1, Int>) -> Tensor<1> {
+    let [num_targets] = targets.dims();
+    let ignored = targets.clone().equal_elem(IGNORE_INDEX);
+    // Ignored targets gather any valid class before being zeroed
+    let indices = targets
+        .mask_fill(ignored.clone(), 0)
+        .reshape([num_targets, 1]);
+    let log_probs = log_softmax(logits, 1)
+        .gather(1, indices)
+        .reshape([num_targets]);
+
+    let kept = ignored.bool_not().float();
+    let num_kept = kept.clone().sum().clamp_min(1.0);
+    (log_probs * kept).sum().neg() / num_kept
+}
+
 impl InferenceStep for Llama {
     type Input = LlamaInput;
     type Output = LlamaOutput;
@@ -90,15 +148,23 @@ impl LlamaInput {
         Self {
             tokens: self.tokens.to_device(device),
             targets: self.targets.to_device(device),
+            mask_pad: self.mask_pad.map(|mask| mask.to_device(device)),
+            document_ids: self.document_ids.map(|ids| ids.to_device(device)),
         }
     }
 }
 
 impl<T: Tokenizer> From<inference::Llama<T>> for Llama {
     fn from(inference_llama: inference::Llama<T>) -> Self {
+        let pos_encoding = inference_llama.pos_encoding;
+        let mut rope = pos_encoding.rope;
+        // The training RoPE table is never shifted, so the attention factor can be folded into it
+        if pos_encoding.attention_factor != 1. {
+            rope.freq_complex = rope.freq_complex.mul_scalar(pos_encoding.attention_factor);
+        }
         Llama {
             model: inference_llama.model,
-            rope: inference_llama.pos_encoding.rope,
+            rope,
         }
     }
 }
@@ -119,7 +185,17 @@ impl Adaptor<AccuracyInput> for LlamaOutput {
             .reshape([batch_size * seq_len, vocab_size]);
         let targets_flattened = self.targets.clone().reshape([batch_size * seq_len]);
 
-        AccuracyInput::new(logits_flattened, targets_flattened)
+        // The ignored targets don't count in the accuracy
+        let indices = targets_flattened
+            .clone()
+            .not_equal_elem(IGNORE_INDEX)
+            .argwhere()
+            .squeeze_dim::<1>(1);
+
+        AccuracyInput::new(
+            logits_flattened.select(0, indices.clone()),
+            targets_flattened.select(0, indices),
+        )
     }
 }
 
@@ -142,3 +218,36 @@ impl ItemLazy for LlamaOutput {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        nn::loss::CrossEntropyLossConfig,
+        tensor::{TensorData, Tolerance},
+    };
+
+    #[test]
+    fn test_cross_entropy_loss_ignore_index() {
+        let device = Default::default();
+        let logits = Tensor::<2>::from_data(
+            [[0.5, -1.0, 2.0], [1.0, 0.0, -1.0], [-2.0, 3.0, 0.5]],
+            &device,
+        );
+        let targets = Tensor::<1, Int>::from_data([IGNORE_INDEX, 0, 1], &device);
+
+        let loss = cross_entropy_loss(logits.clone(), targets);
+        let expected = CrossEntropyLossConfig::new().init(&device).forward(
+            logits.slice([1..3]),
+            Tensor::<1, Int>::from_data([0, 1], &device),
+        );
+
+        loss.into_data()
+            .assert_approx_eq::<f32>(&expected.into_data(), Tolerance::default());
+
+        let ignored = Tensor::<1, Int>::from_data([IGNORE_INDEX, IGNORE_INDEX], &device);
+        cross_entropy_loss(Tensor::<2>::ones([2, 3], &device), ignored)
+            .into_data()
+            .assert_eq(&TensorData::from([0.0f32]), false);
+    }
+}
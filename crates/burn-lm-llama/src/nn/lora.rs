@@ -0,0 +1,376 @@
+use std::path::Path;
+
+use burn::{
+    config::Config,
+    module::Module,
+    nn::{Initializer, Linear, LinearConfig},
+    record::{FullPrecisionSettings, NamedMpkFileRecorder},
+    tensor::{DType, Device, Tensor},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::nn::transformer::Transformer;
+
+/// Configuration file of a saved [LoRA adapter](LoraAdapter).
+pub const LORA_CONFIG_FILE: &str = "lora_config.json";
+/// Weights file of a saved [LoRA adapter](LoraAdapter), without the recorder extension.
+pub const LORA_WEIGHTS_FILE: &str = "lora_adapter";
+
+/// Linear projections of a transformer block that can be adapted with LoRA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoraTarget {
+    /// Attention query projection.
+    Wq,
+    /// Attention key projection.
+    Wk,
+    /// Attention value projection.
+    Wv,
+    /// Attention output projection.
+    Wo,
+    /// Feed-forward SwiGLU gate projection.
+    SwigluInner,
+    /// Feed-forward SwiGLU up projection.
+    SwigluOuter,
+    /// Feed-forward down projection.
+    W2,
+}
+
+impl LoraTarget {
+    /// All the projections that can be adapted.
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Wq,
+            Self::Wk,
+            Self::Wv,
+            Self::Wo,
+            Self::SwigluInner,
+            Self::SwigluOuter,
+            Self::W2,
+        ]
+    }
+
+    /// Map a Hugging Face (PEFT) target module name to the adapted projection.
+    pub fn from_hf_module(name: &str) -> Option<Self> {
+        match name {
+            "q_proj" => Some(Self::Wq),
+            "k_proj" => Some(Self::Wk),
+            "v_proj" => Some(Self::Wv),
+            "o_proj" => Some(Self::Wo),
+            "gate_proj" => Some(Self::SwigluInner),
+            "up_proj" => Some(Self::SwigluOuter),
+            "down_proj" => Some(Self::W2),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration to create [low-rank adapters](LoraAdapter).
+#[derive(Config, Debug)]
+pub struct LoraConfig {
+    /// The rank of the low-rank update.
+    #[config(default = "8")]
+    pub rank: usize,
+    /// The scaling numerator of the low-rank update.
+    #[config(default = "16.0")]
+    pub alpha: f64,
+    /// Scale the update by `alpha / sqrt(rank)` instead of `alpha / rank` (rsLoRA).
+    #[config(default = "false")]
+    pub rank_stabilized: bool,
+    /// The adapted projections.
+    #[config(default = "LoraTarget::all()")]
+    pub targets: Vec<LoraTarget>,
+}
+
+impl LoraConfig {
+    /// The scaling applied to the low-rank update.
+    pub fn scaling(&self) -> f64 {
+        if self.rank_stabilized {
+            self.alpha / (self.rank as f64).sqrt()
+        } else {
+            self.alpha / self.rank as f64
+        }
+    }
+
+    /// Initialize a new [LoRA linear](LoraLinear) update if the projection is targeted.
+    ///
+    /// The up projection is initialized with zeros so that a new adapter leaves the
+    /// model outputs unchanged.
+    pub fn init_linear(
+        &self,
+        target: LoraTarget,
+        d_input: usize,
+        d_output: usize,
+        device: &Device,
+    ) -> Option<LoraLinear> {
+        if !self.targets.contains(&target) {
+            return None;
+        }
+
+        let a = LinearConfig::new(d_input, self.rank)
+            .with_bias(false)
+            .init(device);
+        let b = LinearConfig::new(self.rank, d_output)
+            .with_bias(false)
+            .with_initializer(Initializer::Zeros)
+            .init(device);
+
+        Some(LoraLinear {
+            a,
+            b,
+            scaling: self.scaling(),
+        })
+    }
+}
+
+/// Low-rank update `x A B * scaling` of a frozen linear projection.
+#[derive(Module, Debug)]
+pub struct LoraLinear {
+    /// Down projection to the adapter rank.
+    pub a: Linear,
+    /// Up projection from the adapter rank.
+    pub b: Linear,
+    /// Scaling of the update.
+    pub scaling: f64,
+}
+
+impl LoraLinear {
+    /// Applies the low-rank update on the input tensor.
+    pub fn forward<const D: usize>(&self, input: Tensor<D>) -> Tensor<D> {
+        self.b
+            .forward(self.a.forward(input))
+            .mul_scalar(self.scaling)
+    }
+
+    /// The update of the linear weight, with shape `[d_input, d_output]`.
+    pub fn delta_weight(&self) -> Tensor<2> {
+        self.a
+            .weight
+            .val()
+            .matmul(self.b.weight.val())
+            .mul_scalar(self.scaling)
+    }
+
+    /// Merge the low-rank update into the linear weight.
+    ///
+    /// Quantized weights are rejected, the update would be lost in their quantization error.
+    pub fn merge(&self, mut linear: Linear) -> Result<Linear, String> {
+        if matches!(linear.weight.val().dtype(), DType::QFloat(_)) {
+            return Err(
+                "the adapter can't be merged into quantized weights, apply it unmerged instead"
+                    .to_string(),
+            );
+        }
+        let delta = self.delta_weight();
+        linear.weight = linear.weight.map(|weight| weight + delta);
+        Ok(linear)
+    }
+}
+
+/// Applies a linear projection with its optional low-rank update.
+pub(crate) fn forward_linear<const D: usize>(
+    linear: &Linear,
+    lora: Option<&LoraLinear>,
+    input: Tensor<D>,
+) -> Tensor<D> {
+    match lora {
+        Some(lora) => linear.forward(input.clone()) + lora.forward(input),
+        None => linear.forward(input),
+    }
+}
+
+/// Merges an optional low-rank update into a linear projection.
+pub(crate) fn merge_linear(linear: Linear, lora: Option<&LoraLinear>) -> Result<Linear, String> {
+    match lora {
+        Some(lora) => lora.merge(linear),
+        None => Ok(linear),
+    }
+}
+
+/// Low-rank updates of the [attention](crate::nn::attention::MultiHeadAttention) projections.
+#[derive(Module, Debug)]
+pub struct AttentionLora {
+    pub wq: Option<LoraLinear>,
+    pub wk: Option<LoraLinear>,
+    pub wv: Option<LoraLinear>,
+    pub wo: Option<LoraLinear>,
+}
+
+/// Low-rank updates of the [feed-forward](crate::nn::fftn::FeedForward) projections.
+#[derive(Module, Debug)]
+pub struct FeedForwardLora {
+    pub linear_inner: Option<LoraLinear>,
+    pub linear_outer: Option<LoraLinear>,
+    pub w2: Option<LoraLinear>,
+}
+
+/// Low-rank updates of a [transformer block](crate::nn::transformer::TransformerBlock).
+#[derive(Module, Debug)]
+pub struct BlockLora {
+    pub attention: AttentionLora,
+    pub feed_forward: FeedForwardLora,
+}
+
+/// Low-rank adapter (LoRA) of a [transformer](crate::nn::transformer::Transformer).
+///
+/// An adapter can either be applied unmerged, which allows to swap adapters on the same
+/// base weights, or merged into the base weights for faster inference.
+#[derive(Module, Debug)]
+pub struct LoraAdapter {
+    pub layers: Vec<BlockLora>,
+}
+
+impl LoraAdapter {
+    /// Save the adapter weights and its configuration into a directory, without the base
+    /// weights.
+    pub fn save<P: AsRef<Path>>(self, adapter_dir: P, config: &LoraConfig) -> Result<(), String> {
+        let adapter_dir = adapter_dir.as_ref();
+        std::fs::create_dir_all(adapter_dir)
+            .map_err(|err| format!("Failed to create adapter directory.\nError: {err}"))?;
+        config
+            .save(adapter_dir.join(LORA_CONFIG_FILE))
+            .map_err(|err| format!("Failed to save adapter config.\nError: {err}"))?;
+
+        self.save_file(
+            adapter_dir.join(LORA_WEIGHTS_FILE),
+            &NamedMpkFileRecorder::<FullPrecisionSettings>::new(),
+        )
+        .map_err(|err| format!("Failed to save adapter weights.\nError: {err}"))
+    }
+
+    /// Load an adapter [saved](LoraAdapter::save) for the given transformer.
+    pub fn load<P: AsRef<Path>>(
+        model: &Transformer,
+        adapter_dir: P,
+        device: &Device,
+    ) -> Result<Self, String> {
+        let adapter_dir = adapter_dir.as_ref();
+        let config = LoraConfig::load(adapter_dir.join(LORA_CONFIG_FILE))
+            .map_err(|err| format!("Failed to load adapter config.\nError: {err}"))?;
+
+        model
+            .init_lora(&config, device)
+            .load_file(
+                adapter_dir.join(LORA_WEIGHTS_FILE),
+                &NamedMpkFileRecorder::<FullPrecisionSettings>::new(),
+                device,
+            )
+            .map_err(|err| format!("Failed to load adapter weights.\nError: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{nn::transformer::TransformerConfig, tests::Reinitializer};
+    use burn::{
+        module::Quantizer,
+        tensor::{
+            quantization::{Calibration, QuantScheme},
+            Tolerance,
+        },
+    };
+
+    #[test]
+    fn test_new_adapter_is_identity() {
+        let device: Device = Default::default();
+        let config = LoraConfig::new().with_rank(2);
+        let lora = config.init_linear(LoraTarget::Wq, 4, 6, &device).unwrap();
+
+        let input = Tensor::<3>::ones([1, 3, 4], &device);
+        let output = lora.forward(input);
+
+        let expected = Tensor::<3>::zeros([1, 3, 6], &device).into_data();
+        output
+            .into_data()
+            .assert_approx_eq::<f32>(&expected, Tolerance::default());
+        assert!(LoraConfig::new()
+            .with_targets(vec![LoraTarget::Wv])
+            .init_linear(LoraTarget::Wq, 4, 6, &device)
+            .is_none());
+    }
+
+    #[test]
+    fn test_merged_equals_unmerged() {
+        let device: Device = Default::default();
+        let linear = LinearConfig::new(4, 6).with_bias(false).init(&device);
+        let linear = Reinitializer::default()
+            .random_float(0, -1.0, 1.0)
+            .apply(linear);
+        let lora = LoraConfig::new()
+            .with_rank(2)
+            .init_linear(LoraTarget::Wq, 4, 6, &device)
+            .unwrap();
+        let lora = Reinitializer::default()
+            .random_float(1, -1.0, 1.0)
+            .apply(lora);
+
+        let input = Tensor::<3>::random(
+            [2, 3, 4],
+            burn::tensor::Distribution::Uniform(-1.0, 1.0),
+            &device,
+        );
+        let unmerged = forward_linear(&linear, Some(&lora), input.clone());
+        let merged = merge_linear(linear, Some(&lora)).unwrap().forward(input);
+
+        merged
+            .into_data()
+            .assert_approx_eq::<f32>(&unmerged.into_data(), Tolerance::default());
+    }
+
+    #[test]
+    fn test_merge_into_quantized_weights_is_rejected() {
+        let device: Device = Default::default();
+        let linear = LinearConfig::new(32, 6).with_bias(false).init(&device);
+        let mut quantizer = Quantizer {
+            calibration: Calibration::MinMax,
+            scheme: QuantScheme::default(),
+        };
+        let linear = linear.quantize_weights(&mut quantizer);
+        let lora = LoraConfig::new()
+            .with_rank(2)
+            .init_linear(LoraTarget::Wq, 32, 6, &device)
+            .unwrap();
+
+        assert!(lora.merge(linear.clone()).is_err());
+        // The adapter can still be applied unmerged
+        let input = Tensor::<3>::ones([1, 3, 32], &device);
+        assert_eq!(
+            forward_linear(&linear, Some(&lora), input).dims(),
+            [1, 3, 6]
+        );
+    }
+
+    #[test]
+    fn test_adapter_save_load() {
+        let device: Device = Default::default();
+        let model = TransformerConfig::new(8, 2, 8, 16, 2, 1).init(&device);
+        let config = LoraConfig::new()
+            .with_rank(2)
+            .with_targets(vec![LoraTarget::Wq, LoraTarget::W2]);
+        let adapter = Reinitializer::default()
+            .random_float(0, -1.0, 1.0)
+            .apply(model.init_lora(&config, &device));
+        let expected = adapter.layers[0]
+            .attention
+            .wq
+            .as_ref()
+            .unwrap()
+            .delta_weight()
+            .into_data();
+
+        let dir = tempfile::tempdir().unwrap();
+        adapter.save(dir.path(), &config).unwrap();
+        let loaded = LoraAdapter::load(&model, dir.path(), &device).unwrap();
+
+        assert!(loaded.layers[0].attention.wk.is_none());
+        assert!(loaded.layers[1].feed_forward.w2.is_some());
+        loaded.layers[0]
+            .attention
+            .wq
+            .as_ref()
+            .unwrap()
+            .delta_weight()
+            .into_data()
+            .assert_approx_eq::<f32>(&expected, Tolerance::default());
+    }
+}
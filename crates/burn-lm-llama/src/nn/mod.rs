@@ -4,6 +4,9 @@ pub mod attention;
 /// Feed-forward transformation network module.
 pub mod fftn;
 
+/// Low-rank adaptation (LoRA) modules.
+pub mod lora;
+
 /// Transformer module.
 pub mod transformer;
 
@@ -1,4 +1,8 @@
-use burn::{config::Config, nn::RotaryEncoding, tensor::Tensor};
+use burn::{
+    config::Config,
+    nn::RotaryEncoding,
+    tensor::{Int, Tensor},
+};
 
 /// Tracks the state of rotary positional encodings during autoregressive inference.
 ///
@@ -17,6 +21,8 @@ pub struct PositionalEncodingState {
     pub curr_seq_len: usize,
     /// The index start offset.
     pub start_offset: usize,
+    /// Scale of the rotated queries and keys, e.g. the YaRN attention temperature.
+    pub attention_factor: f32,
 }
 
 impl PositionalEncodingState {
@@ -29,9 +35,16 @@ impl PositionalEncodingState {
             next_position: 0,
             curr_seq_len: 0,
             start_offset: 0,
+            attention_factor: 1.,
         }
     }
 
+    /// Set the scale of the rotated queries and keys.
+    pub fn with_attention_factor(mut self, attention_factor: f32) -> Self {
+        self.attention_factor = attention_factor;
+        self
+    }
+
     pub fn prepare(&mut self, seq_len: usize) {
         self.curr_seq_len = seq_len;
         self.next_position += seq_len;
@@ -54,11 +67,21 @@ impl PositionalEncodingState {
     }
 
     pub fn forward<const D: usize>(&self, x: Tensor<D>) -> Tensor<D> {
-        self.rope.forward(x)
+        self.scale(self.rope.forward(x))
     }
 
     pub fn apply<const D: usize>(&self, x: Tensor<D>) -> Tensor<D> {
-        self.rope.apply(x, self.index())
+        self.scale(self.rope.apply(x, self.index()))
+    }
+
+    fn scale<const D: usize>(&self, x: Tensor<D>) -> Tensor<D> {
+        // Both the queries and the keys are scaled, so the attention logits are scaled by
+        // the squared factor
+        if self.attention_factor != 1. {
+            x.mul_scalar(self.attention_factor)
+        } else {
+            x
+        }
     }
 
     /// Returns the absolute sequence position since the beginning,
@@ -85,6 +108,147 @@ pub struct RopeConfig {
     pub theta: f32,
     #[config(default = "None")]
     pub scaled: Option<RopeFrequencyScaling>,
+    /// Context extension scaling, can't be combined with the frequency scaling by parts.
+    #[config(default = "None")]
+    pub scaling: Option<RopeScaling>,
+}
+
+impl RopeConfig {
+    /// Scale of the rotated queries and keys.
+    pub fn attention_factor(&self) -> f32 {
+        self.scaling
+            .as_ref()
+            .map_or(1., |scaling| scaling.attention_factor())
+    }
+}
+
+/// RoPE scaling methods to extend the context beyond the pre-training length.
+#[derive(Config, Debug)]
+pub enum RopeScaling {
+    /// Linear position interpolation, the frequencies are divided by the factor.
+    Linear(f32),
+    /// Dynamic NTK-aware scaling of the RoPE base.
+    DynamicNtk(DynamicNtkScaling),
+    /// YaRN interpolation by frequency band with attention temperature.
+    Yarn(YarnScaling),
+}
+
+/// Dynamic NTK-aware RoPE scaling.
+///
+/// The base is scaled for the maximum sequence length of the model rather than for the
+/// length of each sequence, since the frequencies are precomputed.
+#[derive(Config, Debug)]
+pub struct DynamicNtkScaling {
+    /// The scaling factor.
+    pub factor: f32,
+    /// The context length used during pre-training.
+    pub original_max_seq_len: usize,
+}
+
+/// [YaRN](https://arxiv.org/abs/2309.00071) RoPE scaling.
+#[derive(Config, Debug)]
+pub struct YarnScaling {
+    /// The scaling factor.
+    pub factor: f32,
+    /// The context length used during pre-training.
+    pub original_max_seq_len: usize,
+    /// Number of rotations over the pre-training context above which the frequencies are
+    /// not interpolated.
+    #[config(default = "32.")]
+    pub beta_fast: f32,
+    /// Number of rotations over the pre-training context below which the frequencies are
+    /// fully interpolated.
+    #[config(default = "1.")]
+    pub beta_slow: f32,
+    /// Attention temperature, defaults to `0.1 * ln(factor) + 1`.
+    #[config(default = "None")]
+    pub attention_factor: Option<f32>,
+}
+
+impl RopeScaling {
+    /// Scale the RoPE frequencies computed with the `theta` base for a model of
+    /// `max_seq_len` positions.
+    pub fn scale_frequencies(&self, freqs: Tensor<1>, theta: f32, max_seq_len: usize) -> Tensor<1> {
+        match self {
+            RopeScaling::Linear(factor) => freqs.div_scalar(*factor),
+            RopeScaling::DynamicNtk(scaling) => {
+                scaling.scale_frequencies(freqs, theta, max_seq_len)
+            }
+            RopeScaling::Yarn(scaling) => scaling.scale_frequencies(freqs, theta),
+        }
+    }
+
+    /// Scale of the rotated queries and keys.
+    pub fn attention_factor(&self) -> f32 {
+        match self {
+            RopeScaling::Yarn(scaling) => scaling.attention_factor(),
+            _ => 1.,
+        }
+    }
+}
+
+impl DynamicNtkScaling {
+    /// Scale the RoPE frequencies.
+    ///
+    /// Adapted from: https://github.com/huggingface/transformers/blob/main/src/transformers/modeling_rope_utils.py
+    pub fn scale_frequencies(&self, freqs: Tensor<1>, theta: f32, max_seq_len: usize) -> Tensor<1> {
+        if max_seq_len <= self.original_max_seq_len {
+            return freqs;
+        }
+        let dim = 2. * freqs.dims()[0] as f32;
+        let ratio = self.factor * max_seq_len as f32 / self.original_max_seq_len as f32;
+        let base = theta * (ratio - (self.factor - 1.)).powf(dim / (dim - 2.));
+        // freqs = theta ^ (-2i / dim), so raising them to ln(base) / ln(theta) changes the base
+        freqs.powf_scalar(base.ln() / theta.ln())
+    }
+}
+
+impl YarnScaling {
+    /// Scale the RoPE frequencies, the high frequencies are kept while the low frequencies
+    /// are interpolated like [linear scaling](RopeScaling::Linear), with a linear ramp in
+    /// between.
+    ///
+    /// Adapted from: https://github.com/huggingface/transformers/blob/main/src/transformers/modeling_rope_utils.py
+    pub fn scale_frequencies(&self, freqs: Tensor<1>, theta: f32) -> Tensor<1> {
+        let [num_freqs] = freqs.dims();
+        let dim = 2. * num_freqs as f32;
+        // Frequency index with the given number of rotations over the pre-training context
+        let correction_dim = |rotations: f32| {
+            dim * (self.original_max_seq_len as f32 / (rotations * 2. * core::f32::consts::PI)).ln()
+                / (2. * theta.ln())
+        };
+        let low = correction_dim(self.beta_fast).floor().max(0.);
+        let high = correction_dim(self.beta_slow).ceil().min(dim - 1.);
+        let high = if low == high { high + 0.001 } else { high };
+
+        // 0 for the extrapolated high frequencies, 1 for the interpolated low frequencies
+        let ramp = Tensor::<1, Int>::arange(0..num_freqs as i64, &freqs.device())
+            .float()
+            .sub_scalar(low)
+            .div_scalar(high - low)
+            .clamp(0., 1.);
+
+        freqs
+            .clone()
+            .div_scalar(self.factor)
+            .mul(ramp.clone())
+            .add(freqs.mul(ramp.neg().add_scalar(1.)))
+    }
+
+    /// The attention temperature.
+    pub fn attention_factor(&self) -> f32 {
+        self.attention_factor
+            .unwrap_or_else(|| yarn_mscale(self.factor, 1.))
+    }
+}
+
+/// YaRN attention scale for a scaling factor.
+pub fn yarn_mscale(factor: f32, mscale: f32) -> f32 {
+    if factor <= 1. {
+        1.
+    } else {
+        0.1 * mscale * factor.ln() + 1.
+    }
 }
 
 /// RoPE frequency scaling.
@@ -283,4 +447,79 @@ mod tests {
             .into_data()
             .assert_approx_eq::<f32>(&expected, Tolerance::relative(0.05));
     }
+
+    #[test]
+    fn test_rope_linear_scaling() {
+        let device: Device = Default::default();
+        let theta = 10000.;
+        let scaling = RopeScaling::Linear(2.);
+        let scaled = RotaryEncodingConfig::new(16, 4)
+            .with_theta(theta)
+            .init_with_frequency_scaling(|x| scaling.scale_frequencies(x, theta, 16), &device);
+        let rope = RotaryEncodingConfig::new(16, 4)
+            .with_theta(theta)
+            .init(&device);
+
+        // The interpolated position 2 is the position 1
+        let input = TestTensor::<4>::from([[[[0.1, -0.2, 0.3, -0.4]]]]);
+        scaled
+            .apply(input.clone(), 2)
+            .into_data()
+            .assert_approx_eq::<f32>(&rope.apply(input, 1).into_data(), Tolerance::default());
+    }
+
+    #[test]
+    fn test_rope_dynamic_ntk_scaling() {
+        let freqs = || TestTensor::<1>::from([1., 0.1, 0.01, 0.001]);
+        let scaling = RopeScaling::DynamicNtk(DynamicNtkScaling::new(2., 16));
+
+        // Not scaled within the pre-training context
+        scaling
+            .scale_frequencies(freqs(), 10000., 16)
+            .into_data()
+            .assert_approx_eq::<f32>(&freqs().into_data(), Tolerance::default());
+
+        let base = 10000f32 * (2f32 * 32. / 16. - 1.).powf(8. / 6.);
+        let expected: [f32; 4] = core::array::from_fn(|i| base.powf(-2. * i as f32 / 8.));
+        scaling
+            .scale_frequencies(freqs(), 10000., 32)
+            .into_data()
+            .assert_approx_eq::<f32>(&TensorData::from(expected), Tolerance::relative(1e-3));
+        assert_eq!(scaling.attention_factor(), 1.);
+    }
+
+    #[test]
+    fn test_rope_yarn_scaling() {
+        let freqs = TestTensor::<1>::from([1., 0.1, 0.01, 0.001]);
+        let scaling = RopeScaling::Yarn(YarnScaling::new(2., 16));
+
+        // Only the highest frequency rotates more than once over the pre-training context
+        scaling
+            .scale_frequencies(freqs, 10000., 32)
+            .into_data()
+            .assert_approx_eq::<f32>(
+                &TensorData::from([1., 0.05, 0.005, 0.0005]),
+                Tolerance::relative(1e-3),
+            );
+        assert_eq!(scaling.attention_factor(), 0.1 * 2f32.ln() + 1.);
+
+        let scaling = YarnScaling::new(2., 16).with_attention_factor(Some(1.5));
+        assert_eq!(scaling.attention_factor(), 1.5);
+    }
+
+    #[test]
+    fn test_attention_factor_scales_rotated_input() {
+        let device: Device = Default::default();
+        let rope = RotaryEncodingConfig::new(16, 4).init(&device);
+        let pos_encoding = PositionalEncodingState::new(rope.clone()).with_attention_factor(2.);
+
+        let input = TestTensor::<4>::from([[[[0.1, -0.2, 0.3, -0.4]]]]);
+        pos_encoding
+            .apply(input.clone())
+            .into_data()
+            .assert_approx_eq::<f32>(
+                &rope.apply(input, 0).mul_scalar(2.).into_data(),
+                Tolerance::default(),
+            );
+    }
 }
@@ -12,6 +12,7 @@ use crate::{
     nn::{
         attention::*,
         fftn::{FeedForward, FeedForwardConfig},
+        lora::{BlockLora, LoraAdapter, LoraConfig},
     },
 };
 
@@ -103,16 +104,78 @@ impl Transformer {
     }
 
     /// Forward with non-autoregressive and creates a mask for training.
-    pub fn forward_train(&self, input: Tensor<2, Int>, rope: &RotaryEncoding) -> Tensor<3> {
+    ///
+    /// The attention mask defaults to a causal mask, see
+    /// [attention_mask_train](crate::nn::attention::attention_mask_train) to mask padding
+    /// tokens and document boundaries.
+    pub fn forward_train(
+        &self,
+        input: Tensor<2, Int>,
+        rope: &RotaryEncoding,
+        mask: Option<Tensor<4, Bool>>,
+    ) -> Tensor<3> {
         let mut h = self.tok_embeddings.forward(input);
 
         for layer in self.layers.iter() {
-            h = layer.forward_train(h, rope);
+            h = layer.forward_train(h, rope, mask.clone());
         }
 
         let h = self.norm.forward(h);
         self.output.forward(h)
     }
+
+    /// Initialize a new [low-rank adapter](LoraAdapter) for the transformer blocks.
+    pub fn init_lora(&self, config: &LoraConfig, device: &Device) -> LoraAdapter {
+        LoraAdapter {
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| layer.init_lora(config, device))
+                .collect(),
+        }
+    }
+
+    /// The low-rank adapter applied unmerged to the transformer blocks, if any.
+    pub fn adapter(&self) -> Option<LoraAdapter> {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| layer.lora())
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(LoraAdapter { layers })
+    }
+
+    /// Set the low-rank adapter applied unmerged to the transformer blocks.
+    ///
+    /// Passing `None` removes the current adapter.
+    pub fn with_adapter(mut self, adapter: Option<LoraAdapter>) -> Self {
+        self.layers = match adapter {
+            Some(adapter) => self
+                .layers
+                .into_iter()
+                .zip(adapter.layers)
+                .map(|(layer, lora)| layer.with_lora(Some(lora)))
+                .collect(),
+            None => self
+                .layers
+                .into_iter()
+                .map(|layer| layer.with_lora(None))
+                .collect(),
+        };
+        self
+    }
+
+    /// Merge the low-rank adapter into the weights of the transformer blocks.
+    pub fn merge_adapter(mut self, adapter: &LoraAdapter) -> Result<Self, String> {
+        self.layers = self
+            .layers
+            .into_iter()
+            .zip(adapter.layers.iter())
+            .map(|(layer, lora)| layer.merge_lora(lora))
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -145,6 +208,11 @@ impl TransformerCache {
         }
     }
 
+    /// The maximum sequence length held by the cache.
+    pub fn max_seq_len(&self) -> usize {
+        self.max_seq_len
+    }
+
     pub fn prepare(&mut self, seq_len: usize) -> Result<Option<Tensor<4, Bool>>, GenerationError> {
         if seq_len > self.max_seq_len {
             return Err(GenerationError::MaxSequenceLengthExceeded {
@@ -256,13 +324,53 @@ impl TransformerBlock {
     }
 
     /// Forward with non-autoregressive and a required mask for training.
-    pub fn forward_train(&self, input: Tensor<3>, rope: &RotaryEncoding) -> Tensor<3> {
+    pub fn forward_train(
+        &self,
+        input: Tensor<3>,
+        rope: &RotaryEncoding,
+        mask: Option<Tensor<4, Bool>>,
+    ) -> Tensor<3> {
         let h = input.clone()
             + self
                 .attention
-                .forward_masked(self.attention_norm.forward(input), rope);
+                .forward_masked(self.attention_norm.forward(input), rope, mask);
         h.clone() + self.feed_forward.forward(self.ffn_norm.forward(h))
     }
+
+    /// The number of query and key-value attention heads.
+    pub fn num_heads(&self) -> (usize, usize) {
+        self.attention.num_heads()
+    }
+
+    fn init_lora(&self, config: &LoraConfig, device: &Device) -> BlockLora {
+        BlockLora {
+            attention: self.attention.init_lora(config, device),
+            feed_forward: self.feed_forward.init_lora(config, device),
+        }
+    }
+
+    fn lora(&self) -> Option<BlockLora> {
+        Some(BlockLora {
+            attention: self.attention.lora()?.clone(),
+            feed_forward: self.feed_forward.lora()?.clone(),
+        })
+    }
+
+    fn with_lora(mut self, lora: Option<BlockLora>) -> Self {
+        let (attention, feed_forward) = match lora {
+            Some(lora) => (Some(lora.attention), Some(lora.feed_forward)),
+            None => (None, None),
+        };
+        self.attention = self.attention.with_lora(attention);
+        self.feed_forward = self.feed_forward.with_lora(feed_forward);
+        self
+    }
+
+    fn merge_lora(mut self, lora: &BlockLora) -> Result<Self, String> {
+        self.attention = self.attention.merge_lora(&lora.attention)?;
+        self.feed_forward = self.feed_forward.merge_lora(&lora.feed_forward)?;
+        Ok(self)
+    }
 }
 
 #[cfg(test)]
@@ -348,6 +456,43 @@ mod tests {
             .assert_approx_eq::<f32>(&expected, Tolerance::relative(0.001));
     }
 
+    #[test]
+    fn test_transformer_adapter_merged_equals_unmerged() {
+        let device: Device = Default::default();
+        let config = TransformerConfig::new(8, 2, 8, 16, 2, 1);
+        let transformer = Reinitializer::default()
+            .random_float(0, -1.0, 1.0)
+            .apply(config.init(&device));
+        let adapter = transformer.init_lora(&LoraConfig::new().with_rank(2), &device);
+        let adapter = Reinitializer::default()
+            .random_float(1, -0.5, 0.5)
+            .apply(adapter);
+
+        let batch_size = 1;
+        let seq_length = 3;
+        let rope = RotaryEncodingConfig::new(seq_length * 2, config.d_model / config.n_heads)
+            .init(&device);
+        let rope = PositionalEncodingState::new(rope);
+        let input = Tensor::arange(0..seq_length as i64, &device).reshape([batch_size, seq_length]);
+
+        let forward = |model: &Transformer| {
+            let mut cache = TransformerCache::new(&config, batch_size, &device);
+            let mask = cache.prepare(seq_length).unwrap();
+            model.forward(input.clone(), &mut cache, &rope, mask)
+        };
+        let base = forward(&transformer);
+        let unmerged = forward(&transformer.clone().with_adapter(Some(adapter.clone())));
+        let merged = forward(&transformer.clone().merge_adapter(&adapter).unwrap());
+        let removed = forward(&transformer.with_adapter(Some(adapter)).with_adapter(None));
+
+        merged
+            .into_data()
+            .assert_approx_eq::<f32>(&unmerged.into_data(), Tolerance::relative(0.001));
+        removed
+            .into_data()
+            .assert_approx_eq::<f32>(&base.into_data(), Tolerance::relative(0.001));
+    }
+
     pub struct ForwardCacheTestCase {
         cache: TransformerCache,
         config: TransformerConfig,
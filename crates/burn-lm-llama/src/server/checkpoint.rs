@@ -0,0 +1,388 @@
+use burn::tensor::Device;
+use serde::Deserialize;
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use super::model::{task_prompt, Sampling, ServerModel};
+use crate::{
+    inference::Llama,
+    tokenizer::{ChatFormat, Tokenizer, HF_TOKENIZER_CONFIG_FILE},
+    LlamaConfig,
+};
+use burn_lm_inference::{InferenceJob, *};
+
+/// The Hugging Face configuration file of a checkpoint.
+const HF_CONFIG_FILE: &str = "config.json";
+
+#[inference_server_config]
+pub struct CheckpointServerConfig {
+    /// Top-p probability threshold.
+    #[config(default = 0.9)]
+    pub top_p: f64,
+    /// Temperature value for controlling randomness in sampling.
+    #[config(default = 0.6)]
+    pub temperature: f64,
+    /// Maximum sequence length for input text.
+    #[config(default = 1024)]
+    pub max_seq_len: usize,
+    /// The number of new tokens to generate (i.e., the number of generation steps to take).
+    #[config(default = 128, openwebui_param = "max_tokens")]
+    pub sample_len: usize,
+    /// The seed to use when generating random samples. If it is 0 then a random seed is used for each inference.
+    #[config(default = 0)]
+    pub seed: u64,
+}
+
+/// Default values of the server configuration of a model, they replace the defaults of
+/// [CheckpointServerConfig] while the values set explicitly still take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelDefaults {
+    pub top_p: Option<f64>,
+    pub temperature: Option<f64>,
+    pub max_seq_len: Option<usize>,
+    pub sample_len: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+impl ModelDefaults {
+    /// Apply the defaults to the fields for which `is_set` returns false.
+    ///
+    /// `is_set` is given the CLI argument name and the JSON key of each field.
+    pub fn apply(&self, config: &mut CheckpointServerConfig, is_set: impl Fn(&str, &str) -> bool) {
+        fn apply<V: Copy>(field: &mut V, default: Option<V>, is_set: bool) {
+            if let (Some(default), false) = (default, is_set) {
+                *field = default;
+            }
+        }
+        apply(&mut config.top_p, self.top_p, is_set("top_p", "top_p"));
+        apply(
+            &mut config.temperature,
+            self.temperature,
+            is_set("temperature", "temperature"),
+        );
+        apply(
+            &mut config.max_seq_len,
+            self.max_seq_len,
+            is_set("max_seq_len", "max_seq_len"),
+        );
+        apply(
+            &mut config.sample_len,
+            self.sample_len,
+            is_set("sample_len", "max_tokens"),
+        );
+        apply(&mut config.seed, self.seed, is_set("seed", "seed"));
+    }
+
+    /// The server configuration with the defaults applied.
+    pub fn config(&self) -> CheckpointServerConfig {
+        let mut config = CheckpointServerConfig::default();
+        self.apply(&mut config, |_, _| false);
+        config
+    }
+}
+
+/// The files of a Llama checkpoint served by a [CheckpointServer].
+#[derive(Debug, Clone)]
+pub struct CheckpointModel {
+    pub model_name: String,
+    /// A Hugging Face checkpoint directory, a `.safetensors` file or a `.gguf` file.
+    pub weights: PathBuf,
+    /// The Hugging Face `config.json` file, defaults to the one next to the safetensors
+    /// weights. GGUF files embed their configuration.
+    pub config: Option<PathBuf>,
+    pub tokenizer: PathBuf,
+    pub chat_format: ChatFormat,
+    pub defaults: ModelDefaults,
+}
+
+impl CheckpointModel {
+    fn is_gguf(&self) -> bool {
+        self.weights.extension().is_some_and(|ext| ext == "gguf")
+    }
+
+    fn config_file(&self) -> PathBuf {
+        match &self.config {
+            Some(config) => config.clone(),
+            None if self.weights.is_dir() => self.weights.join(HF_CONFIG_FILE),
+            None => self.weights.with_file_name(HF_CONFIG_FILE),
+        }
+    }
+
+    fn tokenizer_path(&self) -> InferenceResult<&str> {
+        self.tokenizer.to_str().ok_or_else(|| {
+            InferenceError::LoadError(format!(
+                "Invalid tokenizer path '{}'",
+                self.tokenizer.display()
+            ))
+        })
+    }
+
+    fn load<T: Tokenizer>(&self, max_seq_len: usize, device: &Device) -> InferenceResult<Llama<T>> {
+        let tokenizer_path = self.tokenizer_path()?;
+        if self.is_gguf() {
+            return self.load_gguf(tokenizer_path, max_seq_len, device);
+        }
+        LlamaConfig::from_hf_config(self.config_file(), tokenizer_path)
+            .and_then(|config| {
+                config
+                    .with_max_seq_len(max_seq_len)
+                    .load_safetensors(&self.weights, device)
+            })
+            .map_err(InferenceError::LoadError)
+    }
+
+    #[cfg(feature = "gguf")]
+    fn load_gguf<T: Tokenizer>(
+        &self,
+        tokenizer_path: &str,
+        max_seq_len: usize,
+        device: &Device,
+    ) -> InferenceResult<Llama<T>> {
+        let file = crate::gguf::GgufFile::open(&self.weights).map_err(InferenceError::LoadError)?;
+        LlamaConfig::from_gguf(&file, tokenizer_path)
+            .and_then(|config| {
+                config
+                    .with_max_seq_len(max_seq_len)
+                    .load_gguf_weights(&file, device)
+            })
+            .map_err(InferenceError::LoadError)
+    }
+
+    #[cfg(not(feature = "gguf"))]
+    fn load_gguf<T: Tokenizer>(
+        &self,
+        _tokenizer_path: &str,
+        _max_seq_len: usize,
+        _device: &Device,
+    ) -> InferenceResult<Llama<T>> {
+        Err(InferenceError::LoadError(format!(
+            "Cannot load '{}', GGUF support requires the `gguf` feature",
+            self.weights.display()
+        )))
+    }
+}
+
+/// Return the chat format matching the chat template of a `tokenizer_config.json` file found
+/// in the given directory, if any.
+pub fn chat_format_from_tokenizer_config<P: AsRef<Path>>(dir: P) -> Option<ChatFormat> {
+    #[derive(Deserialize)]
+    struct ChatTemplateConfig {
+        chat_template: Option<serde_json::Value>,
+    }
+
+    let content = std::fs::read_to_string(dir.as_ref().join(HF_TOKENIZER_CONFIG_FILE)).ok()?;
+    let config: ChatTemplateConfig = serde_json::from_str(&content).ok()?;
+    // The chat template is either a string or a list of named templates
+    match config.chat_template? {
+        serde_json::Value::String(template) => ChatFormat::from_chat_template(&template),
+        templates => ChatFormat::from_chat_template(&templates.to_string()),
+    }
+}
+
+/// Server of a Llama checkpoint only known at runtime, e.g. a model downloaded from the hub or
+/// declared in a models file.
+#[derive(Clone, Debug)]
+pub struct CheckpointServer<T: Tokenizer> {
+    config: CheckpointServerConfig,
+    checkpoint: Option<CheckpointModel>,
+    model: ServerModel<T>,
+}
+
+impl<T: Tokenizer> Default for CheckpointServer<T> {
+    fn default() -> Self {
+        Self {
+            config: Default::default(),
+            checkpoint: None,
+            model: ServerModel::default(),
+        }
+    }
+}
+
+impl<T: Tokenizer> CheckpointServer<T> {
+    pub fn new(checkpoint: CheckpointModel) -> Self {
+        Self {
+            config: checkpoint.defaults.config(),
+            checkpoint: Some(checkpoint),
+            ..Default::default()
+        }
+    }
+
+    fn checkpoint(&self) -> &CheckpointModel {
+        self.checkpoint
+            .as_ref()
+            .expect("should be a checkpoint model")
+    }
+
+    fn model_name(&self) -> String {
+        self.checkpoint().model_name.clone()
+    }
+
+    /// The tokenizer file loaded on its own.
+    fn load_tokenizer(&self) -> impl FnOnce() -> Result<T, String> {
+        let path = self.checkpoint().tokenizer_path().map(str::to_string);
+        move || T::new(&path.map_err(|err| err.to_string())?)
+    }
+}
+
+impl<T: Tokenizer> ServerConfigParsing for CheckpointServer<T> {
+    type Config = CheckpointServerConfig;
+
+    fn parse_cli_config(&mut self, args: &clap::ArgMatches) {
+        self.config = Self::Config::from_arg_matches(args)
+            .expect("Should be able to parse arguments from CLI");
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.defaults.apply(&mut self.config, |arg, _| {
+                args.value_source(arg) == Some(clap::parser::ValueSource::CommandLine)
+            });
+        }
+    }
+
+    fn parse_json_config(&mut self, json: &str) {
+        self.config = serde_json::from_str(json).expect("Should be able to parse JSON");
+        if let Some(checkpoint) = &self.checkpoint {
+            let keys: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(json).unwrap_or_default();
+            checkpoint
+                .defaults
+                .apply(&mut self.config, |_, key| keys.contains_key(key));
+        }
+    }
+}
+
+impl<T: Tokenizer + Debug + 'static> InferenceServer for CheckpointServer<T> {
+    fn is_downloaded(&mut self) -> bool {
+        self.checkpoint
+            .as_ref()
+            .is_some_and(|checkpoint| checkpoint.weights.exists() && checkpoint.tokenizer.exists())
+    }
+
+    fn set_device(&mut self, settings: DeviceSettings) {
+        self.model.device = settings;
+    }
+
+    fn load(&mut self) -> InferenceResult<Option<Stats>> {
+        let checkpoint = self
+            .checkpoint
+            .as_ref()
+            .expect("should be a checkpoint model");
+        let max_seq_len = self.config.max_seq_len;
+        self.model
+            .load(|device| checkpoint.load(max_seq_len, device))
+    }
+
+    fn is_loaded(&mut self) -> bool {
+        self.model.is_loaded()
+    }
+
+    fn unload(&mut self) -> InferenceResult<Option<Stats>> {
+        let model_name = self.model_name();
+        self.model.unload(&model_name)
+    }
+
+    fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
+        let load_stats = self.load()?;
+        let sampling = Sampling {
+            top_p: self.config.top_p,
+            temperature: self.config.temperature,
+            sample_len: self.config.sample_len,
+            seed: self.config.seed,
+        };
+        let prompt = task_prompt(self.checkpoint().chat_format, job.task);
+        self.model
+            .generate(prompt, sampling, job.emitter, load_stats)
+    }
+
+    fn clear_state(&mut self) -> InferenceResult<()> {
+        self.model.clear_state()
+    }
+
+    fn perplexity(&mut self, args: PerplexityArgs) -> InferenceResult<Stats> {
+        let load_stats = self.load()?;
+        self.model.perplexity(&self.model_name(), args, load_stats)
+    }
+
+    fn loglikelihood(
+        &mut self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        self.load()?;
+        self.model.loglikelihood(&self.model_name(), requests)
+    }
+
+    fn tokenize(&mut self, task: InferenceTask) -> InferenceResult<Tokenization> {
+        let prompt = task_prompt(self.checkpoint().chat_format, task);
+        let (model_name, load_tokenizer) = (self.model_name(), self.load_tokenizer());
+        self.model.tokenize(&model_name, prompt, load_tokenizer)
+    }
+
+    fn detokenize(&mut self, tokens: Vec<u32>) -> InferenceResult<String> {
+        let (model_name, load_tokenizer) = (self.model_name(), self.load_tokenizer());
+        self.model.detokenize(&model_name, tokens, load_tokenizer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn defaults() -> ModelDefaults {
+        ModelDefaults {
+            temperature: Some(0.2),
+            sample_len: Some(512),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_model_defaults_config() {
+        let config = defaults().config();
+        assert_eq!(config.temperature, 0.2);
+        assert_eq!(config.sample_len, 512);
+        assert_eq!(config.top_p, CheckpointServerConfig::default_top_p());
+    }
+
+    #[test]
+    fn test_model_defaults_cli_precedence() {
+        let args = CheckpointServerConfig::command()
+            .try_get_matches_from(["model", "--temperature", "0.8"])
+            .unwrap();
+        let mut config = CheckpointServerConfig::from_arg_matches(&args).unwrap();
+        defaults().apply(&mut config, |arg, _| {
+            args.value_source(arg) == Some(clap::parser::ValueSource::CommandLine)
+        });
+
+        assert_eq!(config.temperature, 0.8);
+        assert_eq!(config.sample_len, 512);
+    }
+
+    #[test]
+    fn test_model_defaults_json_precedence() {
+        let json = r#"{"max_tokens": 16}"#;
+        let mut config: CheckpointServerConfig = serde_json::from_str(json).unwrap();
+        let keys: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json).unwrap();
+        defaults().apply(&mut config, |_, key| keys.contains_key(key));
+
+        assert_eq!(config.temperature, 0.2);
+        assert_eq!(config.sample_len, 16);
+    }
+
+    #[test]
+    fn test_chat_format_from_tokenizer_config() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(chat_format_from_tokenizer_config(dir.path()), None);
+
+        std::fs::write(
+            dir.path().join(HF_TOKENIZER_CONFIG_FILE),
+            r#"{"chat_template": [{"name": "default", "template": "<|im_start|>"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            chat_format_from_tokenizer_config(dir.path()),
+            Some(ChatFormat::ChatMl)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{inference::Llama, tokenizer::Tokenizer};
+use burn_lm_inference::*;
+
+/// Evaluate the perplexity of a loaded model and report it as stats.
+pub(crate) fn perplexity<T: Tokenizer>(
+    model: &mut Llama<T>,
+    model_name: &str,
+    args: PerplexityArgs,
+) -> InferenceResult<Stats> {
+    let now = std::time::Instant::now();
+    let output = model
+        .perplexity(&args.text, args.stride)
+        .map_err(|err| InferenceError::EvaluationError(model_name.to_string(), err))?;
+    let duration = now.elapsed();
+
+    let mut stats = Stats::new();
+    stats.entries.extend(vec![
+        StatEntry::Named(
+            "Perplexity".to_string(),
+            format!("{:.4}", output.perplexity()),
+        ),
+        StatEntry::Named(
+            "Bits Per Byte".to_string(),
+            format!("{:.4}", output.bits_per_byte()),
+        ),
+        StatEntry::Named("Windows".to_string(), output.num_windows.to_string()),
+        StatEntry::TokensCount(output.num_tokens),
+        StatEntry::TokensPerSecond(output.num_tokens, duration),
+        StatEntry::InferenceDuration(duration),
+    ]);
+    Ok(stats)
+}
+
+/// Score the log-likelihood of continuations with a loaded model.
+pub(crate) fn loglikelihood<T: Tokenizer>(
+    model: &mut Llama<T>,
+    model_name: &str,
+    requests: Vec<LoglikelihoodRequest>,
+) -> InferenceResult<Vec<Loglikelihood>> {
+    requests
+        .iter()
+        .map(|request| {
+            model
+                .loglikelihood(&request.context, &request.continuation)
+                .map(|output| Loglikelihood {
+                    logprob: output.logprob,
+                    num_tokens: output.num_tokens,
+                })
+                .map_err(|err| InferenceError::EvaluationError(model_name.to_string(), err))
+        })
+        .collect()
+}
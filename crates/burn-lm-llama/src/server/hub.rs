@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use super::checkpoint::{
+    chat_format_from_tokenizer_config, CheckpointModel, CheckpointServer, CheckpointServerConfig,
+    ModelDefaults,
+};
+use crate::{
+    pretrained::cache_dir,
+    safetensors::SafetensorsIndex,
+    tokenizer::{
+        ChatFormat, HfTokenizer, HF_GENERATION_CONFIG_FILE, HF_TOKENIZER_CONFIG_FILE,
+        HF_TOKENIZER_FILE,
+    },
+};
+use burn_lm_inference::{
+    download::{DownloadError, Downloader, DEFAULT_ENDPOINT},
+    *,
+};
+
+/// Prefix of the Hugging Face Hub model identifiers, e.g. `hf:org/repo@revision`.
+pub const HUB_PREFIX: &str = "hf:";
+/// Metadata file describing a model downloaded from the hub.
+const HUB_MODEL_FILE: &str = "model.json";
+const CONFIG_FILE: &str = "config.json";
+const WEIGHTS_FILE: &str = "model.safetensors";
+const WEIGHTS_INDEX_FILE: &str = "model.safetensors.index.json";
+const DEFAULT_REVISION: &str = "main";
+
+/// A model repository of the Hugging Face Hub at a given revision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HubModelId {
+    /// The repository, e.g. `meta-llama/Llama-3.2-1B-Instruct`.
+    pub repo: String,
+    /// The branch, tag or commit.
+    pub revision: String,
+}
+
+impl HubModelId {
+    /// Parse a `hf:<org>/<repo>[@revision]` model identifier.
+    pub fn parse(id: &str) -> Result<Self, String> {
+        let invalid =
+            || format!("Invalid model '{id}', expected {HUB_PREFIX}<org>/<repo>[@revision]");
+        let id_without_prefix = id.strip_prefix(HUB_PREFIX).ok_or_else(invalid)?;
+        let (repo, revision) = id_without_prefix
+            .split_once('@')
+            .unwrap_or((id_without_prefix, DEFAULT_REVISION));
+        let valid_part = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        };
+        match repo.split_once('/') {
+            Some((org, name)) if valid_part(org) && valid_part(name) && !revision.is_empty() => {
+                Ok(Self {
+                    repo: repo.to_string(),
+                    revision: revision.to_string(),
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The local directory of the model in the cache directory.
+    pub fn dir(&self, cache_dir: &Path) -> PathBuf {
+        cache_dir.join("hub").join(format!(
+            "{}--{}",
+            self.repo.replace('/', "--"),
+            self.revision.replace('/', "--")
+        ))
+    }
+
+    /// The URL of a file of the repository.
+    pub fn file_url(&self, file_name: &str) -> String {
+        format!(
+            "{DEFAULT_ENDPOINT}/{}/resolve/{}/{file_name}",
+            self.repo, self.revision
+        )
+    }
+
+    fn model_name(&self) -> String {
+        match self.revision.as_str() {
+            DEFAULT_REVISION => self.repo.clone(),
+            revision => format!("{}@{revision}", self.repo),
+        }
+    }
+
+    fn model_cli_param_name(&self) -> String {
+        self.model_name()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect()
+    }
+}
+
+/// The fields of the model `config.json` identifying its architecture.
+#[derive(Deserialize)]
+struct ArchitectureConfig {
+    model_type: Option<String>,
+    #[serde(default)]
+    architectures: Vec<String>,
+}
+
+/// A Llama-architecture model downloaded from the hub with the `download` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HubModel {
+    pub repo: String,
+    pub revision: String,
+    pub model_name: String,
+    pub model_cli_param_name: String,
+    /// The prompt format guessed from the chat template of the tokenizer.
+    pub chat_format: ChatFormat,
+    /// The safetensors weights files.
+    pub weights: Vec<String>,
+    /// The model directory.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+/// Check that a weights shard listed in a remote index is a file of the model directory.
+fn check_shard_name(name: &str) -> Result<(), String> {
+    let mut components = Path::new(name).components();
+    let is_file_name = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) && !name.contains('\\');
+    if is_file_name && name.ends_with(".safetensors") {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid weights shard '{name}' in {WEIGHTS_INDEX_FILE}, it should be a .safetensors file name"
+        ))
+    }
+}
+
+impl HubModel {
+    /// Download the configuration, tokenizer and weights of a model to its directory.
+    pub fn download(
+        id: &HubModelId,
+        downloader: &Downloader,
+        cache_dir: &Path,
+    ) -> Result<Self, String> {
+        let dir = id.dir(cache_dir);
+        let download = |file_name: &str| {
+            downloader
+                .download(&id.file_url(file_name), &dir, file_name)
+                .map_err(|err| err.to_string())
+        };
+        let download_optional =
+            |file_name: &str| match downloader.download(&id.file_url(file_name), &dir, file_name) {
+                Ok(path) => Ok(Some(path)),
+                Err(DownloadError::NotFound(_)) => Ok(None),
+                Err(err) => Err(err.to_string()),
+            };
+
+        // Check the architecture before downloading the weights
+        let config = read_json::<ArchitectureConfig>(&download(CONFIG_FILE)?)?;
+        let is_llama = config.model_type.as_deref() == Some("llama")
+            || config
+                .architectures
+                .iter()
+                .any(|architecture| architecture == "LlamaForCausalLM");
+        if !is_llama {
+            return Err(format!(
+                "Unsupported architecture for '{}', only Llama models are supported",
+                id.repo
+            ));
+        }
+
+        download(HF_TOKENIZER_FILE)?;
+        download_optional(HF_TOKENIZER_CONFIG_FILE)?;
+        let chat_format = chat_format_from_tokenizer_config(&dir);
+        download_optional(HF_GENERATION_CONFIG_FILE)?;
+
+        let weights = match download_optional(WEIGHTS_INDEX_FILE)? {
+            Some(path) => {
+                let index = read_json::<SafetensorsIndex>(&path)?;
+                let shards = index.shards();
+                shards
+                    .iter()
+                    .try_for_each(|shard| check_shard_name(shard))?;
+                shards
+            }
+            None => vec![WEIGHTS_FILE.to_string()],
+        };
+        for file_name in &weights {
+            download(file_name)?;
+        }
+
+        let model = Self {
+            repo: id.repo.clone(),
+            revision: id.revision.clone(),
+            model_name: id.model_name(),
+            model_cli_param_name: id.model_cli_param_name(),
+            chat_format: chat_format.unwrap_or(ChatFormat::Llama3),
+            weights,
+            dir,
+        };
+        model.save()?;
+        Ok(model)
+    }
+
+    /// Read the model metadata from its directory.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let mut model: Self = read_json(&dir.join(HUB_MODEL_FILE))?;
+        model.dir = dir.to_path_buf();
+        Ok(model)
+    }
+
+    /// Write the model metadata into its directory.
+    pub fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|err| format!("Failed to serialize model metadata.\nError: {err}"))?;
+        std::fs::write(self.dir.join(HUB_MODEL_FILE), content)
+            .map_err(|err| format!("Failed to write model metadata.\nError: {err}"))
+    }
+
+    pub fn is_downloaded(&self) -> bool {
+        [CONFIG_FILE, HF_TOKENIZER_FILE]
+            .into_iter()
+            .chain(self.weights.iter().map(String::as_str))
+            .all(|file_name| self.dir.join(file_name).is_file())
+    }
+
+    /// The checkpoint served by the model plugin.
+    pub fn checkpoint(&self) -> CheckpointModel {
+        CheckpointModel {
+            model_name: self.model_name.clone(),
+            weights: self.dir.clone(),
+            config: None,
+            tokenizer: self.dir.join(HF_TOKENIZER_FILE),
+            chat_format: self.chat_format,
+            defaults: ModelDefaults::default(),
+        }
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read '{}'.\nError: {err}", path.display()))?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Failed to parse '{}'.\nError: {err}", path.display()))
+}
+
+/// Download a `hf:<org>/<repo>[@revision]` model to the model cache.
+pub fn download_hub_model(id: &str) -> InferenceResult<Option<Stats>> {
+    let now = std::time::Instant::now();
+    let error = |reason: String| InferenceError::DownloadError(id.to_string(), reason);
+    let model_id = HubModelId::parse(id).map_err(error)?;
+    let model = HubModel::download(&model_id, &Downloader::new(), &cache_dir()).map_err(error)?;
+
+    let mut stats = Stats::new();
+    stats.entries.extend(vec![
+        StatEntry::Named("Model".to_string(), model.model_cli_param_name),
+        StatEntry::ModelDownloadingDuration(now.elapsed()),
+    ]);
+    Ok(Some(stats))
+}
+
+/// Return the hub models found in the model cache.
+pub fn saved_hub_models() -> Vec<HubModel> {
+    let Ok(entries) = std::fs::read_dir(cache_dir().join("hub")) else {
+        return vec![];
+    };
+    let mut models: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|dir| dir.join(HUB_MODEL_FILE).is_file())
+        .filter_map(|dir| match HubModel::load(&dir) {
+            Ok(model) => Some(model),
+            Err(err) => {
+                tracing::warn!("Skipping hub model '{}': {err}", dir.display());
+                None
+            }
+        })
+        .collect();
+    models.sort_by(|a, b| a.model_name.cmp(&b.model_name));
+    models
+}
+
+/// Registry provider of the hub models found in the model cache.
+pub fn hub_models() -> Vec<Box<dyn InferencePlugin>> {
+    fn leak(value: &str) -> &'static str {
+        Box::leak(value.to_string().into_boxed_str())
+    }
+
+    saved_hub_models()
+        .into_iter()
+        .map(|model| {
+            let plugin: Box<dyn InferencePlugin> = Box::new(InferenceClient::new(
+                leak(&model.model_name),
+                leak(&model.model_cli_param_name),
+                "",
+                leak(&model.repo),
+                CheckpointServerConfig::command,
+                MutexChannel::with_server(CheckpointServer::<HfTokenizer>::new(model.checkpoint())),
+            ));
+            plugin
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn_lm_inference::test_server::{Response, TestServer};
+    use std::collections::HashMap;
+
+    /// Serve the files of a repository over HTTP, other paths are not found.
+    fn serve(files: HashMap<String, String>) -> String {
+        let server = TestServer::start(move |request| match files.get(&request.path) {
+            Some(body) => Response::ok(body.as_str()),
+            None => Response::status(404),
+        });
+        server.url().to_string()
+    }
+
+    fn repo_files(config: &str) -> HashMap<String, String> {
+        let prefix = "/org/tiny-llama/resolve/v1";
+        HashMap::from([
+            (format!("{prefix}/config.json"), config.to_string()),
+            (format!("{prefix}/tokenizer.json"), "{}".to_string()),
+            (
+                format!("{prefix}/tokenizer_config.json"),
+                r#"{"chat_template": "{{ '<|im_start|>' + message['role'] }}"}"#.to_string(),
+            ),
+            (
+                format!("{prefix}/model.safetensors.index.json"),
+                r#"{"weight_map": {"a": "model-00001-of-00002.safetensors", "b": "model-00002-of-00002.safetensors"}}"#
+                    .to_string(),
+            ),
+            (format!("{prefix}/model-00001-of-00002.safetensors"), "1".to_string()),
+            (format!("{prefix}/model-00002-of-00002.safetensors"), "2".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_hub_model_id_parse() {
+        let id = HubModelId::parse("hf:org/repo").unwrap();
+        assert_eq!(id.repo, "org/repo");
+        assert_eq!(id.revision, "main");
+        assert_eq!(id.model_cli_param_name(), "org-repo");
+
+        let id = HubModelId::parse("hf:org/Repo-1.1B@v1.0").unwrap();
+        assert_eq!(id.revision, "v1.0");
+        assert_eq!(id.model_name(), "org/Repo-1.1B@v1.0");
+        assert_eq!(id.model_cli_param_name(), "org-repo-1-1b-v1-0");
+        assert_eq!(
+            id.file_url("config.json"),
+            "https://huggingface.co/org/Repo-1.1B/resolve/v1.0/config.json"
+        );
+
+        assert!(HubModelId::parse("org/repo").is_err());
+        assert!(HubModelId::parse("hf:repo").is_err());
+        assert!(HubModelId::parse("hf:org/repo@").is_err());
+        assert!(HubModelId::parse("hf:org/../repo").is_err());
+    }
+
+    #[test]
+    fn test_hub_model_download() {
+        let mirror = serve(repo_files(r#"{"model_type": "llama"}"#));
+        let cache_dir = tempfile::tempdir().unwrap();
+        let id = HubModelId::parse("hf:org/tiny-llama@v1").unwrap();
+
+        let downloader = Downloader::new().with_mirror(mirror);
+        let model = HubModel::download(&id, &downloader, cache_dir.path()).unwrap();
+
+        assert_eq!(model.chat_format, ChatFormat::ChatMl);
+        assert_eq!(
+            model.weights,
+            vec![
+                "model-00001-of-00002.safetensors",
+                "model-00002-of-00002.safetensors"
+            ]
+        );
+        assert!(model.is_downloaded());
+        assert!(!model.dir.join(HF_GENERATION_CONFIG_FILE).exists());
+
+        let loaded = HubModel::load(id.dir(cache_dir.path())).unwrap();
+        assert_eq!(loaded.model_name, "org/tiny-llama@v1");
+        assert_eq!(loaded.model_cli_param_name, "org-tiny-llama-v1");
+        assert_eq!(loaded.weights, model.weights);
+    }
+
+    #[test]
+    fn test_hub_model_download_rejects_shard_paths() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let id = HubModelId::parse("hf:org/tiny-llama@v1").unwrap();
+        for shard in [
+            "../model.safetensors",
+            "/tmp/model.safetensors",
+            "model.bin",
+        ] {
+            let mut files = repo_files(r#"{"model_type": "llama"}"#);
+            files.insert(
+                "/org/tiny-llama/resolve/v1/model.safetensors.index.json".to_string(),
+                format!(r#"{{"weight_map": {{"a": "{shard}"}}}}"#),
+            );
+            let downloader = Downloader::new().with_mirror(serve(files));
+            let result = HubModel::download(&id, &downloader, cache_dir.path());
+            assert!(
+                result.unwrap_err().contains("Invalid weights shard"),
+                "shard '{shard}' should be rejected"
+            );
+        }
+        assert!(!cache_dir
+            .path()
+            .join("hub")
+            .join("model.safetensors")
+            .exists());
+    }
+
+    #[test]
+    fn test_hub_model_download_unsupported_architecture() {
+        let mirror = serve(repo_files(
+            r#"{"model_type": "gpt2", "architectures": ["GPT2LMHeadModel"]}"#,
+        ));
+        let cache_dir = tempfile::tempdir().unwrap();
+        let id = HubModelId::parse("hf:org/tiny-llama@v1").unwrap();
+
+        let downloader = Downloader::new().with_mirror(mirror);
+        let result = HubModel::download(&id, &downloader, cache_dir.path());
+
+        assert!(result.unwrap_err().contains("Unsupported architecture"));
+        assert!(!id.dir(cache_dir.path()).join(HF_TOKENIZER_FILE).exists());
+    }
+}
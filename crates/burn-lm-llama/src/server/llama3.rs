@@ -1,16 +1,41 @@
-use rand::RngExt;
 use serde::Deserialize;
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
 
 use crate::{
-    generation::{GenerationError, Sampler, TopP},
     inference::Llama,
+    nn::lora::LoraAdapter,
     pretrained::ModelMeta,
-    tokenizer::Tiktoken,
+    tokenizer::{ChatFormat, Tiktoken, Tokenizer},
     LlamaConfig, LlamaVersion,
 };
+use burn::record::{HalfPrecisionSettings, NamedMpkFileRecorder};
 use burn_lm_inference::{InferenceJob, *};
 
+use super::{
+    model::{task_prompt, Sampling, ServerModel},
+    quantized::QuantizedVariant,
+};
+
+/// Directory of the adapter `name` in `adapters_dir`.
+///
+/// Adapter names come from the request configs, they must be plain directory names so that
+/// only the adapters of the model can be loaded.
+fn adapter_dir(adapters_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => {
+            Ok(adapters_dir.join(name))
+        }
+        _ => Err(format!(
+            "invalid adapter name '{name}', it should be a directory name in '{}'",
+            adapters_dir.display()
+        )),
+    }
+}
+
 #[inference_server_config]
 pub struct Llama3ServerConfig {
     /// Top-p probability threshold.
@@ -28,6 +53,11 @@ pub struct Llama3ServerConfig {
     /// The seed to use when generating random samples. If it is 0 then a random seed is used for each inference.
     #[config(default = 0)]
     pub seed: u64,
+    /// The LoRA adapter to apply, the name of a directory in the model adapters directory. No adapter is applied when empty.
+    pub adapter: String,
+    /// Merge the LoRA adapter into the model weights for faster inference. Changing a merged adapter reloads the model.
+    #[config(default = false)]
+    pub merge_adapter: bool,
 }
 
 #[derive(InferenceServer, Clone, Debug)]
@@ -42,16 +72,11 @@ pub struct Llama3InstructServer {
     server: Llama3BaseServer,
 }
 
-impl Default for Llama3InstructServer {
-    fn default() -> Self {
-        Self {
-            config: Llama3ServerConfig::default(),
-            server: Llama3BaseServer::new(LlamaVersion::Llama3Instruct),
-        }
-    }
-}
-
-fn llama_downloader(version: LlamaVersion, name: &'static str) -> InferenceResult<Option<Stats>> {
+fn llama_downloader(
+    version: LlamaVersion,
+    name: &'static str,
+    settings: DeviceSettings,
+) -> InferenceResult<Option<Stats>> {
     let now = std::time::Instant::now();
     let model = LlamaVersion::pretrained(&version);
     model
@@ -60,6 +85,16 @@ fn llama_downloader(version: LlamaVersion, name: &'static str) -> InferenceResul
     model
         .download_tokenizer()
         .map_err(|err| InferenceError::DownloadError(name.to_string(), err.to_string()))?;
+    // The weights are converted once so that they are memory-mapped when loading the model
+    #[cfg(feature = "safetensors")]
+    {
+        let error = |reason: String| InferenceError::DownloadError(name.to_string(), reason);
+        version
+            .convert_weights(&settings.device().map_err(error)?)
+            .map_err(error)?;
+    }
+    #[cfg(not(feature = "safetensors"))]
+    let _ = settings;
     let mut stats = Stats::new();
     stats
         .entries
@@ -78,121 +113,368 @@ fn llama_deleter(version: LlamaVersion, name: &'static str) -> InferenceResult<O
     Ok(None)
 }
 
-impl InferenceServer for Llama3InstructServer {
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn downloader() -> InferenceResult<Option<Stats>> {
-            llama_downloader(
-                LlamaVersion::Llama3Instruct,
-                Llama3InstructServer::model_name(),
-            )
-        }
-        Some(downloader)
+const QUANTIZATION_MAX_SEQ_LEN: usize = 128;
+
+fn llama_quantizer(
+    version: LlamaVersion,
+    name: &'static str,
+    variant: QuantizedVariant,
+    settings: DeviceSettings,
+    scheme: QuantizationScheme,
+) -> InferenceResult<Option<Stats>> {
+    let now = std::time::Instant::now();
+    let error = |reason: String| InferenceError::QuantizationError(name.to_string(), reason);
+    let model = version.pretrained();
+    if !model.is_downloaded() {
+        return Err(error("the model has not been downloaded".to_string()));
     }
+    let checkpoint = model
+        .download_weights()
+        .map_err(|err| error(err.to_string()))?;
+    let tokenizer = model
+        .download_tokenizer()
+        .map_err(|err| error(err.to_string()))?;
+
+    // The weights are quantized layer by layer to limit the peak memory usage.
+    // The sequence length only sizes the key-value cache which is not saved.
+    let llama = version
+        .load(
+            checkpoint.to_str().unwrap(),
+            tokenizer.to_str().unwrap(),
+            QUANTIZATION_MAX_SEQ_LEN,
+            &settings.device().map_err(error)?,
+        )
+        .map_err(error)?
+        .quantize(scheme.quant_scheme());
+
+    std::fs::create_dir_all(&variant.dir).map_err(|err| error(err.to_string()))?;
+    std::fs::copy(&tokenizer, variant.tokenizer()).map_err(|err| error(err.to_string()))?;
+    let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
+    llama
+        .save(variant.checkpoint().to_str().unwrap(), &recorder)
+        .map_err(|err| error(err.to_string()))?;
+    variant.save().map_err(error)?;
 
-    fn is_downloaded(&mut self) -> bool {
-        let model = LlamaVersion::Llama3Instruct.pretrained();
-        model.is_downloaded()
+    let mut stats = Stats::new();
+    stats.entries.extend(vec![
+        StatEntry::Named("Quantized Model".to_string(), variant.model_name.clone()),
+        StatEntry::Named("Quantization Scheme".to_string(), variant.scheme.clone()),
+        StatEntry::TotalDuration(now.elapsed()),
+    ]);
+    Ok(Some(stats))
+}
+
+#[cfg(feature = "training")]
+fn llama_trainer(
+    version: LlamaVersion,
+    name: &'static str,
+    variant: QuantizedVariant,
+    settings: DeviceSettings,
+    args: TrainingArgs,
+) -> InferenceResult<Option<Stats>> {
+    use crate::nn::{
+        llama::{
+            dataset::{read_conversations, read_documents, ChatDataset, LlamaBatcher, TextDataset},
+            train::{last_checkpoint, train, LlamaTrainingConfig, LrSchedule},
+        },
+        lora::LoraConfig,
+    };
+    use burn::module::Module;
+
+    let now = std::time::Instant::now();
+    let error = |reason: String| InferenceError::TrainingError(name.to_string(), reason);
+    args.validate().map_err(error)?;
+    let model = version.pretrained();
+    if !model.is_downloaded() {
+        return Err(error("the model has not been downloaded".to_string()));
     }
+    let checkpoint = model
+        .download_weights()
+        .map_err(|err| error(err.to_string()))?;
+    let tokenizer = model
+        .download_tokenizer()
+        .map_err(|err| error(err.to_string()))?;
+
+    let mut llama = version
+        .load(
+            checkpoint.to_str().unwrap(),
+            tokenizer.to_str().unwrap(),
+            args.seq_len,
+            &settings.device().map_err(error)?,
+        )
+        .map_err(error)?;
+    if let Some(scheme) = args.qlora {
+        llama = llama.quantize(scheme.quant_scheme());
+    }
+
+    let lora = args.lora_rank.map(|rank| {
+        LoraConfig::new()
+            .with_rank(rank)
+            .with_alpha(args.lora_alpha)
+    });
+    let config = LlamaTrainingConfig::fine_tuning()
+        .with_seq_len(args.seq_len)
+        .with_batch_size(args.batch_size)
+        .with_num_epochs(args.num_epochs)
+        .with_learning_rate(args.learning_rate)
+        .with_lr_schedule(match args.lr_schedule {
+            burn_lm_inference::LrSchedule::Constant => LrSchedule::Constant,
+            burn_lm_inference::LrSchedule::Linear => LrSchedule::Linear,
+            burn_lm_inference::LrSchedule::Cosine => LrSchedule::Cosine,
+        })
+        .with_warmup_steps(args.warmup_steps)
+        .with_grad_accumulation(args.grad_accumulation)
+        .with_seed(args.seed)
+        .with_lora(lora.clone());
+    // Adapters are saved next to the base model adapters instead of a new variant
+    let adapter_dir = adapter_dir(&model.adapters_dir(), &args.name).map_err(error)?;
+    let artifact_dir = match &lora {
+        Some(_) => adapter_dir.join("training"),
+        None if variant.is_saved() => {
+            return Err(error(format!(
+                "the model variant '{}' already exists, delete it or choose another name",
+                variant.model_cli_param_name
+            )));
+        }
+        None => variant.training_dir(),
+    };
+    let resume_from = match args.resume {
+        true => Some(
+            last_checkpoint(&artifact_dir)
+                .ok_or_else(|| error("no checkpoint to resume the training from".to_string()))?,
+        ),
+        false => None,
+    };
+
+    let (trained, num_sequences) = if args.chat {
+        let conversations =
+            read_conversations(&args.dataset, &args.messages_field).map_err(error)?;
+        let (dataset_train, dataset_valid) = ChatDataset::new(
+            &conversations,
+            &llama.tokenizer,
+            ChatFormat::Llama3,
+            args.seq_len,
+        )
+        .split(args.validation_ratio);
+        let num_sequences = dataset_train.len();
+        let trained = train(
+            llama,
+            dataset_train,
+            dataset_valid,
+            LlamaBatcher,
+            &config,
+            &artifact_dir,
+            resume_from,
+        );
+        (trained, num_sequences)
+    } else {
+        let documents = read_documents(&args.dataset, &args.text_field).map_err(error)?;
+        let (dataset_train, dataset_valid) =
+            TextDataset::new(&documents, &llama.tokenizer, args.seq_len, args.packing)
+                .split(args.validation_ratio);
+        let num_sequences = dataset_train.len();
+        let trained = train(
+            llama,
+            dataset_train,
+            dataset_valid,
+            LlamaBatcher,
+            &config,
+            &artifact_dir,
+            resume_from,
+        );
+        (trained, num_sequences)
+    };
+    let trained = trained.map_err(error)?;
 
-    fn deleter(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn deleter() -> InferenceResult<Option<Stats>> {
-            llama_deleter(
-                LlamaVersion::Llama3Instruct,
-                Llama3InstructServer::model_name(),
-            )
+    let mut stats = Stats::new();
+    match lora {
+        Some(lora) => {
+            // Only the adapter is saved, it is applied with the `adapter` server config
+            let adapter = trained
+                .adapter()
+                .ok_or_else(|| error("the trained model has no adapter".to_string()))?;
+            adapter.save(&adapter_dir, &lora).map_err(error)?;
+            stats.entries.insert(StatEntry::Named(
+                "Trained Adapter".to_string(),
+                args.name.clone(),
+            ));
+        }
+        None => {
+            // The trained weights are saved like the pre-trained ones so that the variant is
+            // loaded by the inference servers.
+            std::fs::copy(&tokenizer, variant.tokenizer()).map_err(|err| error(err.to_string()))?;
+            let recorder = NamedMpkFileRecorder::<HalfPrecisionSettings>::new();
+            trained
+                .save_file(variant.checkpoint(), &recorder)
+                .map_err(|err| error(err.to_string()))?;
+            variant.save().map_err(error)?;
+            stats.entries.insert(StatEntry::Named(
+                "Trained Model".to_string(),
+                variant.model_name.clone(),
+            ));
         }
-        Some(deleter)
     }
+    stats.entries.extend(vec![
+        StatEntry::Named("Training Sequences".to_string(), num_sequences.to_string()),
+        StatEntry::TotalDuration(now.elapsed()),
+    ]);
+    Ok(Some(stats))
+}
 
-    fn load(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.load(&self.config)
-    }
+/// Preferred float type of the 8B models, their weights are trained in bf16 which takes half
+/// the memory of f32.
+const BF16_TRAINED_DTYPE: Option<FloatDtype> = Some(FloatDtype::Bf16);
 
-    fn is_loaded(&mut self) -> bool {
-        self.server.is_loaded()
-    }
+/// Preferred float type of the quantized models, the dequantized activations lose precision in
+/// half float types.
+const QUANTIZED_DTYPE: Option<FloatDtype> = Some(FloatDtype::F32);
 
-    fn unload(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.unload(Self::model_name())
-    }
+/// Implement the [InferenceServer] hooks which run on the base server of a Llama 3 server.
+macro_rules! base_server_hooks {
+    () => {
+        fn set_device(&mut self, settings: DeviceSettings) {
+            self.server.set_device(settings);
+        }
 
-    fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
-        self.server.run_job(job, &self.config)
-    }
+        fn load(&mut self) -> InferenceResult<Option<Stats>> {
+            self.server.load(&self.config)
+        }
 
-    fn clear_state(&mut self) -> InferenceResult<()> {
-        self.server.clear_state()
-    }
-}
+        fn is_loaded(&mut self) -> bool {
+            self.server.is_loaded()
+        }
 
-#[derive(InferenceServer, Clone, Debug)]
-#[inference_server(
-    model_name = "Llama 3.1 (8B Instruct)",
-    model_cli_param_name = "llama31",
-    model_creation_date = "2024/07/23",
-    created_by = "Meta"
-)]
-pub struct Llama31InstructServer {
-    config: Llama3ServerConfig,
-    server: Llama3BaseServer,
-}
+        fn unload(&mut self) -> InferenceResult<Option<Stats>> {
+            self.server.unload()
+        }
 
-impl Default for Llama31InstructServer {
-    fn default() -> Self {
-        Self {
-            config: Llama3ServerConfig::default(),
-            server: Llama3BaseServer::new(LlamaVersion::Llama31Instruct),
+        fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
+            self.server.run_job(job, &self.config)
         }
-    }
-}
 
-impl InferenceServer for Llama31InstructServer {
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn downloader() -> InferenceResult<Option<Stats>> {
-            llama_downloader(
-                LlamaVersion::Llama31Instruct,
-                Llama31InstructServer::model_name(),
-            )
+        fn clear_state(&mut self) -> InferenceResult<()> {
+            self.server.clear_state()
         }
-        Some(downloader)
-    }
 
-    fn is_downloaded(&mut self) -> bool {
-        let model = LlamaVersion::Llama31Instruct.pretrained();
-        model.is_downloaded()
-    }
+        fn perplexity(&mut self, args: PerplexityArgs) -> InferenceResult<Stats> {
+            self.server.perplexity(args, &self.config)
+        }
 
-    fn deleter(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn deleter() -> InferenceResult<Option<Stats>> {
-            llama_deleter(
-                LlamaVersion::Llama31Instruct,
-                Llama31InstructServer::model_name(),
-            )
+        fn loglikelihood(
+            &mut self,
+            requests: Vec<LoglikelihoodRequest>,
+        ) -> InferenceResult<Vec<Loglikelihood>> {
+            self.server.loglikelihood(requests, &self.config)
         }
-        Some(deleter)
-    }
 
-    fn load(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.load(&self.config)
-    }
+        fn tokenize(&mut self, task: InferenceTask) -> InferenceResult<Tokenization> {
+            self.server.tokenize(task)
+        }
 
-    fn is_loaded(&mut self) -> bool {
-        self.server.is_loaded()
-    }
+        fn detokenize(&mut self, tokens: Vec<u32>) -> InferenceResult<String> {
+            self.server.detokenize(tokens)
+        }
+    };
+}
 
-    fn unload(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.unload(Self::model_name())
-    }
+/// Implement [Default] and [InferenceServer] for the server of a pre-trained Llama 3 version,
+/// with the given preferred float type and the `quantizer` and `trainer` hooks when listed.
+macro_rules! pretrained_llama3_server {
+    ($server:ident, $version:expr, $dtype:expr, [$($hook:ident),*]) => {
+        impl Default for $server {
+            fn default() -> Self {
+                Self {
+                    config: Llama3ServerConfig::default(),
+                    server: Llama3BaseServer::new(Self::model_name(), $version),
+                }
+            }
+        }
 
-    fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
-        self.server.run_job(job, &self.config)
-    }
+        impl InferenceServer for $server {
+            const PREFERRED_DTYPE: Option<FloatDtype> = $dtype;
 
-    fn clear_state(&mut self) -> InferenceResult<()> {
-        self.server.clear_state()
-    }
+            fn downloader(
+                &mut self,
+            ) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
+                Some(|settings| llama_downloader($version, Self::model_name(), settings))
+            }
+
+            fn is_downloaded(&mut self) -> bool {
+                $version.pretrained().is_downloaded()
+            }
+
+            fn deleter(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+                Some(|| llama_deleter($version, Self::model_name()))
+            }
+
+            $(pretrained_llama3_server!(@$hook $version);)*
+
+            base_server_hooks!();
+        }
+    };
+    (@quantizer $version:expr) => {
+        fn quantizer(
+            &mut self,
+        ) -> Option<fn(DeviceSettings, QuantizationScheme) -> InferenceResult<Option<Stats>>> {
+            Some(|settings, scheme| {
+                let variant = QuantizedVariant::new(
+                    Self::model_name(),
+                    Self::model_cli_param_name(),
+                    Self::model_creation_date(),
+                    Self::created_by(),
+                    scheme,
+                    $version.pretrained().variant_dir(&scheme.to_string()),
+                );
+                llama_quantizer($version, Self::model_name(), variant, settings, scheme)
+            })
+        }
+    };
+    (@trainer $version:expr) => {
+        #[cfg(feature = "training")]
+        fn trainer(
+            &mut self,
+        ) -> Option<fn(DeviceSettings, TrainingArgs) -> InferenceResult<Option<Stats>>> {
+            Some(|settings, args| {
+                let variant = QuantizedVariant::trained(
+                    Self::model_name(),
+                    Self::model_cli_param_name(),
+                    Self::model_creation_date(),
+                    Self::created_by(),
+                    &args.name,
+                    $version.pretrained().variant_dir(&args.name),
+                );
+                llama_trainer($version, Self::model_name(), variant, settings, args)
+            })
+        }
+    };
 }
 
+pretrained_llama3_server!(
+    Llama3InstructServer,
+    LlamaVersion::Llama3Instruct,
+    BF16_TRAINED_DTYPE,
+    [quantizer, trainer]
+);
+
+#[derive(InferenceServer, Clone, Debug)]
+#[inference_server(
+    model_name = "Llama 3.1 (8B Instruct)",
+    model_cli_param_name = "llama31",
+    model_creation_date = "2024/07/23",
+    created_by = "Meta"
+)]
+pub struct Llama31InstructServer {
+    config: Llama3ServerConfig,
+    server: Llama3BaseServer,
+}
+
+pretrained_llama3_server!(
+    Llama31InstructServer,
+    LlamaVersion::Llama31Instruct,
+    BF16_TRAINED_DTYPE,
+    [quantizer, trainer]
+);
+
 #[derive(InferenceServer, Clone, Debug)]
 #[inference_server(
     model_name = "Llama 3.2 (1B Instruct)",
@@ -205,61 +487,12 @@ pub struct Llama321bInstructServer {
     server: Llama3BaseServer,
 }
 
-impl Default for Llama321bInstructServer {
-    fn default() -> Self {
-        Self {
-            config: Llama3ServerConfig::default(),
-            server: Llama3BaseServer::new(LlamaVersion::Llama321bInstruct),
-        }
-    }
-}
-
-impl InferenceServer for Llama321bInstructServer {
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn downloader() -> InferenceResult<Option<Stats>> {
-            llama_downloader(
-                LlamaVersion::Llama321bInstruct,
-                Llama321bInstructServer::model_name(),
-            )
-        }
-        Some(downloader)
-    }
-
-    fn is_downloaded(&mut self) -> bool {
-        let model = LlamaVersion::Llama321bInstruct.pretrained();
-        model.is_downloaded()
-    }
-
-    fn deleter(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn deleter() -> InferenceResult<Option<Stats>> {
-            llama_deleter(
-                LlamaVersion::Llama321bInstruct,
-                Llama321bInstructServer::model_name(),
-            )
-        }
-        Some(deleter)
-    }
-
-    fn load(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.load(&self.config)
-    }
-
-    fn is_loaded(&mut self) -> bool {
-        self.server.is_loaded()
-    }
-
-    fn unload(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.unload(Self::model_name())
-    }
-
-    fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
-        self.server.run_job(job, &self.config)
-    }
-
-    fn clear_state(&mut self) -> InferenceResult<()> {
-        self.server.clear_state()
-    }
-}
+pretrained_llama3_server!(
+    Llama321bInstructServer,
+    LlamaVersion::Llama321bInstruct,
+    None,
+    [quantizer, trainer]
+);
 
 #[derive(InferenceServer, Clone, Debug)]
 #[inference_server(
@@ -273,61 +506,12 @@ pub struct Llama323bInstructServer {
     server: Llama3BaseServer,
 }
 
-impl Default for Llama323bInstructServer {
-    fn default() -> Self {
-        Self {
-            config: Llama3ServerConfig::default(),
-            server: Llama3BaseServer::new(LlamaVersion::Llama323bInstruct),
-        }
-    }
-}
-
-impl InferenceServer for Llama323bInstructServer {
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn downloader() -> InferenceResult<Option<Stats>> {
-            llama_downloader(
-                LlamaVersion::Llama323bInstruct,
-                Llama323bInstructServer::model_name(),
-            )
-        }
-        Some(downloader)
-    }
-
-    fn is_downloaded(&mut self) -> bool {
-        let model = LlamaVersion::Llama323bInstruct.pretrained();
-        model.is_downloaded()
-    }
-
-    fn deleter(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn deleter() -> InferenceResult<Option<Stats>> {
-            llama_deleter(
-                LlamaVersion::Llama323bInstruct,
-                Llama323bInstructServer::model_name(),
-            )
-        }
-        Some(deleter)
-    }
-
-    fn load(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.load(&self.config)
-    }
-
-    fn is_loaded(&mut self) -> bool {
-        self.server.is_loaded()
-    }
-
-    fn unload(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.unload(Self::model_name())
-    }
-
-    fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
-        self.server.run_job(job, &self.config)
-    }
-
-    fn clear_state(&mut self) -> InferenceResult<()> {
-        self.server.clear_state()
-    }
-}
+pretrained_llama3_server!(
+    Llama323bInstructServer,
+    LlamaVersion::Llama323bInstruct,
+    None,
+    [quantizer, trainer]
+);
 
 #[derive(InferenceServer, Clone, Debug)]
 #[inference_server(
@@ -341,94 +525,99 @@ pub struct Llama321bInstructQ4Server {
     server: Llama3BaseServer,
 }
 
-impl Default for Llama321bInstructQ4Server {
-    fn default() -> Self {
+pretrained_llama3_server!(
+    Llama321bInstructQ4Server,
+    LlamaVersion::Llama321bInstructQ4FB32,
+    QUANTIZED_DTYPE,
+    []
+);
+
+/// Server of a model variant produced by the `quantize` or `train` commands, trained variants
+/// run with the float type of their base model.
+///
+/// Variants are discovered in the model cache at runtime, see
+/// [quantized_variants](super::quantized::quantized_variants).
+#[derive(Clone, Debug, Default)]
+pub struct Llama3QuantizedServer {
+    config: Llama3ServerConfig,
+    server: Llama3BaseServer,
+}
+
+impl Llama3QuantizedServer {
+    pub fn new(variant: QuantizedVariant) -> Self {
+        let version = variant.version().unwrap_or_default();
         Self {
             config: Llama3ServerConfig::default(),
-            server: Llama3BaseServer::new(LlamaVersion::Llama321bInstructQ4FB32),
+            server: Llama3BaseServer::with_variant(version, variant),
         }
     }
 }
 
-impl InferenceServer for Llama321bInstructQ4Server {
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn downloader() -> InferenceResult<Option<Stats>> {
-            llama_downloader(
-                LlamaVersion::Llama321bInstructQ4FB32,
-                Llama321bInstructServer::model_name(),
-            )
-        }
-        Some(downloader)
-    }
-
-    fn is_downloaded(&mut self) -> bool {
-        let model = LlamaVersion::Llama321bInstructQ4FB32.pretrained();
-        model.is_downloaded()
-    }
+impl ServerConfigParsing for Llama3QuantizedServer {
+    type Config = Llama3ServerConfig;
 
-    fn deleter(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        fn deleter() -> InferenceResult<Option<Stats>> {
-            llama_deleter(
-                LlamaVersion::Llama321bInstructQ4FB32,
-                Llama321bInstructServer::model_name(),
-            )
-        }
-        Some(deleter)
+    fn parse_cli_config(&mut self, args: &clap::ArgMatches) {
+        self.config = Self::Config::from_arg_matches(args)
+            .expect("Should be able to parse arguments from CLI");
     }
 
-    fn load(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.load(&self.config)
-    }
-
-    fn is_loaded(&mut self) -> bool {
-        self.server.is_loaded()
+    fn parse_json_config(&mut self, json: &str) {
+        self.config = serde_json::from_str(json).expect("Should be able to parse JSON");
     }
+}
 
-    fn unload(&mut self) -> InferenceResult<Option<Stats>> {
-        self.server.unload(Self::model_name())
-    }
+impl InferenceServer for Llama3QuantizedServer {
+    const PREFERRED_DTYPE: Option<FloatDtype> = QUANTIZED_DTYPE;
 
-    fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
-        self.server.run_job(job, &self.config)
+    fn is_downloaded(&mut self) -> bool {
+        self.server.variant.as_ref().is_some_and(|v| v.is_saved())
     }
 
-    fn clear_state(&mut self) -> InferenceResult<()> {
-        self.server.clear_state()
-    }
+    base_server_hooks!();
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Llama3BaseServer {
-    model: Option<Arc<Mutex<Llama<Tiktoken>>>>,
+    model_name: String,
+    model: ServerModel<Tiktoken>,
     version: LlamaVersion,
+    variant: Option<QuantizedVariant>,
+    /// Loaded LoRA adapters by name.
+    adapters: HashMap<String, LoraAdapter>,
+    /// The LoRA adapter currently applied to the model.
+    adapter: Option<String>,
+    /// Whether the current adapter is merged into the model weights.
+    adapter_merged: bool,
 }
 
 impl Llama3BaseServer {
-    pub fn new(version: LlamaVersion) -> Self {
+    pub fn new(model_name: &str, version: LlamaVersion) -> Self {
         Self {
-            model: None,
+            model_name: model_name.to_string(),
             version,
+            ..Default::default()
         }
     }
 
-    fn unload(&mut self, model_name: &str) -> InferenceResult<Option<Stats>> {
-        if let Some(arc_model) = self.model.take() {
-            match Arc::try_unwrap(arc_model) {
-                Ok(mutex) => {
-                    let model = mutex
-                        .into_inner()
-                        .expect("should be able to extract model from mutex");
-                    drop(model);
-                }
-                Err(_) => {
-                    return Err(InferenceError::UnloadError(
-                        model_name.to_string(),
-                        "Multiple references exist".to_string(),
-                    ))
-                }
-            }
+    pub fn with_variant(version: LlamaVersion, variant: QuantizedVariant) -> Self {
+        Self {
+            model_name: variant.model_name.clone(),
+            version,
+            variant: Some(variant),
+            ..Default::default()
         }
-        Ok(None)
+    }
+
+    /// Select the device of the model, used on the next load.
+    pub fn set_device(&mut self, settings: DeviceSettings) {
+        self.model.device = settings;
+    }
+
+    fn unload(&mut self) -> InferenceResult<Option<Stats>> {
+        self.adapters.clear();
+        self.adapter = None;
+        self.adapter_merged = false;
+        self.model.unload(&self.model_name)
     }
 
     fn run_job(
@@ -436,142 +625,212 @@ impl Llama3BaseServer {
         job: InferenceJob,
         config: &Llama3ServerConfig,
     ) -> InferenceResult<Stats> {
-        let prompt = match job.task {
-            InferenceTask::Message(message) => self.prompt(vec![message])?,
-            InferenceTask::Context(messages) => self.prompt(messages)?,
-            InferenceTask::Prompt(prompt) => prompt,
+        let load_stats = self.load(config)?;
+        let sampling = Sampling {
+            top_p: config.top_p,
+            temperature: config.temperature,
+            sample_len: config.sample_len,
+            seed: config.seed,
         };
-        self.complete(prompt, config, job.emitter)
+        let prompt = task_prompt(ChatFormat::Llama3, job.task);
+        self.model
+            .generate(prompt, sampling, job.emitter, load_stats)
+    }
+
+    fn clear_state(&mut self) -> InferenceResult<()> {
+        self.model.clear_state()
     }
 
-    fn complete(
+    fn perplexity(
         &mut self,
-        prompt: Prompt,
+        args: PerplexityArgs,
         config: &Llama3ServerConfig,
-        emitter: GeneratedItemEmitter,
     ) -> InferenceResult<Stats> {
         let load_stats = self.load(config)?;
-        let seed = match config.seed {
-            0 => rand::rng().random::<u64>(),
-            s => s,
-        };
-        let mut sampler = if config.temperature > 0.0 {
-            Sampler::TopP(TopP::new(config.top_p, seed))
-        } else {
-            Sampler::Argmax
-        };
-        let generated = match &self.model {
-            Some(arc_model) => {
-                let mut model = arc_model
-                    .lock()
-                    .expect("should lock the model for inference");
-                match model.generate(
-                    &prompt,
-                    config.sample_len,
-                    config.temperature,
-                    &mut sampler,
-                    emitter,
-                ) {
-                    Ok(result) => result,
-                    Err(GenerationError::MaxSequenceLengthExceeded { actual, max }) => {
-                        return Err(InferenceError::ContextLengthExceeded(actual, max));
-                    }
-                }
-            }
-            None => return Err(InferenceError::ModelNotLoaded),
-        };
-        let mut stats = Stats::default();
-        let mut total_duration = generated.time;
-        stats.entries.extend(vec![
-            StatEntry::InferenceDuration(generated.time),
-            StatEntry::TokensCount(generated.tokens),
-            StatEntry::TokensPerSecond(generated.tokens, generated.time),
-        ]);
-        if let Some(load_stats) = load_stats {
-            let model_loading = load_stats
-                .entries
-                .iter()
-                .find(|e| matches!(e, StatEntry::ModelLoadingDuration(_)));
-            if let Some(model_stats) = model_loading {
-                total_duration += model_stats
-                    .get_duration()
-                    .expect("should be a ModelLoadingDuration stat")
-            }
-            stats.entries.extend(load_stats.entries);
-        }
-        stats
-            .entries
-            .insert(StatEntry::TotalDuration(total_duration));
-        Ok(stats)
+        self.model.perplexity(&self.model_name, args, load_stats)
     }
 
-    fn clear_state(&mut self) -> InferenceResult<()> {
-        match &self.model {
-            Some(arc_model) => {
-                let mut model = arc_model
-                    .lock()
-                    .expect("should lock the model for inference");
-                model.reset();
-                Ok(())
-            }
-            None => Err(InferenceError::ModelNotLoaded),
+    fn loglikelihood(
+        &mut self,
+        requests: Vec<LoglikelihoodRequest>,
+        config: &Llama3ServerConfig,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        self.load(config)?;
+        self.model.loglikelihood(&self.model_name, requests)
+    }
+
+    fn tokenize(&mut self, task: InferenceTask) -> InferenceResult<Tokenization> {
+        let prompt = task_prompt(ChatFormat::Llama3, task);
+        let load_tokenizer = self.load_tokenizer();
+        self.model
+            .tokenize(&self.model_name, prompt, load_tokenizer)
+    }
+
+    fn detokenize(&mut self, tokens: Vec<u32>) -> InferenceResult<String> {
+        let load_tokenizer = self.load_tokenizer();
+        self.model
+            .detokenize(&self.model_name, tokens, load_tokenizer)
+    }
+
+    /// The tokenizer file loaded on its own.
+    fn load_tokenizer(&self) -> impl FnOnce() -> Result<Tiktoken, String> {
+        let (version, variant) = (self.version.clone(), self.variant.clone());
+        move || {
+            let path = match variant {
+                Some(variant) => variant.tokenizer(),
+                None => version
+                    .pretrained()
+                    .download_tokenizer()
+                    .map_err(|err| err.to_string())?,
+            };
+            Tiktoken::new(path.to_str().unwrap())
         }
     }
 
     fn load(&mut self, config: &Llama3ServerConfig) -> InferenceResult<Option<Stats>> {
-        if !self.is_loaded() {
-            let now = std::time::Instant::now();
-            let model = match self.version {
-                LlamaVersion::Llama3Instruct => {
-                    LlamaConfig::llama3_8b_pretrained(config.max_seq_len, &*INFERENCE_DEVICE)
-                        .unwrap()
+        let mut stats = self.load_base(config)?;
+        if let Some(adapter_stats) = self.apply_adapter(config)? {
+            stats
+                .get_or_insert_with(Stats::new)
+                .entries
+                .extend(adapter_stats.entries);
+        }
+        Ok(stats)
+    }
+
+    fn load_base(&mut self, config: &Llama3ServerConfig) -> InferenceResult<Option<Stats>> {
+        let (variant, version) = (&self.variant, &self.version);
+        let max_seq_len = config.max_seq_len;
+        self.model.load(|device| {
+            Ok(match (variant, version) {
+                (Some(variant), version) => version
+                    .load(
+                        variant.checkpoint().to_str().unwrap(),
+                        variant.tokenizer().to_str().unwrap(),
+                        max_seq_len,
+                        device,
+                    )
+                    .map_err(InferenceError::LoadError)?,
+                (None, LlamaVersion::Llama3Instruct) => {
+                    LlamaConfig::llama3_8b_pretrained(max_seq_len, device).unwrap()
                 }
-                LlamaVersion::Llama31Instruct => {
-                    LlamaConfig::llama3_1_8b_pretrained(config.max_seq_len, &*INFERENCE_DEVICE)
-                        .unwrap()
+                (None, LlamaVersion::Llama31Instruct) => {
+                    LlamaConfig::llama3_1_8b_pretrained(max_seq_len, device).unwrap()
                 }
-                LlamaVersion::Llama323bInstruct => {
-                    LlamaConfig::llama3_2_3b_pretrained(config.max_seq_len, &*INFERENCE_DEVICE)
-                        .unwrap()
+                (None, LlamaVersion::Llama323bInstruct) => {
+                    LlamaConfig::llama3_2_3b_pretrained(max_seq_len, device).unwrap()
                 }
-                LlamaVersion::Llama321bInstruct => {
-                    LlamaConfig::llama3_2_1b_pretrained(config.max_seq_len, &*INFERENCE_DEVICE)
-                        .unwrap()
+                (None, LlamaVersion::Llama321bInstruct) => {
+                    LlamaConfig::llama3_2_1b_pretrained(max_seq_len, device).unwrap()
                 }
-                LlamaVersion::Llama321bInstructQ4FB32 => {
-                    LlamaConfig::llama3_2_1b_pretrained_q4(config.max_seq_len, &*INFERENCE_DEVICE)
-                        .unwrap()
+                (None, LlamaVersion::Llama321bInstructQ4FB32) => {
+                    LlamaConfig::llama3_2_1b_pretrained_q4(max_seq_len, device).unwrap()
                 }
-            };
-            self.model = Some(Arc::new(Mutex::new(model)));
-            let mut stats = Stats::new();
-            stats
-                .entries
-                .insert(StatEntry::ModelLoadingDuration(now.elapsed()));
-            Ok(Some(stats))
-        } else {
-            Ok(None)
-        }
+            })
+        })
     }
 
     fn is_loaded(&mut self) -> bool {
-        self.model.is_some()
+        self.model.is_loaded()
     }
 
-    fn prompt(
-        &self,
-        messages: Vec<burn_lm_inference::message::Message>,
-    ) -> InferenceResult<burn_lm_inference::Prompt> {
-        let mut prompt: Vec<String> = vec![];
-        for message in messages {
-            prompt.push(format!(
-                "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
-                message.role.to_string().to_lowercase(),
-                message.content
-            ));
+    fn adapters_dir(&self) -> PathBuf {
+        match &self.variant {
+            Some(variant) => variant.adapters_dir(),
+            None => self.version.pretrained().adapters_dir(),
+        }
+    }
+
+    /// Apply the LoRA adapter selected in the config, if it differs from the current one.
+    fn apply_adapter(&mut self, config: &Llama3ServerConfig) -> InferenceResult<Option<Stats>> {
+        let requested = (!config.adapter.is_empty()).then(|| config.adapter.clone());
+        let merge = requested.is_some() && config.merge_adapter;
+        if requested == self.adapter && merge == self.adapter_merged {
+            return Ok(None);
+        }
+
+        let mut stats = Stats::new();
+        // A merged adapter has modified the base weights which must be reloaded
+        if self.adapter_merged {
+            self.model.unload(&self.model_name)?;
+            self.adapter = None;
+            self.adapter_merged = false;
+            if let Some(load_stats) = self.load_base(config)? {
+                stats.entries.extend(load_stats.entries);
+            }
+        }
+
+        let mut model = self.model.lock()?;
+        match &requested {
+            Some(name) => {
+                if !self.adapters.contains_key(name) {
+                    let adapter = Self::load_adapter(&model, self.adapters_dir(), name)?;
+                    self.adapters.insert(name.clone(), adapter);
+                }
+                let adapter = &self.adapters[name];
+                if merge {
+                    model.merge_adapter(adapter).map_err(|err| {
+                        InferenceError::LoadError(format!("LoRA adapter '{name}': {err}"))
+                    })?;
+                    model.set_adapter(None);
+                } else {
+                    model.set_adapter(Some(adapter.clone()));
+                }
+                stats.entries.insert(StatEntry::Named(
+                    "LoRA Adapter".to_string(),
+                    format!("{name}{}", if merge { " (merged)" } else { "" }),
+                ));
+            }
+            None => model.set_adapter(None),
+        }
+        self.adapter = requested;
+        self.adapter_merged = merge;
+        Ok(Some(stats))
+    }
+
+    fn load_adapter(
+        model: &Llama<Tiktoken>,
+        adapters_dir: PathBuf,
+        name: &str,
+    ) -> InferenceResult<LoraAdapter> {
+        let dir = adapter_dir(&adapters_dir, name)
+            .map_err(|err| InferenceError::LoadError(format!("LoRA adapter '{name}': {err}")))?;
+        model
+            .load_adapter(&dir)
+            .map_err(|err| InferenceError::LoadError(format!("LoRA adapter '{name}': {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adapter_dir_is_in_adapters_dir() {
+        let adapters_dir = Path::new("models").join("adapters");
+        assert_eq!(
+            adapter_dir(&adapters_dir, "french").unwrap(),
+            adapters_dir.join("french")
+        );
+    }
+
+    #[test]
+    fn test_adapter_dir_rejects_paths() {
+        let adapters_dir = Path::new("models").join("adapters");
+        for name in [
+            "../x",
+            "/tmp/x",
+            "french/../../x",
+            "a/b",
+            "a\\b",
+            "..",
+            ".",
+            "",
+        ] {
+            assert!(
+                adapter_dir(&adapters_dir, name).is_err(),
+                "adapter name '{name}' should be rejected"
+            );
         }
-        let mut prompt = prompt.join("");
-        prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
-        Ok(prompt)
     }
 }
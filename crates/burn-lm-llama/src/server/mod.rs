@@ -1,5 +1,27 @@
+#[cfg(any(feature = "llama3", feature = "tiny", feature = "safetensors"))]
+mod evaluation;
+
+#[cfg(any(feature = "llama3", feature = "tiny", feature = "safetensors"))]
+mod model;
+
+#[cfg(any(feature = "llama3", feature = "tiny", feature = "safetensors"))]
+mod tokenization;
+
 #[cfg(feature = "llama3")]
 pub mod llama3;
 
+#[cfg(feature = "llama3")]
+pub mod quantized;
+
 #[cfg(feature = "tiny")]
 pub mod tiny;
+
+#[cfg(feature = "safetensors")]
+pub mod checkpoint;
+
+#[cfg(all(
+    feature = "hf-tokenizer",
+    feature = "safetensors",
+    feature = "pretrained"
+))]
+pub mod hub;
@@ -0,0 +1,237 @@
+use burn::tensor::Device;
+use rand::RngExt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{
+    generation::{GenerationError, Sampler, TopP},
+    inference::Llama,
+    tokenizer::{ChatFormat, ChatPrompt, Tokenizer},
+};
+use burn_lm_inference::*;
+
+/// The sampling settings of a generation, from the server configuration.
+pub(crate) struct Sampling {
+    pub top_p: f64,
+    pub temperature: f64,
+    pub sample_len: usize,
+    /// A random seed is used for each generation when 0.
+    pub seed: u64,
+}
+
+/// The model of a Llama server, with its loading, generation, evaluation and tokenization.
+///
+/// The servers only differ by how they load the model and its tokenizer and by their
+/// configuration.
+#[derive(Clone, Debug)]
+pub(crate) struct ServerModel<T: Tokenizer> {
+    model: Option<Arc<Mutex<Llama<T>>>>,
+    /// The tokenizer, loaded without the model weights for tokenization.
+    tokenizer: Option<T>,
+    /// The device the model is loaded on.
+    pub device: DeviceSettings,
+}
+
+impl<T: Tokenizer> Default for ServerModel<T> {
+    fn default() -> Self {
+        Self {
+            model: None,
+            tokenizer: None,
+            device: DeviceSettings::default(),
+        }
+    }
+}
+
+impl<T: Tokenizer> ServerModel<T> {
+    pub fn is_loaded(&self) -> bool {
+        self.model.is_some()
+    }
+
+    /// Load the model on the selected device with `load`, unless it is already loaded.
+    pub fn load(
+        &mut self,
+        load: impl FnOnce(&Device) -> InferenceResult<Llama<T>>,
+    ) -> InferenceResult<Option<Stats>> {
+        if self.is_loaded() {
+            return Ok(None);
+        }
+        let now = std::time::Instant::now();
+        let device = self.device.device().map_err(InferenceError::LoadError)?;
+        let memory = PeakMemoryTracker::start();
+        self.model = Some(Arc::new(Mutex::new(load(&device)?)));
+        let mut stats = Stats::new();
+        stats
+            .entries
+            .insert(StatEntry::ModelLoadingDuration(now.elapsed()));
+        if let Some(peak) = memory.stop() {
+            stats.entries.insert(StatEntry::PeakMemory(peak));
+        }
+        Ok(Some(stats))
+    }
+
+    pub fn unload(&mut self, model_name: &str) -> InferenceResult<Option<Stats>> {
+        if let Some(arc_model) = self.model.take() {
+            match Arc::try_unwrap(arc_model) {
+                Ok(mutex) => {
+                    let model = mutex
+                        .into_inner()
+                        .expect("should be able to extract model from mutex");
+                    drop(model);
+                }
+                Err(_) => {
+                    return Err(InferenceError::UnloadError(
+                        model_name.to_string(),
+                        "Multiple references exist".to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lock the loaded model.
+    pub fn lock(&self) -> InferenceResult<MutexGuard<'_, Llama<T>>> {
+        let arc_model = self.model.as_ref().ok_or(InferenceError::ModelNotLoaded)?;
+        Ok(arc_model.lock().expect("should lock the model"))
+    }
+
+    /// Generate the completion of a prompt, `load_stats` are the stats of the model loading
+    /// required by the job, if any.
+    pub fn generate(
+        &self,
+        prompt: ChatPrompt,
+        sampling: Sampling,
+        emitter: GeneratedItemEmitter,
+        load_stats: Option<Stats>,
+    ) -> InferenceResult<Stats> {
+        let seed = match sampling.seed {
+            0 => rand::rng().random::<u64>(),
+            s => s,
+        };
+        let mut sampler = if sampling.temperature > 0.0 {
+            Sampler::TopP(TopP::new(sampling.top_p, seed))
+        } else {
+            Sampler::Argmax
+        };
+        let generated = {
+            let mut model = self.lock()?;
+            let tokens = prompt.encode(&model.tokenizer, false);
+            match model.generate_tokens(
+                tokens,
+                sampling.sample_len,
+                sampling.temperature,
+                &mut sampler,
+                emitter,
+            ) {
+                Ok(result) => result,
+                Err(GenerationError::MaxSequenceLengthExceeded { actual, max }) => {
+                    return Err(InferenceError::ContextLengthExceeded(actual, max));
+                }
+            }
+        };
+        let mut stats = Stats::default();
+        let mut total_duration = generated.time;
+        stats.entries.extend(vec![
+            StatEntry::InferenceDuration(generated.time),
+            StatEntry::TokensCount(generated.tokens),
+            StatEntry::TokensPerSecond(generated.tokens, generated.time),
+        ]);
+        if let Some(load_stats) = load_stats {
+            let model_loading = load_stats
+                .entries
+                .iter()
+                .find(|e| matches!(e, StatEntry::ModelLoadingDuration(_)));
+            if let Some(model_stats) = model_loading {
+                total_duration += model_stats
+                    .get_duration()
+                    .expect("should be a ModelLoadingDuration stat")
+            }
+            stats.entries.extend(load_stats.entries);
+        }
+        stats
+            .entries
+            .insert(StatEntry::TotalDuration(total_duration));
+        Ok(stats)
+    }
+
+    pub fn clear_state(&self) -> InferenceResult<()> {
+        self.lock()?.reset();
+        Ok(())
+    }
+
+    pub fn perplexity(
+        &self,
+        model_name: &str,
+        args: PerplexityArgs,
+        load_stats: Option<Stats>,
+    ) -> InferenceResult<Stats> {
+        let mut stats = super::evaluation::perplexity(&mut self.lock()?, model_name, args)?;
+        if let Some(load_stats) = load_stats {
+            stats.entries.extend(load_stats.entries);
+        }
+        Ok(stats)
+    }
+
+    pub fn loglikelihood(
+        &self,
+        model_name: &str,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        super::evaluation::loglikelihood(&mut self.lock()?, model_name, requests)
+    }
+
+    /// The tokenizer of the loaded model, or the tokenizer loaded on its own with `load` when
+    /// the model is not loaded.
+    pub fn tokenizer(
+        &mut self,
+        model_name: &str,
+        load: impl FnOnce() -> Result<T, String>,
+    ) -> InferenceResult<&T> {
+        if self.tokenizer.is_none() {
+            let tokenizer = match &self.model {
+                Some(_) => self.lock()?.tokenizer.clone(),
+                None => load().map_err(|err| {
+                    InferenceError::TokenizationError(model_name.to_string(), err)
+                })?,
+            };
+            self.tokenizer = Some(tokenizer);
+        }
+        Ok(self.tokenizer.as_ref().expect("tokenizer should be loaded"))
+    }
+
+    pub fn tokenize(
+        &mut self,
+        model_name: &str,
+        prompt: ChatPrompt,
+        load: impl FnOnce() -> Result<T, String>,
+    ) -> InferenceResult<Tokenization> {
+        let tokenizer = self.tokenizer(model_name, load)?;
+        Ok(super::tokenization::tokenize(tokenizer, prompt))
+    }
+
+    pub fn detokenize(
+        &mut self,
+        model_name: &str,
+        tokens: Vec<u32>,
+        load: impl FnOnce() -> Result<T, String>,
+    ) -> InferenceResult<String> {
+        let tokenizer = self.tokenizer(model_name, load)?;
+        super::tokenization::detokenize(tokenizer, model_name, &tokens)
+    }
+}
+
+/// The prompt of a task in the chat format of the model.
+pub(crate) fn task_prompt(format: ChatFormat, task: InferenceTask) -> ChatPrompt {
+    let prompt = |messages: Vec<Message>| {
+        format.prompt(
+            messages
+                .into_iter()
+                .map(|message| (message.role.to_string(), message.content)),
+        )
+    };
+    match task {
+        InferenceTask::Message(message) => prompt(vec![message]),
+        InferenceTask::Context(messages) => prompt(messages),
+        // Raw prompts are trusted, their special tokens text is encoded as special tokens
+        InferenceTask::Prompt(prompt) => ChatPrompt::new().markup(prompt),
+    }
+}
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{pretrained::cache_dir, LlamaVersion};
+use burn_lm_inference::*;
+
+use super::llama3::{
+    Llama31InstructServer, Llama321bInstructServer, Llama323bInstructServer, Llama3InstructServer,
+    Llama3QuantizedServer, Llama3ServerConfig,
+};
+
+/// Metadata file describing a quantized model variant.
+const VARIANT_FILE: &str = "variant.json";
+/// Weights file of a quantized model variant.
+const VARIANT_WEIGHTS_FILE: &str = "model.mpk";
+/// Tokenizer file of a quantized model variant.
+const VARIANT_TOKENIZER_FILE: &str = "tokenizer.model";
+
+/// How a model variant has been created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariantKind {
+    /// Quantized by the `quantize` command, also the kind of the variants saved without one.
+    #[default]
+    Quantized,
+    /// Fully fine-tuned by the `train` command.
+    Trained,
+}
+
+/// A model variant saved in the model cache by the `quantize` or `train` commands.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuantizedVariant {
+    /// The CLI parameter name of the model the variant has been created from.
+    pub base_model: String,
+    #[serde(default)]
+    pub kind: VariantKind,
+    /// The quantization scheme, e.g. `q4-block32`, or the name of a trained variant.
+    pub scheme: String,
+    pub model_name: String,
+    pub model_cli_param_name: String,
+    pub model_creation_date: String,
+    pub created_by: String,
+    /// The variant directory.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl QuantizedVariant {
+    /// Create the variant metadata of a base model quantized with the given scheme.
+    pub fn new(
+        base_model_name: &str,
+        base_model_cli_param_name: &str,
+        model_creation_date: &str,
+        created_by: &str,
+        scheme: QuantizationScheme,
+        dir: PathBuf,
+    ) -> Self {
+        Self::with_suffix(
+            base_model_name,
+            base_model_cli_param_name,
+            model_creation_date,
+            created_by,
+            VariantKind::Quantized,
+            &scheme.to_string(),
+            dir,
+        )
+    }
+
+    /// Create the variant metadata of a base model trained with the `train` command.
+    pub fn trained(
+        base_model_name: &str,
+        base_model_cli_param_name: &str,
+        model_creation_date: &str,
+        created_by: &str,
+        name: &str,
+        dir: PathBuf,
+    ) -> Self {
+        Self::with_suffix(
+            base_model_name,
+            base_model_cli_param_name,
+            model_creation_date,
+            created_by,
+            VariantKind::Trained,
+            name,
+            dir,
+        )
+    }
+
+    fn with_suffix(
+        base_model_name: &str,
+        base_model_cli_param_name: &str,
+        model_creation_date: &str,
+        created_by: &str,
+        kind: VariantKind,
+        scheme: &str,
+        dir: PathBuf,
+    ) -> Self {
+        Self {
+            base_model: base_model_cli_param_name.to_string(),
+            kind,
+            scheme: scheme.to_string(),
+            model_name: format!("{base_model_name} [{scheme}]"),
+            model_cli_param_name: format!("{base_model_cli_param_name}-{scheme}"),
+            model_creation_date: model_creation_date.to_string(),
+            created_by: created_by.to_string(),
+            dir,
+        }
+    }
+
+    /// Read the variant metadata from its directory.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let content = std::fs::read_to_string(dir.join(VARIANT_FILE))
+            .map_err(|err| format!("Failed to read variant metadata.\nError: {err}"))?;
+        let mut variant: Self = serde_json::from_str(&content)
+            .map_err(|err| format!("Failed to parse variant metadata.\nError: {err}"))?;
+        variant.dir = dir.to_path_buf();
+        Ok(variant)
+    }
+
+    /// Write the variant metadata into its directory.
+    pub fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|err| format!("Failed to serialize variant metadata.\nError: {err}"))?;
+        std::fs::write(self.dir.join(VARIANT_FILE), content)
+            .map_err(|err| format!("Failed to write variant metadata.\nError: {err}"))
+    }
+
+    pub fn checkpoint(&self) -> PathBuf {
+        self.dir.join(VARIANT_WEIGHTS_FILE)
+    }
+
+    pub fn tokenizer(&self) -> PathBuf {
+        self.dir.join(VARIANT_TOKENIZER_FILE)
+    }
+
+    /// Local directory of the LoRA adapters of the variant.
+    pub fn adapters_dir(&self) -> PathBuf {
+        self.dir.join("adapters")
+    }
+
+    /// Directory of the training checkpoints and logs of a trained variant.
+    pub fn training_dir(&self) -> PathBuf {
+        self.dir.join("training")
+    }
+
+    pub fn is_saved(&self) -> bool {
+        self.checkpoint().exists() && self.tokenizer().exists()
+    }
+
+    /// The Llama version of the base model.
+    pub fn version(&self) -> Option<LlamaVersion> {
+        match self.base_model.as_str() {
+            name if name == Llama3InstructServer::model_cli_param_name() => {
+                Some(LlamaVersion::Llama3Instruct)
+            }
+            name if name == Llama31InstructServer::model_cli_param_name() => {
+                Some(LlamaVersion::Llama31Instruct)
+            }
+            name if name == Llama323bInstructServer::model_cli_param_name() => {
+                Some(LlamaVersion::Llama323bInstruct)
+            }
+            name if name == Llama321bInstructServer::model_cli_param_name() => {
+                Some(LlamaVersion::Llama321bInstruct)
+            }
+            _ => None,
+        }
+    }
+
+    /// The preferred float type of the variant, the one of its base model when trained.
+    pub fn preferred_dtype(&self) -> Option<FloatDtype> {
+        match self.kind {
+            VariantKind::Quantized => Llama3QuantizedServer::PREFERRED_DTYPE,
+            VariantKind::Trained => match self.version()? {
+                LlamaVersion::Llama3Instruct => Llama3InstructServer::PREFERRED_DTYPE,
+                LlamaVersion::Llama31Instruct => Llama31InstructServer::PREFERRED_DTYPE,
+                LlamaVersion::Llama323bInstruct => Llama323bInstructServer::PREFERRED_DTYPE,
+                LlamaVersion::Llama321bInstruct => Llama321bInstructServer::PREFERRED_DTYPE,
+                LlamaVersion::Llama321bInstructQ4FB32 => None,
+            },
+        }
+    }
+}
+
+/// Return the quantized and trained variants found in the model cache.
+pub fn saved_variants() -> Vec<QuantizedVariant> {
+    let Ok(entries) = std::fs::read_dir(cache_dir()) else {
+        return vec![];
+    };
+    let mut variants: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|dir| dir.join(VARIANT_FILE).is_file())
+        .filter_map(|dir| match QuantizedVariant::load(&dir) {
+            Ok(variant) => Some(variant),
+            Err(err) => {
+                tracing::warn!("Skipping model variant '{}': {err}", dir.display());
+                None
+            }
+        })
+        .filter(|variant| variant.version().is_some())
+        .collect();
+    variants.sort_by(|a, b| a.model_name.cmp(&b.model_name));
+    variants
+}
+
+/// Registry provider of the quantized and trained variants found in the model cache.
+///
+/// Plugin names are only known at runtime, they are leaked to satisfy the
+/// static lifetime of the registry entries.
+pub fn quantized_variants() -> Vec<Box<dyn InferencePlugin>> {
+    fn leak(value: &str) -> &'static str {
+        Box::leak(value.to_string().into_boxed_str())
+    }
+
+    saved_variants()
+        .into_iter()
+        .map(|variant| {
+            let kind = variant.kind;
+            let dtype = variant.preferred_dtype();
+            let plugin: Box<dyn InferencePlugin> = Box::new(InferenceClient::new(
+                leak(&variant.model_name),
+                leak(&variant.model_cli_param_name),
+                leak(&variant.model_creation_date),
+                leak(&variant.created_by),
+                Llama3ServerConfig::command,
+                MutexChannel::with_server(Llama3QuantizedServer::new(variant)),
+            ));
+            // The server prefers the float type of the quantized variants
+            if kind == VariantKind::Trained {
+                let settings = dtype.map_or_else(
+                    DeviceSettings::default,
+                    DeviceSettings::with_preferred_dtype,
+                );
+                plugin
+                    .set_device(settings)
+                    .expect("the preferred float type should be supported");
+            }
+            plugin
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_metadata_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_path_buf();
+        let scheme: QuantizationScheme = "q4-block32".parse().unwrap();
+        let variant = QuantizedVariant::new(
+            Llama321bInstructServer::model_name(),
+            Llama321bInstructServer::model_cli_param_name(),
+            Llama321bInstructServer::model_creation_date(),
+            Llama321bInstructServer::created_by(),
+            scheme,
+            dir.clone(),
+        );
+        variant.save().unwrap();
+
+        let loaded = QuantizedVariant::load(&dir).unwrap();
+
+        assert_eq!(loaded.model_name, "Llama 3.2 (1B Instruct) [q4-block32]");
+        assert_eq!(loaded.model_cli_param_name, "llama32-q4-block32");
+        assert_eq!(loaded.dir, dir);
+        assert_eq!(loaded.kind, VariantKind::Quantized);
+        assert_eq!(loaded.preferred_dtype(), Some(FloatDtype::F32));
+        assert!(matches!(
+            loaded.version(),
+            Some(LlamaVersion::Llama321bInstruct)
+        ));
+        assert!(!loaded.is_saved());
+    }
+
+    #[test]
+    fn test_trained_variant_kind() {
+        let variant = QuantizedVariant::trained(
+            Llama3InstructServer::model_name(),
+            Llama3InstructServer::model_cli_param_name(),
+            Llama3InstructServer::model_creation_date(),
+            Llama3InstructServer::created_by(),
+            "chat",
+            PathBuf::new(),
+        );
+        assert_eq!(variant.kind, VariantKind::Trained);
+        assert_eq!(variant.preferred_dtype(), Some(FloatDtype::Bf16));
+
+        // Variants saved before the kind was recorded are quantized
+        let variant: QuantizedVariant = serde_json::from_str(
+            r#"{"base_model": "llama3", "scheme": "q4-block32", "model_name": "Llama 3",
+                "model_cli_param_name": "llama3-q4-block32", "model_creation_date": "",
+                "created_by": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(variant.kind, VariantKind::Quantized);
+    }
+}
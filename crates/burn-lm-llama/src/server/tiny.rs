@@ -1,12 +1,9 @@
-use rand::RngExt;
 use serde::Deserialize;
-use std::sync::{Arc, Mutex};
 
+use super::model::{task_prompt, Sampling, ServerModel};
 use crate::{
-    generation::{GenerationError, Sampler, TopP},
-    inference::Llama,
     pretrained::ModelMeta,
-    tokenizer::SentencePieceTokenizer,
+    tokenizer::{ChatFormat, SentencePieceTokenizer, Tokenizer},
     LlamaConfig, TinyLlamaVersion,
 };
 use burn_lm_inference::{InferenceJob, *};
@@ -39,71 +36,23 @@ pub struct TinyLlamaServerConfig {
 // [StatNLP Research Group](https://arxiv.org/abs/2401.02385)
 pub struct TinyLlamaServer {
     config: TinyLlamaServerConfig,
-    model: Option<Arc<Mutex<Llama<SentencePieceTokenizer>>>>,
+    model: ServerModel<SentencePieceTokenizer>,
 }
 
 impl TinyLlamaServer {
-    fn run_prompt(
-        &mut self,
-        prompt: Prompt,
-        emitter: GeneratedItemEmitter,
-    ) -> InferenceResult<Stats> {
-        let load_stats = self.load()?;
-        let seed = match self.config.seed {
-            0 => rand::rng().random::<u64>(),
-            s => s,
-        };
-        let mut sampler = if self.config.temperature > 0.0 {
-            Sampler::TopP(TopP::new(self.config.top_p, seed))
-        } else {
-            Sampler::Argmax
-        };
-        let generated = match &self.model {
-            Some(arc_model) => match arc_model
-                .lock()
-                .expect("should be able to lock the model for inference")
-                .generate(
-                    &prompt,
-                    self.config.sample_len,
-                    self.config.temperature,
-                    &mut sampler,
-                    emitter,
-                ) {
-                Ok(result) => result,
-                Err(GenerationError::MaxSequenceLengthExceeded { actual, max }) => {
-                    return Err(InferenceError::ContextLengthExceeded(actual, max));
-                }
-            },
-            _ => return Err(InferenceError::ModelNotLoaded),
-        };
-        let mut stats = Stats::default();
-        let mut total_duration = generated.time;
-        stats.entries.extend(vec![
-            StatEntry::InferenceDuration(generated.time),
-            StatEntry::TokensCount(generated.tokens),
-            StatEntry::TokensPerSecond(generated.tokens, generated.time),
-        ]);
-        if let Some(load_stats) = load_stats {
-            let model_loading = load_stats
-                .entries
-                .iter()
-                .find(|e| matches!(e, StatEntry::ModelLoadingDuration(_)));
-            if let Some(model_stats) = model_loading {
-                total_duration += model_stats
-                    .get_duration()
-                    .expect("should be a ModelLoadingDuration stat")
-            }
-            stats.entries.extend(load_stats.entries);
-        }
-        stats
-            .entries
-            .insert(StatEntry::TotalDuration(total_duration));
-        Ok(stats)
+    /// The tokenizer file loaded on its own.
+    fn load_tokenizer() -> Result<SentencePieceTokenizer, String> {
+        let path = TinyLlamaVersion::V1
+            .pretrained()
+            .download_tokenizer()
+            .map_err(|err| err.to_string())?;
+        SentencePieceTokenizer::new(path.to_str().unwrap())
     }
 }
+
 impl InferenceServer for TinyLlamaServer {
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
-        Some(|| {
+    fn downloader(&mut self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
+        Some(|settings| {
             let now = std::time::Instant::now();
             let model = TinyLlamaVersion::V1.pretrained();
             model.download_weights().map_err(|err| {
@@ -112,6 +61,17 @@ impl InferenceServer for TinyLlamaServer {
             model.download_tokenizer().map_err(|err| {
                 InferenceError::DownloadError(Self::model_name().to_string(), err.to_string())
             })?;
+            #[cfg(feature = "safetensors")]
+            {
+                let error = |reason: String| {
+                    InferenceError::DownloadError(Self::model_name().to_string(), reason)
+                };
+                TinyLlamaVersion::V1
+                    .convert_weights(&settings.device().map_err(error)?)
+                    .map_err(error)?;
+            }
+            #[cfg(not(feature = "safetensors"))]
+            let _ = settings;
             let mut stats = Stats::new();
             stats
                 .entries
@@ -138,78 +98,62 @@ impl InferenceServer for TinyLlamaServer {
         })
     }
 
+    fn set_device(&mut self, settings: DeviceSettings) {
+        self.model.device = settings;
+    }
+
     fn load(&mut self) -> InferenceResult<Option<Stats>> {
-        if !self.is_loaded() {
-            let now = std::time::Instant::now();
-            let model =
-                LlamaConfig::tiny_llama_pretrained(self.config.max_seq_len, &*INFERENCE_DEVICE)
-                    .unwrap();
-            self.model = Some(Arc::new(Mutex::new(model)));
-            let mut stats = Stats::new();
-            stats
-                .entries
-                .insert(StatEntry::ModelLoadingDuration(now.elapsed()));
-            Ok(Some(stats))
-        } else {
-            Ok(None)
-        }
+        let max_seq_len = self.config.max_seq_len;
+        self.model
+            .load(|device| Ok(LlamaConfig::tiny_llama_pretrained(max_seq_len, device).unwrap()))
     }
 
     fn is_loaded(&mut self) -> bool {
-        self.model.is_some()
+        self.model.is_loaded()
     }
 
     fn unload(&mut self) -> InferenceResult<Option<Stats>> {
-        if let Some(arc_model) = self.model.take() {
-            match Arc::try_unwrap(arc_model) {
-                Ok(mutex) => {
-                    let model = mutex
-                        .into_inner()
-                        .expect("should be able to extract model from mutex");
-                    drop(model);
-                }
-                Err(_) => {
-                    return Err(InferenceError::UnloadError(
-                        Self::model_name().to_string(),
-                        "Multiple references exist".to_string(),
-                    ))
-                }
-            }
-        }
-        Ok(None)
+        self.model.unload(Self::model_name())
     }
 
     fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
-        let prompt = match job.task {
-            InferenceTask::Message(message) => self.prompt(vec![message])?,
-            InferenceTask::Context(messages) => self.prompt(messages)?,
-            InferenceTask::Prompt(prompt) => prompt,
+        let load_stats = self.load()?;
+        let sampling = Sampling {
+            top_p: self.config.top_p,
+            temperature: self.config.temperature,
+            sample_len: self.config.sample_len,
+            seed: self.config.seed,
         };
-        self.run_prompt(prompt, job.emitter)
+        let prompt = task_prompt(ChatFormat::Zephyr, job.task);
+        self.model
+            .generate(prompt, sampling, job.emitter, load_stats)
     }
 
     fn clear_state(&mut self) -> InferenceResult<()> {
-        match &self.model {
-            Some(arc_model) => {
-                let mut model = arc_model
-                    .lock()
-                    .expect("should lock the model for inference");
-                model.reset();
-                Ok(())
-            }
-            None => Err(InferenceError::ModelNotLoaded),
-        }
+        self.model.clear_state()
     }
-}
 
-impl TinyLlamaServer {
-    fn prompt(&self, messages: Vec<Message>) -> InferenceResult<burn_lm_inference::Prompt> {
-        let mut prompt: Vec<String> = vec![];
-        for message in messages {
-            prompt.push(format!("<|{}|>\n{}</s>\n", message.role, message.content));
-        }
-        let mut prompt = prompt.join("\n");
-        prompt.push_str("<|assistant|>\n");
-        Ok(prompt)
+    fn perplexity(&mut self, args: PerplexityArgs) -> InferenceResult<Stats> {
+        let load_stats = self.load()?;
+        self.model.perplexity(Self::model_name(), args, load_stats)
+    }
+
+    fn loglikelihood(
+        &mut self,
+        requests: Vec<LoglikelihoodRequest>,
+    ) -> InferenceResult<Vec<Loglikelihood>> {
+        self.load()?;
+        self.model.loglikelihood(Self::model_name(), requests)
+    }
+
+    fn tokenize(&mut self, task: InferenceTask) -> InferenceResult<Tokenization> {
+        let prompt = task_prompt(ChatFormat::Zephyr, task);
+        self.model
+            .tokenize(Self::model_name(), prompt, Self::load_tokenizer)
+    }
+
+    fn detokenize(&mut self, tokens: Vec<u32>) -> InferenceResult<String> {
+        self.model
+            .detokenize(Self::model_name(), tokens, Self::load_tokenizer)
     }
 }
@@ -0,0 +1,59 @@
+use crate::tokenizer::{ChatPrompt, Tokenizer};
+use burn_lm_inference::*;
+
+/// Tokenize a prompt the same way it is encoded for generation.
+pub(crate) fn tokenize<T: Tokenizer>(tokenizer: &T, prompt: ChatPrompt) -> Tokenization {
+    Tokenization {
+        tokens: prompt.encode(tokenizer, false),
+        prompt: prompt.render(),
+    }
+}
+
+/// Decode token identifiers, the identifiers must be part of the vocabulary.
+pub(crate) fn detokenize<T: Tokenizer>(
+    tokenizer: &T,
+    model_name: &str,
+    tokens: &[u32],
+) -> InferenceResult<String> {
+    if let Some(id) = tokens
+        .iter()
+        .find(|id| tokenizer.id_to_token(**id).is_none())
+    {
+        return Err(InferenceError::TokenizationError(
+            model_name.to_string(),
+            format!("unknown token identifier {id}"),
+        ));
+    }
+    Ok(tokenizer.decode(tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::{byte::ByteTokenizer, ChatFormat};
+
+    #[test]
+    fn test_tokenize_detokenize() {
+        let tokenizer = ByteTokenizer::new("").unwrap();
+        let prompt = ChatFormat::Zephyr.prompt([("user", "hi")]);
+
+        let tokenization = tokenize(&tokenizer, prompt);
+        assert_eq!(tokenization.prompt, "<|user|>\nhi</s>\n<|assistant|>\n");
+        assert_eq!(
+            tokenization.tokens,
+            tokenization
+                .prompt
+                .bytes()
+                .map(u32::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            detokenize(&tokenizer, "test", &[104, 105]).unwrap(),
+            "[104, 105]"
+        );
+        assert!(matches!(
+            detokenize(&tokenizer, "test", &[104, 256]),
+            Err(InferenceError::TokenizationError(..))
+        ));
+    }
+}
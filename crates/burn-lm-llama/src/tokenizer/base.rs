@@ -5,8 +5,17 @@ pub trait Tokenizer: Send + Sync + Clone {
         Self: Sized;
 
     /// Encode a string into a list of token identifiers.
+    ///
+    /// The text of special tokens is encoded as the special tokens, use
+    /// [encode_text](Tokenizer::encode_text) for untrusted content.
     fn encode(&self, text: &str, bos: bool, eos: bool) -> Vec<u32>;
 
+    /// Encode a string as ordinary text into a list of token identifiers.
+    ///
+    /// The text of special tokens is encoded like any other text, so that user content can't
+    /// inject control tokens.
+    fn encode_text(&self, text: &str, bos: bool, eos: bool) -> Vec<u32>;
+
     /// Decode a list of token identifiers into a string.
     fn decode(&self, tokens: &[u32]) -> String;
 
@@ -29,6 +38,15 @@ pub trait Tokenizer: Send + Sync + Clone {
     /// Stop token identifiers.
     fn stop_ids(&self) -> Vec<u32>;
 
+    /// Number of tokens in the vocabulary, including the special tokens.
+    fn vocab_size(&self) -> usize;
+
+    /// The identifier of a token, if it is part of the vocabulary.
+    fn token_to_id(&self, token: &str) -> Option<u32>;
+
+    /// The token of an identifier, if it is part of the vocabulary.
+    fn id_to_token(&self, id: u32) -> Option<String>;
+
     /// Number of tokens needed as context for incremental streaming decoding.
     /// Default is 0 (no context/buffering needed).
     fn streaming_context_size(&self) -> usize {
@@ -31,6 +31,10 @@ impl Tokenizer for ByteTokenizer {
             .collect()
     }
 
+    fn encode_text(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
+        self.encode(text, bos, eos)
+    }
+
     fn decode(&self, tokens: &[u32]) -> String {
         format!("{tokens:?}")
     }
@@ -46,4 +50,19 @@ impl Tokenizer for ByteTokenizer {
     fn stop_ids(&self) -> Vec<u32> {
         vec![2]
     }
+
+    fn vocab_size(&self) -> usize {
+        256
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        match token.as_bytes() {
+            [byte] => Some(*byte as u32),
+            _ => None,
+        }
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        u8::try_from(id).ok().map(|byte| format!("[{byte}]"))
+    }
 }
@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+
+use super::Tokenizer;
+
+/// A part of a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSegment {
+    /// Template markup, encoded with the special tokens.
+    Markup(String),
+    /// Content, encoded as ordinary text.
+    Text(String),
+}
+
+/// A prompt made of template markup and content.
+///
+/// The content (messages, roles) is encoded as ordinary text so that a message containing the
+/// text of a special token, e.g. `<|eot_id|>`, can't inject control tokens in the template.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatPrompt {
+    segments: Vec<PromptSegment>,
+}
+
+impl ChatPrompt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append template markup.
+    pub fn markup(mut self, markup: impl Into<String>) -> Self {
+        self.segments.push(PromptSegment::Markup(markup.into()));
+        self
+    }
+
+    /// Append content.
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.segments.push(PromptSegment::Text(text.into()));
+        self
+    }
+
+    /// Append the segments of another prompt.
+    pub fn extend(mut self, prompt: ChatPrompt) -> Self {
+        self.segments.extend(prompt.segments);
+        self
+    }
+
+    pub fn segments(&self) -> &[PromptSegment] {
+        &self.segments
+    }
+
+    /// Encode the prompt into a list of token identifiers.
+    pub fn encode<T: Tokenizer>(&self, tokenizer: &T, bos: bool) -> Vec<u32> {
+        // Only the beginning of sentence token, if any
+        let mut tokens = tokenizer.encode_text("", bos, false);
+        for segment in &self.segments {
+            tokens.extend(match segment {
+                PromptSegment::Markup(markup) => tokenizer.encode(markup, false, false),
+                PromptSegment::Text(text) => tokenizer.encode_text(text, false, false),
+            });
+        }
+        tokens
+    }
+
+    /// Render the prompt as a string.
+    pub fn render(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                PromptSegment::Markup(s) | PromptSegment::Text(s) => s.as_str(),
+            })
+            .collect()
+    }
+}
+
+/// Prompt format of the chat conversations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatFormat {
+    /// Llama 3 instruct header and end of turn tokens.
+    Llama3,
+    /// Zephyr role tags, used by TinyLlama chat.
+    Zephyr,
+    /// ChatML start and end of message tokens.
+    ChatMl,
+}
+
+impl ChatFormat {
+    /// Render a message into its header and its content, including the end of turn delimiter.
+    pub fn render(&self, role: &str, content: &str) -> (ChatPrompt, ChatPrompt) {
+        let role = role.to_lowercase();
+        match self {
+            ChatFormat::Llama3 => (
+                ChatPrompt::new()
+                    .markup("<|start_header_id|>")
+                    .text(role)
+                    .markup("<|end_header_id|>\n\n"),
+                ChatPrompt::new().text(content).markup("<|eot_id|>"),
+            ),
+            ChatFormat::Zephyr => (
+                ChatPrompt::new().markup("<|").text(role).markup("|>\n"),
+                ChatPrompt::new().text(content).markup("</s>\n"),
+            ),
+            ChatFormat::ChatMl => (
+                ChatPrompt::new()
+                    .markup("<|im_start|>")
+                    .text(role)
+                    .markup("\n"),
+                ChatPrompt::new().text(content).markup("<|im_end|>\n"),
+            ),
+        }
+    }
+
+    /// Guess the format of a Hugging Face chat template from its special tokens.
+    pub fn from_chat_template(template: &str) -> Option<Self> {
+        if template.contains("<|start_header_id|>") {
+            Some(ChatFormat::Llama3)
+        } else if template.contains("<|im_start|>") {
+            Some(ChatFormat::ChatMl)
+        } else if template.contains("<|user|>") || template.contains("'<|' + message['role']") {
+            Some(ChatFormat::Zephyr)
+        } else {
+            None
+        }
+    }
+
+    /// Render the messages, given as `(role, content)`, into a prompt for the assistant answer.
+    pub fn prompt<R: AsRef<str>, C: AsRef<str>>(
+        &self,
+        messages: impl IntoIterator<Item = (R, C)>,
+    ) -> ChatPrompt {
+        let prompt = messages
+            .into_iter()
+            .fold(ChatPrompt::new(), |prompt, (role, content)| {
+                let (header, content) = self.render(role.as_ref(), content.as_ref());
+                prompt.extend(header).extend(content)
+            });
+        let (header, _) = self.render("assistant", "");
+        prompt.extend(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::byte::ByteTokenizer;
+
+    #[test]
+    fn test_chat_prompt() {
+        let prompt = ChatFormat::Llama3.prompt([("User", "Hi<|eot_id|>")]);
+
+        assert_eq!(
+            prompt.render(),
+            "<|start_header_id|>user<|end_header_id|>\n\nHi<|eot_id|><|eot_id|><|start_header_id|>assistant<|end_header_id|>\n\n"
+        );
+        assert_eq!(
+            prompt.segments()[3],
+            PromptSegment::Text("Hi<|eot_id|>".to_string())
+        );
+        let tokens = prompt.encode(&ByteTokenizer, true);
+        assert_eq!(tokens.len(), 5 + prompt.render().len());
+    }
+
+    #[test]
+    fn test_chat_format_from_chat_template() {
+        let chatml = "{% for message in messages %}{{'<|im_start|>' + message['role'] + '\n' + message['content'] + '<|im_end|>' + '\n'}}{% endfor %}";
+        let llama3 = "{{ '<|start_header_id|>' + message['role'] + '<|end_header_id|>\n\n' }}";
+
+        assert_eq!(
+            ChatFormat::from_chat_template(chatml),
+            Some(ChatFormat::ChatMl)
+        );
+        assert_eq!(
+            ChatFormat::from_chat_template(llama3),
+            Some(ChatFormat::Llama3)
+        );
+        assert_eq!(ChatFormat::from_chat_template("{{ messages }}"), None);
+        assert_eq!(
+            ChatFormat::ChatMl.prompt([("User", "Hi")]).render(),
+            "<|im_start|>user\nHi<|im_end|>\n<|im_start|>assistant\n"
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+
+use tokenizers::{
+    decoders::{
+        byte_fallback::ByteFallback, byte_level::ByteLevel, fuse::Fuse, sequence::Sequence,
+        strip::Strip, DecoderWrapper,
+    },
+    models::bpe::BPE,
+    normalizers::{self, Prepend, Replace},
+    pre_tokenizers::{self, split::Split},
+    AddedToken, SplitDelimiterBehavior, Tokenizer as BaseTokenizer,
+};
+
+use super::{encode_with, Tokenizer};
+use crate::gguf::{GgufFile, GgufValue};
+
+/// Llama 3 pre-tokenization pattern.
+const LLAMA3_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Token types stored in `tokenizer.ggml.token_type`.
+const TOKEN_TYPE_UNKNOWN: i64 = 2;
+const TOKEN_TYPE_CONTROL: i64 = 3;
+const TOKEN_TYPE_USER_DEFINED: i64 = 4;
+
+/// Tokenizer built from the vocabulary embedded in a GGUF file.
+///
+/// Supports the SentencePiece (`llama`) and byte-level BPE (`gpt2`) tokenizer models.
+#[derive(Debug, Clone)]
+pub struct GgufTokenizer {
+    bpe: BaseTokenizer,
+    /// The tokenizer encoding the text of special tokens as ordinary text.
+    text_bpe: BaseTokenizer,
+    bos_token_id: u32,
+    eos_token_id: u32,
+    stop_ids: Vec<u32>,
+    streaming_context_size: usize,
+}
+
+impl Tokenizer for GgufTokenizer {
+    /// Load the tokenizer embedded in the GGUF file.
+    fn new(gguf_path: &str) -> Result<Self, String> {
+        Self::from_gguf(&GgufFile::open(gguf_path)?)
+    }
+
+    fn encode(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
+        encode_with(
+            &self.bpe,
+            text,
+            bos.then_some(self.bos_token_id),
+            eos.then_some(self.eos_token_id),
+        )
+    }
+
+    fn encode_text(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
+        encode_with(
+            &self.text_bpe,
+            text,
+            bos.then_some(self.bos_token_id),
+            eos.then_some(self.eos_token_id),
+        )
+    }
+
+    fn decode(&self, tokens: &[u32]) -> String {
+        self.bpe.decode(tokens, false).unwrap()
+    }
+
+    fn bos_id(&self) -> u32 {
+        self.bos_token_id
+    }
+
+    fn eos_id(&self) -> u32 {
+        self.eos_token_id
+    }
+
+    fn stop_ids(&self) -> Vec<u32> {
+        self.stop_ids.clone()
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.bpe.get_vocab_size(true)
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.bpe.token_to_id(token)
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.bpe.id_to_token(id)
+    }
+
+    fn streaming_context_size(&self) -> usize {
+        self.streaming_context_size
+    }
+}
+
+impl GgufTokenizer {
+    /// Build the tokenizer from the GGUF `tokenizer.ggml.*` metadata.
+    pub fn from_gguf(file: &GgufFile) -> Result<Self, String> {
+        let model = file.get_str("tokenizer.ggml.model")?;
+        let tokens = string_array(file, "tokenizer.ggml.tokens")?;
+        let token_types = file
+            .get("tokenizer.ggml.token_type")
+            .and_then(GgufValue::as_array)
+            .map(|types| {
+                types
+                    .iter()
+                    .map(|t| t.as_f64().unwrap_or(1.) as i64)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let token_type = |id: usize| token_types.get(id).copied().unwrap_or(1);
+
+        let vocab = tokens
+            .iter()
+            .enumerate()
+            .map(|(id, token)| (token.clone(), id as u32))
+            .collect::<HashMap<_, _>>();
+
+        let (mut bpe, streaming_context_size) = match model {
+            "llama" => {
+                let scores = file
+                    .get("tokenizer.ggml.scores")
+                    .and_then(GgufValue::as_array)
+                    .ok_or("Missing GGUF metadata 'tokenizer.ggml.scores'")?
+                    .iter()
+                    .map(|score| score.as_f64().unwrap_or(0.) as f32)
+                    .collect::<Vec<_>>();
+                let unk_token = tokens
+                    .iter()
+                    .enumerate()
+                    .find(|(id, _)| token_type(*id) == TOKEN_TYPE_UNKNOWN)
+                    .map(|(_, token)| token.clone())
+                    .unwrap_or_else(|| "<unk>".to_string());
+                let merges = sentence_piece_merges(&tokens, &scores, &vocab, token_type);
+                let model = BPE::builder()
+                    .vocab_and_merges(vocab, merges)
+                    .unk_token(unk_token)
+                    .fuse_unk(true)
+                    .byte_fallback(true)
+                    .build()
+                    .map_err(|err| err.to_string())?;
+
+                let mut bpe = BaseTokenizer::new(model);
+                bpe.with_normalizer(normalizers::Sequence::new(vec![
+                    Prepend::new("▁".to_string()).into(),
+                    Replace::new(" ", "▁")
+                        .map_err(|err| err.to_string())?
+                        .into(),
+                ]));
+                bpe.with_decoder(Sequence::new(vec![
+                    DecoderWrapper::Replace(Replace::new("▁", " ").map_err(|err| err.to_string())?),
+                    ByteFallback::new().into(),
+                    Fuse::new().into(),
+                    Strip::new(' ', 1, 0).into(),
+                ]));
+                // Same as the SentencePiece tokenizer: spacing and utf-8 bytes fallback
+                (bpe, 4)
+            }
+            "gpt2" => {
+                let merges = string_array(file, "tokenizer.ggml.merges")?
+                    .into_iter()
+                    .map(|merge| {
+                        merge
+                            .split_once(' ')
+                            .map(|(a, b)| (a.to_string(), b.to_string()))
+                            .ok_or_else(|| format!("Invalid BPE merge '{merge}'"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let model = BPE::builder()
+                    .vocab_and_merges(vocab, merges)
+                    .ignore_merges(true)
+                    .build()
+                    .map_err(|err| err.to_string())?;
+
+                let mut bpe = BaseTokenizer::new(model);
+                bpe.with_pre_tokenizer(pre_tokenizers::sequence::Sequence::new(vec![
+                    Split::new(
+                        pre_tokenizers::split::SplitPattern::Regex(LLAMA3_PATTERN.to_string()),
+                        SplitDelimiterBehavior::Isolated,
+                        false,
+                    )
+                    .map_err(|err| err.to_string())?
+                    .into(),
+                    ByteLevel::new(false, true, false).into(),
+                ]));
+                bpe.with_decoder(ByteLevel::default());
+                // Multi-byte characters can be split across byte-level tokens
+                (bpe, 4)
+            }
+            model => return Err(format!("Unsupported GGUF tokenizer model '{model}'")),
+        };
+
+        let added_tokens = tokens
+            .iter()
+            .enumerate()
+            .filter_map(|(id, token)| match token_type(id) {
+                TOKEN_TYPE_CONTROL => Some(AddedToken::from(token.clone(), true)),
+                TOKEN_TYPE_USER_DEFINED => Some(AddedToken::from(token.clone(), false)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        bpe.add_tokens(&added_tokens);
+
+        let bos_token_id = file.get_u64("tokenizer.ggml.bos_token_id")? as u32;
+        let eos_token_id = file.get_u64("tokenizer.ggml.eos_token_id")? as u32;
+
+        let mut stop_ids = vec![eos_token_id];
+        for key in ["tokenizer.ggml.eot_token_id", "tokenizer.ggml.eom_token_id"] {
+            if let Some(id) = file.get(key).and_then(GgufValue::as_u64) {
+                stop_ids.push(id as u32);
+            }
+        }
+        // Older GGUF files don't record the end of turn and end of message tokens
+        for token in ["<|eot_id|>", "<|eom_id|>"] {
+            if let Some(id) = tokens.iter().position(|t| t == token) {
+                stop_ids.push(id as u32);
+            }
+        }
+        stop_ids.sort();
+        stop_ids.dedup();
+
+        let mut text_bpe = bpe.clone();
+        text_bpe.set_encode_special_tokens(true);
+
+        Ok(Self {
+            bpe,
+            text_bpe,
+            bos_token_id,
+            eos_token_id,
+            stop_ids,
+            streaming_context_size,
+        })
+    }
+}
+
+fn string_array(file: &GgufFile, key: &str) -> Result<Vec<String>, String> {
+    file.get(key)
+        .and_then(GgufValue::as_array)
+        .ok_or_else(|| format!("Missing GGUF metadata '{key}'"))?
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("Invalid GGUF metadata '{key}'"))
+        })
+        .collect()
+}
+
+/// Derive the BPE merges of a SentencePiece vocabulary from the token scores.
+///
+/// Each token that can be split into two tokens of the vocabulary produces a merge, ranked by
+/// the score of the merged token.
+fn sentence_piece_merges(
+    tokens: &[String],
+    scores: &[f32],
+    vocab: &HashMap<String, u32>,
+    token_type: impl Fn(usize) -> i64,
+) -> Vec<(String, String)> {
+    let mut merges = Vec::new();
+    for (id, token) in tokens.iter().enumerate() {
+        if token_type(id) != 1 {
+            continue;
+        }
+        let score = scores.get(id).copied().unwrap_or(0.);
+        for (split, _) in token.char_indices().skip(1) {
+            let (left, right) = token.split_at(split);
+            if let (Some(left_id), Some(right_id)) = (vocab.get(left), vocab.get(right)) {
+                merges.push((score, *left_id, *right_id, left, right));
+            }
+        }
+    }
+
+    merges.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+    merges
+        .into_iter()
+        .map(|(_, _, _, left, right)| (left.to_string(), right.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gguf::tests::GgufWriter;
+
+    fn strings(values: &[&str]) -> GgufValue {
+        GgufValue::Array(
+            values
+                .iter()
+                .map(|v| GgufValue::String(v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_gguf_sentence_piece_tokenizer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spm-tokenizer.gguf");
+        GgufWriter::default()
+            .metadata("tokenizer.ggml.model", GgufValue::String("llama".into()))
+            .metadata(
+                "tokenizer.ggml.tokens",
+                strings(&["<unk>", "<s>", "</s>", "▁", "h", "i", "▁h", "▁hi"]),
+            )
+            .metadata(
+                "tokenizer.ggml.scores",
+                GgufValue::Array(
+                    [0., 0., 0., -1., -2., -3., -4., -5.]
+                        .into_iter()
+                        .map(GgufValue::F32)
+                        .collect(),
+                ),
+            )
+            .metadata(
+                "tokenizer.ggml.token_type",
+                GgufValue::Array(
+                    [2, 3, 3, 1, 1, 1, 1, 1]
+                        .into_iter()
+                        .map(GgufValue::I32)
+                        .collect(),
+                ),
+            )
+            .metadata("tokenizer.ggml.bos_token_id", GgufValue::U32(1))
+            .metadata("tokenizer.ggml.eos_token_id", GgufValue::U32(2))
+            .write(&path);
+
+        let tokenizer = GgufTokenizer::new(path.to_str().unwrap()).unwrap();
+
+        let tokens = tokenizer.encode("hi", true, false);
+        assert_eq!(tokens, vec![1, 7]);
+        assert_eq!(tokenizer.decode(&tokens[1..]), "hi");
+        assert_eq!(tokenizer.stop_ids(), vec![2]);
+    }
+
+    #[test]
+    fn test_gguf_byte_level_tokenizer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bpe-tokenizer.gguf");
+        GgufWriter::default()
+            .metadata("tokenizer.ggml.model", GgufValue::String("gpt2".into()))
+            .metadata(
+                "tokenizer.ggml.tokens",
+                strings(&[
+                    "h",
+                    "i",
+                    "Ġ",
+                    "hi",
+                    "Ġhi",
+                    "<|begin_of_text|>",
+                    "<|eot_id|>",
+                ]),
+            )
+            .metadata("tokenizer.ggml.merges", strings(&["h i", "Ġ hi"]))
+            .metadata(
+                "tokenizer.ggml.token_type",
+                GgufValue::Array(
+                    [1, 1, 1, 1, 1, 3, 3]
+                        .into_iter()
+                        .map(GgufValue::I32)
+                        .collect(),
+                ),
+            )
+            .metadata("tokenizer.ggml.bos_token_id", GgufValue::U32(5))
+            .metadata("tokenizer.ggml.eos_token_id", GgufValue::U32(6))
+            .write(&path);
+
+        let tokenizer = GgufTokenizer::new(path.to_str().unwrap()).unwrap();
+
+        let tokens = tokenizer.encode("hi hi<|eot_id|>", false, false);
+        assert_eq!(tokens, vec![3, 4, 6]);
+        assert_eq!(tokenizer.decode(&tokens[..2]), "hi hi");
+        assert_eq!(tokenizer.stop_ids(), vec![6]);
+    }
+}
@@ -0,0 +1,338 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use tokenizers::{AddedToken, Tokenizer as BaseTokenizer};
+
+use super::{encode_with, Tokenizer};
+
+/// The tokenizer file of a Hugging Face model.
+pub const HF_TOKENIZER_FILE: &str = "tokenizer.json";
+/// The tokenizer configuration file of a Hugging Face model, next to the tokenizer file.
+pub const HF_TOKENIZER_CONFIG_FILE: &str = "tokenizer_config.json";
+/// The generation configuration file of a Hugging Face model, next to the tokenizer file.
+pub const HF_GENERATION_CONFIG_FILE: &str = "generation_config.json";
+
+/// End of turn tokens of the common chat templates, used as stop tokens when present.
+const END_OF_TURN_TOKENS: [&str; 5] = [
+    "<|eot_id|>",
+    "<|eom_id|>",
+    "<|im_end|>",
+    "<|end|>",
+    "<end_of_turn>",
+];
+
+/// A special token, either as its content or as an added token object.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SpecialToken {
+    Content(String),
+    Token { content: String },
+}
+
+impl SpecialToken {
+    fn content(&self) -> &str {
+        match self {
+            SpecialToken::Content(content) | SpecialToken::Token { content } => content,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AddedTokenConfig {
+    content: String,
+    #[serde(default)]
+    special: bool,
+}
+
+/// The fields of `tokenizer_config.json` used by the tokenizer.
+#[derive(Deserialize, Default)]
+struct TokenizerConfig {
+    bos_token: Option<SpecialToken>,
+    eos_token: Option<SpecialToken>,
+    add_bos_token: Option<bool>,
+    #[serde(default)]
+    added_tokens_decoder: HashMap<String, AddedTokenConfig>,
+}
+
+/// A token identifier or a list of token identifiers.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenIds {
+    One(u32),
+    Many(Vec<u32>),
+}
+
+impl TokenIds {
+    fn ids(&self) -> Vec<u32> {
+        match self {
+            TokenIds::One(id) => vec![*id],
+            TokenIds::Many(ids) => ids.clone(),
+        }
+    }
+}
+
+/// The fields of `generation_config.json` used by the tokenizer.
+#[derive(Deserialize, Default)]
+struct GenerationConfig {
+    bos_token_id: Option<u32>,
+    eos_token_id: Option<TokenIds>,
+}
+
+/// Tokenizer of a Hugging Face model.
+///
+/// The tokenizer is loaded from `tokenizer.json`, while the special tokens are read from the
+/// optional `tokenizer_config.json` and `generation_config.json` files in the same directory.
+#[derive(Debug, Clone)]
+pub struct HfTokenizer {
+    bpe: BaseTokenizer,
+    /// The tokenizer encoding the text of special tokens as ordinary text.
+    text_bpe: BaseTokenizer,
+    bos_token_id: Option<u32>,
+    eos_token_id: u32,
+    stop_ids: Vec<u32>,
+}
+
+impl Tokenizer for HfTokenizer {
+    /// Load the tokenizer from a `tokenizer.json` file or from the model directory holding it.
+    fn new(tokenizer_path: &str) -> Result<Self, String> {
+        let path = Path::new(tokenizer_path);
+        let (dir, file) = if path.is_dir() {
+            (path, path.join(HF_TOKENIZER_FILE))
+        } else {
+            (path.parent().unwrap_or(Path::new(".")), path.to_path_buf())
+        };
+        let bpe = BaseTokenizer::from_file(&file).map_err(|err| {
+            format!(
+                "Failed to load tokenizer '{}'.\nError: {err}",
+                file.display()
+            )
+        })?;
+        let config = read_config(&dir.join(HF_TOKENIZER_CONFIG_FILE))?;
+        let generation_config = read_config(&dir.join(HF_GENERATION_CONFIG_FILE))?;
+
+        Self::from_configs(bpe, config, generation_config)
+    }
+
+    fn encode(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
+        encode_with(
+            &self.bpe,
+            text,
+            self.bos_token_id.filter(|_| bos),
+            eos.then_some(self.eos_token_id),
+        )
+    }
+
+    fn encode_text(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
+        encode_with(
+            &self.text_bpe,
+            text,
+            self.bos_token_id.filter(|_| bos),
+            eos.then_some(self.eos_token_id),
+        )
+    }
+
+    fn decode(&self, tokens: &[u32]) -> String {
+        self.bpe.decode(tokens, false).unwrap()
+    }
+
+    /// Beginning of sentence token identifier, or the end of sentence token identifier for
+    /// models without one.
+    fn bos_id(&self) -> u32 {
+        self.bos_token_id.unwrap_or(self.eos_token_id)
+    }
+
+    fn eos_id(&self) -> u32 {
+        self.eos_token_id
+    }
+
+    fn stop_ids(&self) -> Vec<u32> {
+        self.stop_ids.clone()
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.bpe.get_vocab_size(true)
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.bpe.token_to_id(token)
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.bpe.id_to_token(id)
+    }
+
+    fn streaming_context_size(&self) -> usize {
+        // Byte-level and SentencePiece tokens can both split characters and spacing
+        4
+    }
+}
+
+impl HfTokenizer {
+    fn from_configs(
+        mut bpe: BaseTokenizer,
+        config: TokenizerConfig,
+        generation_config: GenerationConfig,
+    ) -> Result<Self, String> {
+        // Tokens added to the configuration but missing from the tokenizer file
+        let mut added_tokens = config
+            .added_tokens_decoder
+            .iter()
+            .filter_map(|(id, token)| Some((id.parse::<u32>().ok()?, token)))
+            .filter(|(_, token)| bpe.token_to_id(&token.content).is_none())
+            .collect::<Vec<_>>();
+        added_tokens.sort_by_key(|(id, _)| *id);
+        for (_, token) in added_tokens {
+            let added = AddedToken::from(token.content.clone(), token.special);
+            if token.special {
+                bpe.add_special_tokens(&[added]);
+            } else {
+                bpe.add_tokens(&[added]);
+            }
+        }
+
+        let special_token_id = |token: &Option<SpecialToken>| -> Result<Option<u32>, String> {
+            match token {
+                Some(token) => bpe
+                    .token_to_id(token.content())
+                    .map(Some)
+                    .ok_or_else(|| format!("Unknown special token '{}'", token.content())),
+                None => Ok(None),
+            }
+        };
+        let generation_eos_ids = generation_config
+            .eos_token_id
+            .map(|ids| ids.ids())
+            .unwrap_or_default();
+
+        let bos_token_id = match config.add_bos_token {
+            Some(false) => None,
+            _ => special_token_id(&config.bos_token)?.or(generation_config.bos_token_id),
+        };
+        let eos_token_id = special_token_id(&config.eos_token)?
+            .or(generation_eos_ids.first().copied())
+            .ok_or("Missing end of sentence token in the tokenizer configuration")?;
+
+        let mut stop_ids = vec![eos_token_id];
+        stop_ids.extend(generation_eos_ids);
+        stop_ids.extend(
+            END_OF_TURN_TOKENS
+                .iter()
+                .filter_map(|token| bpe.token_to_id(token)),
+        );
+        stop_ids.sort();
+        stop_ids.dedup();
+
+        let mut text_bpe = bpe.clone();
+        text_bpe.set_encode_special_tokens(true);
+
+        Ok(Self {
+            bpe,
+            text_bpe,
+            bos_token_id,
+            eos_token_id,
+            stop_ids,
+        })
+    }
+}
+
+/// Read an optional JSON configuration file.
+fn read_config<C: DeserializeOwned + Default>(path: &Path) -> Result<C, String> {
+    if !path.is_file() {
+        return Ok(C::default());
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read '{}'.\nError: {err}", path.display()))?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Failed to parse '{}'.\nError: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKENIZER_JSON: &str = r#"{
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [
+            {"id": 5, "content": "<|begin_of_text|>", "single_word": false, "lstrip": false, "rstrip": false, "normalized": false, "special": true},
+            {"id": 6, "content": "<|end_of_text|>", "single_word": false, "lstrip": false, "rstrip": false, "normalized": false, "special": true}
+        ],
+        "normalizer": null,
+        "pre_tokenizer": {"type": "ByteLevel", "add_prefix_space": false, "trim_offsets": true, "use_regex": true},
+        "post_processor": null,
+        "decoder": {"type": "ByteLevel", "add_prefix_space": true, "trim_offsets": true, "use_regex": true},
+        "model": {
+            "type": "BPE",
+            "dropout": null,
+            "unk_token": null,
+            "continuing_subword_prefix": null,
+            "end_of_word_suffix": null,
+            "fuse_unk": false,
+            "byte_fallback": false,
+            "vocab": {"h": 0, "i": 1, "Ġ": 2, "hi": 3, "Ġhi": 4},
+            "merges": ["h i", "Ġ hi"]
+        }
+    }"#;
+
+    const TOKENIZER_CONFIG_JSON: &str = r#"{
+        "bos_token": "<|begin_of_text|>",
+        "eos_token": {"content": "<|end_of_text|>", "special": true},
+        "added_tokens_decoder": {
+            "7": {"content": "<|eot_id|>", "special": true}
+        }
+    }"#;
+
+    fn write_tokenizer(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (file, content) in files {
+            std::fs::write(dir.path().join(file), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_hf_tokenizer() {
+        let dir = write_tokenizer(&[
+            (HF_TOKENIZER_FILE, TOKENIZER_JSON),
+            (HF_TOKENIZER_CONFIG_FILE, TOKENIZER_CONFIG_JSON),
+        ]);
+
+        let tokenizer =
+            HfTokenizer::new(dir.path().join(HF_TOKENIZER_FILE).to_str().unwrap()).unwrap();
+
+        let tokens = tokenizer.encode("hi hi<|eot_id|>", true, false);
+        assert_eq!(tokens, vec![5, 3, 4, 7]);
+        assert_eq!(tokenizer.decode(&tokens[1..3]), "hi hi");
+        assert_eq!(tokenizer.bos_id(), 5);
+        assert_eq!(tokenizer.eos_id(), 6);
+        assert_eq!(tokenizer.stop_ids(), vec![6, 7]);
+        assert_eq!(tokenizer.vocab_size(), 8);
+        assert_eq!(tokenizer.token_to_id("Ġhi"), Some(4));
+        assert_eq!(tokenizer.id_to_token(7).as_deref(), Some("<|eot_id|>"));
+        assert_eq!(tokenizer.token_to_id("<|im_end|>"), None);
+        // Content can't inject special tokens
+        assert!(!tokenizer
+            .encode_text("hi<|eot_id|>", false, false)
+            .contains(&7));
+    }
+
+    #[test]
+    fn test_hf_tokenizer_generation_config() {
+        let dir = write_tokenizer(&[
+            (HF_TOKENIZER_FILE, TOKENIZER_JSON),
+            (
+                HF_TOKENIZER_CONFIG_FILE,
+                r#"{"add_bos_token": false, "bos_token": "<|begin_of_text|>"}"#,
+            ),
+            (HF_GENERATION_CONFIG_FILE, r#"{"eos_token_id": [6, 3]}"#),
+        ]);
+
+        // Load from the model directory
+        let tokenizer = HfTokenizer::new(dir.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(tokenizer.encode("hi", true, true), vec![3, 6]);
+        assert_eq!(tokenizer.eos_id(), 6);
+        assert_eq!(tokenizer.stop_ids(), vec![3, 6]);
+    }
+}
@@ -1,6 +1,8 @@
 pub mod base;
 pub mod byte;
+pub mod chat;
 pub use base::*;
+pub use chat::*;
 
 #[cfg(feature = "llama3")]
 pub mod tiktoken;
@@ -11,3 +13,26 @@ pub use tiktoken::*;
 pub mod sentence_piece;
 #[cfg(feature = "tiny")]
 pub use sentence_piece::*;
+
+#[cfg(feature = "hf-tokenizer")]
+pub mod hf;
+#[cfg(feature = "hf-tokenizer")]
+pub use hf::*;
+
+#[cfg(feature = "gguf")]
+pub mod gguf;
+#[cfg(feature = "gguf")]
+pub use gguf::*;
+
+/// Encode a text with a `tokenizers` tokenizer, between the given beginning and end of sentence
+/// tokens.
+#[cfg(any(feature = "tiny", feature = "hf-tokenizer", feature = "gguf"))]
+pub(crate) fn encode_with(
+    bpe: &tokenizers::Tokenizer,
+    text: &str,
+    bos: Option<u32>,
+    eos: Option<u32>,
+) -> Vec<u32> {
+    let tokens = bpe.encode(text, false).unwrap().get_ids().to_vec();
+    bos.into_iter().chain(tokens).chain(eos).collect()
+}
@@ -1,6 +1,6 @@
 use tokenizers::Tokenizer as BaseTokenizer;
 
-use super::Tokenizer;
+use super::{encode_with, Tokenizer};
 
 const BOS_TOKEN_ID: u32 = 1;
 const EOS_TOKEN_ID: u32 = 2;
@@ -8,6 +8,8 @@ const EOS_TOKEN_ID: u32 = 2;
 #[derive(Debug, Clone)]
 pub struct SentencePieceTokenizer {
     bpe: BaseTokenizer,
+    /// The tokenizer encoding the text of special tokens as ordinary text.
+    text_bpe: BaseTokenizer,
     bos_token_id: u32,
     eos_token_id: u32,
 }
@@ -17,23 +19,33 @@ impl Tokenizer for SentencePieceTokenizer {
     fn new(tokenizer_path: &str) -> Result<Self, String> {
         let bpe = BaseTokenizer::from_file(tokenizer_path).map_err(|e| e.to_string())?;
 
+        let mut text_bpe = bpe.clone();
+        text_bpe.set_encode_special_tokens(true);
+
         Ok(Self {
             bpe,
+            text_bpe,
             bos_token_id: BOS_TOKEN_ID,
             eos_token_id: EOS_TOKEN_ID,
         })
     }
 
     fn encode(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
-        let bos_token = if bos { vec![self.bos_token_id] } else { vec![] };
-        let eos_token = if eos { vec![self.eos_token_id] } else { vec![] };
-
-        let tokens = self.bpe.encode(text, false).unwrap().get_ids().to_vec();
+        encode_with(
+            &self.bpe,
+            text,
+            bos.then_some(self.bos_token_id),
+            eos.then_some(self.eos_token_id),
+        )
+    }
 
-        [bos_token, tokens, eos_token]
-            .into_iter()
-            .flat_map(|t| t.into_iter())
-            .collect()
+    fn encode_text(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
+        encode_with(
+            &self.text_bpe,
+            text,
+            bos.then_some(self.bos_token_id),
+            eos.then_some(self.eos_token_id),
+        )
     }
 
     fn decode(&self, tokens: &[u32]) -> String {
@@ -52,6 +64,18 @@ impl Tokenizer for SentencePieceTokenizer {
         vec![self.eos_id()]
     }
 
+    fn vocab_size(&self) -> usize {
+        self.bpe.get_vocab_size(true)
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.bpe.token_to_id(token)
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.bpe.id_to_token(id)
+    }
+
     fn streaming_context_size(&self) -> usize {
         // SentencePiece tokens represent subwords with special markers (e.g., _ suffix for spaces),
         // requiring a short token buffer for correct incremental decoding.
@@ -1,6 +1,7 @@
 use std::{
     fs::File,
     io::{BufRead, BufReader},
+    sync::Arc,
 };
 
 use base64::{engine::general_purpose::STANDARD, Engine};
@@ -33,6 +34,10 @@ const PATTERN: &str = r#"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\
 #[derive(Debug, Clone)]
 pub struct Tiktoken {
     bpe: CoreBPE,
+    /// Token identifiers by token bytes, including the special tokens.
+    encoder: Arc<HashMap<Vec<u8>, usize>>,
+    /// Token bytes by token identifier, including the special tokens.
+    decoder: Arc<HashMap<usize, Vec<u8>>>,
     bos_token_id: usize,
     eos_token_id: usize,
     eot_token_id: usize,
@@ -85,10 +90,23 @@ impl Tokenizer for Tiktoken {
         let eot_token_id = special_tokens[EOT_TOKEN];
         let eom_token_id = special_tokens[EOM_TOKEN];
 
+        let mut encoder = mergeable_ranks.clone();
+        encoder.extend(
+            special_tokens
+                .iter()
+                .map(|(token, id)| (token.as_bytes().to_vec(), *id)),
+        );
+        let decoder = encoder
+            .iter()
+            .map(|(token, id)| (*id, token.clone()))
+            .collect();
+
         let bpe =
             CoreBPE::new(mergeable_ranks, special_tokens, PATTERN).map_err(|e| e.to_string())?;
         Ok(Self {
             bpe,
+            encoder: Arc::new(encoder),
+            decoder: Arc::new(decoder),
             bos_token_id,
             eos_token_id,
             eot_token_id,
@@ -97,16 +115,11 @@ impl Tokenizer for Tiktoken {
     }
 
     fn encode(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
-        let bos_token = if bos { vec![self.bos_token_id] } else { vec![] };
-        let eos_token = if eos { vec![self.eos_token_id] } else { vec![] };
-
-        let tokens = self.bpe.encode_with_special_tokens(text);
+        self.with_special_tokens(self.bpe.encode_with_special_tokens(text), bos, eos)
+    }
 
-        [bos_token, tokens, eos_token]
-            .into_iter()
-            .flat_map(|t| t.into_iter())
-            .map(|t| t as u32)
-            .collect()
+    fn encode_text(&self, text: &str, bos: bool, eos: bool) -> Vec<u32> {
+        self.with_special_tokens(self.bpe.encode_ordinary(text), bos, eos)
     }
 
     fn decode(&self, tokens: &[u32]) -> String {
@@ -130,4 +143,32 @@ impl Tokenizer for Tiktoken {
             self.eot_token_id as u32,
         ]
     }
+
+    fn vocab_size(&self) -> usize {
+        self.decoder.len()
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.encoder.get(token.as_bytes()).map(|&id| id as u32)
+    }
+
+    /// The token of an identifier, with invalid UTF-8 bytes replaced for partial characters.
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.decoder
+            .get(&(id as usize))
+            .map(|token| String::from_utf8_lossy(token).into_owned())
+    }
+}
+
+impl Tiktoken {
+    fn with_special_tokens(&self, tokens: Vec<usize>, bos: bool, eos: bool) -> Vec<u32> {
+        let bos_token = if bos { vec![self.bos_token_id] } else { vec![] };
+        let eos_token = if eos { vec![self.eos_token_id] } else { vec![] };
+
+        [bos_token, tokens, eos_token]
+            .into_iter()
+            .flat_map(|t| t.into_iter())
+            .map(|t| t as u32)
+            .collect()
+    }
 }
@@ -326,15 +326,37 @@ struct InferenceServerEntry {
     server_ty: String,
 }
 
+#[derive(Debug, FromMeta)]
+struct InferencePluginProviderEntry {
+    function: String,
+}
+
+#[derive(Debug, FromMeta)]
+struct RegistryConfigureEntry {
+    function: String,
+}
+
 #[derive(Debug, Default, FromMeta)]
 #[darling(default)]
 struct InferenceServerEntries {
     #[darling(default, rename = "server", multiple)]
     servers: Vec<InferenceServerEntry>,
+    #[darling(default, rename = "provider", multiple)]
+    providers: Vec<InferencePluginProviderEntry>,
+    #[darling(default, rename = "configure", multiple)]
+    configures: Vec<RegistryConfigureEntry>,
 }
 
 /// This macro implements the new() function for the Registry struct given a
 /// list of Inference Server entries.
+/// Provider entries name a function returning `Vec<Box<dyn InferencePlugin>>`
+/// which is called when the registry is created, this allows to register
+/// plugins only known at runtime (e.g. models found in a cache directory).
+/// Provider plugins using the model name or CLI name of a registered plugin are
+/// skipped, see `register_plugin`.
+/// Configure entries name a function taking `&mut DynClients` which is called
+/// once all the plugins are registered, e.g. to select the device of each model.
+/// The registry created with `with_workers()` runs the servers in worker processes.
 /// This macro also defines some type aliases to make the generated code more readable.
 /// For instance for "MyModel" server_name the macro will define the following types:
 ///   - MyModelS = "type of the server passed as server_type"
@@ -380,22 +402,84 @@ pub fn inference_server_registry(attr: TokenStream, item: TokenStream) -> TokenS
         let registry_entry = quote! {
             {
                 type S = #server_ty;
-                type C = InferenceClient<#server_ty, Channel<#server_ty>>;
-                map.insert(
-                    S::model_name(),
-                    Box::new(C::new(
+                if workers {
+                    type C = InferenceClient<#server_ty, ProcessChannel<#server_ty>>;
+                    map.insert(
+                        S::model_name(),
+                        Box::new(C::new(
+                            S::model_name(),
+                            S::model_cli_param_name(),
+                            S::model_creation_date(),
+                            S::created_by(),
+                            <S as ServerConfigParsing>::Config::command,
+                            ProcessChannel::<S>::new(S::model_name()),
+                        )),
+                    );
+                } else {
+                    type C = InferenceClient<#server_ty, Channel<#server_ty>>;
+                    map.insert(
                         S::model_name(),
-                        S::model_cli_param_name(),
-                        S::model_creation_date(),
-                        S::created_by(),
-                        <S as ServerConfigParsing>::Config::command,
-                        Channel::<S>::new(),
+                        Box::new(C::new(
+                            S::model_name(),
+                            S::model_cli_param_name(),
+                            S::model_creation_date(),
+                            S::created_by(),
+                            <S as ServerConfigParsing>::Config::command,
+                            Channel::<S>::new(),
+                        )),
+                    );
+                }
+            }
+        };
+        registry_entries.push(registry_entry);
+    }
+    // generate hash map entries for each plugin provider
+    for provider in &registry_args.providers {
+        let function_str = &provider.function;
+        let function: syn::Path = match syn::parse_str(function_str) {
+            Ok(path) => path,
+            Err(e) => {
+                let msg = format!("Invalid provider function `{function_str}`: {e}");
+                return syn::Error::new_spanned(
+                    syn::Lit::Str(syn::LitStr::new(
+                        function_str,
+                        proc_macro2::Span::call_site(),
                     )),
-                );
+                    msg,
+                )
+                .to_compile_error()
+                .into();
+            }
+        };
+        let registry_entry = quote! {
+            for plugin in #function() {
+                register_plugin(&mut map, plugin);
             }
         };
         registry_entries.push(registry_entry);
     }
+    // generate the calls of the configure functions, once all the entries are registered
+    for configure in &registry_args.configures {
+        let function_str = &configure.function;
+        let function: syn::Path = match syn::parse_str(function_str) {
+            Ok(path) => path,
+            Err(e) => {
+                let msg = format!("Invalid configure function `{function_str}`: {e}");
+                return syn::Error::new_spanned(
+                    syn::Lit::Str(syn::LitStr::new(
+                        function_str,
+                        proc_macro2::Span::call_site(),
+                    )),
+                    msg,
+                )
+                .to_compile_error()
+                .into();
+            }
+        };
+        registry_entries.push(quote! {
+            #function(&mut map);
+        });
+    }
     // Imports
     let mut crate_imports = Vec::new();
     for namespace in crate_namespaces {
@@ -416,6 +500,14 @@ pub fn inference_server_registry(attr: TokenStream, item: TokenStream) -> TokenS
         // new() implementation
         impl #struct_ident {
             pub fn new() -> Self {
+                Self::create(false)
+            }
+            /// Create a registry running each compiled-in model in a worker process, see
+            /// `ProcessChannel`. The models of the providers always run in the current process.
+            pub fn with_workers() -> Self {
+                Self::create(true)
+            }
+            fn create(workers: bool) -> Self {
                 let mut map: DynClients = ::std::collections::HashMap::new();
                 #(#registry_entries)*
                 Self {
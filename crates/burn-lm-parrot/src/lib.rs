@@ -1,5 +1,7 @@
 #![recursion_limit = "256"]
 
+use std::time::{Duration, Instant};
+
 use burn_lm_inference::{InferenceJob, *};
 
 // This is where you can declare the configuration parameters for
@@ -18,6 +20,26 @@ pub struct ParrotServerConfig {
     /// Temperature value for controlling randomness in sampling.
     #[config(default = 0.1)]
     pub temperature: f64,
+    // The following parameters script the parrot to test the clients and the HTTP
+    // server without model weights.
+    /// The number of tokens to generate by repeating the words of the echoed message. If it is 0 then the message is echoed once.
+    #[config(default = 0, openwebui_param = "max_tokens")]
+    pub sample_len: usize,
+    /// Delay before each generated token, in milliseconds.
+    #[config(default = 0)]
+    pub token_delay_ms: u64,
+    /// Simulated loading time of the model, in milliseconds.
+    #[config(default = 1000)]
+    pub load_time_ms: u64,
+    /// Fail the loading of the model with an error.
+    #[config(default = false)]
+    pub load_error: bool,
+    /// Fail the generation with an error before the given token, counted from 1. If it is 0 then the generation does not fail.
+    #[config(default = 0)]
+    pub error_at_token: usize,
+    /// Panic before the given token, counted from 1. If it is 0 then the generation does not panic.
+    #[config(default = 0)]
+    pub panic_at_token: usize,
 }
 
 // Declare the model server info using the `InferenceServer` derive
@@ -42,11 +64,35 @@ pub struct ParrotServerConfig {
 )]
 pub struct ParrotServer {
     config: ParrotServerConfig,
+    loaded: bool,
+}
+
+impl ParrotServer {
+    /// Split the echoed text into word tokens, repeated up to the configured number of tokens.
+    fn tokens(&self, text: &str) -> Vec<String> {
+        let words: Vec<&str> = text.split_inclusive(' ').collect();
+        if self.config.sample_len == 0 || words.is_empty() {
+            return words.into_iter().map(str::to_string).collect();
+        }
+        (0..self.config.sample_len)
+            .map(|index| {
+                let word = words[index % words.len()];
+                // Separate the repetitions of the text
+                let repeated =
+                    index % words.len() == words.len() - 1 && index + 1 < self.config.sample_len;
+                if repeated && !word.ends_with(' ') {
+                    format!("{word} ")
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect()
+    }
 }
 
 // Implement the `InferenceServer` trait for the server.
 impl InferenceServer for ParrotServer {
-    fn downloader(&mut self) -> Option<fn() -> InferenceResult<Option<Stats>>> {
+    fn downloader(&mut self) -> Option<fn(DeviceSettings) -> InferenceResult<Option<Stats>>> {
         // Return a closure with code to download the model if available.
         // Return none if there is no possibility to download the model or if
         // this model does not need to be downloaded.
@@ -68,54 +114,197 @@ impl InferenceServer for ParrotServer {
     }
 
     fn load(&mut self) -> InferenceResult<Option<Stats>> {
-        // Load the model here
-        let now = std::time::Instant::now();
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        // Load the model here, a loaded model is not reloaded.
+        if self.loaded {
+            return Ok(None);
+        }
+        if self.config.load_error {
+            return Err(InferenceError::LoadError(format!(
+                "{} (reason: injected loading error)",
+                Self::model_name()
+            )));
+        }
+        let now = Instant::now();
+        std::thread::sleep(Duration::from_millis(self.config.load_time_ms));
         let mut stats = Stats::new();
         stats
             .entries
             .insert(StatEntry::ModelLoadingDuration(now.elapsed()));
+        self.loaded = true;
         Ok(Some(stats))
     }
 
     fn is_loaded(&mut self) -> bool {
         // Return true when the model is loaded and ready for inference.
-        true
+        self.loaded
     }
 
     fn unload(&mut self) -> InferenceResult<Option<Stats>> {
         // Drop the model here.
+        self.loaded = false;
         Ok(None)
     }
 
     fn run_job(&mut self, job: InferenceJob) -> InferenceResult<Stats> {
-        match job.task {
-            InferenceTask::Message(message) => {
-                job.emitter.completed(GeneratedItem::Text(message.content));
-            }
-            InferenceTask::Context(mut messages) => {
-                job.emitter.completed(GeneratedItem::Text(
-                    messages
-                        .pop()
-                        .map(|msg| msg.content)
-                        .unwrap_or_else(|| "...".to_string()),
+        // Load the model on the first job.
+        let load_stats = self.load()?;
+        let text = match job.task {
+            InferenceTask::Message(message) => message.content,
+            InferenceTask::Context(mut messages) => messages
+                .pop()
+                .map(|msg| msg.content)
+                .unwrap_or_else(|| "...".to_string()),
+            InferenceTask::Prompt(text) => text,
+        };
+
+        // Stream the generated tokens as soon as they are generated.
+        let now = Instant::now();
+        let tokens = self.tokens(&text);
+        let count = tokens.len();
+        for (index, token) in tokens.into_iter().enumerate() {
+            let step = index + 1;
+            if step == self.config.error_at_token {
+                return Err(InferenceError::GenerationError(
+                    Self::model_name().to_string(),
+                    format!("injected error at token {step}"),
                 ));
             }
-            InferenceTask::Prompt(text) => {
-                job.emitter.completed(GeneratedItem::Text(text));
+            if step == self.config.panic_at_token {
+                panic!("{} panicked at token {step}", Self::model_name());
             }
+            std::thread::sleep(Duration::from_millis(self.config.token_delay_ms));
+            job.emitter.completed(GeneratedItem::Text(token));
         }
+        let duration = now.elapsed();
 
-        // Example of returned statistics about the completion.
+        // Return statistics about the completion.
         let mut stats = Stats::default();
+        let mut total_duration = duration;
+        stats.entries.extend([
+            StatEntry::InferenceDuration(duration),
+            StatEntry::TokensCount(count),
+            StatEntry::TokensPerSecond(count, duration),
+        ]);
+        if let Some(load_stats) = load_stats {
+            total_duration += load_stats
+                .entries
+                .iter()
+                .filter(|entry| matches!(entry, StatEntry::ModelLoadingDuration(_)))
+                .filter_map(StatEntry::get_duration)
+                .sum::<Duration>();
+            stats.entries.extend(load_stats.entries);
+        }
         stats
             .entries
-            .insert(StatEntry::Named("Everything".to_string(), "42".to_string()));
+            .insert(StatEntry::TotalDuration(total_duration));
         Ok(stats)
     }
 
     fn clear_state(&mut self) -> InferenceResult<()> {
-        // No state
+        // No state, but the model must be loaded
+        if !self.loaded {
+            return Err(InferenceError::ModelNotLoaded);
+        }
         Ok(())
     }
 }
+
+// Check that the server behaves as expected by Burn LM with the conformance tests
+// of `burn-lm-inference`, they run with `cargo test`.
+//
+// Pass a JSON configuration making the generation deterministic if needed, here
+// the configuration only skips the simulated loading time.
+burn_lm_inference::inference_server_conformance_tests!(
+    ParrotServer,
+    config = r#"{"load_time_ms": 0}"#
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parrot(config: &str) -> ParrotServer {
+        let mut server = ParrotServer::default();
+        server.parse_json_config(config);
+        server
+    }
+
+    fn run(server: &mut ParrotServer, prompt: &str) -> (InferenceResult<Stats>, Vec<String>) {
+        #[derive(Default)]
+        struct Tokens(Vec<String>);
+
+        impl InferenceJobListener for Tokens {
+            type CompletedItem = Vec<String>;
+
+            fn on_text(&mut self, text: String) {
+                self.0.push(text);
+            }
+
+            fn on_finished(self) -> Self::CompletedItem {
+                self.0
+            }
+        }
+
+        let task = InferenceTask::Prompt(prompt.to_string());
+        let (job, handle) = InferenceJob::create(task, Tokens::default());
+        let result = server.run_job(job);
+        (result, handle.join())
+    }
+
+    #[test]
+    fn test_parrot_streams_tokens() {
+        let mut server = parrot(r#"{"load_time_ms": 0}"#);
+        let (stats, tokens) = run(&mut server, "Hello Parrot!");
+        assert_eq!(tokens, ["Hello ", "Parrot!"]);
+
+        let stats = stats.unwrap();
+        assert!(stats.entries.contains(&StatEntry::TokensCount(2)));
+        assert!(stats
+            .entries
+            .iter()
+            .any(|entry| matches!(entry, StatEntry::ModelLoadingDuration(_))));
+        assert!(stats
+            .entries
+            .iter()
+            .any(|entry| matches!(entry, StatEntry::TotalDuration(_))));
+    }
+
+    #[test]
+    fn test_parrot_sample_len_and_delay() {
+        let mut server = parrot(r#"{"load_time_ms": 0, "max_tokens": 5, "token_delay_ms": 10}"#);
+        server.load().unwrap();
+        let (stats, tokens) = run(&mut server, "Hello Parrot!");
+        assert_eq!(tokens.concat(), "Hello Parrot! Hello Parrot! Hello ");
+
+        let stats = stats.unwrap();
+        assert!(stats.entries.contains(&StatEntry::TokensCount(5)));
+        let duration = stats
+            .entries
+            .iter()
+            .find_map(|entry| match entry {
+                StatEntry::InferenceDuration(duration) => Some(*duration),
+                _ => None,
+            })
+            .unwrap();
+        assert!(duration >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_parrot_injected_errors() {
+        let mut server = parrot(r#"{"load_error": true}"#);
+        assert!(matches!(server.load(), Err(InferenceError::LoadError(_))));
+        assert!(!server.is_loaded());
+
+        let mut server = parrot(r#"{"load_time_ms": 0, "error_at_token": 2}"#);
+        let (result, tokens) = run(&mut server, "Hello Parrot!");
+        assert!(matches!(result, Err(InferenceError::GenerationError(..))));
+        assert_eq!(tokens, ["Hello "]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Parrot panicked at token 1")]
+    fn test_parrot_injected_panic() {
+        let mut server = parrot(r#"{"load_time_ms": 0, "panic_at_token": 1}"#);
+        let _ = run(&mut server, "Hello Parrot!");
+    }
+}
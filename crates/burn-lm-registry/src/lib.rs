@@ -2,8 +2,41 @@ use burn_lm_inference::*;
 use burn_lm_macros::inference_server_registry;
 use std::{collections::HashMap, sync::Arc};
 
+pub mod models;
+pub mod plugins;
+
+pub use burn_lm_llama::server::hub::{download_hub_model, HUB_PREFIX};
+
 pub type Channel<B> = MutexChannel<B>;
 
+/// The `burn-lm` configuration directory, e.g. `~/.config/burn-lm`.
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("burn-lm"))
+}
+
+/// Serve the model of the current process if it is a worker process started by a
+/// [ProcessChannel], then exit.
+///
+/// Executables using [Registry::with_workers] must call this function before anything else.
+pub fn run_worker_if_requested() {
+    let Some(model_name) = channels::process::worker_model() else {
+        return;
+    };
+    let registry = Registry::new();
+    let Some(plugin) = registry.get().get(model_name.as_str()).cloned() else {
+        eprintln!("Unknown worker model '{model_name}'");
+        std::process::exit(1);
+    };
+    let code = match channels::process::run_worker(plugin) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Model worker of '{model_name}' failed: {err}");
+            1
+        }
+    };
+    std::process::exit(code);
+}
+
 pub type DynClients = HashMap<&'static str, Box<dyn InferencePlugin>>;
 
 // Register model crates
@@ -32,12 +65,23 @@ pub type DynClients = HashMap<&'static str, Box<dyn InferencePlugin>>;
         crate_namespace = "burn_lm_llama::server::tiny",
         server_type = "TinyLlamaServer",
     ),
-    server(
-        crate_namespace = "burn_lm_parrot",
-        server_type = "ParrotServer",
-    )
+    server(crate_namespace = "burn_lm_parrot", server_type = "ParrotServer",),
+    provider(function = "burn_lm_llama::server::quantized::quantized_variants"),
+    provider(function = "burn_lm_llama::server::hub::hub_models"),
+    provider(function = "crate::models::declared_models"),
+    provider(function = "crate::plugins::dynamic_plugins"),
+    configure(function = "crate::models::configure_devices")
 )]
 #[derive(Debug)]
 pub struct Registry {
     clients: Arc<DynClients>,
 }
+
+impl Registry {
+    /// Create a registry serving the given clients only, e.g. to test a subset of the models.
+    pub fn from_clients(clients: DynClients) -> Self {
+        Self {
+            clients: Arc::new(clients),
+        }
+    }
+}
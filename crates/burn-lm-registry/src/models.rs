@@ -0,0 +1,500 @@
+//! Models declared in a `models.toml` file.
+//!
+//! Models using an architecture implemented by a model crate can be served without writing a
+//! new crate by declaring their checkpoint in the models file:
+//!
+//! ```toml
+//! [[models]]
+//! name = "Llama 3.2 1B Instruct (local)"
+//! architecture = "llama"
+//! weights = "Llama-3.2-1B-Instruct"
+//! tokenizer = "hf"
+//! chat_template = "llama3"
+//!
+//! [models.defaults]
+//! temperature = 0.6
+//! sample_len = 512
+//! ```
+//!
+//! Relative paths are resolved from the directory of the models file.
+//!
+//! The models of other `burn-lm-http` servers can also be served by declaring the servers:
+//!
+//! ```toml
+//! [[remotes]]
+//! url = "http://gpu-1:3000"
+//! prefix = "gpu-1"
+//! ```
+//!
+//! The models of each server are listed when the registry is first created and reused by the
+//! registries created later in the same process.
+//!
+//! The backend, device index and float type of any registered model can be selected with a
+//! `device` table in a model entry or in the `devices` table, by model name:
+//!
+//! ```toml
+//! [devices."Llama 3.2 1B Instruct"]
+//! backend = "cuda"
+//! index = 1
+//! dtype = "bf16"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use burn_lm_inference::*;
+use burn_lm_llama::{
+    server::checkpoint::{
+        chat_format_from_tokenizer_config, CheckpointModel, CheckpointServer,
+        CheckpointServerConfig, ModelDefaults,
+    },
+    tokenizer::{
+        ChatFormat, GgufTokenizer, HfTokenizer, SentencePieceTokenizer, Tiktoken, Tokenizer,
+        HF_TOKENIZER_FILE,
+    },
+};
+use serde::Deserialize;
+
+/// Environment variable overriding the location of the models file.
+pub const MODELS_FILE_ENV: &str = "BURNLM_MODELS_FILE";
+const MODELS_FILE: &str = "models.toml";
+
+/// The content of a models file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelsFile {
+    #[serde(default)]
+    pub models: Vec<ModelEntry>,
+    #[serde(default)]
+    pub remotes: Vec<RemoteEntry>,
+    /// The device of the registered models, by model name.
+    #[serde(default)]
+    pub devices: BTreeMap<String, DeviceSettings>,
+}
+
+/// A remote `burn-lm-http` server declared in the models file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteEntry {
+    /// The base URL of the server, e.g. `http://localhost:3000`.
+    pub url: String,
+    /// Prefix of the model names, to tell apart the models of several servers.
+    pub prefix: Option<String>,
+}
+
+/// The architecture of a declared model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Architecture {
+    Llama,
+}
+
+/// The tokenizer type of a declared model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerType {
+    /// Hugging Face `tokenizer.json`.
+    Hf,
+    /// Tiktoken BPE ranks file of the Llama 3 models.
+    Tiktoken,
+    /// SentencePiece model of the Llama 2 and TinyLlama models.
+    SentencePiece,
+    /// Tokenizer embedded in the GGUF weights file.
+    Gguf,
+}
+
+/// A model declared in the models file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelEntry {
+    /// The model name displayed in the model lists.
+    pub name: String,
+    /// The model name on the command line, derived from the name if omitted.
+    pub cli_name: Option<String>,
+    pub architecture: Architecture,
+    /// A Hugging Face checkpoint directory, a `.safetensors` file or a `.gguf` file.
+    pub weights: PathBuf,
+    /// The Hugging Face `config.json` file, defaults to the one next to the weights.
+    pub config: Option<PathBuf>,
+    pub tokenizer: TokenizerType,
+    /// The tokenizer file, defaults to `tokenizer.json` next to the weights for `hf`
+    /// tokenizers and to the weights file for `gguf` tokenizers.
+    pub tokenizer_path: Option<PathBuf>,
+    /// The prompt format, guessed from the `tokenizer_config.json` chat template if omitted.
+    pub chat_template: Option<ChatFormat>,
+    #[serde(default)]
+    pub created_by: String,
+    #[serde(default)]
+    pub creation_date: String,
+    /// Default values of the inference configuration.
+    #[serde(default)]
+    pub defaults: ModelDefaults,
+    /// The device of the model, the default device if omitted.
+    pub device: Option<DeviceSettings>,
+}
+
+impl ModelEntry {
+    fn cli_name(&self) -> String {
+        match &self.cli_name {
+            Some(cli_name) => cli_name.clone(),
+            None => self
+                .name
+                .to_lowercase()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect::<String>()
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-"),
+        }
+    }
+
+    /// The checkpoint of the model, with its paths resolved from the models file directory.
+    pub fn checkpoint(&self, base_dir: &Path) -> Result<CheckpointModel, String> {
+        let weights = base_dir.join(&self.weights);
+        let weights_dir = if weights.is_dir() {
+            weights.clone()
+        } else {
+            weights.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+        let tokenizer = match (&self.tokenizer_path, self.tokenizer) {
+            (Some(path), _) => base_dir.join(path),
+            (None, TokenizerType::Hf) => weights_dir.join(HF_TOKENIZER_FILE),
+            (None, TokenizerType::Gguf) => weights.clone(),
+            (None, tokenizer) => {
+                return Err(format!(
+                    "Model '{}' requires a tokenizer_path for its {tokenizer:?} tokenizer",
+                    self.name
+                ))
+            }
+        };
+        let chat_format = self
+            .chat_template
+            .or_else(|| chat_format_from_tokenizer_config(&weights_dir))
+            .unwrap_or(ChatFormat::Llama3);
+        Ok(CheckpointModel {
+            model_name: self.name.clone(),
+            config: self.config.as_ref().map(|config| base_dir.join(config)),
+            weights,
+            tokenizer,
+            chat_format,
+            defaults: self.defaults.clone(),
+        })
+    }
+
+    /// Create the inference plugin of the model.
+    pub fn plugin(&self, base_dir: &Path) -> Result<Box<dyn InferencePlugin>, String> {
+        let checkpoint = self.checkpoint(base_dir)?;
+        let plugin = match (self.architecture, self.tokenizer) {
+            (Architecture::Llama, TokenizerType::Hf) => self.client::<HfTokenizer>(checkpoint),
+            (Architecture::Llama, TokenizerType::Tiktoken) => self.client::<Tiktoken>(checkpoint),
+            (Architecture::Llama, TokenizerType::SentencePiece) => {
+                self.client::<SentencePieceTokenizer>(checkpoint)
+            }
+            (Architecture::Llama, TokenizerType::Gguf) => self.client::<GgufTokenizer>(checkpoint),
+        };
+        if let Some(device) = self.device {
+            plugin.set_device(device).map_err(|err| err.to_string())?;
+        }
+        Ok(plugin)
+    }
+
+    /// Create the inference client of the model with the tokenizer `T`.
+    fn client<T: Tokenizer + std::fmt::Debug + 'static>(
+        &self,
+        checkpoint: CheckpointModel,
+    ) -> Box<dyn InferencePlugin> {
+        fn leak(value: String) -> &'static str {
+            Box::leak(value.into_boxed_str())
+        }
+
+        Box::new(InferenceClient::new(
+            leak(self.name.clone()),
+            leak(self.cli_name()),
+            leak(self.creation_date.clone()),
+            leak(self.created_by.clone()),
+            CheckpointServerConfig::command,
+            MutexChannel::with_server(CheckpointServer::<T>::new(checkpoint)),
+        ))
+    }
+}
+
+impl ModelsFile {
+    /// Read and parse a models file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read '{}'.\nError: {err}", path.display()))?;
+        toml::from_str(&content)
+            .map_err(|err| format!("Failed to parse '{}'.\nError: {err}", path.display()))
+    }
+}
+
+/// The location of the models file.
+///
+/// Defaults to `models.toml` in the `burn-lm` configuration directory, e.g.
+/// `~/.config/burn-lm/models.toml`, and can be overridden with the `BURNLM_MODELS_FILE`
+/// environment variable.
+pub fn models_file() -> Option<PathBuf> {
+    match std::env::var_os(MODELS_FILE_ENV) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => crate::config_dir().map(|dir| dir.join(MODELS_FILE)),
+    }
+}
+
+/// Registry provider of the models declared in the models file and of the models of the
+/// declared remote servers.
+///
+/// An invalid models file or model entry and an unreachable remote server are reported and
+/// skipped so that the compiled-in models remain available.
+pub fn declared_models() -> Vec<Box<dyn InferencePlugin>> {
+    let Some(path) = models_file().filter(|path| path.is_file()) else {
+        return vec![];
+    };
+    let models_file = match ModelsFile::load(&path) {
+        Ok(models_file) => models_file,
+        Err(err) => {
+            tracing::error!("{err}");
+            return vec![];
+        }
+    };
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut plugins: Vec<_> = models_file
+        .models
+        .iter()
+        .filter_map(|entry| match entry.plugin(base_dir) {
+            Ok(plugin) => Some(plugin),
+            Err(err) => {
+                tracing::error!(
+                    "Skipping model '{}' of '{}': {err}",
+                    entry.name,
+                    path.display()
+                );
+                None
+            }
+        })
+        .collect();
+    for remote in &models_file.remotes {
+        match remote::remote_models(&remote.url, remote.prefix.as_deref()) {
+            Ok(models) => plugins.extend(
+                models
+                    .into_iter()
+                    .map(|plugin| Box::new(plugin) as Box<dyn InferencePlugin>),
+            ),
+            Err(err) => tracing::error!("Skipping remote server: {err}"),
+        }
+    }
+    plugins
+}
+
+/// Registry configuration selecting the devices of the `devices` table of the models file.
+///
+/// Unknown models and invalid devices are reported and the models keep their device.
+pub fn configure_devices(clients: &mut crate::DynClients) {
+    let Some(path) = models_file().filter(|path| path.is_file()) else {
+        return;
+    };
+    // Errors of the models file itself are already reported by `declared_models`.
+    let Ok(models_file) = ModelsFile::load(&path) else {
+        return;
+    };
+    for (model_name, device) in models_file.devices {
+        match clients.get(model_name.as_str()) {
+            Some(plugin) => {
+                if let Err(err) = plugin.set_device(device) {
+                    tracing::error!("{err}");
+                }
+            }
+            None => tracing::error!(
+                "Unknown model '{model_name}' in the devices of '{}'",
+                path.display()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODELS: &str = r#"
+        [[models]]
+        name = "My Llama 3.2 (1B)"
+        architecture = "llama"
+        weights = "llama/model.safetensors"
+        tokenizer = "hf"
+        chat_template = "chatml"
+
+        [models.defaults]
+        temperature = 0.2
+
+        [[models]]
+        name = "Quantized"
+        cli_name = "q4"
+        architecture = "llama"
+        weights = "/models/llama.gguf"
+        tokenizer = "gguf"
+    "#;
+
+    #[test]
+    fn test_models_file_parse() {
+        let models_file: ModelsFile = toml::from_str(MODELS).unwrap();
+        assert_eq!(models_file.models.len(), 2);
+
+        let entry = &models_file.models[0];
+        assert_eq!(entry.cli_name(), "my-llama-3-2-1b");
+        assert_eq!(entry.tokenizer, TokenizerType::Hf);
+        assert_eq!(entry.defaults.temperature, Some(0.2));
+
+        let checkpoint = entry.checkpoint(Path::new("/config")).unwrap();
+        assert_eq!(
+            checkpoint.weights,
+            PathBuf::from("/config/llama/model.safetensors")
+        );
+        assert_eq!(
+            checkpoint.tokenizer,
+            PathBuf::from("/config/llama/tokenizer.json")
+        );
+        assert_eq!(checkpoint.chat_format, ChatFormat::ChatMl);
+
+        let entry = &models_file.models[1];
+        assert_eq!(entry.cli_name(), "q4");
+        let checkpoint = entry.checkpoint(Path::new("/config")).unwrap();
+        assert_eq!(checkpoint.tokenizer, PathBuf::from("/models/llama.gguf"));
+        assert_eq!(checkpoint.chat_format, ChatFormat::Llama3);
+    }
+
+    #[test]
+    fn test_models_file_invalid_entries() {
+        let unknown_architecture = r#"
+            [[models]]
+            name = "GPT"
+            architecture = "gpt2"
+            weights = "gpt2"
+            tokenizer = "hf"
+        "#;
+        assert!(toml::from_str::<ModelsFile>(unknown_architecture).is_err());
+
+        let unknown_default = r#"
+            [[models]]
+            name = "Llama"
+            architecture = "llama"
+            weights = "llama"
+            tokenizer = "hf"
+            defaults = { temprature = 0.2 }
+        "#;
+        assert!(toml::from_str::<ModelsFile>(unknown_default).is_err());
+
+        let missing_tokenizer_path = r#"
+            [[models]]
+            name = "Llama"
+            architecture = "llama"
+            weights = "llama"
+            tokenizer = "tiktoken"
+        "#;
+        let models_file: ModelsFile = toml::from_str(missing_tokenizer_path).unwrap();
+        assert!(models_file.models[0].plugin(Path::new("/config")).is_err());
+
+        let unknown_remote_field = r#"
+            [[remotes]]
+            address = "http://localhost:3000"
+        "#;
+        assert!(toml::from_str::<ModelsFile>(unknown_remote_field).is_err());
+    }
+
+    #[test]
+    fn test_models_file_remotes() {
+        let remotes = r#"
+            [[remotes]]
+            url = "http://gpu-1:3000"
+            prefix = "gpu-1"
+
+            [[remotes]]
+            url = "http://gpu-2:3000"
+        "#;
+        let models_file: ModelsFile = toml::from_str(remotes).unwrap();
+        assert!(models_file.models.is_empty());
+        assert_eq!(models_file.remotes.len(), 2);
+        assert_eq!(models_file.remotes[0].prefix.as_deref(), Some("gpu-1"));
+        assert_eq!(models_file.remotes[1].url, "http://gpu-2:3000");
+        assert!(models_file.remotes[1].prefix.is_none());
+    }
+
+    #[test]
+    fn test_models_file_devices() {
+        let devices = r#"
+            [[models]]
+            name = "Llama"
+            architecture = "llama"
+            weights = "llama"
+            tokenizer = "hf"
+            device = { backend = "ndarray" }
+
+            [devices.TinyLlama]
+            dtype = "f32"
+
+            [devices."Llama 3.2 1B Instruct"]
+            index = 1
+        "#;
+        let models_file: ModelsFile = toml::from_str(devices).unwrap();
+        let device = models_file.models[0].device.unwrap();
+        assert_eq!(device.backend, BackendKind::Ndarray);
+        assert_eq!(device.index, None);
+        assert_eq!(models_file.devices["TinyLlama"].dtype, FloatDtype::F32);
+        assert_eq!(models_file.devices["Llama 3.2 1B Instruct"].index, Some(1));
+
+        let plugin = models_file.models[0].plugin(Path::new("/config")).unwrap();
+        assert_eq!(plugin.device_settings(), device);
+
+        let unknown_backend = r#"
+            [devices.TinyLlama]
+            backend = "tpu"
+        "#;
+        assert!(toml::from_str::<ModelsFile>(unknown_backend).is_err());
+    }
+
+    #[test]
+    fn test_models_file_plugin() {
+        let models_file: ModelsFile = toml::from_str(MODELS).unwrap();
+        let plugin = models_file.models[0].plugin(Path::new("/config")).unwrap();
+
+        assert_eq!(plugin.model_name(), "My Llama 3.2 (1B)");
+        assert_eq!(plugin.model_cli_param_name(), "my-llama-3-2-1b");
+        assert!(!plugin.is_downloaded());
+    }
+
+    #[test]
+    fn test_duplicate_model_names_are_skipped() {
+        type S = crate::ParrotServer;
+        fn parrot(name: &'static str, cli_name: &'static str) -> Box<dyn InferencePlugin> {
+            Box::new(InferenceClient::<S, crate::Channel<S>>::new(
+                name,
+                cli_name,
+                S::model_creation_date(),
+                S::created_by(),
+                <S as ServerConfigParsing>::Config::command,
+                crate::Channel::<S>::new(),
+            ))
+        }
+
+        let mut clients = crate::DynClients::new();
+        assert!(register_plugin(&mut clients, parrot("Parrot", "parrot")));
+        assert!(!register_plugin(
+            &mut clients,
+            parrot("Parrot", "my-parrot")
+        ));
+        assert!(!register_plugin(
+            &mut clients,
+            parrot("My Parrot", "parrot")
+        ));
+        assert!(register_plugin(
+            &mut clients,
+            parrot("My Parrot", "my-parrot")
+        ));
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients["Parrot"].model_cli_param_name(), "parrot");
+    }
+}
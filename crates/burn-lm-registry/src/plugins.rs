@@ -0,0 +1,31 @@
+//! Inference plugins loaded from shared libraries, see [burn_lm_inference::dylib].
+
+use std::path::PathBuf;
+
+use burn_lm_inference::InferencePlugin;
+
+/// Environment variable overriding the plugins directory.
+pub const PLUGINS_DIR_ENV: &str = "BURNLM_PLUGINS_DIR";
+
+/// The directory of the plugin libraries.
+///
+/// Defaults to `plugins` in the `burn-lm` configuration directory, e.g.
+/// `~/.config/burn-lm/plugins`, and can be overridden with the `BURNLM_PLUGINS_DIR`
+/// environment variable.
+pub fn plugins_dir() -> Option<PathBuf> {
+    match std::env::var_os(PLUGINS_DIR_ENV) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => crate::config_dir().map(|dir| dir.join("plugins")),
+    }
+}
+
+/// Registry provider of the plugins of the libraries found in the plugins directory.
+///
+/// Requires the `dylib` feature, no library is loaded without it.
+pub fn dynamic_plugins() -> Vec<Box<dyn InferencePlugin>> {
+    #[cfg(feature = "dylib")]
+    if let Some(dir) = plugins_dir() {
+        return burn_lm_inference::dylib::load_plugins_dir(dir);
+    }
+    vec![]
+}